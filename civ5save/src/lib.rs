@@ -11,6 +11,9 @@ use tracing::{debug, instrument, trace};
 type Result<T> = anyhow::Result<T, anyhow::Error>;
 type Error = anyhow::Error;
 
+/// Section holding the hotseat password of every player slot, one string each.
+const PASSWORDS_SECTION: usize = 11;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum PlayerType {
     AI = 1,
@@ -65,12 +68,30 @@ pub struct Header {
     pub game_speed: String,
     pub world_size: String,
     pub map_script: String,
+    /// Every DLC the host has installed, enabled or not.
+    #[serde(default)]
+    pub dlc: Vec<Dlc>,
+    #[serde(default)]
+    pub mods: Vec<Mod>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dlc {
+    pub name: String,
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Mod {
+    pub id: String,
+    pub version: u32,
+    pub name: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Player {
-    name: String,
-    player_type: PlayerType,
+    pub name: String,
+    pub player_type: PlayerType,
 }
 
 pub struct Civ5SaveReader<'a> {
@@ -97,7 +118,6 @@ impl<'a> Civ5SaveReader<'a> {
         debug!(?header);
 
         self.load_chunks()?;
-        // self.dump_chunks()?;
 
         self.chunk(1)?;
         let player_names = self.strings()?;
@@ -146,6 +166,11 @@ impl<'a> Civ5SaveReader<'a> {
         let game_speed = self.string()?;
         let world_size = self.string()?;
         let map_script = self.string()?;
+        // Only seen without mods, so a save that doesn't fit shouldn't stop the rest.
+        let (dlc, mods) = self.content().unwrap_or_else(|err| {
+            debug!(?err, "Couldn't read DLC and mods.");
+            (vec![], vec![])
+        });
         Ok(Header {
             save,
             game,
@@ -158,9 +183,30 @@ impl<'a> Civ5SaveReader<'a> {
             game_speed,
             world_size,
             map_script,
+            dlc,
+            mods,
         })
     }
 
+    /// Right after the map script: DLC as a GUID, whether it's enabled and its name, then mods.
+    fn content(&mut self) -> Result<(Vec<Dlc>, Vec<Mod>)> {
+        let mut dlc = vec![];
+        for _ in 0..self.u32()? {
+            self.exact(16)?;
+            let enabled = self.u32()? != 0;
+            let name = self.string()?;
+            dlc.push(Dlc { name, enabled });
+        }
+        let mut mods = vec![];
+        for _ in 0..self.u32()? {
+            let id = self.string()?;
+            let version = self.u32()?;
+            let name = self.string()?;
+            mods.push(Mod { id, version, name });
+        }
+        Ok((dlc, mods))
+    }
+
     fn strings(&mut self) -> Result<Vec<String>> {
         let mut v = vec![];
         loop {
@@ -237,14 +283,6 @@ impl<'a> Civ5SaveReader<'a> {
         }
     }
 
-    fn dump_chunks(&mut self) -> Result<()> {
-        for chunk in &self.chunks {
-            println!("Chunk {} {:?} {:?}", chunk.id, chunk.offset, chunk.size);
-            println!("{}", pretty_hex(&chunk.data));
-        }
-        Ok(())
-    }
-
     fn chunk(&mut self, chunk: usize) -> Result<()> {
         let info = &self.chunks[chunk];
         trace!(?chunk, ?info);
@@ -253,6 +291,44 @@ impl<'a> Civ5SaveReader<'a> {
     }
 }
 
+/// Rewrites parts of a save, leaving every other byte as it was.
+pub struct Civ5SaveWriter<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Civ5SaveWriter<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Civ5SaveWriter { bytes }
+    }
+
+    /// Removes every hotseat password, so the game doesn't ask for one when the turn is
+    /// handed over. Passwords are stored encoded, so setting one isn't supported.
+    pub fn clear_passwords(&self) -> Result<Vec<u8>> {
+        let mut reader = Civ5SaveReader::new(self.bytes);
+        reader.load_chunks()?;
+        let start = reader.chunks[PASSWORDS_SECTION].offset;
+        // Chunk offsets point past the boundary, which isn't part of the section.
+        let end = reader.chunks[PASSWORDS_SECTION + 1].offset - 4;
+
+        reader.cursor.set_position(start);
+        let mut slots = 0;
+        while reader.cursor.position() < end {
+            reader.string()?;
+            slots += 1;
+        }
+        if reader.cursor.position() != end {
+            return Err(anyhow!("Password section doesn't end on a boundary."));
+        }
+        trace!(?slots, "Clearing passwords.");
+
+        let mut bytes = Vec::with_capacity(self.bytes.len());
+        bytes.extend_from_slice(&self.bytes[..start as usize]);
+        bytes.extend(std::iter::repeat(0u8).take(slots * 4));
+        bytes.extend_from_slice(&self.bytes[end as usize..]);
+        Ok(bytes)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Civ5Save {
     pub header: Header,
@@ -283,6 +359,140 @@ impl Civ5Save {
         }
         Ok(diff)
     }
+
+    /// Everything that differs between two saves, field by field and chunk by chunk.
+    pub fn diff(&self, other: &Civ5Save) -> Result<SaveDiff> {
+        let mut fields = vec![];
+        let mut field = |name: &str, a: String, b: String| {
+            if a != b {
+                fields.push(FieldChange {
+                    field: name.to_string(),
+                    a,
+                    b,
+                });
+            }
+        };
+        let (a, b) = (&self.header, &other.header);
+        field("save", a.save.to_string(), b.save.to_string());
+        field("game", a.game.clone(), b.game.clone());
+        field("build", a.build.clone(), b.build.clone());
+        field("turn", a.turn.to_string(), b.turn.to_string());
+        field(
+            "starting_civ",
+            a.starting_civ.clone(),
+            b.starting_civ.clone(),
+        );
+        field("handicap", a.handicap.clone(), b.handicap.clone());
+        field("era", a.era.clone(), b.era.clone());
+        field("current_era", a.current_era.clone(), b.current_era.clone());
+        field("game_speed", a.game_speed.clone(), b.game_speed.clone());
+        field("world_size", a.world_size.clone(), b.world_size.clone());
+        field("map_script", a.map_script.clone(), b.map_script.clone());
+        // Content is listed by what's only on one side, the full lists are long.
+        let only = |from: &[String], not_in: &[String]| {
+            from.iter()
+                .filter(|item| !not_in.contains(item))
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let dlc = |h: &Header| -> Vec<String> {
+            h.dlc
+                .iter()
+                .filter(|d| d.enabled)
+                .map(|d| d.name.clone())
+                .collect()
+        };
+        let (dlc_a, dlc_b) = (dlc(a), dlc(b));
+        field("dlc", only(&dlc_a, &dlc_b), only(&dlc_b, &dlc_a));
+        let mods = |h: &Header| -> Vec<String> {
+            h.mods
+                .iter()
+                .map(|m| format!("{} v{}", m.name, m.version))
+                .collect()
+        };
+        let (mods_a, mods_b) = (mods(a), mods(b));
+        field("mods", only(&mods_a, &mods_b), only(&mods_b, &mods_a));
+        for slot in 0..self.players.len().max(other.players.len()) {
+            let (a, b) = (self.players.get(slot), other.players.get(slot));
+            let name = |p: Option<&Player>| p.map(|p| p.name.clone()).unwrap_or_default();
+            let typ = |p: Option<&Player>| {
+                p.map(|p| format!("{:?}", p.player_type))
+                    .unwrap_or_default()
+            };
+            field(&format!("player {} name", slot), name(a), name(b));
+            field(&format!("player {} type", slot), typ(a), typ(b));
+        }
+
+        let empty = vec![];
+        let mut chunks = vec![];
+        for id in 0..self.chunks.len().max(other.chunks.len()) {
+            let a = self.chunks.get(id).map(|c| &c.data).unwrap_or(&empty);
+            let b = other.chunks.get(id).map(|c| &c.data).unwrap_or(&empty);
+            // A byte only on one side counts as changed.
+            let changed: Vec<usize> = (0..a.len().max(b.len()))
+                .filter(|&i| a.get(i) != b.get(i))
+                .collect();
+            if let Some(&first_change) = changed.first() {
+                chunks.push(ChunkChange {
+                    id,
+                    size_a: a.len(),
+                    size_b: b.len(),
+                    changed_bytes: changed.len(),
+                    first_change,
+                });
+            }
+        }
+
+        Ok(SaveDiff {
+            fields,
+            chunks,
+            score: self.difference_score(other)?,
+        })
+    }
+
+    /// Every chunk as a hex dump with its offset and size, for working out the format.
+    pub fn hex_dump(&self) -> String {
+        self.chunks
+            .iter()
+            .map(|chunk| {
+                format!(
+                    "Chunk {} offset {} size {}\n{}\n",
+                    chunk.id,
+                    chunk.offset,
+                    chunk.size,
+                    pretty_hex(&chunk.data)
+                )
+            })
+            .collect()
+    }
+}
+
+/// See `Civ5Save::diff()`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveDiff {
+    pub fields: Vec<FieldChange>,
+    /// Only the chunks that changed.
+    pub chunks: Vec<ChunkChange>,
+    /// `difference_score()`, which is what saves are matched to games with.
+    pub score: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub a: String,
+    pub b: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChunkChange {
+    pub id: usize,
+    pub size_a: usize,
+    pub size_b: usize,
+    pub changed_bytes: usize,
+    /// Offset into the chunk of the first byte that differs.
+    pub first_change: usize,
 }
 
 #[cfg(test)]
@@ -311,6 +521,15 @@ mod tests {
         assert_eq!(save.header.turn, 29);
     }
 
+    #[test_env_log::test]
+    fn content() {
+        let save = load("saves/Casimir III_0028 BC-2320.Civ5Save");
+        assert_eq!(save.header.dlc.len(), 12);
+        assert_eq!(save.header.dlc[0].name, "Mongolia");
+        assert!(save.header.dlc[0].enabled);
+        assert!(save.header.mods.is_empty());
+    }
+
     #[test_env_log::test]
     fn same() {
         let save_a = load("saves/Casimir III_0028 BC-2320.Civ5Save".into());
@@ -328,6 +547,39 @@ mod tests {
         assert_eq!(save_b.difference_score(&save_c).unwrap(), 9);
     }
 
+    #[test_env_log::test]
+    fn diff() {
+        let save_a = load("saves/Casimir III_0028 BC-2320.Civ5Save");
+        let save_b = load("saves/Casimir III_0029 BC-2260.Civ5Save");
+        assert!(save_a.diff(&save_a).unwrap().fields.is_empty());
+        assert!(save_a.diff(&save_a).unwrap().chunks.is_empty());
+
+        let diff = save_a.diff(&save_b).unwrap();
+        assert!(diff.fields.contains(&FieldChange {
+            field: "turn".to_string(),
+            a: "28".to_string(),
+            b: "29".to_string(),
+        }));
+        assert!(!diff.chunks.is_empty());
+        assert_eq!(diff.score, 9);
+    }
+
+    #[test_env_log::test]
+    fn clear_passwords() {
+        let path = "saves/Casimir III_0029 BC-2260.Civ5Save";
+        let bytes = std::fs::read(path).unwrap();
+        let cleared = Civ5SaveWriter::new(&bytes).clear_passwords().unwrap();
+        assert!(cleared.len() < bytes.len());
+
+        let save = Civ5SaveReader::new(&cleared).parse().unwrap();
+        assert_eq!(save.header.turn, 29);
+        assert_eq!(save.players.len(), load(path).players.len());
+
+        // Nothing left to clear the second time.
+        let again = Civ5SaveWriter::new(&cleared).clear_passwords().unwrap();
+        assert_eq!(again, cleared);
+    }
+
     #[test_env_log::test]
     fn big_diff() {
         let saves = [
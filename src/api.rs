@@ -1,4 +1,7 @@
+use crate::clock;
+use crate::temp_files::TempFiles;
 use anyhow::{anyhow, Context};
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
 use iced::futures::{Stream, StreamExt};
 use reqwest::multipart::{Form, Part};
 use reqwest::{Body, Method, RequestBuilder, Response};
@@ -8,14 +11,17 @@ use std::convert::{TryFrom, TryInto};
 use std::fmt::{Display, Formatter};
 use std::io::{Bytes, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tempfile::{NamedTempFile, TempPath};
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
-use tracing::{info, instrument, trace, trace_span, Instrument};
+use tracing::{debug, info, instrument, trace, trace_span, Instrument};
 
-#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Ord, PartialOrd)]
+#[derive(
+    Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Hash, Ord, PartialOrd,
+)]
 pub struct UserId(u64);
 
 impl From<u64> for UserId {
@@ -45,7 +51,9 @@ impl Display for GameId {
     }
 }
 
-#[derive(Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize, Hash, Eq)]
+#[derive(
+    Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize, Hash, Eq, Ord, PartialOrd,
+)]
 pub struct TurnId(u64);
 
 impl From<u64> for TurnId {
@@ -76,13 +84,78 @@ pub struct Game {
     pub players: Vec<PlayerOrder>,
     pub current_turn: CurrentTurn,
     #[serde(rename = "Type")]
-    pub typ: u8,
+    pub typ: GameType,
 }
 
 impl Game {
     pub fn is_user_id_turn(&self, user_id: &UserId) -> bool {
         &self.current_turn.user_id == user_id
     }
+
+    /// GMR has no flag for finished games, but a game is over for the user once they've dropped
+    /// out of the player list, e.g. after being defeated.
+    pub fn has_left(&self, user_id: &UserId) -> bool {
+        !self.players.iter().any(|p| &p.user_id == user_id)
+    }
+
+    /// Nobody has a turn. That's usually because the game is over, but it's only a guess, so it's
+    /// for hinting that the game could be archived rather than archiving it.
+    pub fn looks_finished(&self) -> bool {
+        self.current_turn.user_id == UserId::default()
+    }
+}
+
+/// The `Type` field of a game. Only the values seen from GMR so far are named, anything else is
+/// kept as `Other` so it survives a round trip through the db.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum GameType {
+    #[default]
+    Standard,
+    Mod,
+    Scenario,
+    Other(u8),
+}
+
+impl GameType {
+    /// Mods and scenarios need every player to have the same content installed, so it's worth
+    /// warning about before someone plays a turn.
+    pub fn requires_matching_content(&self) -> bool {
+        matches!(self, GameType::Mod | GameType::Scenario)
+    }
+}
+
+impl From<u8> for GameType {
+    fn from(typ: u8) -> Self {
+        match typ {
+            0 => GameType::Standard,
+            1 => GameType::Mod,
+            2 => GameType::Scenario,
+            other => GameType::Other(other),
+        }
+    }
+}
+
+impl From<GameType> for u8 {
+    fn from(typ: GameType) -> Self {
+        match typ {
+            GameType::Standard => 0,
+            GameType::Mod => 1,
+            GameType::Scenario => 2,
+            GameType::Other(other) => other,
+        }
+    }
+}
+
+impl Display for GameType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameType::Standard => write!(f, "Standard"),
+            GameType::Mod => write!(f, "Mod"),
+            GameType::Scenario => write!(f, "Scenario"),
+            GameType::Other(other) => write!(f, "Unknown ({})", other),
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -92,19 +165,184 @@ pub struct PlayerOrder {
     pub turn_order: u16,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct CurrentTurn {
     pub turn_id: TurnId,
     pub number: u64,
     pub user_id: UserId,
-    pub started: String,
-    pub expires: Option<String>,
+    #[serde(with = "gmr_date")]
+    pub started: DateTime<Utc>,
+    #[serde(with = "gmr_date_option")]
+    pub expires: Option<DateTime<Utc>>,
     pub skipped: bool,
     pub player_number: u64,
     pub is_first_turn: bool,
 }
 
+impl Default for CurrentTurn {
+    fn default() -> Self {
+        Self {
+            turn_id: Default::default(),
+            number: 0,
+            user_id: Default::default(),
+            started: Utc.timestamp(0, 0),
+            expires: None,
+            skipped: false,
+            player_number: 0,
+            is_first_turn: false,
+        }
+    }
+}
+
+impl CurrentTurn {
+    /// Time left before the turn expires. None when the game has no turn timer.
+    ///
+    /// This will be negative when the turn has already expired.
+    pub fn time_remaining(&self) -> Option<Duration> {
+        self.time_remaining_at(Utc::now())
+    }
+
+    pub fn time_remaining_at(&self, now: DateTime<Utc>) -> Option<Duration> {
+        self.expires.map(|expires| expires - now)
+    }
+
+    /// How long the current player has been sitting on this turn.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed_at(Utc::now())
+    }
+
+    pub fn elapsed_at(&self, now: DateTime<Utc>) -> Duration {
+        now - self.started
+    }
+}
+
+/// How close a turn is to being skipped, to colour its countdown.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Urgency {
+    /// More than a day left.
+    Relaxed,
+    /// Less than a day.
+    Soon,
+    /// Less than three hours, or already expired.
+    Urgent,
+}
+
+impl Urgency {
+    pub fn of(remaining: Duration) -> Self {
+        if remaining < Duration::hours(3) {
+            Urgency::Urgent
+        } else if remaining < Duration::hours(24) {
+            Urgency::Soon
+        } else {
+            Urgency::Relaxed
+        }
+    }
+}
+
+/// Soonest `expires` first, so the turn closest to being skipped goes first. Turns without a
+/// timer go last, oldest first.
+pub fn sort_by_deadline(games: &mut [Game]) {
+    games.sort_by_key(|game| {
+        let turn = &game.current_turn;
+        (turn.expires.is_none(), turn.expires, turn.started)
+    });
+}
+
+/// Moves a finished download into place. The temp dir can be on a different drive to the save
+/// dir, where a rename won't work, so fall back to copying.
+fn persist(temp_file: NamedTempFile, save_path: &Path) -> anyhow::Result<()> {
+    info!(?save_path, "Saving to disk.");
+    if let Err(err) = temp_file.persist(save_path) {
+        debug!(?err, "Could not move the download, copying instead.");
+        std::fs::copy(err.file.path(), save_path)
+            .with_context(|| format!("Saving download to {:?}", save_path))?;
+    }
+    Ok(())
+}
+
+/// GMR is a .NET service and sends dates in a few formats:
+///  - `2021-10-10T05:04:03.123` with no timezone, which is UTC.
+///  - `2021-10-10T05:04:03.123Z` or with an offset, i.e. RFC 3339.
+///  - `/Date(1633842243123)/` which is milliseconds since the epoch.
+pub fn parse_gmr_date(s: &str) -> anyhow::Result<DateTime<Utc>> {
+    let s = s.trim();
+    if let Some(ms) = s.strip_prefix("/Date(").and_then(|s| s.strip_suffix(")/")) {
+        // There can be a trailing offset, e.g. /Date(1633842243123+0000)/, which we can ignore
+        // because the milliseconds are always UTC. The first character is skipped because it can
+        // be a minus sign.
+        let end = ms
+            .get(1..)
+            .and_then(|rest| rest.find(&['+', '-'][..]))
+            .map(|i| i + 1)
+            .unwrap_or_else(|| ms.len());
+        let ms: i64 = ms[..end]
+            .parse()
+            .with_context(|| format!("Parsing .NET date: {}", s))?;
+        return Utc
+            .timestamp_millis_opt(ms)
+            .single()
+            .with_context(|| format!(".NET date out of range: {}", s));
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+
+    let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+        .with_context(|| format!("Parsing date: {}", s))?;
+    Ok(DateTime::from_utc(naive, Utc))
+}
+
+/// Serde adapter for GMR dates. Dates are written back out as RFC 3339.
+pub mod gmr_date {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(dt: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&dt.to_rfc3339())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        super::parse_gmr_date(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Same as `gmr_date` but for nullable fields.
+pub mod gmr_date_option {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(dt: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match dt {
+            Some(dt) => super::gmr_date::serialize(dt, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => super::parse_gmr_date(&s)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Player {
@@ -118,9 +356,31 @@ pub struct Player {
     pub game_id: GameId,
 }
 
+impl Player {
+    /// Steam's `personastate`, as of when the player was fetched.
+    pub fn online_state(&self) -> &'static str {
+        match self.persona_state {
+            0 => "Offline",
+            1 => "Online",
+            2 => "Busy",
+            3 => "Away",
+            4 => "Snoozing",
+            5 | 6 => "Looking to play",
+            _ => "Unknown",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Percentage(f32);
 
+impl Percentage {
+    /// Between 0.0 and 1.0.
+    pub fn value(&self) -> f32 {
+        self.0
+    }
+}
+
 impl TryFrom<f32> for Percentage {
     type Error = anyhow::Error;
 
@@ -142,31 +402,122 @@ pub struct UploadResponse {
 #[derive(Clone, Debug)]
 pub enum DownloadMessage {
     Error(String),
+    /// Stopped part way because transfers were paused, see `Api::with_pause()`.
+    Paused,
     Started(Option<u64>),
-    Chunk(Option<Percentage>),
+    /// `bytes` is how much has been received so far.
+    Chunk {
+        progress: Option<Percentage>,
+        bytes: u64,
+    },
     Done(PathBuf),
 }
 
 #[derive(Clone, Debug)]
 pub enum UploadMessage {
     Error(String),
+    /// Stopped part way because transfers were paused, see `Api::with_pause()`.
+    Paused,
     Started,
-    Chunk(Option<Percentage>),
+    /// `bytes` is how much has been sent so far.
+    Chunk {
+        progress: Option<Percentage>,
+        bytes: u64,
+    },
     Done,
 }
 
+/// Uploads are sent in pieces this big so progress can be reported and the speed limited.
+const UPLOAD_CHUNK_SIZE: usize = 16 * 1024;
+
+pub const GMR_URL: &str = "http://multiplayerrobot.com";
+
+/// Where a signed in GMR user can find their auth key.
+pub const AUTH_KEY_URL: &str = "http://multiplayerrobot.com/Download";
+
+/// Longer than any real auth key, to catch something else being pasted in.
+const MAX_AUTH_KEY_LEN: usize = 64;
+
+/// Catches obvious mistakes before bothering GMR with them. Real keys are letters and digits.
+pub fn validate_auth_key(auth_key: &str) -> anyhow::Result<()> {
+    if auth_key.is_empty() {
+        return Err(anyhow!("The auth key is empty."));
+    }
+    if auth_key.len() > MAX_AUTH_KEY_LEN || !auth_key.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(anyhow!(
+            "That doesn't look like an auth key, which is only letters and numbers. Try copying \
+             it from GMR again."
+        ));
+    }
+    Ok(())
+}
+
+/// Hides the auth key inside `text`, e.g. an error with a request URL in it, so it doesn't end
+/// up in logs, events or diagnostics.
+pub fn redact_auth_key(text: &str, auth_key: &str) -> String {
+    if auth_key.is_empty() {
+        return text.to_string();
+    }
+    text.replace(auth_key, "(auth key)")
+}
+
+/// GMR's API only covers authenticating, listing games and players, and sending and receiving
+/// saves. Finding, creating and joining games can only be done on the website.
 #[derive(Clone)]
 pub struct Api {
     auth_key: String,
+    base_url: String,
+    download_limit_kib: Option<u32>,
+    upload_limit_kib: Option<u32>,
+    temp_files: Option<TempFiles>,
+    paused: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
 }
 
 impl Api {
     pub fn new(auth_key: &str) -> Self {
         Self {
             auth_key: auth_key.to_owned(),
+            base_url: GMR_URL.to_string(),
+            download_limit_kib: None,
+            upload_limit_kib: None,
+            temp_files: None,
+            paused: None,
         }
     }
 
+    /// Stop transfers between chunks once `paused` returns true. They end with a `Paused`
+    /// message instead of an error, and can be started again later.
+    pub fn with_pause<F>(mut self, paused: F) -> Self
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.paused = Some(Arc::new(paused));
+        self
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.as_ref().map_or(false, |paused| paused())
+    }
+
+    /// Download into tracked temp files instead of the system temp dir.
+    pub fn with_temp_files(mut self, temp_files: TempFiles) -> Self {
+        self.temp_files = Some(temp_files);
+        self
+    }
+
+    /// Talk to something other than GMR, e.g. a mock server in tests.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Limit save transfers to this many KiB per second. None is unlimited.
+    pub fn with_limits(mut self, download_kib: Option<u32>, upload_kib: Option<u32>) -> Self {
+        self.download_limit_kib = download_kib;
+        self.upload_limit_kib = upload_kib;
+        self
+    }
+
     #[instrument(skip(self))]
     fn query(
         &self,
@@ -178,14 +529,19 @@ impl Api {
         let mut query = vec![];
         query.push(("authKey", self.auth_key.as_str()));
         query.extend_from_slice(extra_query);
-        let url = format!("http://multiplayerrobot.com/api/Diplomacy/{}", endpoint);
+        let url = format!("{}/api/Diplomacy/{}", self.base_url, endpoint);
         Ok(client.request(method, url).query(&query))
     }
 
     #[instrument(skip(self))]
     async fn get(&self, endpoint: &str, extra_query: &[(&str, &str)]) -> anyhow::Result<Response> {
         let req = self.query(Method::GET, endpoint, extra_query)?;
-        Ok(req.send().await?)
+        req.send().await.map_err(|err| self.redacted(err))
+    }
+
+    /// Errors from reqwest include the URL, which has the auth key in it.
+    fn redacted(&self, err: reqwest::Error) -> anyhow::Error {
+        anyhow!(redact_auth_key(&err.to_string(), &self.auth_key))
     }
 
     #[instrument(skip(self))]
@@ -195,7 +551,7 @@ impl Api {
         extra_query: &[(&str, &str)],
     ) -> anyhow::Result<String> {
         let response = self.get(endpoint, extra_query).await?;
-        let text = response.text().await?;
+        let text = response.text().await.map_err(|err| self.redacted(err))?;
         trace!("Response: {}", text);
         Ok(text)
     }
@@ -233,15 +589,44 @@ impl Api {
         &self,
         player_ids: &[UserId],
     ) -> anyhow::Result<GetGamesAndPlayers> {
+        Ok(self.get_games_and_players_with_skew(player_ids).await?.0)
+    }
+
+    /// Like `get_games_and_players()`, also returning how far GMR's clock is ahead of ours when
+    /// the response says what time it is. See `clock::skew()`.
+    #[instrument(skip(self))]
+    pub async fn get_games_and_players_with_skew(
+        &self,
+        player_ids: &[UserId],
+    ) -> anyhow::Result<(GetGamesAndPlayers, Option<Duration>)> {
         let player_id_text = player_ids
             .iter()
             .map(|u| format!("{}", u))
             .collect::<Vec<_>>()
             .join("_");
-        self.get_json("GetGamesAndPlayers", &[("playerIDText", &player_id_text)])
-            .await
+        let sent = Utc::now();
+        let response = self
+            .get("GetGamesAndPlayers", &[("playerIDText", &player_id_text)])
+            .await?;
+        let received = Utc::now();
+        let skew = response
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| clock::parse_http_date(date).ok())
+            .map(|server| clock::skew(server, sent, received));
+        let text = response.text().await.map_err(|err| self.redacted(err))?;
+        trace!(?skew, "Response: {}", text);
+        let games = serde_json::from_str(&text)
+            .with_context(|| format!("Endpoint: GetGamesAndPlayers JSON: {}", text))?;
+        Ok((games, skew))
     }
 
+    /// Downloads the save for a game where it's the user's turn.
+    ///
+    /// GMR only serves the latest save to the player whose turn it is, and the save for our turn
+    /// doesn't exist until the player before us has submitted theirs. There's no way to fetch it
+    /// ahead of time for games where our turn is still a player or two away.
     #[instrument(skip(self))]
     pub fn get_latest_save_file_bytes(
         &self,
@@ -267,79 +652,347 @@ impl Api {
         game_id: GameId,
         save_path: PathBuf,
     ) {
+        if let Err(err) = self.download_to_path(&tx, game_id, save_path).await {
+            let message = if self.is_paused() {
+                DownloadMessage::Paused
+            } else {
+                DownloadMessage::Error(format!("{:#}", err))
+            };
+            // If this fails the receiver is gone and nobody is listening anyway.
+            let _ = tx.send(message).await;
+        }
+    }
+
+    async fn download_to_path(
+        &self,
+        tx: &mpsc::Sender<DownloadMessage>,
+        game_id: GameId,
+        save_path: PathBuf,
+    ) -> anyhow::Result<()> {
         let response = self
             .get(
                 "GetLatestSaveFileBytes",
                 &[("gameId", &format!("{}", game_id))],
             )
-            .await
-            .unwrap(); // TODO: unwrap
+            .await?
+            .error_for_status()
+            .map_err(|err| self.redacted(err))?;
         let size = response.content_length();
         trace!(?size);
-        tx.send(DownloadMessage::Started(size)).await.unwrap();
+        tx.send(DownloadMessage::Started(size)).await?;
+
+        let mut temp_file = match &self.temp_files {
+            Some(temp_files) => temp_files.create("download-")?,
+            None => NamedTempFile::new().context("Creating temp file for download.")?,
+        };
+        let temp_path = temp_file.path().to_path_buf();
+        let result = match self.stream_download(tx, response, &mut temp_file).await {
+            Ok(()) => persist(temp_file, &save_path),
+            Err(err) => Err(err),
+        };
+        if let Some(temp_files) = &self.temp_files {
+            temp_files.forget(&temp_path)?;
+        }
+        result?;
+        tx.send(DownloadMessage::Done(save_path)).await?;
+        trace!("Done.");
+        Ok(())
+    }
 
+    async fn stream_download(
+        &self,
+        tx: &mpsc::Sender<DownloadMessage>,
+        response: Response,
+        temp_file: &mut NamedTempFile,
+    ) -> anyhow::Result<()> {
+        let size = response.content_length();
         let mut stream = response.bytes_stream();
-        let mut temp_file = NamedTempFile::new().unwrap(); // TODO: unwrap
         let mut downloaded = 0;
+        let started = tokio::time::Instant::now();
         while let Some(bytes) = stream.next().await {
-            let bytes = bytes.unwrap();
+            if self.is_paused() {
+                return Err(anyhow!("Paused."));
+            }
+            let bytes = bytes
+                .map_err(|err| self.redacted(err))
+                .context("Reading download.")?;
             downloaded += bytes.len();
-            temp_file.write_all(&bytes).unwrap(); // TODO: lots of unwrap
-            let percentage = size.map(|size| (downloaded as f32 / size as f32).try_into().unwrap()); // TODO: unwrap
-            tx.send(DownloadMessage::Chunk(percentage)).await.unwrap();
+            temp_file.write_all(&bytes)?;
+            if let Some(limit) = self.download_limit_kib {
+                tokio::time::sleep(throttle_delay(limit, downloaded, started.elapsed())).await;
+            }
+            // The content length can be wrong, so ignore any percentage that doesn't make sense.
+            let progress = size.and_then(|size| (downloaded as f32 / size as f32).try_into().ok());
+            tx.send(DownloadMessage::Chunk {
+                progress,
+                bytes: downloaded as u64,
+            })
+            .await?;
         }
-        info!(?save_path, "Saving to disk.");
-        temp_file.persist(&save_path).unwrap(); // TODO: unwrap
-        tx.send(DownloadMessage::Done(save_path)).await.unwrap();
-        trace!("Done.");
+        Ok(())
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self, bytes))]
     pub fn upload_save_client(
         &self,
         turn_id: TurnId,
         bytes: Vec<u8>,
-    ) -> anyhow::Result<(mpsc::Receiver<UploadMessage>)> {
+    ) -> anyhow::Result<mpsc::Receiver<UploadMessage>> {
         let (tx, rx) = mpsc::channel(32);
 
         let s = self.clone();
         tokio::spawn(async move {
-            trace!("Starting upload.");
-            tx.send(UploadMessage::Started).await?;
-
-            let auth_key = s.auth_key.clone();
-            let form = Form::new()
-                .part("turnId", text_part(format!("{}", turn_id)))
-                .part("isCompressed", text_part("False".into()))
-                .part("authKey", text_part(auth_key))
-                .part(
-                    "saveFileUpload",
-                    Part::bytes(bytes).file_name(format!("{}.Civ5Save", turn_id)),
-                );
-
-            let url = "http://multiplayerrobot.com/Game/UploadSaveClient";
-            let response = reqwest::Client::new()
-                .post(url)
-                .multipart(form)
-                .send()
-                .await?;
-            trace!("Upload done.");
-
-            let text = response.text().await?;
-            let resp: UploadResponse = serde_json::from_str(&text)?;
-            trace!(?resp);
-            if resp.result_type == 0 {
-                return Err(anyhow!("Response returned 0 for an unknown reason."));
+            if let Err(err) = s.upload_save_client_async(&tx, turn_id, bytes).await {
+                let message = if s.is_paused() {
+                    UploadMessage::Paused
+                } else {
+                    UploadMessage::Error(format!("{:#}", err))
+                };
+                // If this fails the receiver is gone and nobody is listening anyway.
+                let _ = tx.send(message).await;
             }
-
-            tx.send(UploadMessage::Done).await?;
-            Ok(())
         });
 
         Ok(rx)
     }
+
+    #[instrument(skip(self, tx, bytes))]
+    async fn upload_save_client_async(
+        &self,
+        tx: &mpsc::Sender<UploadMessage>,
+        turn_id: TurnId,
+        bytes: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        trace!("Starting upload.");
+        tx.send(UploadMessage::Started).await?;
+
+        let auth_key = self.auth_key.clone();
+        let total = bytes.len();
+        let form = Form::new()
+            .part("turnId", text_part(format!("{}", turn_id)))
+            .part("isCompressed", text_part("False".into()))
+            .part("authKey", text_part(auth_key))
+            .part(
+                "saveFileUpload",
+                Part::stream_with_length(self.upload_body(tx.clone(), bytes), total as u64)
+                    .file_name(format!("{}.Civ5Save", turn_id)),
+            );
+
+        let url = format!("{}/Game/UploadSaveClient", self.base_url);
+        let response = reqwest::Client::new()
+            .post(url)
+            .multipart(form)
+            .send()
+            .await?;
+        trace!("Upload done.");
+
+        let text = response.text().await.map_err(|err| self.redacted(err))?;
+        let resp: UploadResponse =
+            serde_json::from_str(&text).with_context(|| format!("Upload response: {}", text))?;
+        trace!(?resp);
+        if resp.result_type == 0 {
+            return Err(anyhow!("Response returned 0 for an unknown reason."));
+        }
+
+        tx.send(UploadMessage::Done).await?;
+        Ok(())
+    }
+}
+
+impl Api {
+    /// Streams `bytes` in chunks, sending progress to `tx` and keeping under the upload limit.
+    fn upload_body(&self, tx: mpsc::Sender<UploadMessage>, bytes: Vec<u8>) -> Body {
+        let total = bytes.len();
+        let chunks: Vec<Vec<u8>> = bytes
+            .chunks(UPLOAD_CHUNK_SIZE)
+            .map(<[u8]>::to_vec)
+            .collect();
+        let limit = self.upload_limit_kib;
+        let started = tokio::time::Instant::now();
+        let api = self.clone();
+        let stream =
+            iced::futures::stream::unfold((chunks.into_iter(), 0), move |(mut chunks, sent)| {
+                let tx = tx.clone();
+                let paused = api.is_paused();
+                async move {
+                    let chunk = chunks.next()?;
+                    if paused {
+                        // Failing the body abandons the request, so GMR never sees a partial save.
+                        let err = std::io::Error::new(std::io::ErrorKind::Interrupted, "Paused.");
+                        return Some((Err(err), (chunks, sent)));
+                    }
+                    let sent = sent + chunk.len();
+                    if let Some(limit) = limit {
+                        tokio::time::sleep(throttle_delay(limit, sent, started.elapsed())).await;
+                    }
+                    let progress = (sent as f32 / total as f32).try_into().ok();
+                    // Progress is only informational, so it's fine to drop it if the channel is full.
+                    let _ = tx.try_send(UploadMessage::Chunk {
+                        progress,
+                        bytes: sent as u64,
+                    });
+                    Some((Ok::<_, std::io::Error>(chunk), (chunks, sent)))
+                }
+            });
+        Body::wrap_stream(stream)
+    }
+}
+
+/// How long to wait so that `bytes` sent over `elapsed` stays under `limit_kib` KiB per second.
+fn throttle_delay(
+    limit_kib: u32,
+    bytes: usize,
+    elapsed: std::time::Duration,
+) -> std::time::Duration {
+    let allowed = std::time::Duration::from_secs_f64(bytes as f64 / (limit_kib as f64 * 1024.0));
+    allowed.saturating_sub(elapsed)
 }
 
 fn text_part(s: String) -> Part {
     Part::text(s).mime_str("text/plain; charset=utf-8").unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_keys() {
+        assert!(validate_auth_key("ABC123DEF456").is_ok());
+        assert!(validate_auth_key("").is_err());
+        assert!(validate_auth_key("ABC 123").is_err());
+        assert!(validate_auth_key("http://multiplayerrobot.com/Download").is_err());
+        assert_eq!(
+            redact_auth_key("url (http://x/?authKey=ABC123)", "ABC123"),
+            "url (http://x/?authKey=(auth key))"
+        );
+        assert_eq!(redact_auth_key("nothing", ""), "nothing");
+    }
+
+    #[test]
+    fn finished_games() {
+        let me = UserId::from(1);
+        let mut game = Game {
+            players: vec![PlayerOrder {
+                user_id: me,
+                turn_order: 0,
+            }],
+            current_turn: CurrentTurn {
+                user_id: UserId::from(2),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(!game.has_left(&me));
+        assert!(!game.looks_finished());
+        game.current_turn.user_id = UserId::default();
+        assert!(!game.has_left(&me));
+        assert!(game.looks_finished());
+        game.current_turn.user_id = me;
+        game.players.clear();
+        assert!(game.has_left(&me));
+    }
+
+    #[test]
+    fn deadline_order() {
+        let game = |id: u32, started: i64, expires: Option<i64>| Game {
+            game_id: GameId::from(id),
+            current_turn: CurrentTurn {
+                started: Utc.timestamp(started, 0),
+                expires: expires.map(|e| Utc.timestamp(e, 0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut games = vec![
+            game(1, 200, None),
+            game(2, 0, Some(5000)),
+            game(3, 100, None),
+            game(4, 0, Some(1000)),
+        ];
+        sort_by_deadline(&mut games);
+        let order: Vec<_> = games.iter().map(|g| g.game_id).collect();
+        assert_eq!(
+            order,
+            vec![
+                GameId::from(4),
+                GameId::from(2),
+                GameId::from(3),
+                GameId::from(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn urgency() {
+        assert_eq!(Urgency::of(Duration::hours(30)), Urgency::Relaxed);
+        assert_eq!(Urgency::of(Duration::hours(24)), Urgency::Relaxed);
+        assert_eq!(Urgency::of(Duration::hours(23)), Urgency::Soon);
+        assert_eq!(Urgency::of(Duration::minutes(179)), Urgency::Urgent);
+        assert_eq!(Urgency::of(Duration::hours(-1)), Urgency::Urgent);
+    }
+
+    #[test]
+    fn throttle() {
+        let second = std::time::Duration::from_secs(1);
+        assert_eq!(throttle_delay(100, 200 * 1024, second), second);
+        assert_eq!(
+            throttle_delay(100, 200 * 1024, second * 3),
+            std::time::Duration::ZERO
+        );
+        assert_eq!(
+            throttle_delay(1, 0, std::time::Duration::ZERO),
+            std::time::Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn gmr_dates() {
+        let expected = Utc.ymd(2021, 10, 10).and_hms_milli(5, 4, 3, 123);
+        assert_eq!(parse_gmr_date("2021-10-10T05:04:03.123").unwrap(), expected);
+        assert_eq!(
+            parse_gmr_date("2021-10-10T05:04:03.123Z").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_gmr_date("2021-10-10T15:04:03.123+10:00").unwrap(),
+            expected
+        );
+        assert_eq!(parse_gmr_date("/Date(1633842243123)/").unwrap(), expected);
+        assert_eq!(
+            parse_gmr_date("/Date(1633842243123+0000)/").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_gmr_date("2021-10-10T05:04:03").unwrap(),
+            Utc.ymd(2021, 10, 10).and_hms(5, 4, 3)
+        );
+        assert!(parse_gmr_date("yesterday").is_err());
+        assert!(parse_gmr_date("/Date()/").is_err());
+        assert!(parse_gmr_date("/Date(9223372036854775807)/").is_err());
+    }
+
+    #[test]
+    fn current_turn_round_trip() {
+        let json = r#"{"TurnId":1,"Number":2,"UserId":3,"Started":"2021-10-10T05:04:03.123",
+            "Expires":null,"Skipped":false,"PlayerNumber":0,"IsFirstTurn":false}"#;
+        let turn: CurrentTurn = serde_json::from_str(json).unwrap();
+        assert_eq!(turn.expires, None);
+        assert!(turn.time_remaining().is_none());
+
+        let encoded = serde_json::to_string(&turn).unwrap();
+        let decoded: CurrentTurn = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(turn, decoded);
+    }
+
+    #[test]
+    fn game_type() {
+        for typ in 0..=255u8 {
+            let game_type = GameType::from(typ);
+            assert_eq!(u8::from(game_type), typ);
+        }
+        let game_type: GameType = serde_json::from_str("7").unwrap();
+        assert_eq!(game_type, GameType::Other(7));
+        assert_eq!(serde_json::to_string(&game_type).unwrap(), "7");
+    }
+}
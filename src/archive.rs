@@ -0,0 +1,329 @@
+use crate::api::{GameId, TurnId};
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{debug, instrument};
+
+type Result<T> = anyhow::Result<T>;
+
+pub const ARCHIVE_DIR_NAME: &str = "civfun Archive";
+const KEY_PREFIX: &str = "archive-";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveKind {
+    /// The save we downloaded from GMR to play.
+    Downloaded,
+    /// The save the user made after playing, which was sent to GMR.
+    Uploaded,
+}
+
+impl ArchiveKind {
+    pub(crate) fn tag(&self) -> &'static str {
+        match self {
+            ArchiveKind::Downloaded => "dn",
+            ArchiveKind::Uploaded => "up",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub game_id: GameId,
+    pub turn_id: TurnId,
+    pub kind: ArchiveKind,
+    pub original_name: String,
+    pub path: PathBuf,
+    pub archived_at: DateTime<Utc>,
+}
+
+/// Moves old saves into a `civfun Archive` folder inside the hotseat directory, so Civ V doesn't
+/// list them.
+///
+/// Files are named `[game_id]_[turn_id]_[dn|up]_[original name]` and every move is recorded in
+/// sled so the entries can be listed per game.
+pub struct Archive {
+    db: sled::Tree,
+    dir: PathBuf,
+}
+
+impl Archive {
+    pub fn new(db: sled::Tree, save_dir: &Path) -> Self {
+        Self {
+            db,
+            dir: save_dir.join(ARCHIVE_DIR_NAME),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Moves `path` into the archive. Returns None if the file doesn't exist any more.
+    #[instrument(skip(self))]
+    pub fn add(
+        &self,
+        path: &Path,
+        game_id: &GameId,
+        turn_id: &TurnId,
+        kind: ArchiveKind,
+    ) -> Result<Option<ArchiveEntry>> {
+        if !path.exists() {
+            debug!("Nothing to archive.");
+            return Ok(None);
+        }
+
+        std::fs::create_dir_all(&self.dir).context("Creating archive dir.")?;
+        let original_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("No filename for {:?}", path))?
+            .to_string_lossy()
+            .to_string();
+        let name = format!("{}_{}_{}_{}", game_id, turn_id, kind.tag(), original_name);
+        let dest = unique_path(&self.dir.join(name));
+        debug!(?dest, "Archiving.");
+        move_file(path, &dest)?;
+
+        let archived_at = Utc::now();
+        let key = format!(
+            "{}{}-{}",
+            Self::key_prefix(game_id),
+            archived_at.timestamp_nanos(),
+            kind.tag()
+        );
+        let entry = ArchiveEntry {
+            game_id: *game_id,
+            turn_id: *turn_id,
+            kind,
+            original_name,
+            path: dest,
+            archived_at,
+        };
+        self.db.insert(key, serde_json::to_vec(&entry)?)?;
+        Ok(Some(entry))
+    }
+
+    /// Oldest first.
+    pub fn entries(&self, game_id: &GameId) -> Result<Vec<ArchiveEntry>> {
+        let mut entries: Vec<ArchiveEntry> = self
+            .scan(&Self::key_prefix(game_id))?
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .collect();
+        entries.sort_by_key(|e| e.archived_at);
+        Ok(entries)
+    }
+
+    /// Entries for every game, in no particular order.
+    pub fn all_entries(&self) -> Result<Vec<ArchiveEntry>> {
+        Ok(self
+            .scan(KEY_PREFIX)?
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .collect())
+    }
+
+    /// Copies an archived save back into `save_dir` under its original name, e.g. to replay a
+    /// turn. The archived copy stays where it is. Returns where the save went.
+    #[instrument(skip(self))]
+    pub fn restore(&self, entry: &ArchiveEntry, save_dir: &Path) -> Result<PathBuf> {
+        if !entry.path.exists() {
+            return Err(anyhow!("{:?} isn't in the archive any more.", entry.path));
+        }
+        let dest = unique_path(&save_dir.join(&entry.original_name));
+        debug!(?dest, "Restoring.");
+        std::fs::copy(&entry.path, &dest)
+            .with_context(|| format!("Copying {:?} to {:?}", entry.path, dest))?;
+        Ok(dest)
+    }
+
+    /// Deletes the archived files for a turn. Returns how many bytes were freed.
+    #[instrument(skip(self))]
+    pub fn remove_turn(&self, game_id: &GameId, turn_id: &TurnId) -> Result<u64> {
+        let mut freed = 0;
+        for (key, entry) in self.scan(&Self::key_prefix(game_id))? {
+            if &entry.turn_id != turn_id {
+                continue;
+            }
+            if let Ok(metadata) = std::fs::metadata(&entry.path) {
+                freed += metadata.len();
+                std::fs::remove_file(&entry.path)
+                    .with_context(|| format!("Removing {:?}", entry.path))?;
+            }
+            debug!(path = ?entry.path, "Removed from archive.");
+            self.db.remove(key)?;
+        }
+        Ok(freed)
+    }
+
+    /// Moves saves archived in another folder into this one, e.g. ones archived next to the save
+    /// dir before switching to portable mode. Returns how many were moved.
+    #[instrument(skip(self))]
+    pub fn gather(&self) -> Result<usize> {
+        let mut moved = 0;
+        for (key, mut entry) in self.scan(KEY_PREFIX)? {
+            if entry.path.parent() == Some(self.dir.as_path()) || !entry.path.exists() {
+                continue;
+            }
+            std::fs::create_dir_all(&self.dir).context("Creating archive dir.")?;
+            let name = entry
+                .path
+                .file_name()
+                .ok_or_else(|| anyhow!("No filename for {:?}", entry.path))?;
+            let dest = unique_path(&self.dir.join(name));
+            move_file(&entry.path, &dest)?;
+            debug!(from = ?entry.path, to = ?dest, "Moved into archive.");
+            entry.path = dest;
+            self.db.insert(key, serde_json::to_vec(&entry)?)?;
+            moved += 1;
+        }
+        Ok(moved)
+    }
+
+    fn scan(&self, prefix: &str) -> Result<Vec<(sled::IVec, ArchiveEntry)>> {
+        let mut entries = vec![];
+        for item in self.db.scan_prefix(prefix) {
+            let (key, value) = item?;
+            let mut entry: ArchiveEntry =
+                serde_json::from_slice(&value).context("Decoding archive entry.")?;
+            // A portable archive can turn up under a different drive letter.
+            if !entry.path.exists() {
+                if let Some(moved) = entry.path.file_name().map(|n| self.dir.join(n)) {
+                    if moved.exists() {
+                        entry.path = moved;
+                    }
+                }
+            }
+            entries.push((key, entry));
+        }
+        Ok(entries)
+    }
+
+    fn key_prefix(game_id: &GameId) -> String {
+        format!("{}{}-", KEY_PREFIX, game_id)
+    }
+}
+
+/// Renames, or copies and removes when `to` is on another drive, e.g. a portable archive on a USB
+/// stick.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if let Err(err) = std::fs::rename(from, to) {
+        debug!(?err, "Could not rename, copying instead.");
+        std::fs::copy(from, to).with_context(|| format!("Copying {:?} to {:?}", from, to))?;
+        std::fs::remove_file(from).with_context(|| format!("Removing {:?}", from))?;
+    }
+    Ok(())
+}
+
+/// Appends ` (2)`, ` (3)`, etc. to the file stem until the path doesn't exist.
+pub(crate) fn unique_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path.extension().map(|s| s.to_string_lossy().to_string());
+    let mut n = 2;
+    loop {
+        let name = match &extension {
+            Some(extension) => format!("{} ({}).{}", stem, n, extension),
+            None => format!("{} ({})", stem, n),
+        };
+        let candidate = path.with_file_name(name);
+        if !candidate.exists() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let archive = Archive::new(db.open_tree("archive").unwrap(), dir.path());
+        let save = dir.path().join("Casimir III_0028 BC-2320.Civ5Save");
+        std::fs::write(&save, b"turn 28").unwrap();
+        let entry = archive
+            .add(
+                &save,
+                &GameId::from(1),
+                &TurnId::from(2),
+                ArchiveKind::Uploaded,
+            )
+            .unwrap()
+            .unwrap();
+        assert!(!save.exists());
+
+        assert_eq!(archive.restore(&entry, dir.path()).unwrap(), save);
+        assert_eq!(std::fs::read(&save).unwrap(), b"turn 28");
+        assert!(entry.path.exists());
+
+        // The restored save is left alone.
+        let again = archive.restore(&entry, dir.path()).unwrap();
+        assert_eq!(
+            again,
+            dir.path().join("Casimir III_0028 BC-2320 (2).Civ5Save")
+        );
+
+        std::fs::remove_file(&entry.path).unwrap();
+        assert!(archive.restore(&entry, dir.path()).is_err());
+    }
+
+    #[test]
+    fn gather() {
+        let save_dir = tempfile::tempdir().unwrap();
+        let data_dir = tempfile::tempdir().unwrap();
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let tree = db.open_tree("archive").unwrap();
+        let save = save_dir.path().join("Casimir III_0028 BC-2320.Civ5Save");
+        std::fs::write(&save, b"turn 28").unwrap();
+        let old = Archive::new(tree.clone(), save_dir.path())
+            .add(
+                &save,
+                &GameId::from(1),
+                &TurnId::from(2),
+                ArchiveKind::Uploaded,
+            )
+            .unwrap()
+            .unwrap();
+
+        let portable = Archive::new(tree, data_dir.path());
+        assert_eq!(portable.gather().unwrap(), 1);
+        assert!(!old.path.exists());
+        let entries = portable.entries(&GameId::from(1)).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path.parent(), Some(portable.dir()));
+        assert_eq!(
+            portable.restore(&entries[0], save_dir.path()).unwrap(),
+            save
+        );
+        assert_eq!(portable.gather().unwrap(), 0);
+    }
+
+    #[test]
+    fn collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("1_2_up_Casimir III_0028 BC-2320.Civ5Save");
+        assert_eq!(unique_path(&path), path);
+
+        std::fs::write(&path, b"").unwrap();
+        let second = dir
+            .path()
+            .join("1_2_up_Casimir III_0028 BC-2320 (2).Civ5Save");
+        assert_eq!(unique_path(&path), second);
+
+        std::fs::write(&second, b"").unwrap();
+        assert_eq!(
+            unique_path(&path),
+            dir.path()
+                .join("1_2_up_Casimir III_0028 BC-2320 (3).Civ5Save")
+        );
+    }
+}
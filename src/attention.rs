@@ -0,0 +1,74 @@
+use tracing::{debug, warn};
+
+/// Asks the OS to draw the user's attention to the window titled `title`, which flashes it in
+/// the taskbar on Windows and bounces the dock icon on macOS. Best used when the window isn't
+/// focused, since some desktops ignore it otherwise.
+///
+/// iced doesn't hand out its window, so on Windows it's found by its title. Other desktops are
+/// asked through `wmctrl`, which does nothing when it isn't installed.
+pub fn request(title: &str) {
+    debug!(title, "Requesting attention.");
+    if let Err(err) = platform::request(title) {
+        warn!(?err, "Requesting attention.");
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use anyhow::anyhow;
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::winuser::{
+        FindWindowW, FlashWindowEx, FLASHWINFO, FLASHW_ALL, FLASHW_TIMERNOFG,
+    };
+
+    pub fn request(title: &str) -> anyhow::Result<()> {
+        let title: Vec<u16> = OsStr::new(title).encode_wide().chain(Some(0)).collect();
+        unsafe {
+            let hwnd = FindWindowW(std::ptr::null(), title.as_ptr());
+            if hwnd.is_null() {
+                return Err(anyhow!("Couldn't find the window."));
+            }
+            // Flashes until the window is brought to the front.
+            let mut info = FLASHWINFO {
+                cbSize: std::mem::size_of::<FLASHWINFO>() as u32,
+                hwnd,
+                dwFlags: FLASHW_ALL | FLASHW_TIMERNOFG,
+                uCount: 0,
+                dwTimeout: 0,
+            };
+            FlashWindowEx(&mut info);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use cocoa::appkit::{NSApp, NSApplication, NSRequestUserAttentionType};
+
+    /// There's only the one window, so the whole app asks.
+    pub fn request(_title: &str) -> anyhow::Result<()> {
+        unsafe {
+            NSApp().requestUserAttention_(NSRequestUserAttentionType::NSInformationalRequest);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+mod platform {
+    use std::process::Command;
+
+    pub fn request(title: &str) -> anyhow::Result<()> {
+        match Command::new("wmctrl")
+            .args(["-r", title, "-b", "add,demands_attention"])
+            .status()
+        {
+            Ok(_) => Ok(()),
+            // Not installed, which is fine.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use crate::poll;
+use std::time::{Duration, Instant};
+
+/// Failures in a row before a subsystem is paused.
+pub const FAILURE_THRESHOLD: u32 = 3;
+
+/// The first pause. It doubles with each failure after that, like polling backoff.
+pub const PAUSE: Duration = Duration::from_secs(5 * 60);
+
+/// Stops calling a subsystem for a while once it keeps failing, instead of failing on every
+/// `process()`.
+#[derive(Debug, Default)]
+pub struct Breaker {
+    failures: u32,
+    paused_until: Option<Instant>,
+}
+
+impl Breaker {
+    pub fn is_paused(&self, now: Instant) -> bool {
+        matches!(self.paused_until, Some(until) if now < until)
+    }
+
+    pub fn success(&mut self) {
+        self.failures = 0;
+        self.paused_until = None;
+    }
+
+    /// Returns whether the subsystem is now paused.
+    pub fn failure(&mut self, now: Instant) -> bool {
+        self.failures += 1;
+        if self.failures < FAILURE_THRESHOLD {
+            return false;
+        }
+        let pause = poll::delay(PAUSE, self.failures - FAILURE_THRESHOLD);
+        self.paused_until = Some(now + pause);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trips_and_resets() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+        assert!(!breaker.failure(now));
+        assert!(!breaker.failure(now));
+        assert!(!breaker.is_paused(now));
+
+        assert!(breaker.failure(now));
+        assert!(breaker.is_paused(now));
+        assert!(!breaker.is_paused(now + PAUSE));
+
+        // Failing again straight after the pause waits twice as long.
+        assert!(breaker.failure(now + PAUSE));
+        assert!(breaker.is_paused(now + PAUSE * 2));
+        assert!(!breaker.is_paused(now + PAUSE * 3));
+
+        breaker.success();
+        assert!(!breaker.is_paused(now));
+        assert!(!breaker.failure(now));
+    }
+}
@@ -0,0 +1,952 @@
+//! Subcommands for using civfun from a terminal or a script, without the window.
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Local, Utc};
+use clap::{App, ArgEnum, ArgSettings, Clap, IntoApp};
+use clap_generate::generators::{Bash, Fish, PowerShell, Zsh};
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+use civ5save::{Civ5Save, Civ5SaveReader};
+use civfun_gmr::api::{self, DownloadMessage, Game, GameId, Percentage, UserId};
+use civfun_gmr::event_log::{self, ActivityKind};
+use civfun_gmr::instance;
+use civfun_gmr::manager::{DroppedSave, Event, Manager, Status, TransferState, PROCESS_INTERVAL};
+use civfun_gmr::progress::{format_bytes, TransferProgress};
+use civfun_gmr::watcher;
+use tracing::{info, trace, warn};
+
+/// How often transfers are checked on while waiting for one.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Characters in a full progress bar.
+const BAR_WIDTH: usize = 30;
+
+#[derive(Clap)]
+pub enum SubCommand {
+    // Login(LoginOpts),
+    /// Lists your games, soonest deadline first.
+    List(ListOpts),
+    /// Downloads the save for a game where it's your turn.
+    Download(DownloadOpts),
+    /// Uploads a save as your turn. Exits with 2 when the save is rejected, 3 when it isn't clear
+    /// which game it's for and 4 when the upload fails.
+    Submit(SubmitOpts),
+    /// Runs without a window until Ctrl+C or SIGTERM: polls for turns, downloads them, watches
+    /// the save folder and uploads played turns. Everything it does is logged to stderr.
+    Watch,
+    /// Shows what's in a save: its header, players, DLC and mods.
+    Analyze(AnalyzeOpts),
+    /// Shows how two saves differ, to see why one was or wasn't matched to a game.
+    Diff(DiffOpts),
+    /// Shows transfers, when games were last updated, whether saves are being picked up and any
+    /// saves waiting for a game to be picked. Asks civfun if it's running.
+    Status(StatusOpts),
+    /// Shows or changes settings, by the names in `civfun config list`.
+    Config(ConfigOpts),
+    /// Prints a completion script for a shell, e.g. `civfun completions bash >
+    /// /etc/bash_completion.d/civfun`.
+    Completions(CompletionsOpts),
+}
+
+#[derive(Clap)]
+pub struct ListOpts {
+    /// Print JSON instead of a table, for scripts.
+    #[clap(long)]
+    json: bool,
+    /// Use the games as of the app's last refresh instead of asking GMR.
+    #[clap(long)]
+    cached: bool,
+}
+
+#[derive(Clap)]
+pub struct DownloadOpts {
+    /// The game's id, or enough of its name to tell it apart.
+    game: String,
+    /// Where to put the save instead of the save folder. The app won't know about this copy. A
+    /// folder gets the save under its usual name.
+    #[clap(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Clap)]
+pub struct SubmitOpts {
+    /// The .Civ5Save to upload. It's left where it is.
+    path: PathBuf,
+    /// The game's id or name, when the save can't be matched to one.
+    #[clap(long)]
+    game: Option<String>,
+}
+
+#[derive(Clap)]
+pub struct AnalyzeOpts {
+    path: PathBuf,
+    /// Print JSON instead of text, for scripts.
+    #[clap(long, conflicts_with = "hex")]
+    json: bool,
+    /// Also dump every chunk of the save as hex.
+    #[clap(long)]
+    hex: bool,
+}
+
+#[derive(Clap)]
+pub struct DiffOpts {
+    a: PathBuf,
+    b: PathBuf,
+    /// Print JSON instead of text, for scripts.
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Clap)]
+pub struct StatusOpts {
+    /// Print JSON instead of text, for scripts.
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Clap)]
+pub struct ConfigOpts {
+    #[clap(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(Clap)]
+pub enum ConfigAction {
+    /// Shows every setting.
+    List,
+    /// Shows one setting.
+    Get { key: String },
+    /// Changes a setting. The value is JSON, e.g. `120`, `true`, `null` or `["-dx11"]`, but
+    /// text like a path doesn't need quotes.
+    Set { key: String, value: String },
+}
+
+#[derive(Clap)]
+pub struct CompletionsOpts {
+    #[clap(arg_enum)]
+    shell: Shell,
+}
+
+#[derive(ArgEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    #[clap(name = "powershell")]
+    PowerShell,
+}
+
+/// Why a save given to `submit` wasn't sent, so scripts can tell from the exit code.
+#[derive(Debug)]
+pub enum SubmitError {
+    /// Not a save, not your turn, already sent, or the wrong setup for a first turn.
+    Rejected(String),
+    /// Could be for any of these games.
+    Unclear(Vec<Game>),
+    /// GMR didn't take it.
+    UploadFailed(String),
+}
+
+impl SubmitError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SubmitError::Rejected(_) => 2,
+            SubmitError::Unclear(_) => 3,
+            SubmitError::UploadFailed(_) => 4,
+        }
+    }
+}
+
+impl Display for SubmitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmitError::Rejected(message) => write!(f, "Rejected: {}", message),
+            SubmitError::Unclear(games) => write!(
+                f,
+                "Couldn't tell which game this save is for. It could be {}. Pick one with --game.",
+                games
+                    .iter()
+                    .map(|g| format!("{} ({})", g.name, g.game_id))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            SubmitError::UploadFailed(message) => write!(f, "Upload failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+/// The exit code for an error from `run()`.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    match err.downcast_ref::<SubmitError>() {
+        Some(err) => err.exit_code(),
+        None => 1,
+    }
+}
+
+pub fn run(manager: &Manager, cmd: SubCommand) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Runtime::new().context("Starting the runtime.")?;
+    match cmd {
+        SubCommand::List(opts) => runtime.block_on(list(manager, opts)),
+        SubCommand::Download(opts) => runtime.block_on(download(manager, opts)),
+        SubCommand::Submit(opts) => runtime.block_on(submit(manager, opts)),
+        SubCommand::Watch => runtime.block_on(watch(manager)),
+        SubCommand::Analyze(opts) => analyze(opts),
+        SubCommand::Diff(opts) => diff(opts),
+        SubCommand::Config(opts) => config(manager, opts),
+        SubCommand::Completions(opts) => completions(opts),
+        SubCommand::Status(opts) => {
+            manager.fill_transfer_states()?;
+            print_status(&manager.status()?, false, opts)
+        }
+    }
+}
+
+/// One row of `list`. The JSON form is meant to be stable enough for scripts.
+#[derive(Serialize)]
+struct ListedGame {
+    game_id: GameId,
+    name: String,
+    turn: u64,
+    /// Whose turn it is.
+    user_id: UserId,
+    /// Their Steam name, when the app has fetched it.
+    player: Option<String>,
+    my_turn: bool,
+    started: DateTime<Utc>,
+    /// None when the game has no turn timer.
+    expires: Option<DateTime<Utc>>,
+}
+
+async fn list(manager: &Manager, opts: ListOpts) -> anyhow::Result<()> {
+    let me = manager
+        .user_id()?
+        .context("Not signed in. Open civfun and enter your auth key first.")?;
+    let mut games = if opts.cached {
+        manager.games()?
+    } else {
+        manager
+            .fetch_games_now()
+            .await
+            .context("Fetching games from GMR.")?
+    };
+    api::sort_by_deadline(&mut games);
+    let listed = games
+        .iter()
+        .map(|game| listed_game(manager, game, &me))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&listed)?);
+        return Ok(());
+    }
+    if listed.is_empty() {
+        println!("No games.");
+        return Ok(());
+    }
+    let rows: Vec<[String; 4]> = listed
+        .iter()
+        .map(|game| {
+            [
+                game.name.clone(),
+                game.turn.to_string(),
+                if game.my_turn {
+                    "You".to_string()
+                } else {
+                    game.player
+                        .clone()
+                        .unwrap_or_else(|| game.user_id.to_string())
+                },
+                match game.expires {
+                    Some(expires) => expires
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string(),
+                    None => "-".to_string(),
+                },
+            ]
+        })
+        .collect();
+    print!("{}", table(["GAME", "TURN", "PLAYING", "DEADLINE"], &rows));
+    Ok(())
+}
+
+fn listed_game(manager: &Manager, game: &Game, me: &UserId) -> anyhow::Result<ListedGame> {
+    let turn = &game.current_turn;
+    Ok(ListedGame {
+        game_id: game.game_id,
+        name: game.name.clone(),
+        turn: turn.number,
+        user_id: turn.user_id,
+        player: manager
+            .stored_player(&turn.user_id)?
+            .map(|stored| stored.player().persona_name.clone()),
+        my_turn: game.is_user_id_turn(me),
+        started: turn.started,
+        expires: turn.expires,
+    })
+}
+
+async fn download(manager: &Manager, opts: DownloadOpts) -> anyhow::Result<()> {
+    let me = manager
+        .user_id()?
+        .context("Not signed in. Open civfun and enter your auth key first.")?;
+    manager.fill_transfer_states()?;
+    let games = manager
+        .refresh_games_now()
+        .await
+        .context("Fetching games from GMR.")?;
+    let game = find_game(&games, &opts.game)?;
+    if !game.is_user_id_turn(&me) {
+        return Err(anyhow!("It isn't your turn in {}.", game.name));
+    }
+    if game.current_turn.is_first_turn {
+        println!(
+            "There's no save for the first turn of {}. Start it from Civ V.",
+            game.name
+        );
+        return Ok(());
+    }
+
+    let path = match opts.out {
+        Some(out) if out.is_dir() => {
+            let path = manager.save_path(game)?;
+            let filename = path.file_name().context("Save has no filename.")?;
+            download_to(manager, game, &out.join(filename)).await?
+        }
+        Some(out) => download_to(manager, game, &out).await?,
+        None => download_to_save_dir(manager, game).await?,
+    };
+    println!("Downloaded {} to {}", game.name, path.display());
+    Ok(())
+}
+
+/// Through the transfers, so the app knows the turn has been downloaded.
+async fn download_to_save_dir(manager: &Manager, game: &Game) -> anyhow::Result<PathBuf> {
+    if manager.transfer_state(&game.game_id) == TransferState::Downloaded {
+        let path = manager.save_path(game)?;
+        if path.exists() {
+            println!("Already downloaded.");
+            return Ok(path);
+        }
+        manager.redownload(&game.game_id)?;
+    }
+
+    let mut events = manager.subscribe();
+    if manager.transfer_state(&game.game_id) != TransferState::Downloading {
+        manager.download(&game.game_id)?;
+    }
+    let mut bar = ProgressBar::new();
+    let result = wait_for(manager, &mut events, |event| match event {
+        Event::DownloadProgress {
+            game_id,
+            progress,
+            bytes,
+        } if game_id == game.game_id => {
+            bar.show(progress.as_ref(), bytes);
+            None
+        }
+        Event::DownloadComplete { game_id, path } if game_id == game.game_id => Some(Ok(path)),
+        Event::DownloadFailed { game_id, message } if game_id == game.game_id => {
+            Some(Err(anyhow!(message)))
+        }
+        _ => None,
+    })
+    .await;
+    bar.finish();
+    result
+}
+
+/// Runs the transfers until `handle` returns something for one of the manager's events.
+async fn wait_for<T, F>(
+    manager: &Manager,
+    events: &mut broadcast::Receiver<Event>,
+    mut handle: F,
+) -> anyhow::Result<T>
+where
+    F: FnMut(Event) -> Option<anyhow::Result<T>>,
+{
+    loop {
+        manager.process_transfers()?;
+        loop {
+            let event = match events.try_recv() {
+                Ok(event) => event,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(TryRecvError::Closed) => return Err(anyhow!("The manager stopped.")),
+            };
+            if let Some(result) = handle(event) {
+                return result;
+            }
+        }
+        tokio::time::sleep(TICK).await;
+    }
+}
+
+async fn download_to(manager: &Manager, game: &Game, out: &Path) -> anyhow::Result<PathBuf> {
+    let mut rx = manager.download_to(&game.game_id, out)?;
+    let mut bar = ProgressBar::new();
+    while let Some(message) = rx.recv().await {
+        match message {
+            DownloadMessage::Started(_) => {}
+            DownloadMessage::Chunk { progress, bytes } => bar.show(progress.as_ref(), bytes),
+            DownloadMessage::Done(path) => {
+                bar.finish();
+                return Ok(path);
+            }
+            DownloadMessage::Error(message) => {
+                bar.finish();
+                return Err(anyhow!(message));
+            }
+            DownloadMessage::Paused => {
+                bar.finish();
+                return Err(anyhow!("Transfers are paused."));
+            }
+        }
+    }
+    bar.finish();
+    Err(anyhow!("Download stopped unexpectedly."))
+}
+
+async fn submit(manager: &Manager, opts: SubmitOpts) -> anyhow::Result<()> {
+    manager
+        .user_id()?
+        .context("Not signed in. Open civfun and enter your auth key first.")?;
+    manager.fill_transfer_states()?;
+    let games = manager
+        .refresh_games_now()
+        .await
+        .context("Fetching games from GMR.")?;
+
+    let mut events = manager.subscribe();
+    let game = match opts.game {
+        Some(query) => {
+            let game = find_game(&games, &query)?;
+            manager
+                .upload_file(&game.game_id, &opts.path)
+                .map_err(|err| SubmitError::Rejected(format!("{:#}", err)))?;
+            game.clone()
+        }
+        None => match manager
+            .upload_dropped(&opts.path)
+            .map_err(|err| SubmitError::Rejected(format!("{:#}", err)))?
+        {
+            DroppedSave::Queued(game) => game,
+            DroppedSave::Unclear(pending) => {
+                let games = pending.candidates.into_iter().map(|c| c.game).collect();
+                return Err(SubmitError::Unclear(games).into());
+            }
+        },
+    };
+    println!(
+        "Uploading {} as your turn in {}",
+        opts.path.display(),
+        game.name
+    );
+
+    let mut bar = ProgressBar::new();
+    let result = wait_for(manager, &mut events, |event| match event {
+        Event::UploadProgress {
+            game_id,
+            progress,
+            bytes,
+        } if game_id == game.game_id => {
+            bar.show(progress.as_ref(), bytes);
+            None
+        }
+        Event::UploadComplete(game_id) if game_id == game.game_id => Some(Ok(())),
+        Event::UploadFailed { game_id, message } if game_id == game.game_id => {
+            Some(Err(SubmitError::UploadFailed(message).into()))
+        }
+        _ => None,
+    })
+    .await;
+    bar.finish();
+    result?;
+    println!("Submitted.");
+    Ok(())
+}
+
+/// What the window does, minus the window. Transfers cut short by stopping pick up again next
+/// time, like after closing the app.
+async fn watch(manager: &Manager) -> anyhow::Result<()> {
+    if manager.user_id()?.is_none() {
+        warn!("Not signed in. Open civfun and enter your auth key, or nothing will happen.");
+    }
+    let mut events = manager.subscribe();
+    manager.start()?;
+    info!("Watching. Press Ctrl+C to stop.");
+
+    let stop = stop_signal();
+    tokio::pin!(stop);
+    let mut interval = tokio::time::interval(PROCESS_INTERVAL);
+    loop {
+        tokio::select! {
+            result = &mut stop => {
+                result?;
+                break;
+            }
+            _ = interval.tick() => {}
+        }
+        if let Err(err) = manager.process() {
+            warn!(?err, "Processing.");
+        }
+        loop {
+            match events.try_recv() {
+                Ok(event) => log_event(manager, &event),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Lagged(missed)) => warn!(missed, "Missed some events."),
+                Err(TryRecvError::Closed) => return Err(anyhow!("The manager stopped.")),
+            }
+        }
+    }
+    info!("Stopping.");
+    Ok(())
+}
+
+/// Ctrl+C, or SIGTERM from systemd and friends.
+async fn stop_signal() -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+/// One line per event, worded like the activity feed, with fields to filter on.
+fn log_event(manager: &Manager, event: &Event) {
+    let game_id = event.game_id();
+    let game = game_id.and_then(|game_id| {
+        manager
+            .games()
+            .ok()?
+            .into_iter()
+            .find(|g| g.game_id == game_id)
+    });
+    let description = match event_log::describe(event, game.as_ref()) {
+        Some(description) => description,
+        None => {
+            trace!(?game_id, "Event without a description.");
+            return;
+        }
+    };
+    let kind = ActivityKind::of(event);
+    match kind {
+        ActivityKind::Problem => warn!(?kind, ?game_id, "{}", description),
+        _ => info!(?kind, ?game_id, "{}", description),
+    }
+}
+
+/// What `analyze` prints as JSON.
+#[derive(Serialize)]
+struct Analysis<'a> {
+    fingerprint: String,
+    #[serde(flatten)]
+    save: &'a AnalyzedSave,
+}
+
+#[derive(Serialize)]
+struct AnalyzedSave {
+    header: civ5save::Header,
+    players: Vec<civ5save::Player>,
+}
+
+fn read_save(path: &Path) -> anyhow::Result<(Vec<u8>, Civ5Save)> {
+    let bytes = std::fs::read(path).with_context(|| format!("Reading {:?}", path))?;
+    let save = Civ5SaveReader::new(&bytes)
+        .parse()
+        .with_context(|| format!("{:?} isn't a Civ V save.", path))?;
+    Ok((bytes, save))
+}
+
+pub fn analyze(opts: AnalyzeOpts) -> anyhow::Result<()> {
+    let (bytes, save) = read_save(&opts.path)?;
+    let fingerprint = watcher::fingerprint(&bytes);
+    let analyzed = AnalyzedSave {
+        header: save.header.clone(),
+        players: save.players.clone(),
+    };
+
+    if opts.json {
+        let analysis = Analysis {
+            fingerprint,
+            save: &analyzed,
+        };
+        println!("{}", serde_json::to_string_pretty(&analysis)?);
+        return Ok(());
+    }
+
+    let header = &analyzed.header;
+    println!("Fingerprint  {}", fingerprint);
+    println!("Turn         {}", header.turn);
+    println!("Version      {}", header.game);
+    println!("Save format  {}", header.save);
+    println!("Started as   {}", header.starting_civ);
+    println!("Difficulty   {}", header.handicap);
+    println!(
+        "Era          {} (started in {})",
+        header.current_era, header.era
+    );
+    println!("Speed        {}", header.game_speed);
+    println!("Map          {}, {}", header.map_script, header.world_size);
+
+    println!("\nPlayers");
+    for (slot, player) in analyzed.players.iter().enumerate() {
+        println!(
+            "  {:>2}  {:<6}  {}",
+            slot,
+            format!("{:?}", player.player_type),
+            player.name
+        );
+    }
+    println!("\nDLC");
+    if header.dlc.is_empty() {
+        println!("  None");
+    }
+    for dlc in &header.dlc {
+        let enabled = if dlc.enabled { "" } else { " (disabled)" };
+        println!("  {}{}", dlc.name, enabled);
+    }
+    println!("\nMods");
+    if header.mods.is_empty() {
+        println!("  None");
+    }
+    for m in &header.mods {
+        println!("  {} v{}  {}", m.name, m.version, m.id);
+    }
+
+    if opts.hex {
+        println!("\n{}", save.hex_dump());
+    }
+    Ok(())
+}
+
+pub fn diff(opts: DiffOpts) -> anyhow::Result<()> {
+    let (_, a) = read_save(&opts.a)?;
+    let (_, b) = read_save(&opts.b)?;
+    let diff = a.diff(&b)?;
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    // Consecutive turns of the same game are usually a handful apart, unrelated games hundreds.
+    println!("Difference score  {}", diff.score);
+    if diff.fields.is_empty() && diff.chunks.is_empty() {
+        println!("\nThe saves are the same.");
+        return Ok(());
+    }
+    if !diff.fields.is_empty() {
+        let rows: Vec<[String; 3]> = diff
+            .fields
+            .iter()
+            .map(|change| [change.field.clone(), change.a.clone(), change.b.clone()])
+            .collect();
+        print!("\n{}", table(["FIELD", "A", "B"], &rows));
+    }
+    if !diff.chunks.is_empty() {
+        let rows: Vec<[String; 4]> = diff
+            .chunks
+            .iter()
+            .map(|chunk| {
+                [
+                    chunk.id.to_string(),
+                    if chunk.size_a == chunk.size_b {
+                        chunk.size_a.to_string()
+                    } else {
+                        format!("{} → {}", chunk.size_a, chunk.size_b)
+                    },
+                    chunk.changed_bytes.to_string(),
+                    format!("{:#x}", chunk.first_change),
+                ]
+            })
+            .collect();
+        print!(
+            "\n{}",
+            table(["CHUNK", "SIZE", "CHANGED BYTES", "FIRST CHANGE"], &rows)
+        );
+    }
+    Ok(())
+}
+
+fn config(manager: &Manager, opts: ConfigOpts) -> anyhow::Result<()> {
+    let config = manager.config()?;
+    match opts.action {
+        ConfigAction::List => {
+            for (key, value) in config.settings()? {
+                println!("{} = {}", key, value);
+            }
+        }
+        ConfigAction::Get { key } => println!("{}", config.setting(&key)?),
+        ConfigAction::Set { key, value } => {
+            let changed = config.with_setting(&key, &value)?;
+            let config = manager.update_config(|config| *config = changed)?;
+            println!("{} = {}", key, config.setting(&key)?);
+        }
+    }
+    Ok(())
+}
+
+pub fn completions(opts: CompletionsOpts) -> anyhow::Result<()> {
+    let mut app = crate::Opts::into_app();
+    let bin_name = env!("CARGO_BIN_NAME");
+    let out = &mut std::io::stdout();
+    match opts.shell {
+        Shell::Bash => clap_generate::generate::<Bash, _>(&mut app, bin_name, out),
+        Shell::Zsh => clap_generate::generate::<Zsh, _>(&mut app, bin_name, out),
+        Shell::Fish => clap_generate::generate::<Fish, _>(&mut app, bin_name, out),
+        Shell::PowerShell => clap_generate::generate::<PowerShell, _>(&mut app, bin_name, out),
+    }
+    Ok(())
+}
+
+/// A roff man page for every subcommand, from the same doc comments as `--help`. Packagers run
+/// `civfun --generate-man > civfun.1`.
+pub fn man_page() -> String {
+    let app = crate::Opts::into_app();
+    let bin_name = env!("CARGO_BIN_NAME");
+    let mut page = format!(
+        ".TH {} 1 \"\" \"{}\"\n.SH NAME\n{} \\- {}\n",
+        bin_name.to_uppercase(),
+        crate::VERSION,
+        roff(bin_name),
+        roff(crate::TITLE),
+    );
+    page.push_str(".SH SYNOPSIS\n");
+    page.push_str(&format!(".B {}\n[\\fISUBCOMMAND\\fR]\n", roff(bin_name)));
+    if let Some(about) = app.get_about() {
+        page.push_str(&format!(".SH DESCRIPTION\n{}\n", roff(about)));
+    }
+    page.push_str(".SH SUBCOMMANDS\n");
+    for sub in app.get_subcommands() {
+        man_subcommand(&mut page, bin_name, sub);
+    }
+    page
+}
+
+fn man_subcommand(page: &mut String, parent: &str, app: &App) {
+    let name = format!("{} {}", parent, app.get_name());
+    page.push_str(&format!(".TP\n.B {}", roff(&name)));
+    for arg in app.get_positionals() {
+        page.push_str(&format!(
+            " \\fI{}\\fR",
+            roff(&arg.get_name().to_uppercase())
+        ));
+    }
+    page.push('\n');
+    page.push_str(&roff(app.get_about().unwrap_or("")));
+    page.push('\n');
+    for arg in app.get_arguments() {
+        let long = match arg.get_long() {
+            Some(long) if !arg.is_set(ArgSettings::Hidden) => long,
+            _ => continue,
+        };
+        page.push_str(&format!(".RS\n.TP\n.B \\-\\-{}", roff(long)));
+        if arg.is_set(ArgSettings::TakesValue) {
+            page.push_str(&format!(
+                " \\fI{}\\fR",
+                roff(&arg.get_name().to_uppercase())
+            ));
+        }
+        page.push_str(&format!("\n{}\n.RE\n", roff(arg.get_about().unwrap_or(""))));
+    }
+    for sub in app.get_subcommands() {
+        man_subcommand(page, &name, sub);
+    }
+}
+
+/// Escapes text so roff doesn't take it for a request or an escape.
+fn roff(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    match text.chars().next() {
+        Some('.') | Some('\'') => format!("\\&{}", text),
+        _ => text,
+    }
+}
+
+/// Asks the running instance instead of opening the database it has locked.
+pub fn running_status(data_dir: &Path, opts: StatusOpts) -> anyhow::Result<()> {
+    let json = instance::request_status(data_dir)?;
+    let status: Status = serde_json::from_str(&json)
+        .context("civfun is running but couldn't say how it's doing.")?;
+    print_status(&status, true, opts)
+}
+
+#[derive(Serialize)]
+struct RunningStatus<'a> {
+    running: bool,
+    #[serde(flatten)]
+    status: &'a Status,
+}
+
+fn print_status(status: &Status, running: bool, opts: StatusOpts) -> anyhow::Result<()> {
+    if opts.json {
+        let status = RunningStatus { running, status };
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        match (running, status.paused) {
+            (true, false) => "civfun is running.",
+            (true, true) => "civfun is running, but paused.",
+            (false, false) => "civfun isn't running.",
+            (false, true) => "civfun isn't running, and is paused.",
+        }
+    );
+    let save_dir = match &status.save_dir {
+        Some(dir) => dir.display().to_string(),
+        None => "not found".to_string(),
+    };
+    let watching = match (&status.save_dir_problem, status.watching) {
+        (Some(problem), _) => format!(", {}", problem),
+        (None, true) => ", watching for saves".to_string(),
+        (None, false) if running => ", not watching for saves".to_string(),
+        (None, false) => String::new(),
+    };
+    println!("Save folder    {}{}", save_dir, watching);
+    if !status.signed_in {
+        println!("Not signed in.");
+        return Ok(());
+    }
+    let fetched_at = match status.games_fetched_at {
+        Some(at) => at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        None => "never".to_string(),
+    };
+    println!("Games updated  {}", fetched_at);
+
+    if !status.transfers.is_empty() {
+        let rows: Vec<[String; 2]> = status
+            .transfers
+            .iter()
+            .map(|t| [t.name.clone(), format!("{:?}", t.state)])
+            .collect();
+        print!("\n{}", table(["GAME", "TRANSFER"], &rows));
+    }
+    if !status.pending_saves.is_empty() {
+        println!("\nSaves waiting for a game to be picked:");
+        for path in &status.pending_saves {
+            println!("  {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// The game with this id, or the only one whose name contains `query`, ignoring case. An exact
+/// name wins over names that only contain it.
+fn find_game<'a>(games: &'a [Game], query: &str) -> anyhow::Result<&'a Game> {
+    if let Ok(id) = query.parse::<u32>() {
+        if let Some(game) = games.iter().find(|g| g.game_id == GameId::from(id)) {
+            return Ok(game);
+        }
+    }
+    let query = query.to_lowercase();
+    if let Some(game) = games.iter().find(|g| g.name.to_lowercase() == query) {
+        return Ok(game);
+    }
+    let matches: Vec<&Game> = games
+        .iter()
+        .filter(|g| g.name.to_lowercase().contains(&query))
+        .collect();
+    match matches.as_slice() {
+        [game] => Ok(game),
+        [] => Err(anyhow!("No game matches {:?}. See `civfun list`.", query)),
+        _ => Err(anyhow!(
+            "{:?} matches {}. Use more of the name or the game's id.",
+            query,
+            matches
+                .iter()
+                .map(|g| format!("{} ({})", g.name, g.game_id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// A transfer's progress on one line of stderr, redrawn in place. Nothing is drawn when stderr
+/// isn't a terminal, so logs and scripts don't fill up with it.
+struct ProgressBar {
+    progress: TransferProgress,
+    drawn: bool,
+}
+
+impl ProgressBar {
+    fn new() -> Self {
+        Self {
+            progress: TransferProgress::new(Instant::now()),
+            drawn: false,
+        }
+    }
+
+    fn show(&mut self, progress: Option<&Percentage>, bytes: u64) {
+        self.progress.update(progress, bytes);
+        if !std::io::stderr().is_terminal() {
+            return;
+        }
+        let details = self.progress.describe_at(Instant::now());
+        let line = match self.progress.fraction {
+            Some(fraction) => format!(
+                "[{:<width$}] {:>3}%  {}",
+                "#".repeat((fraction * BAR_WIDTH as f32) as usize),
+                (fraction * 100.0) as u32,
+                details,
+                width = BAR_WIDTH
+            ),
+            None => format!("{}  {}", format_bytes(bytes as f64), details),
+        };
+        // Clears whatever is left of a longer line drawn before.
+        eprint!("\r{}\x1b[K", line);
+        let _ = std::io::stderr().flush();
+        self.drawn = true;
+    }
+
+    /// Moves past the bar so what's printed next starts on its own line.
+    fn finish(&mut self) {
+        if std::mem::take(&mut self.drawn) {
+            eprintln!();
+        }
+    }
+}
+
+/// Left aligned columns as wide as their widest cell, two spaces apart.
+fn table<const N: usize>(headings: [&str; N], rows: &[[String; N]]) -> String {
+    let mut widths = headings.map(|heading| heading.chars().count());
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    let mut push_row = |cells: Vec<&str>| {
+        let line = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        out.push_str(line.trim_end());
+        out.push('\n');
+    };
+    push_row(headings.to_vec());
+    for row in rows {
+        push_row(row.iter().map(String::as_str).collect());
+    }
+    out
+}
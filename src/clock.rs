@@ -0,0 +1,89 @@
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use std::fmt::Debug;
+use std::sync::Mutex;
+
+type Result<T> = anyhow::Result<T>;
+
+/// Where `Manager` gets the time from, so tests can choose it.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Stays at the time it was given until it's moved with `set()` or `advance()`.
+#[derive(Debug)]
+pub struct MockClock(Mutex<DateTime<Utc>>);
+
+impl MockClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self(Mutex::new(now))
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.0.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.0.lock().unwrap();
+        *now = *now + by;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// Less than this is put down to the `Date` header only having whole seconds and the request
+/// taking a while.
+pub fn significant_skew() -> Duration {
+    Duration::minutes(2)
+}
+
+/// How far ahead of the local clock the server's is, going by the `Date` header of a response.
+/// The server's time is taken to be from halfway through the request.
+pub fn skew(server: DateTime<Utc>, sent: DateTime<Utc>, received: DateTime<Utc>) -> Duration {
+    let midpoint = sent + (received - sent) / 2;
+    server - midpoint
+}
+
+pub fn is_significant(skew: Duration) -> bool {
+    skew.num_seconds().abs() >= significant_skew().num_seconds()
+}
+
+/// e.g. `Tue, 15 Nov 1994 08:12:31 GMT`
+pub fn parse_http_date(s: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc2822(s)
+        .with_context(|| format!("Parsing HTTP date: {}", s))?
+        .with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn skew_from_response() {
+        let server = parse_http_date("Tue, 15 Nov 1994 08:12:31 GMT").unwrap();
+        assert_eq!(server, Utc.ymd(1994, 11, 15).and_hms(8, 12, 31));
+
+        let sent = server - Duration::minutes(10);
+        let received = sent + Duration::seconds(2);
+        let skew = skew(server, sent, received);
+        assert_eq!(skew, Duration::minutes(10) - Duration::seconds(1));
+        assert!(is_significant(skew));
+        assert!(is_significant(-skew));
+        assert!(!is_significant(Duration::seconds(30)));
+    }
+}
@@ -0,0 +1,547 @@
+use crate::api::{sort_by_deadline, Game};
+use crate::email::EmailSettings;
+use crate::logging::LogLevel;
+use crate::notify::QuietHours;
+use crate::webhook::Webhook;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+type Result<T> = anyhow::Result<T>;
+
+/// User settings, stored as JSON in sled. Change them with `Manager::update_config()`.
+///
+/// Every field needs a serde default so that a config saved by an older version still loads.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Use this hotseat directory instead of detecting it.
+    pub save_dir: Option<PathBuf>,
+
+    /// Which renderer Civ V is launched with.
+    pub directx: DirectXVersion,
+
+    /// Extra arguments for Civ V when launching it through Steam.
+    pub launch_args: Vec<String>,
+
+    /// How often to check GMR for games, in seconds.
+    pub poll_seconds: u64,
+
+    /// Remind the player when this many minutes are left on their turn.
+    pub reminder_minutes: Vec<u32>,
+
+    /// Start warning this many hours before a turn is skipped, escalating from notifications
+    /// to webhooks to email as it gets closer.
+    pub skip_warning_hours: Option<u32>,
+
+    /// Show desktop notifications.
+    pub notifications: bool,
+
+    /// Flash the window in the taskbar, or bounce it in the dock, when it becomes the user's turn
+    /// while the window is in the background.
+    pub request_attention: bool,
+
+    /// Hold back desktop notifications and webhooks during this time of day, or at weekends,
+    /// until it's over. Warnings about turns being skipped still go out straight away.
+    pub quiet_hours: Option<QuietHours>,
+
+    /// Posted to for new turns, uploads and approaching deadlines, e.g. to ping a Discord
+    /// channel.
+    pub webhooks: Vec<Webhook>,
+
+    /// Email new turns and approaching deadlines.
+    pub email: Option<EmailSettings>,
+
+    /// Download at most this many KiB per second.
+    pub download_limit_kib: Option<u32>,
+
+    /// Upload at most this many KiB per second.
+    pub upload_limit_kib: Option<u32>,
+
+    /// Ask before uploading a save that was matched to a game automatically.
+    pub confirm_uploads: bool,
+
+    /// Remove hotseat passwords from downloaded saves, so the game goes straight to the turn.
+    /// Off by default because it changes the save.
+    pub clear_hotseat_passwords: bool,
+
+    /// Steam names and avatars of other players are fetched again once they're this old.
+    pub player_cache_hours: u32,
+
+    /// Copy downloaded and uploaded saves into this folder, e.g. one synced by Dropbox or
+    /// OneDrive, to share turn history between machines.
+    pub sync_dir: Option<PathBuf>,
+
+    /// Keep saves for this many past turns of each game. None keeps them all, since pruning
+    /// deletes archived saves for good.
+    pub keep_turns: Option<u32>,
+
+    /// Delete the oldest saves once they take up more than this many MiB.
+    pub max_storage_mib: Option<u64>,
+
+    /// How much detail goes into the log files. Takes effect after a restart.
+    pub log_level: LogLevel,
+
+    /// After a crash, open a GitHub issue prefilled with the crash report. Reports are kept in
+    /// the data dir either way.
+    pub crash_reports: bool,
+
+    /// Look for a newer civfun on GitHub at startup and once a day.
+    pub check_for_updates: bool,
+
+    /// How the games list is ordered.
+    pub games_sort: GamesSort,
+
+    /// Only list games where it's the user's turn.
+    pub my_turn_only: bool,
+
+    /// Leave archived games out of the games list.
+    pub hide_finished: bool,
+
+    /// Dark or light, or whatever the OS is set to.
+    pub theme: ThemeChoice,
+
+    /// The colour for highlights, e.g. buttons under the mouse.
+    pub accent: Accent,
+
+    /// Black and white with outlined buttons, for when the usual colours are hard to tell apart.
+    pub high_contrast: bool,
+
+    /// Nothing on screen moves by itself, e.g. the spinner while refreshing.
+    pub reduced_motion: bool,
+
+    /// Makes text, icons and everything else bigger, for high DPI screens. See `ui_scale()`.
+    pub ui_scale_percent: u16,
+
+    /// The window's size when it was last resized, in logical pixels. None for the default
+    /// size. iced doesn't say where the window is, so the position is left to the OS.
+    pub window_size: Option<(u32, u32)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            save_dir: None,
+            directx: Default::default(),
+            launch_args: vec![],
+            poll_seconds: 60,
+            reminder_minutes: vec![24 * 60, 3 * 60, 30],
+            skip_warning_hours: Some(8),
+            notifications: true,
+            request_attention: true,
+            quiet_hours: None,
+            webhooks: vec![],
+            email: None,
+            download_limit_kib: None,
+            upload_limit_kib: None,
+            confirm_uploads: false,
+            clear_hotseat_passwords: false,
+            player_cache_hours: 24,
+            sync_dir: None,
+            keep_turns: None,
+            max_storage_mib: None,
+            log_level: Default::default(),
+            crash_reports: false,
+            check_for_updates: true,
+            games_sort: Default::default(),
+            my_turn_only: false,
+            hide_finished: true,
+            theme: Default::default(),
+            accent: Default::default(),
+            high_contrast: false,
+            reduced_motion: false,
+            ui_scale_percent: 100,
+            window_size: None,
+        }
+    }
+}
+
+/// The choices offered in settings. Anything in `MIN_UI_SCALE..=MAX_UI_SCALE` works.
+pub const UI_SCALES: [u16; 5] = [100, 125, 150, 175, 200];
+const MIN_UI_SCALE: u16 = 50;
+const MAX_UI_SCALE: u16 = 300;
+/// Shown instead of the email password by `settings()` and `setting()`.
+const MASK: &str = "********";
+
+impl Config {
+    /// e.g. 1.5 for `ui_scale_percent` 150.
+    pub fn ui_scale(&self) -> f64 {
+        self.ui_scale_percent as f64 / 100.0
+    }
+
+    /// Every setting by its field name, as JSON, for `civfun config`. The email password is
+    /// masked.
+    pub fn settings(&self) -> Result<Vec<(String, serde_json::Value)>> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(password) = value.pointer_mut("/email/password") {
+            if password.as_str().map_or(false, |p| !p.is_empty()) {
+                *password = MASK.into();
+            }
+        }
+        match value {
+            serde_json::Value::Object(settings) => Ok(settings.into_iter().collect()),
+            _ => Err(anyhow!("Config isn't an object.")),
+        }
+    }
+
+    pub fn setting(&self, key: &str) -> Result<serde_json::Value> {
+        self.settings()?
+            .into_iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+            .ok_or_else(|| anyhow!("There's no setting called {:?}.", key))
+    }
+
+    /// A copy with one setting changed. `value` is JSON, or a string when it isn't valid JSON so
+    /// paths don't need quoting. Not validated, `Manager::update_config()` does that.
+    ///
+    /// A masked email password, as pasted back from `setting("email")`, keeps the current one.
+    pub fn with_setting(&self, key: &str, value: &str) -> Result<Config> {
+        let mut config = serde_json::to_value(self)?;
+        let old_password = config.pointer("/email/password").cloned();
+        let setting = config
+            .get_mut(key)
+            .ok_or_else(|| anyhow!("There's no setting called {:?}.", key))?;
+        *setting = serde_json::from_str(value).unwrap_or_else(|_| value.into());
+        if let (Some(password), Some(old)) = (config.pointer_mut("/email/password"), old_password) {
+            if *password == MASK {
+                *password = old;
+            }
+        }
+        serde_json::from_value(config).map_err(|err| anyhow!("Can't set {}: {}", key, err))
+    }
+
+    /// Checks the settings, going from `old`. Folders are only checked when they're changed, so
+    /// a missing one, e.g. on an unplugged drive, doesn't stop anything else being changed.
+    pub fn validate(&self, old: &Config) -> Result<()> {
+        if self.save_dir != old.save_dir {
+            if let Some(save_dir) = &self.save_dir {
+                if !save_dir.is_dir() {
+                    return Err(anyhow!("{:?} is not a directory.", save_dir));
+                }
+            }
+        }
+        if self.poll_seconds == 0 {
+            return Err(anyhow!("The poll interval needs to be at least a second."));
+        }
+        for webhook in &self.webhooks {
+            webhook.validate()?;
+        }
+        if self.sync_dir != old.sync_dir {
+            if let Some(sync_dir) = &self.sync_dir {
+                if !sync_dir.is_dir() {
+                    return Err(anyhow!("{:?} is not a directory.", sync_dir));
+                }
+            }
+        }
+        if let Some(email) = &self.email {
+            email.validate()?;
+        }
+        if self.skip_warning_hours == Some(0) {
+            return Err(anyhow!(
+                "Skip warnings need to start at least an hour before."
+            ));
+        }
+        if self.player_cache_hours == 0 {
+            return Err(anyhow!("Players need to be kept for at least an hour."));
+        }
+        if !(MIN_UI_SCALE..=MAX_UI_SCALE).contains(&self.ui_scale_percent) {
+            return Err(anyhow!(
+                "The UI scale needs to be between {}% and {}%.",
+                MIN_UI_SCALE,
+                MAX_UI_SCALE
+            ));
+        }
+        if self.download_limit_kib == Some(0) || self.upload_limit_kib == Some(0) {
+            return Err(anyhow!("A bandwidth limit needs to be at least 1 KiB/s."));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DirectXVersion {
+    /// Let Steam ask, or use whatever was picked last time.
+    Auto,
+    #[default]
+    Dx9,
+    /// DirectX 10 and 11 are the same option in Civ V.
+    #[serde(alias = "Dx10")]
+    Dx11,
+}
+
+impl Display for DirectXVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DirectXVersion::Auto => write!(f, "Ask Steam"),
+            DirectXVersion::Dx9 => write!(f, "DirectX 9"),
+            DirectXVersion::Dx11 => write!(f, "DirectX 10 and 11"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GamesSort {
+    /// Soonest `expires` first, see `api::sort_by_deadline()`.
+    #[default]
+    Deadline,
+    Name,
+    /// Most recent turn first.
+    LastActivity,
+}
+
+impl GamesSort {
+    pub const ALL: [GamesSort; 3] = [
+        GamesSort::Deadline,
+        GamesSort::Name,
+        GamesSort::LastActivity,
+    ];
+
+    pub fn sort(&self, games: &mut [Game]) {
+        match self {
+            GamesSort::Deadline => sort_by_deadline(games),
+            GamesSort::Name => games.sort_by_key(|g| g.name.to_lowercase()),
+            GamesSort::LastActivity => {
+                games.sort_by_key(|g| std::cmp::Reverse(g.current_turn.started))
+            }
+        }
+    }
+}
+
+impl Display for GamesSort {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GamesSort::Deadline => write!(f, "Deadline"),
+            GamesSort::Name => write!(f, "Name"),
+            GamesSort::LastActivity => write!(f, "Last activity"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ThemeChoice {
+    /// Follow the OS, see `os_theme::prefers_dark()`. Dark when it can't be told.
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+impl ThemeChoice {
+    pub const ALL: [ThemeChoice; 3] = [ThemeChoice::System, ThemeChoice::Dark, ThemeChoice::Light];
+}
+
+impl Display for ThemeChoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemeChoice::System => write!(f, "Same as the system"),
+            ThemeChoice::Dark => write!(f, "Dark"),
+            ThemeChoice::Light => write!(f, "Light"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Accent {
+    #[default]
+    Blue,
+    Green,
+    Orange,
+    Purple,
+    Red,
+}
+
+impl Accent {
+    pub const ALL: [Accent; 5] = [
+        Accent::Blue,
+        Accent::Green,
+        Accent::Orange,
+        Accent::Purple,
+        Accent::Red,
+    ];
+}
+
+impl Display for Accent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Accent::Blue => write!(f, "Blue"),
+            Accent::Green => write!(f, "Green"),
+            Accent::Orange => write!(f, "Orange"),
+            Accent::Purple => write!(f, "Purple"),
+            Accent::Red => write!(f, "Red"),
+        }
+    }
+}
+
+/// Settings for a single game, stored per account in sled. Change them with
+/// `Manager::update_game_settings()`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    /// No desktop notifications for this game.
+    pub muted: bool,
+
+    /// Download the save as soon as it's the user's turn. Turn this off for a game played on
+    /// another machine.
+    pub auto_download: bool,
+
+    /// Name for downloaded saves instead of the default. `{game}`, `{game_id}` and `{turn}` are
+    /// replaced with the game's name, id and turn number. `.Civ5Save` is added if it's missing.
+    pub filename_format: Option<String>,
+
+    /// The game is over, so it's hidden from the games list and nothing is transferred for it.
+    /// Set automatically when a game looks finished, see `Manager::archive_game()`.
+    pub archived: bool,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            muted: false,
+            auto_download: true,
+            filename_format: None,
+            archived: false,
+        }
+    }
+}
+
+impl GameSettings {
+    pub const DEFAULT_FILENAME_FORMAT: &'static str = "(civfun {game_id}) {game}";
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(format) = &self.filename_format {
+            if format.trim().is_empty() {
+                return Err(anyhow!("The filename format can't be empty."));
+            }
+        }
+        Ok(())
+    }
+
+    /// The filename for this game's downloaded save.
+    pub fn filename(&self, game: &Game) -> PathBuf {
+        let format = self
+            .filename_format
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_FILENAME_FORMAT);
+        let name = format
+            .replace("{game}", &game.name)
+            .replace("{game_id}", &game.game_id.to_string())
+            .replace("{turn}", &game.current_turn.number.to_string());
+        let name = name.strip_suffix(".Civ5Save").unwrap_or(&name);
+        format!("{}.Civ5Save", clean_filename(name)).into()
+    }
+}
+
+/// Replaces characters that aren't allowed in filenames on some OS.
+pub(crate) fn clean_filename(name: &str) -> String {
+    name.chars()
+        .map(|c| match "./\\\"<>|:*?".contains(c) {
+            true => '_',
+            false => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> Game {
+        let mut game = Game {
+            name: "Marathon: Madness".into(),
+            game_id: 123u32.into(),
+            ..Default::default()
+        };
+        game.current_turn.number = 45;
+        game
+    }
+
+    #[test]
+    fn missing_save_dir_only_checked_when_changed() {
+        let old = Config {
+            save_dir: Some(PathBuf::from("/this/drive/is/unplugged")),
+            ..Default::default()
+        };
+        let mut config = old.clone();
+        config.my_turn_only = true;
+        assert!(config.validate(&old).is_ok());
+        assert!(config.validate(&Config::default()).is_err());
+    }
+
+    #[test]
+    fn sort_games() {
+        use chrono::{TimeZone, Utc};
+
+        let game = |name: &str, started: i64| {
+            let mut game = game();
+            game.name = name.into();
+            game.current_turn.started = Utc.timestamp(started, 0);
+            game
+        };
+        let mut games = vec![game("b", 100), game("C", 300), game("a", 200)];
+        let names = |games: &[Game]| games.iter().map(|g| g.name.clone()).collect::<Vec<_>>();
+
+        GamesSort::Name.sort(&mut games);
+        assert_eq!(names(&games), vec!["a", "b", "C"]);
+        GamesSort::LastActivity.sort(&mut games);
+        assert_eq!(names(&games), vec!["C", "a", "b"]);
+        // Without turn timers it's oldest turn first.
+        GamesSort::Deadline.sort(&mut games);
+        assert_eq!(names(&games), vec!["b", "a", "C"]);
+    }
+
+    #[test]
+    fn settings_by_name() {
+        let config = Config::default();
+        assert_eq!(config.setting("poll_seconds").unwrap(), config.poll_seconds);
+        assert!(config.setting("nope").is_err());
+
+        let config = config.with_setting("poll_seconds", "120").unwrap();
+        assert_eq!(config.poll_seconds, 120);
+        let config = config.with_setting("save_dir", "/saves/hotseat").unwrap();
+        assert_eq!(config.save_dir, Some(PathBuf::from("/saves/hotseat")));
+        let config = config.with_setting("save_dir", "null").unwrap();
+        assert_eq!(config.save_dir, None);
+        assert!(config.with_setting("poll_seconds", "often").is_err());
+        assert!(config.with_setting("nope", "1").is_err());
+
+        let config = Config {
+            email: Some(EmailSettings {
+                password: "hunter2".into(),
+                ..Default::default()
+            }),
+            ..config
+        };
+        let email = config.setting("email").unwrap();
+        assert_eq!(email["password"], MASK);
+        let config = config.with_setting("email", &email.to_string()).unwrap();
+        assert_eq!(config.email.unwrap().password, "hunter2");
+    }
+
+    #[test]
+    fn default_filename() {
+        assert_eq!(
+            GameSettings::default().filename(&game()),
+            PathBuf::from("(civfun 123) Marathon_ Madness.Civ5Save")
+        );
+    }
+
+    #[test]
+    fn custom_filename() {
+        let mut settings = GameSettings {
+            filename_format: Some("{game} turn {turn}".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.filename(&game()),
+            PathBuf::from("Marathon_ Madness turn 45.Civ5Save")
+        );
+
+        settings.filename_format = Some("mine.Civ5Save".into());
+        assert_eq!(settings.filename(&game()), PathBuf::from("mine.Civ5Save"));
+
+        settings.filename_format = Some("../{game_id}".into());
+        assert_eq!(settings.filename(&game()), PathBuf::from("___123.Civ5Save"));
+    }
+}
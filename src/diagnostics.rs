@@ -0,0 +1,232 @@
+use crate::config::Config;
+use crate::event_log::LoggedEvent;
+use crate::logging;
+use crate::manager::{data_dir_path, Manager};
+use crate::steam::percent_encode;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use std::backtrace::Backtrace;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, instrument};
+
+type Result<T> = anyhow::Result<T>;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const NEW_ISSUE_URL: &str = "https://github.com/civfun/civfun_gmr/issues/new";
+
+/// Browsers and GitHub give up on URLs much longer than this.
+const MAX_ISSUE_BODY: usize = 2000;
+
+const REDACTED: &str = "(redacted)";
+
+/// Where crash reports are written.
+pub fn crash_dir() -> Result<PathBuf> {
+    data_dir_path(Path::new("crashes"))
+}
+
+/// Writes a crash report to `crash_dir()` whenever something panics. With `crash_reports` on,
+/// a GitHub issue prefilled with the report is opened too. The usual panic output still
+/// happens afterwards.
+pub fn install_panic_hook(manager: Manager) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let events = manager.recent_events(20).unwrap_or_default();
+        let report = crash_report(
+            &info.to_string(),
+            &Backtrace::force_capture().to_string(),
+            &events,
+        );
+        match write_crash_report(&report) {
+            Ok(path) => error!(?path, "Crashed. Wrote a crash report."),
+            Err(err) => error!(?err, "Crashed. Could not write a crash report."),
+        }
+        let opted_in = manager.config().map(|c| c.crash_reports).unwrap_or(false);
+        if opted_in {
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("panic");
+            if let Err(err) = open::that(issue_url(message, &report)) {
+                error!(?err, "Could not open a GitHub issue for the crash.");
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+fn crash_report(message: &str, backtrace: &str, events: &[LoggedEvent]) -> String {
+    let mut report = format!(
+        "civfun {} on {} {}\n\n{}\n\nRecent events:\n",
+        VERSION,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        message
+    );
+    report.push_str(&events_text(events));
+    report.push_str("\nBacktrace:\n");
+    report.push_str(backtrace);
+    report
+}
+
+/// The version and OS with `events`, to paste into a bug report.
+pub fn summary(events: &[LoggedEvent]) -> String {
+    format!(
+        "civfun {} on {} {}\n\nRecent events:\n{}",
+        VERSION,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        events_text(events)
+    )
+}
+
+fn events_text(events: &[LoggedEvent]) -> String {
+    events
+        .iter()
+        .map(|event| format!("- {} {}\n", event.at.to_rfc3339(), event.description))
+        .collect()
+}
+
+fn write_crash_report(report: &str) -> Result<PathBuf> {
+    let dir = crash_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("crash-{}.txt", Utc::now().format("%Y%m%d-%H%M%S")));
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
+
+fn issue_url(message: &str, report: &str) -> String {
+    let title = format!("Crash: {}", message.lines().next().unwrap_or_default());
+    let body = match report.char_indices().nth(MAX_ISSUE_BODY) {
+        Some((end, _)) => format!("```\n{}\n...\n```", &report[..end]),
+        None => format!("```\n{}\n```", report),
+    };
+    format!(
+        "{}?title={}&body={}",
+        NEW_ISSUE_URL,
+        percent_encode(&title),
+        percent_encode(&body)
+    )
+}
+
+/// Everything useful for a bug report in one file, gzipped JSON. Secrets are left out: no
+/// auth keys, email password or webhook URLs.
+#[derive(Debug, Serialize)]
+pub struct Bundle {
+    pub version: String,
+    pub os: String,
+    pub created_at: DateTime<Utc>,
+    pub config: Config,
+    pub db_size_bytes: u64,
+    /// Each sled tree and how many entries it has.
+    pub trees: Vec<(String, usize)>,
+    pub recent_events: Vec<LoggedEvent>,
+    /// Filename and contents of each log and crash report.
+    pub files: Vec<(String, String)>,
+}
+
+impl Bundle {
+    pub fn new(db: &sled::Db, config: &Config, recent_events: Vec<LoggedEvent>) -> Result<Self> {
+        let mut trees = vec![];
+        for name in db.tree_names() {
+            let len = db.open_tree(&name)?.len();
+            trees.push((String::from_utf8_lossy(&name).to_string(), len));
+        }
+        let mut files = vec![];
+        for dir in [logging::log_dir()?, crash_dir()?] {
+            files.extend(read_files(&dir)?);
+        }
+        Ok(Self {
+            version: VERSION.to_string(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            created_at: Utc::now(),
+            config: redact(config),
+            db_size_bytes: db.size_on_disk()?,
+            trees,
+            recent_events,
+            files,
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = File::create(path).with_context(|| format!("Creating {:?}", path))?;
+        let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+        serde_json::to_writer(&mut encoder, self)?;
+        encoder.finish()?;
+        info!(files = self.files.len(), "Wrote diagnostic bundle.");
+        Ok(())
+    }
+}
+
+fn read_files(dir: &Path) -> Result<Vec<(String, String)>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let contents = std::fs::read(&path)?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        files.push((name, String::from_utf8_lossy(&contents).to_string()));
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn redact(config: &Config) -> Config {
+    let mut config = config.clone();
+    if let Some(email) = &mut config.email {
+        email.password = REDACTED.to_string();
+    }
+    for webhook in &mut config.webhooks {
+        webhook.url = REDACTED.to_string();
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::EmailSettings;
+    use crate::webhook::Webhook;
+
+    #[test]
+    fn redacts_secrets() {
+        let config = Config {
+            email: Some(EmailSettings {
+                password: "hunter2".into(),
+                ..Default::default()
+            }),
+            webhooks: vec![Webhook {
+                url: "https://discord.com/api/webhooks/1/secret".into(),
+                format: Default::default(),
+            }],
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&redact(&config)).unwrap();
+        assert!(!json.contains("hunter2"));
+        assert!(!json.contains("secret"));
+    }
+
+    #[test]
+    fn issue_url_is_bounded() {
+        let report = crash_report("oh no\nmore", &"frame\n".repeat(10_000), &[]);
+        let url = issue_url("oh no\nmore", &report);
+        assert!(url.starts_with(NEW_ISSUE_URL));
+        assert!(url.contains("title=Crash%3A%20oh%20no&"));
+        assert!(url.len() < MAX_ISSUE_BODY * 3 + 200);
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::{anyhow, Context};
+use lettre::message::header::ContentType;
+use lettre::message::{Mailbox, Message};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+type Result<T> = anyhow::Result<T>;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where to send turn alerts by email, for when the desktop client isn't running.
+///
+/// `password` is only kept here when there's no keychain. Otherwise it's empty and
+/// `Manager` fills it in from its `KeyStore` before sending.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmailSettings {
+    pub server: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: String,
+    pub password: String,
+    pub from: String,
+    pub to: String,
+    /// At most one email per game in this many minutes.
+    pub min_interval_minutes: u32,
+}
+
+impl Default for EmailSettings {
+    fn default() -> Self {
+        Self {
+            server: String::new(),
+            port: 465,
+            security: Default::default(),
+            username: String::new(),
+            password: String::new(),
+            from: String::new(),
+            to: String::new(),
+            min_interval_minutes: 60,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SmtpSecurity {
+    /// TLS from the start, usually on port 465.
+    #[default]
+    Tls,
+    /// Plain text upgraded with STARTTLS, usually on port 587.
+    StartTls,
+}
+
+impl EmailSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.server.is_empty() {
+            return Err(anyhow!("The email server is missing."));
+        }
+        mailbox(&self.from)?;
+        mailbox(&self.to)?;
+        Ok(())
+    }
+
+    pub fn min_interval(&self) -> Duration {
+        Duration::from_secs(self.min_interval_minutes as u64 * 60)
+    }
+}
+
+fn mailbox(address: &str) -> Result<Mailbox> {
+    address
+        .parse()
+        .with_context(|| format!("{:?} isn't an email address.", address))
+}
+
+/// Sends a plain text email. This blocks, so call it from `spawn_blocking()`.
+#[instrument(skip(settings, body), fields(server = %settings.server))]
+pub fn send(settings: &EmailSettings, subject: &str, body: &str) -> Result<()> {
+    debug!("Sending email.");
+    let message = message(settings, subject, body)?;
+    let transport = match settings.security {
+        SmtpSecurity::Tls => SmtpTransport::relay(&settings.server),
+        SmtpSecurity::StartTls => SmtpTransport::starttls_relay(&settings.server),
+    }
+    .with_context(|| format!("Connecting to {}.", settings.server))?
+    .port(settings.port)
+    .credentials(Credentials::new(
+        settings.username.clone(),
+        settings.password.clone(),
+    ))
+    .timeout(Some(TIMEOUT))
+    .build();
+    transport
+        .send(&message)
+        .map_err(|err| anyhow!("The email server said: {}", err))?;
+    Ok(())
+}
+
+fn message(settings: &EmailSettings, subject: &str, body: &str) -> Result<Message> {
+    Ok(Message::builder()
+        .from(mailbox(&settings.from)?)
+        .to(mailbox(&settings.to)?)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(body.to_string())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        let settings = EmailSettings {
+            from: "civfun@example.com".into(),
+            to: "me@example.com".into(),
+            ..Default::default()
+        };
+        let message = message(&settings, "Your turn", "First\nLast").unwrap();
+        let message = String::from_utf8(message.formatted()).unwrap();
+        assert!(message.contains("From: civfun@example.com\r\n"));
+        assert!(message.contains("To: me@example.com\r\n"));
+        assert!(message.contains("Subject: Your turn\r\n"));
+        assert!(message.contains("\r\n\r\nFirst\r\nLast"));
+
+        let settings = EmailSettings {
+            to: "me".into(),
+            ..settings
+        };
+        assert!(super::message(&settings, "Your turn", "").is_err());
+    }
+
+    #[test]
+    fn validation() {
+        let mut settings = EmailSettings {
+            server: "smtp.example.com".into(),
+            from: "civfun@example.com".into(),
+            to: "Me <me@example.com>".into(),
+            ..Default::default()
+        };
+        settings.validate().unwrap();
+        settings.from = "civfun".into();
+        assert!(settings.validate().is_err());
+    }
+}
@@ -0,0 +1,327 @@
+use crate::api::{Game, GameId};
+use crate::clock;
+use crate::manager::Event;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+type Result<T> = anyhow::Result<T>;
+
+pub const TREE_NAME: &str = "events";
+
+/// The oldest events are dropped once there are more than this.
+pub const MAX_EVENTS: usize = 1000;
+
+/// How far past `MAX_EVENTS` the log may grow before it's trimmed, so trimming happens once per
+/// this many events rather than on every one.
+const TRIM_BATCH: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    pub at: DateTime<Utc>,
+    pub game_id: Option<GameId>,
+    /// e.g. "Downloaded turn 43 of Marathon Madness".
+    pub description: String,
+    /// Events logged before there were kinds are `Other`.
+    #[serde(default)]
+    pub kind: ActivityKind,
+}
+
+/// Roughly what an event was about, to filter the activity feed by.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ActivityKind {
+    /// Saves downloaded or uploaded.
+    Transfer,
+    /// Anything that went wrong, including warnings and errors from the log files.
+    Problem,
+    /// Things the user is told about, e.g. a new turn or a deadline.
+    Notification,
+    #[default]
+    Other,
+}
+
+impl ActivityKind {
+    pub const ALL: [ActivityKind; 4] = [
+        ActivityKind::Transfer,
+        ActivityKind::Problem,
+        ActivityKind::Notification,
+        ActivityKind::Other,
+    ];
+
+    pub fn of(event: &Event) -> Self {
+        match event {
+            Event::DownloadComplete { .. }
+            | Event::UploadComplete(_)
+            | Event::DuplicateTurnIgnored { .. }
+            | Event::SaveRepaired { .. } => ActivityKind::Transfer,
+            Event::AuthenticationFailure
+            | Event::DownloadFailed { .. }
+            | Event::UploadFailed { .. }
+            | Event::FirstTurnSaveRejected { .. }
+            | Event::SaveDirNotFound { .. }
+            | Event::SaveDirProblem { .. }
+            | Event::FetchGamesFailed { .. }
+            | Event::DatabaseCorrupt { .. }
+            | Event::Error { .. }
+            | Event::ClockSkew { .. } => ActivityKind::Problem,
+            Event::NewTurn(_)
+            | Event::UploadNeedsApproval { .. }
+            | Event::SaveNeedsConfirmation { .. }
+            | Event::TurnDeadlineApproaching { .. }
+            | Event::TurnAboutToBeSkipped { .. } => ActivityKind::Notification,
+            _ => ActivityKind::Other,
+        }
+    }
+}
+
+impl Display for ActivityKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActivityKind::Transfer => write!(f, "Transfers"),
+            ActivityKind::Problem => write!(f, "Problems"),
+            ActivityKind::Notification => write!(f, "Notifications"),
+            ActivityKind::Other => write!(f, "Other"),
+        }
+    }
+}
+
+/// What has happened recently, for an activity feed and bug reports.
+///
+/// Kept in its own tree, keyed by sled generated ids so iteration is oldest first. Open it once
+/// and keep it, as opening counts the events already logged.
+#[derive(Debug)]
+pub struct EventLog {
+    db: sled::Db,
+    tree: sled::Tree,
+    /// `tree.len()` walks the whole tree, so the count is kept here instead.
+    len: AtomicUsize,
+}
+
+impl EventLog {
+    pub fn new(db: &sled::Db) -> Result<Self> {
+        let tree = db.open_tree(TREE_NAME)?;
+        Ok(Self {
+            db: db.clone(),
+            len: AtomicUsize::new(tree.len()),
+            tree,
+        })
+    }
+
+    /// Stores `event` unless it's too noisy to be interesting. `game` is the game the event is
+    /// about, to describe it by name.
+    pub fn add(&self, event: &Event, game: Option<&Game>, at: DateTime<Utc>) -> Result<()> {
+        let description = match describe(event, game) {
+            Some(description) => description,
+            None => return Ok(()),
+        };
+        let logged = LoggedEvent {
+            at,
+            game_id: event.game_id(),
+            description,
+            kind: ActivityKind::of(event),
+        };
+        let id = self.db.generate_id()?;
+        self.tree
+            .insert(id.to_be_bytes(), serde_json::to_vec(&logged)?)?;
+        if self.len.fetch_add(1, Ordering::SeqCst) + 1 > MAX_EVENTS + TRIM_BATCH {
+            self.trim()?;
+        }
+        Ok(())
+    }
+
+    /// Drops the oldest events down to `MAX_EVENTS`.
+    fn trim(&self) -> Result<()> {
+        while self.len.load(Ordering::SeqCst) > MAX_EVENTS {
+            if self.tree.pop_min()?.is_none() {
+                self.len.store(0, Ordering::SeqCst);
+                break;
+            }
+            self.len.fetch_sub(1, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    /// Up to `limit` events, newest first.
+    pub fn recent(&self, limit: usize) -> Result<Vec<LoggedEvent>> {
+        self.tree
+            .iter()
+            .values()
+            .rev()
+            .take(limit)
+            .map(|value| serde_json::from_slice(&value?).context("Decoding logged event."))
+            .collect()
+    }
+}
+
+/// False for events `describe()` ignores, so callers can skip looking up the game for them.
+pub fn is_logged(event: &Event) -> bool {
+    !matches!(
+        event,
+        Event::UpdatedGames(_)
+            | Event::DeferredAlerts(_)
+            | Event::UpdatedPlayer(_)
+            | Event::DownloadStarted(_)
+            | Event::DownloadProgress { .. }
+            | Event::UploadStarted(_)
+            | Event::UploadProgress { .. }
+    )
+}
+
+/// A sentence for the event, or None for ones too minor to show anywhere.
+pub fn describe(event: &Event, game: Option<&Game>) -> Option<String> {
+    let name = || match (game, event.game_id()) {
+        (Some(game), _) => game.name.clone(),
+        (None, Some(game_id)) => format!("game {}", game_id),
+        (None, None) => "a game".to_string(),
+    };
+    let turn = || match game {
+        Some(game) => format!("turn {} of {}", game.current_turn.number, game.name),
+        None => format!("a turn of {}", name()),
+    };
+    Some(match event {
+        Event::AuthenticationSuccess => "Signed in".to_string(),
+        Event::AuthenticationFailure => "Signing in failed".to_string(),
+        Event::AccountSwitched(user_id) => format!("Switched to account {}", user_id),
+        Event::ConfigChanged(_) => "Settings changed".to_string(),
+        Event::GameSettingsChanged { .. } => format!("Settings for {} changed", name()),
+        Event::NewTurn(game) => format!(
+            "It's your turn {} in {}",
+            game.current_turn.number, game.name
+        ),
+        Event::DownloadComplete { .. } => format!("Downloaded {}", turn()),
+        Event::DownloadFailed { message, .. } => {
+            format!("Downloading {} failed: {}", turn(), message)
+        }
+        Event::UploadNeedsApproval { .. } => format!("Upload of {} needs approval", turn()),
+        Event::UploadComplete(_) => format!("Uploaded {}", turn()),
+        Event::UploadFailed { message, .. } => {
+            format!("Uploading {} failed: {}", turn(), message)
+        }
+        Event::DuplicateTurnIgnored { path, .. } => {
+            format!("Ignored {:?}, {} was already sent", path, turn())
+        }
+        Event::FirstTurnSaveRejected { path, .. } => {
+            format!("{:?} doesn't match how {} was set up", path, name())
+        }
+        Event::SaveRepaired { problem, .. } => {
+            format!("Downloading {} again: {}", turn(), problem)
+        }
+        Event::SaveNeedsConfirmation { path, .. } => {
+            format!("{:?} could belong to more than one game", path)
+        }
+        Event::SaveDirNotFound { .. } => "The hotseat directory wasn't found".to_string(),
+        Event::SaveDirProblem { problem, .. } => problem.to_string(),
+        Event::TurnDeadlineApproaching { game, remaining } => format!(
+            "Turn {} of {} expires in {} minutes",
+            game.current_turn.number,
+            game.name,
+            remaining.num_minutes()
+        ),
+        Event::TurnAboutToBeSkipped {
+            game, remaining, ..
+        } => format!(
+            "Turn {} of {} will be skipped in {} minutes",
+            game.current_turn.number,
+            game.name,
+            remaining.num_minutes()
+        ),
+        Event::FetchGamesFailed { message } => format!("Refreshing games failed: {}", message),
+        Event::DatabaseCorrupt { message } => format!("The database is damaged: {}", message),
+        Event::Error {
+            source, message, ..
+        } => format!("Error in {}: {}", source, message),
+        Event::AnotherInstanceStarted => "Another copy of civfun was started".to_string(),
+        Event::GameRunning(true) => "Civ V started".to_string(),
+        Event::GameRunning(false) => "Civ V stopped".to_string(),
+        Event::Paused(true) => "Paused".to_string(),
+        Event::Paused(false) => "Resumed".to_string(),
+        Event::ClockSkew { skew } if clock::is_significant(*skew) => format!(
+            "This computer's clock is {} minutes {} GMR's",
+            skew.num_minutes().abs(),
+            if skew.num_seconds() > 0 {
+                "behind"
+            } else {
+                "ahead of"
+            }
+        ),
+        Event::ClockSkew { .. } => "This computer's clock is right again".to_string(),
+        Event::UpdatedGames(_)
+        | Event::DeferredAlerts(_)
+        | Event::UpdatedPlayer(_)
+        | Event::DownloadStarted(_)
+        | Event::DownloadProgress { .. }
+        | Event::UploadStarted(_)
+        | Event::UploadProgress { .. } => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::CurrentTurn;
+
+    #[test]
+    fn descriptions() {
+        let game = Game {
+            name: "Marathon Madness".into(),
+            game_id: GameId::from(7),
+            current_turn: CurrentTurn {
+                number: 43,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let event = Event::DownloadComplete {
+            game_id: game.game_id,
+            path: "a.Civ5Save".into(),
+        };
+        assert_eq!(
+            describe(&event, Some(&game)).unwrap(),
+            "Downloaded turn 43 of Marathon Madness"
+        );
+        assert_eq!(
+            describe(&Event::UploadComplete(GameId::from(8)), None).unwrap(),
+            "Uploaded a turn of game 8"
+        );
+        assert!(describe(&Event::UpdatedGames(vec![]), None).is_none());
+        assert!(!is_logged(&Event::UpdatedGames(vec![])));
+        assert!(is_logged(&Event::UploadComplete(GameId::from(8))));
+    }
+
+    #[test]
+    fn capped() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let log = EventLog::new(&db).unwrap();
+        for _ in 0..MAX_EVENTS + TRIM_BATCH {
+            log.add(&Event::AuthenticationSuccess, None, Utc::now())
+                .unwrap();
+        }
+        assert_eq!(log.tree.len(), MAX_EVENTS + TRIM_BATCH);
+        log.add(&Event::GameRunning(true), None, Utc::now())
+            .unwrap();
+
+        assert_eq!(log.tree.len(), MAX_EVENTS);
+        // Reopening counts what's there.
+        assert_eq!(
+            EventLog::new(&db).unwrap().len.load(Ordering::SeqCst),
+            MAX_EVENTS
+        );
+        let recent = log.recent(2).unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].description, "Civ V started");
+        assert_eq!(recent[1].description, "Signed in");
+
+        log.add(
+            &Event::UploadFailed {
+                game_id: GameId::from(1),
+                message: "timed out".into(),
+            },
+            None,
+            Utc::now(),
+        )
+        .unwrap();
+        assert_eq!(log.recent(1).unwrap()[0].kind, ActivityKind::Problem);
+    }
+}
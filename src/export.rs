@@ -0,0 +1,232 @@
+use crate::manager::ACCOUNTS_KEY;
+use crate::migrations;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use tracing::{debug, info, instrument};
+
+type Result<T> = anyhow::Result<T>;
+
+/// Bump when the layout of the export file itself changes. Changes to what's in sled are handled
+/// by the schema version and migrations.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Include the auth keys of every account. Without them the user has to enter their key
+    /// again after importing. Keys kept in the OS keychain are never included.
+    pub include_auth_keys: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportSummary {
+    pub schema_version: u32,
+    pub trees: usize,
+    pub entries: usize,
+}
+
+/// The whole file, gzipped JSON.
+#[derive(Serialize, Deserialize)]
+struct Export {
+    format_version: u32,
+    schema_version: u32,
+    exported_at: DateTime<Utc>,
+    trees: Vec<ExportedTree>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedTree {
+    /// Base64, like the keys and values.
+    name: String,
+    entries: Vec<(String, String)>,
+}
+
+/// Writes every tree in `db` to a single file at `path`.
+#[instrument(skip(db))]
+pub fn export(db: &sled::Db, path: &Path, options: &ExportOptions) -> Result<ExportSummary> {
+    let mut trees = vec![];
+    let mut entries = 0;
+    for name in db.tree_names() {
+        let is_default = name == db.name();
+        let tree = db.open_tree(&name)?;
+        let mut exported = ExportedTree {
+            name: base64::encode(&name),
+            entries: vec![],
+        };
+        for item in tree.iter() {
+            let (key, value) = item?;
+            if is_default && key == migrations::SCHEMA_VERSION_KEY.as_bytes() {
+                continue;
+            }
+            if is_default && key == ACCOUNTS_KEY.as_bytes() && !options.include_auth_keys {
+                debug!("Leaving out auth keys.");
+                continue;
+            }
+            exported
+                .entries
+                .push((base64::encode(&key), base64::encode(&value)));
+        }
+        entries += exported.entries.len();
+        trees.push(exported);
+    }
+
+    let export = Export {
+        format_version: FORMAT_VERSION,
+        schema_version: migrations::schema_version(db)?,
+        exported_at: Utc::now(),
+        trees,
+    };
+    let file = File::create(path).with_context(|| format!("Creating {:?}", path))?;
+    let encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+    serde_json::to_writer(encoder, &export).context("Writing export.")?;
+
+    let summary = ExportSummary {
+        schema_version: export.schema_version,
+        trees: export.trees.len(),
+        entries,
+    };
+    info!(?summary, "Exported.");
+    Ok(summary)
+}
+
+/// Copies everything from an export at `path` into `db`, replacing keys that are in both.
+///
+/// An export from an older version is migrated on its own first, so it ends up in the same
+/// layout as `db`.
+#[instrument(skip(db))]
+pub fn import(db: &sled::Db, path: &Path) -> Result<ExportSummary> {
+    let file = File::open(path).with_context(|| format!("Opening {:?}", path))?;
+    let export: Export = serde_json::from_reader(GzDecoder::new(BufReader::new(file)))
+        .context("Reading export. Is this a civfun export?")?;
+    if export.format_version > FORMAT_VERSION {
+        return Err(anyhow!(
+            "This export was made by a newer version of civfun. Please upgrade civfun."
+        ));
+    }
+    let latest = migrations::latest_version();
+    if export.schema_version > latest {
+        return Err(anyhow!(
+            "The export is version {} but this version of civfun only understands up to {}. \
+            Please upgrade civfun.",
+            export.schema_version,
+            latest
+        ));
+    }
+
+    // Load into a throwaway db so migrations only ever see the export's own data.
+    let staging = sled::Config::new().temporary(true).open()?;
+    let mut entries = 0;
+    for exported in &export.trees {
+        let name = base64::decode(&exported.name).context("Decoding tree name.")?;
+        let tree = staging.open_tree(name)?;
+        for (key, value) in &exported.entries {
+            let key = base64::decode(key).context("Decoding key.")?;
+            let value = base64::decode(value).context("Decoding value.")?;
+            tree.insert(key, value)?;
+            entries += 1;
+        }
+    }
+    staging.insert(
+        migrations::SCHEMA_VERSION_KEY,
+        export.schema_version.to_string().as_str(),
+    )?;
+    migrations::migrate(&staging).context("Migrating export.")?;
+
+    for name in staging.tree_names() {
+        let from = staging.open_tree(&name)?;
+        let to = db.open_tree(&name)?;
+        for item in from.iter() {
+            let (key, value) = item?;
+            if key == migrations::SCHEMA_VERSION_KEY.as_bytes() {
+                continue;
+            }
+            to.insert(key, value)?;
+        }
+    }
+    db.flush()?;
+
+    let summary = ExportSummary {
+        schema_version: export.schema_version,
+        trees: export.trees.len(),
+        entries,
+    };
+    info!(?summary, "Imported.");
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary_db() -> sled::Db {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        migrations::migrate(&db).unwrap();
+        db
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("civfun.export");
+
+        let db = temporary_db();
+        db.insert("config", "{}").unwrap();
+        db.insert(ACCOUNTS_KEY, "[]").unwrap();
+        let user = db.open_tree("user-1").unwrap();
+        user.insert("saved-bytes-2-3", vec![0u8, 1, 2, 255])
+            .unwrap();
+
+        let options = ExportOptions {
+            include_auth_keys: true,
+        };
+        let exported = export(&db, &path, &options).unwrap();
+        assert_eq!(exported.entries, 3);
+
+        let other = temporary_db();
+        other.insert("config", "old").unwrap();
+        import(&other, &path).unwrap();
+        assert_eq!(other.get("config").unwrap().unwrap(), "{}");
+        assert_eq!(other.get(ACCOUNTS_KEY).unwrap().unwrap(), "[]");
+        assert_eq!(
+            other
+                .open_tree("user-1")
+                .unwrap()
+                .get("saved-bytes-2-3")
+                .unwrap()
+                .unwrap(),
+            vec![0u8, 1, 2, 255]
+        );
+        assert_eq!(
+            migrations::schema_version(&other).unwrap(),
+            migrations::latest_version()
+        );
+    }
+
+    #[test]
+    fn without_auth_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("civfun.export");
+
+        let db = temporary_db();
+        db.insert(ACCOUNTS_KEY, "[]").unwrap();
+        export(&db, &path, &ExportOptions::default()).unwrap();
+
+        let other = temporary_db();
+        import(&other, &path).unwrap();
+        assert!(other.get(ACCOUNTS_KEY).unwrap().is_none());
+    }
+
+    #[test]
+    fn not_an_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("civfun.export");
+        std::fs::write(&path, b"nope").unwrap();
+        assert!(import(&temporary_db(), &path).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::api::{Game, GameType, UserId};
+use civ5save::{Civ5Save, PlayerType};
+use std::fmt::{Display, Formatter};
+
+/// What the first save of a game has to look like, as far as GMR tells us. GMR doesn't send the
+/// map, speed or difficulty, so those are up to the game's host to share.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectedSetup {
+    pub game_name: String,
+    pub game_type: GameType,
+    /// In turn order.
+    pub players: Vec<ExpectedPlayer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectedPlayer {
+    pub user_id: UserId,
+    pub turn_order: u16,
+    /// The Steam name, when the player's info has been fetched.
+    pub name: Option<String>,
+}
+
+impl ExpectedSetup {
+    pub fn new(game: &Game, name_of: impl Fn(&UserId) -> Option<String>) -> Self {
+        let mut players: Vec<ExpectedPlayer> = game
+            .players
+            .iter()
+            .map(|p| ExpectedPlayer {
+                user_id: p.user_id,
+                turn_order: p.turn_order,
+                name: name_of(&p.user_id),
+            })
+            .collect();
+        players.sort_by_key(|p| p.turn_order);
+        Self {
+            game_name: game.name.clone(),
+            game_type: game.typ,
+            players,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetupProblem {
+    /// The save was made after the game had already started.
+    NotFirstTurn { turn: u32 },
+    /// Every GMR player needs a human slot in the hotseat game, and there can't be any extras.
+    HumanPlayers { expected: usize, found: usize },
+}
+
+impl Display for SetupProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetupProblem::NotFirstTurn { turn } => write!(
+                f,
+                "The save is from turn {}, it needs to be saved on the first turn.",
+                turn
+            ),
+            SetupProblem::HumanPlayers { expected, found } => write!(
+                f,
+                "The game needs {} human players but the save has {}.",
+                expected, found
+            ),
+        }
+    }
+}
+
+/// Everything wrong with `save` as the first save of the game. Empty when it can be uploaded.
+pub fn check(save: &Civ5Save, expected: &ExpectedSetup) -> Vec<SetupProblem> {
+    let mut problems = vec![];
+    if save.header.turn != 0 {
+        problems.push(SetupProblem::NotFirstTurn {
+            turn: save.header.turn,
+        });
+    }
+    let humans = save
+        .players
+        .iter()
+        .filter(|p| matches!(p.player_type, PlayerType::Human))
+        .count();
+    if humans != expected.players.len() {
+        problems.push(SetupProblem::HumanPlayers {
+            expected: expected.players.len(),
+            found: humans,
+        });
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::PlayerOrder;
+    use civ5save::Civ5SaveReader;
+
+    fn expected(players: u64) -> ExpectedSetup {
+        let game = Game {
+            players: (0..players)
+                .map(|i| PlayerOrder {
+                    user_id: UserId::from(i),
+                    turn_order: i as u16,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        ExpectedSetup::new(&game, |_| None)
+    }
+
+    #[test]
+    fn problems() {
+        // Two humans, saved on turn 5.
+        let bytes = std::fs::read("civ5save/saves/Casimir III_0005 BC-3700.Civ5Save").unwrap();
+        let save = Civ5SaveReader::new(&bytes).parse().unwrap();
+
+        assert_eq!(
+            check(&save, &expected(2)),
+            vec![SetupProblem::NotFirstTurn { turn: 5 }]
+        );
+        assert_eq!(
+            check(&save, &expected(3)),
+            vec![
+                SetupProblem::NotFirstTurn { turn: 5 },
+                SetupProblem::HumanPlayers {
+                    expected: 3,
+                    found: 2
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,73 @@
+/// How well `query` matches `text`, higher is better. None unless every character of the query
+/// is in the text, in order. Case is ignored, and characters at the start of a word or right
+/// after the previous match count for more, so "ps" prefers "Play Skirmish" to "Pause".
+pub fn score(query: &str, text: &str) -> Option<u32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut from = 0;
+    let mut last: Option<usize> = None;
+    for q in query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+    {
+        let i = from + text[from..].iter().position(|&c| c == q)?;
+        score += 1;
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 3;
+        }
+        if last.is_some_and(|last| last + 1 == i) {
+            score += 2;
+        }
+        last = Some(i);
+        from = i + 1;
+    }
+    Some(score)
+}
+
+/// The items matching `query`, best first. Equally good matches keep their order, and an empty
+/// query matches everything.
+pub fn filter<'a, T, F>(query: &str, items: &'a [T], text: F) -> Vec<&'a T>
+where
+    F: Fn(&T) -> &str,
+{
+    let mut matches: Vec<(u32, &T)> = items
+        .iter()
+        .filter_map(|item| Some((score(query, text(item))?, item)))
+        .collect();
+    matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    matches.into_iter().map(|(_, item)| item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores() {
+        assert_eq!(score("", "Refresh"), Some(0));
+        assert!(score("rfr", "Refresh").is_some());
+        assert!(score("REF", "refresh").is_some());
+        assert_eq!(score("xyz", "Refresh"), None);
+        assert_eq!(score("hr", "Refresh"), None);
+        assert!(score("ps", "Play Skirmish") > score("ps", "Pause"));
+        assert!(score("ref", "Refresh") > score("ref", "Open the free folder"));
+    }
+
+    #[test]
+    fn filtering() {
+        let items = [
+            "Open settings",
+            "Refresh",
+            "Open save folder",
+            "Play Marathon",
+        ];
+        assert_eq!(
+            filter("ose", &items, |s| s),
+            vec![&"Open settings", &"Open save folder"]
+        );
+        assert_eq!(filter("osf", &items, |s| s), vec![&"Open save folder"]);
+        assert_eq!(filter("", &items, |s| s).len(), items.len());
+        assert!(filter("zzz", &items, |s| s).is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::Path;
+use std::time::Duration;
+use sysinfo::{ProcessExt, System, SystemExt};
+use tokio::sync::mpsc;
+use tracing::trace;
+
+/// How often the process list is checked.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Executable names for each renderer on Windows and under Proton, plus the one inside the macOS
+/// app bundle.
+const EXE_NAMES: &[&str] = &[
+    "CivilizationV.exe",
+    "CivilizationV_DX11.exe",
+    "CivilizationV_Tablet.exe",
+    "Civilization V",
+];
+
+/// Whether Civ V is running. `system` is kept between calls so only the process list is
+/// refreshed.
+pub fn is_running(system: &mut System) -> bool {
+    system.refresh_processes();
+    system
+        .processes()
+        .values()
+        .any(|process| is_civ5(process.exe(), process.name(), process.cmd()))
+}
+
+/// Matches on the executable's file name. Under Proton the executable is Wine's, so the first
+/// argument, which is the path of the Windows executable, is checked as well.
+fn is_civ5(exe: &Path, name: &str, cmd: &[String]) -> bool {
+    let exe_name = exe.file_name().map(|name| name.to_string_lossy());
+    let program = cmd
+        .first()
+        .and_then(|program| program.rsplit(&['/', '\\'][..]).next());
+    let candidates = [exe_name.as_deref(), Some(name), program];
+    candidates.iter().flatten().any(|candidate| {
+        EXE_NAMES
+            .iter()
+            .any(|exe_name| exe_name.eq_ignore_ascii_case(candidate))
+    })
+}
+
+/// Sends on `tx` whenever Civ V starts or stops, starting with the current state. Stops when
+/// `tx` is closed.
+pub fn spawn(tx: mpsc::Sender<bool>) {
+    std::thread::spawn(move || {
+        let mut system = System::new();
+        let mut last = None;
+        loop {
+            let running = is_running(&mut system);
+            if last != Some(running) {
+                trace!(running, "Civ V running changed.");
+                if tx.blocking_send(running).is_err() {
+                    break;
+                }
+                last = Some(running);
+            }
+            std::thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exe_names() {
+        assert!(is_civ5(
+            Path::new(r"C:\Games\Civilization V\CivilizationV_DX11.exe"),
+            "CivilizationV_DX11.exe",
+            &[],
+        ));
+
+        // Proton: Wine's preloader, with the truncated comm as the name.
+        let proton = [
+            r"Z:\home\me\.steam\steam\steamapps\common\Sid Meier's Civilization V\CivilizationV.exe"
+                .to_string(),
+        ];
+        assert!(is_civ5(
+            Path::new("/usr/bin/wine64-preloader"),
+            "CivilizationV.e",
+            &proton
+        ));
+
+        assert!(is_civ5(
+            Path::new("/Applications/Civilization V.app/Contents/MacOS/Civilization V"),
+            "Civilization V",
+            &[],
+        ));
+
+        // Mentioning it in an argument isn't enough.
+        let editor = [
+            "/usr/bin/vim".to_string(),
+            "notes/CivilizationV.exe.txt".to_string(),
+        ];
+        assert!(!is_civ5(Path::new("/usr/bin/vim"), "vim", &editor));
+        assert!(!is_civ5(Path::new(""), "civfun_gmr", &[]));
+    }
+}
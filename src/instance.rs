@@ -0,0 +1,186 @@
+use anyhow::{anyhow, Context};
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, trace, warn};
+
+type Result<T> = anyhow::Result<T>;
+
+const LOCK_FILE: &str = "civfun.lock";
+/// Kept apart from the lock file because Windows won't let other processes read a locked file.
+const INFO_FILE: &str = "civfun.instance";
+const NUDGE: &[u8] = b"nudge\n";
+const STATUS: &[u8] = b"status\n";
+
+/// Held for as long as this process is the running instance. The lock is released by the OS
+/// when the process exits, however that happens.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+    listener: TcpListener,
+}
+
+#[derive(Debug)]
+pub enum Acquired {
+    Lock(InstanceLock),
+    /// The process id of the other instance, if it could be read.
+    AlreadyRunning(Option<u32>),
+}
+
+/// Makes this process the only one using the data in `dir`.
+pub fn acquire(dir: &Path) -> Result<Acquired> {
+    std::fs::create_dir_all(dir).context("Creating data dir.")?;
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(dir.join(LOCK_FILE))
+        .context("Opening lock file.")?;
+    if let Err(err) = file.try_lock_exclusive() {
+        if err.kind() != fs2::lock_contended_error().kind() {
+            return Err(err).context("Locking lock file.");
+        }
+        let pid = read_info(dir).map(|(pid, _)| pid);
+        debug!(?pid, "Already running.");
+        return Ok(Acquired::AlreadyRunning(pid));
+    }
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).context("Listening for nudges.")?;
+    let port = listener.local_addr()?.port();
+    std::fs::write(
+        dir.join(INFO_FILE),
+        format!("{} {}", std::process::id(), port),
+    )
+    .context("Writing instance info.")?;
+    Ok(Acquired::Lock(InstanceLock {
+        _file: file,
+        listener,
+    }))
+}
+
+impl InstanceLock {
+    /// Sends on `tx` whenever another instance calls `nudge()`, and answers `request_status()`
+    /// with whatever `status` returns. Stops when `tx` is closed.
+    pub fn listen<F>(&self, tx: mpsc::Sender<()>, status: F) -> Result<()>
+    where
+        F: Fn() -> String + Send + 'static,
+    {
+        let listener = self.listener.try_clone()?;
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        warn!(?err, "Accepting nudge.");
+                        continue;
+                    }
+                };
+                let _ = stream.set_read_timeout(Some(Duration::from_secs(1)));
+                let mut buf = [0u8; 16];
+                let len = stream.read(&mut buf).unwrap_or(0);
+                match &buf[..len] {
+                    NUDGE => {
+                        if tx.blocking_send(()).is_err() {
+                            break;
+                        }
+                    }
+                    STATUS => {
+                        if let Err(err) = stream.write_all(status().as_bytes()) {
+                            warn!(?err, "Answering status request.");
+                        }
+                    }
+                    _ => trace!("Ignoring connection that isn't a request."),
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Asks the running instance to show itself.
+pub fn nudge(dir: &Path) -> Result<()> {
+    connect(dir)?.write_all(NUDGE)?;
+    Ok(())
+}
+
+/// Asks the running instance how things stand. It answers with JSON, see `Manager::status()`.
+pub fn request_status(dir: &Path) -> Result<String> {
+    let mut stream = connect(dir)?;
+    stream.write_all(STATUS)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut status = String::new();
+    stream
+        .read_to_string(&mut status)
+        .context("Reading status from running instance.")?;
+    Ok(status)
+}
+
+fn connect(dir: &Path) -> Result<TcpStream> {
+    let (_, port) = read_info(dir).ok_or_else(|| anyhow!("No running instance info."))?;
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    TcpStream::connect_timeout(&addr, Duration::from_secs(1))
+        .context("Connecting to running instance.")
+}
+
+fn read_info(dir: &Path) -> Option<(u32, u16)> {
+    parse_info(&std::fs::read_to_string(dir.join(INFO_FILE)).ok()?)
+}
+
+fn parse_info(info: &str) -> Option<(u32, u16)> {
+    let mut parts = info.split_whitespace();
+    let pid = parts.next()?.parse().ok()?;
+    let port = parts.next()?.parse().ok()?;
+    Some((pid, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info() {
+        assert_eq!(parse_info("1234 50000"), Some((1234, 50000)));
+        assert_eq!(parse_info("1234"), None);
+        assert_eq!(parse_info(""), None);
+    }
+
+    #[test]
+    fn second_instance_nudges_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = match acquire(dir.path()).unwrap() {
+            Acquired::Lock(lock) => lock,
+            Acquired::AlreadyRunning(_) => panic!("Nothing else is running."),
+        };
+        let (tx, mut rx) = mpsc::channel(1);
+        lock.listen(tx, String::new).unwrap();
+
+        match acquire(dir.path()).unwrap() {
+            Acquired::AlreadyRunning(pid) => assert_eq!(pid, Some(std::process::id())),
+            Acquired::Lock(_) => panic!("Should already be locked."),
+        }
+        nudge(dir.path()).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+        });
+    }
+
+    #[test]
+    fn second_instance_asks_for_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = match acquire(dir.path()).unwrap() {
+            Acquired::Lock(lock) => lock,
+            Acquired::AlreadyRunning(_) => panic!("Nothing else is running."),
+        };
+        let (tx, _rx) = mpsc::channel(1);
+        lock.listen(tx, || "all good".to_string()).unwrap();
+        assert_eq!(request_status(dir.path()).unwrap(), "all good");
+    }
+}
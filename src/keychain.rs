@@ -0,0 +1,197 @@
+//! Keeps auth keys and the email password in the OS keychain instead of the sled db where
+//! possible: the Keychain on macOS, Credential Manager on Windows and the Secret Service on Linux.
+//!
+//! Where there's no keychain, e.g. a server without a Secret Service or another OS, `store()`
+//! returns false and the caller keeps the secret in sled like before.
+use crate::api::UserId;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Mutex;
+
+type Result<T> = anyhow::Result<T>;
+
+/// The account the SMTP password is stored under, next to the auth keys stored by user id.
+pub const EMAIL_PASSWORD: &str = "email-password";
+
+/// The account an auth key is stored under.
+pub fn auth_key_account(user_id: &UserId) -> String {
+    user_id.to_string()
+}
+
+/// Somewhere to keep secrets by account name. `Manager` uses `Keychain` unless it's given another
+/// with `with_key_store()`.
+pub trait KeyStore: Debug + Send + Sync {
+    /// Returns false when there's nowhere to store the secret.
+    fn store(&self, account: &str, secret: &str) -> Result<bool>;
+    fn load(&self, account: &str) -> Result<Option<String>>;
+    fn remove(&self, account: &str) -> Result<()>;
+}
+
+/// The OS keychain.
+#[derive(Debug, Default)]
+pub struct Keychain;
+
+impl Keychain {
+    const SERVICE: &'static str = "civ.fun gmr";
+}
+
+impl KeyStore for Keychain {
+    fn store(&self, account: &str, secret: &str) -> Result<bool> {
+        platform::store(Self::SERVICE, account, secret)
+    }
+
+    fn load(&self, account: &str) -> Result<Option<String>> {
+        platform::load(Self::SERVICE, account)
+    }
+
+    fn remove(&self, account: &str) -> Result<()> {
+        platform::remove(Self::SERVICE, account)
+    }
+}
+
+#[cfg(any(target_os = "macos", windows))]
+mod platform {
+    use super::Result;
+
+    fn entry(service: &str, account: &str) -> Result<keyring::Entry> {
+        Ok(keyring::Entry::new(service, account)?)
+    }
+
+    pub fn store(service: &str, account: &str, secret: &str) -> Result<bool> {
+        match entry(service, account)?.set_password(secret) {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoStorageAccess(_)) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn load(service: &str, account: &str) -> Result<Option<String>> {
+        match entry(service, account)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn remove(service: &str, account: &str) -> Result<()> {
+        match entry(service, account)?.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Talks to the Secret Service over libdbus. Items are found by `service` and `account`
+/// attributes, the same ones `secret-tool` and keyring use.
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::Result;
+    use anyhow::Context;
+    use dbus_secret_service::{EncryptionType, SecretService};
+    use std::collections::HashMap;
+    use tracing::debug;
+
+    /// None when there's no session bus or nothing on it provides the Secret Service.
+    fn connect() -> Option<SecretService> {
+        match SecretService::connect(EncryptionType::Dh) {
+            Ok(service) => Some(service),
+            Err(err) => {
+                debug!(?err, "No Secret Service.");
+                None
+            }
+        }
+    }
+
+    fn attributes<'a>(service: &'a str, account: &'a str) -> HashMap<&'a str, &'a str> {
+        HashMap::from([("service", service), ("account", account)])
+    }
+
+    pub fn store(service: &str, account: &str, secret: &str) -> Result<bool> {
+        let secret_service = match connect() {
+            Some(secret_service) => secret_service,
+            None => return Ok(false),
+        };
+        let collection = match secret_service.get_default_collection() {
+            Ok(collection) => collection,
+            Err(dbus_secret_service::Error::NoResult) => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+        collection.ensure_unlocked()?;
+        collection.create_item(
+            &format!("{} {}", service, account),
+            attributes(service, account),
+            secret.as_bytes(),
+            true,
+            "text/plain",
+        )?;
+        Ok(true)
+    }
+
+    pub fn load(service: &str, account: &str) -> Result<Option<String>> {
+        let secret_service = match connect() {
+            Some(secret_service) => secret_service,
+            None => return Ok(None),
+        };
+        let found = secret_service.search_items(attributes(service, account))?;
+        let item = match found.unlocked.first().or_else(|| found.locked.first()) {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+        item.ensure_unlocked()?;
+        let secret = String::from_utf8(item.get_secret()?).context("Decoding secret.")?;
+        Ok(Some(secret))
+    }
+
+    pub fn remove(service: &str, account: &str) -> Result<()> {
+        let secret_service = match connect() {
+            Some(secret_service) => secret_service,
+            None => return Ok(()),
+        };
+        let found = secret_service.search_items(attributes(service, account))?;
+        for item in found.unlocked.iter().chain(&found.locked) {
+            item.delete()?;
+        }
+        Ok(())
+    }
+}
+
+/// No keychain, so secrets stay in sled.
+#[cfg(not(any(target_os = "macos", windows, target_os = "linux")))]
+mod platform {
+    use super::Result;
+
+    pub fn store(_service: &str, _account: &str, _secret: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    pub fn load(_service: &str, _account: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    pub fn remove(_service: &str, _account: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Forgets everything when dropped, for tests.
+#[derive(Debug, Default)]
+pub struct MemoryKeyStore(Mutex<HashMap<String, String>>);
+
+impl KeyStore for MemoryKeyStore {
+    fn store(&self, account: &str, secret: &str) -> Result<bool> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(account.to_string(), secret.to_string());
+        Ok(true)
+    }
+
+    fn load(&self, account: &str) -> Result<Option<String>> {
+        Ok(self.0.lock().unwrap().get(account).cloned())
+    }
+
+    fn remove(&self, account: &str) -> Result<()> {
+        self.0.lock().unwrap().remove(account);
+        Ok(())
+    }
+}
@@ -1,2 +1,36 @@
 pub mod api;
+pub mod archive;
+pub mod attention;
+pub mod breaker;
+pub mod clock;
+pub mod config;
+pub mod diagnostics;
+pub mod email;
+pub mod event_log;
+pub mod export;
+pub mod first_turn;
+pub mod fuzzy;
+pub mod game_process;
+pub mod instance;
+pub mod keychain;
+pub mod logging;
 pub mod manager;
+pub mod migrations;
+pub mod mirror;
+pub mod notify;
+pub mod os_theme;
+pub mod poll;
+pub mod progress;
+pub mod reminders;
+pub mod retention;
+pub mod save_dir;
+pub mod stats;
+pub mod steam;
+pub mod temp_files;
+pub mod update;
+pub mod usage;
+pub mod watchdog;
+pub mod watcher;
+pub mod webhook;
+
+pub use config::Config;
@@ -0,0 +1,314 @@
+use crate::event_log::{ActivityKind, LoggedEvent};
+use crate::manager::data_dir_path;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::Subscriber;
+use tracing_subscriber::fmt::time::ChronoUtc;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+type Result<T> = anyhow::Result<T>;
+
+pub const LOG_FILENAME: &str = "civfun.log";
+
+/// The folder in the data dir logs are written to.
+pub const LOG_DIR: &str = "logs";
+
+/// Start a new log file once the current one gets this big.
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Older log files kept around, as `civfun.1.log` and so on.
+const KEEP_LOGS: usize = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn directive(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+}
+
+/// Where logs are written, under the data dir.
+pub fn log_dir() -> Result<PathBuf> {
+    data_dir_path(Path::new(LOG_DIR))
+}
+
+/// Sends tracing output to stdout as usual and, as JSON lines, to rotating files in `dir`.
+/// `RUST_LOG` overrides `level` when it's set.
+///
+/// Call this as early as possible, before the config can be read, and use the returned handle to
+/// switch to the configured level once it can.
+pub fn init(dir: &Path, level: LogLevel) -> Result<LevelHandle> {
+    let file = RotatingFile::open(dir, MAX_LOG_BYTES, KEEP_LOGS)?;
+    let from_env = EnvFilter::try_from_default_env().ok();
+    let overridden = from_env.is_some();
+    let (filter, reload) =
+        reload::Layer::new(from_env.unwrap_or_else(|| EnvFilter::new(level.directive())));
+    tracing_subscriber::registry()
+        .with(filter)
+        // stdout is kept for the output of subcommands. Colours would garble the journal when
+        // running under systemd.
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(std::io::stderr().is_terminal())
+                .with_writer(std::io::stderr),
+        )
+        .with(file_layer(file))
+        .try_init()
+        .context("Setting up logging.")?;
+    Ok(LevelHandle(if overridden { None } else { Some(reload) }))
+}
+
+/// JSON lines with RFC 3339 timestamps, which is what `parse_problem()` reads back.
+fn file_layer<S>(file: RotatingFile) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_subscriber::fmt::layer()
+        .json()
+        .with_timer(ChronoUtc::rfc3339())
+        .with_ansi(false)
+        .with_writer(file)
+}
+
+/// Changes the level logging was started with. Does nothing when `RUST_LOG` is set.
+pub struct LevelHandle(Option<reload::Handle<EnvFilter, Registry>>);
+
+impl LevelHandle {
+    pub fn set(&self, level: LogLevel) -> Result<()> {
+        if let Some(reload) = &self.0 {
+            reload
+                .reload(EnvFilter::new(level.directive()))
+                .context("Changing the log level.")?;
+        }
+        Ok(())
+    }
+}
+
+/// Warnings and errors from the current log file, newest first, as events for the activity feed.
+pub fn recent_problems(dir: &Path, limit: usize) -> Result<Vec<LoggedEvent>> {
+    let path = dir.join(LOG_FILENAME);
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+    let contents = std::fs::read_to_string(&path).with_context(|| format!("Reading {:?}", path))?;
+    Ok(contents
+        .lines()
+        .rev()
+        .filter_map(parse_problem)
+        .take(limit)
+        .collect())
+}
+
+/// A line written by the JSON layer in `init()`, if it's a warning or an error.
+fn parse_problem(line: &str) -> Option<LoggedEvent> {
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    let level = value.get("level")?.as_str()?;
+    if level != "WARN" && level != "ERROR" {
+        return None;
+    }
+    let at = DateTime::parse_from_rfc3339(value.get("timestamp")?.as_str()?).ok()?;
+    let fields = value.get("fields")?.as_object()?;
+    let mut description = fields
+        .get("message")
+        .and_then(|m| m.as_str())
+        .unwrap_or_default()
+        .to_string();
+    // e.g. the `?err` in `warn!(?err, "Fetching games failed.")`.
+    for (name, field) in fields.iter().filter(|(name, _)| *name != "message") {
+        let field = match field.as_str() {
+            Some(s) => s.to_string(),
+            None => field.to_string(),
+        };
+        description.push_str(&format!(" {}={}", name, field));
+    }
+    Some(LoggedEvent {
+        at: at.with_timezone(&Utc),
+        game_id: None,
+        description: format!("{}: {}", level, description),
+        kind: ActivityKind::Problem,
+    })
+}
+
+/// A log file that's moved aside once it's `max_bytes` long, keeping `keep` old ones.
+#[derive(Debug, Clone)]
+pub struct RotatingFile {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    dir: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn open(dir: &Path, max_bytes: u64, keep: usize) -> Result<Self> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Creating {:?}", dir))?;
+        let file = append(&dir.join(LOG_FILENAME))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Inner {
+                dir: dir.to_path_buf(),
+                max_bytes,
+                keep,
+                file,
+                size,
+            })),
+        })
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn numbered(dir: &Path, n: usize) -> PathBuf {
+    dir.join(format!("civfun.{}.log", n))
+}
+
+impl Inner {
+    fn rotate(&mut self) -> io::Result<()> {
+        let _ = std::fs::remove_file(numbered(&self.dir, self.keep));
+        for n in (1..self.keep).rev() {
+            let from = numbered(&self.dir, n);
+            if from.exists() {
+                std::fs::rename(from, numbered(&self.dir, n + 1))?;
+            }
+        }
+        let current = self.dir.join(LOG_FILENAME);
+        if self.keep == 0 {
+            std::fs::remove_file(&current)?;
+        } else {
+            std::fs::rename(&current, numbered(&self.dir, 1))?;
+        }
+        self.file = append(&current)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut inner = self.inner.lock().unwrap_or_else(|p| p.into_inner());
+        if inner.size > 0 && inner.size + buf.len() as u64 > inner.max_bytes {
+            inner.rotate()?;
+        }
+        let written = inner.file.write(buf)?;
+        inner.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .file
+            .flush()
+    }
+}
+
+impl MakeWriter for RotatingFile {
+    type Writer = RotatingFile;
+
+    fn make_writer(&self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = RotatingFile::open(dir.path(), 10, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(dir.path().join(LOG_FILENAME)), "fourth\n");
+        assert_eq!(read(numbered(dir.path(), 1)), "third\n");
+        assert_eq!(read(numbered(dir.path(), 2)), "second\n");
+        assert!(!numbered(dir.path(), 3).exists());
+    }
+
+    #[test]
+    fn problems_from_log() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(LOG_FILENAME),
+            concat!(
+                r#"{"timestamp":"2021-10-16T10:00:00.000000Z","level":"WARN","fields":{"message":"Fetching games failed.","err":"timed out"},"target":"civfun_gmr::manager"}"#,
+                "\n",
+                r#"{"timestamp":"2021-10-16T10:01:00.000000Z","level":"INFO","fields":{"message":"Uploaded."},"target":"civfun_gmr::manager"}"#,
+                "\n",
+                "not json\n",
+                r#"{"timestamp":"2021-10-16T10:02:00.000000Z","level":"ERROR","fields":{"message":"Processing."},"target":"civfun_gmr::ui"}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let problems = recent_problems(dir.path(), 10).unwrap();
+        let descriptions: Vec<_> = problems.iter().map(|p| p.description.as_str()).collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                "ERROR: Processing.",
+                "WARN: Fetching games failed. err=timed out"
+            ]
+        );
+        assert_eq!(recent_problems(dir.path(), 1).unwrap().len(), 1);
+        assert!(recent_problems(&dir.path().join("missing"), 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn problems_from_file_layer() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = RotatingFile::open(dir.path(), MAX_LOG_BYTES, KEEP_LOGS).unwrap();
+        let subscriber = tracing_subscriber::registry().with(file_layer(file));
+        let before = Utc::now() - chrono::Duration::seconds(1);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(err = "timed out", "Fetching games failed.");
+            tracing::info!("Uploaded.");
+        });
+
+        let problems = recent_problems(dir.path(), 10).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(
+            problems[0].description,
+            "WARN: Fetching games failed. err=timed out"
+        );
+        assert!(problems[0].at >= before);
+        assert!(problems[0].at <= Utc::now());
+    }
+}
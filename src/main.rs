@@ -1,44 +1,123 @@
-use anyhow::Context;
-use civfun_gmr::manager::{data_dir_path, Manager};
+use anyhow::{anyhow, Context};
+use civfun_gmr::instance::{self, Acquired};
+use civfun_gmr::manager::{data_dir_path, set_data_dir, DataDir, Manager};
+use civfun_gmr::{diagnostics, logging};
 use clap::{AppSettings, Clap};
 use std::path::PathBuf;
 use tracing::debug;
 
+mod cli;
 mod ui;
 
 pub const TITLE: &str = "civ.fun's Multiplayer Robot";
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// A file next to the executable that turns on `--portable`.
+const PORTABLE_MARKER: &str = "portable";
+/// The folder next to the executable that a portable install keeps its data in.
+const PORTABLE_DATA_DIR: &str = "data";
 
+/// Plays your Giant Multi Robot turns of Civilization V: downloads them when it's your go and
+/// uploads them when you've played.
 #[derive(Clap)]
 #[clap(setting = AppSettings::ColoredHelp)]
 struct Opts {
-    #[clap(env = "GMR_AUTH_KEY")]
-    auth_key: String,
-    // cmd: SubCommand,
-}
-
-#[derive(Clap)]
-enum SubCommand {
-    // Login(LoginOpts),
-// List(ListOpts),
-// Download(DownloadOpts),
-// Submit(SubmitOpts),
+    /// Print a man page and exit.
+    #[clap(long, hidden = true)]
+    generate_man: bool,
+    /// Keep the database, logs and temp files here instead of the usual place.
+    #[clap(long, env = "CIVFUN_DATA_DIR", global = true)]
+    data_dir: Option<PathBuf>,
+    /// Keep the database, logs and archived saves in a `data` folder next to civfun, e.g. on a USB
+    /// stick. Also on when there's a file called `portable` next to it and no data dir was given.
+    #[clap(long, global = true)]
+    portable: bool,
+    /// Without one, the window opens.
+    #[clap(subcommand)]
+    cmd: Option<cli::SubCommand>,
 }
 
 fn main() {
-    run().unwrap();
+    if let Err(err) = run() {
+        eprintln!("{:#}", err);
+        std::process::exit(cli::exit_code(&err));
+    }
 }
 
 fn run() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
+    let opts: Opts = Opts::parse();
+    if opts.generate_man {
+        print!("{}", cli::man_page());
+        return Ok(());
+    }
+    let cmd = match opts.cmd {
+        // These only read the files they're given, so they work while civfun is running.
+        Some(cli::SubCommand::Analyze(opts)) => return cli::analyze(opts),
+        Some(cli::SubCommand::Diff(opts)) => return cli::diff(opts),
+        Some(cli::SubCommand::Completions(opts)) => return cli::completions(opts),
+        cmd => cmd,
+    };
 
-    // let opts: Opts = Opts::parse();
+    let exe_dir = exe_dir()?;
+    match (opts.portable, opts.data_dir) {
+        (true, Some(_)) => {
+            return Err(anyhow!(
+                "--portable and --data-dir (or CIVFUN_DATA_DIR) can't be used together."
+            ))
+        }
+        (true, None) => set_data_dir(DataDir::Portable(exe_dir.join(PORTABLE_DATA_DIR)))?,
+        (false, Some(data_dir)) => set_data_dir(DataDir::Custom(data_dir))?,
+        (false, None) if exe_dir.join(PORTABLE_MARKER).exists() => {
+            set_data_dir(DataDir::Portable(exe_dir.join(PORTABLE_DATA_DIR)))?
+        }
+        (false, None) => {}
+    }
+    let data_dir = data_dir_path(&PathBuf::new()).context("Constructing data dir path")?;
+    // Only the instance holding the lock writes to the log files.
+    let lock = match instance::acquire(&data_dir)? {
+        Acquired::Lock(lock) => lock,
+        Acquired::AlreadyRunning(pid) => {
+            match cmd {
+                Some(cli::SubCommand::Status(opts)) => return cli::running_status(&data_dir, opts),
+                // Subcommands are often scripted, so the window is left where it is.
+                Some(_) => {
+                    return Err(anyhow!(
+                        "civfun is already running and has the database open. Close it first."
+                    ))
+                }
+                None => {}
+            }
+            if let Err(err) = instance::nudge(&data_dir) {
+                debug!(?err, "Could not nudge the running instance.");
+            }
+            return Err(match pid {
+                Some(pid) => anyhow!("civfun is already running (process {}).", pid),
+                None => anyhow!("civfun is already running."),
+            });
+        }
+    };
+
+    // Logging starts before the db is opened so problems opening or migrating it are logged. The
+    // configured level applies once the config can be read.
+    let log_level = logging::init(&logging::log_dir()?, Default::default())?;
 
     let db_path = data_dir_path(&PathBuf::from("db.sled")).context("Constructing db.sled path")?;
     debug!(?db_path);
 
     let db =
         sled::open(&db_path).with_context(|| format!("Could not create db at {:?}", &db_path))?;
-    let mut manager = Manager::new(db);
+    let manager = Manager::new(db)?;
+    log_level.set(manager.config()?.log_level)?;
+    diagnostics::install_panic_hook(manager.clone());
+    manager.listen_for_instances(&lock)?;
+    if let Some(cmd) = cmd {
+        return cli::run(&manager, cmd);
+    }
     ui::run(manager)
 }
+
+fn exe_dir() -> anyhow::Result<PathBuf> {
+    let exe = std::env::current_exe().context("Finding the executable")?;
+    exe.parent()
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("The executable {:?} isn't in a folder.", exe))
+}
@@ -1,34 +1,81 @@
 use crate::api::{
-    Api, DownloadMessage, Game, GameId, GetGamesAndPlayers, Player, TurnId, UploadMessage, UserId,
+    self, sort_by_deadline, Api, DownloadMessage, Game, GameId, GetGamesAndPlayers, Percentage,
+    Player, TurnId, UploadMessage, UserId,
+};
+use crate::archive::{Archive, ArchiveEntry, ArchiveKind};
+use crate::breaker::Breaker;
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Config, GameSettings};
+use crate::diagnostics::Bundle;
+use crate::email::EmailSettings;
+use crate::event_log::{self, EventLog, LoggedEvent};
+use crate::export::{self, ExportOptions, ExportSummary};
+use crate::first_turn::{self, ExpectedSetup, SetupProblem};
+use crate::instance::InstanceLock;
+use crate::keychain::{KeyStore, Keychain};
+use crate::mirror::Mirror;
+use crate::notify::{HeldAlerts, Notification, QuietHours};
+use crate::retention::{StorageUsage, StoredTurn};
+use crate::save_dir::SaveDirProblem;
+use crate::stats::{Dashboard, GameStats, TurnRecord};
+use crate::temp_files::TempFiles;
+use crate::usage::{Transferred, Usage};
+use crate::watchdog::{self, Escalation};
+use crate::{
+    clock, email, game_process, keychain, logging, migrations, notify, poll, reminders, retention,
+    save_dir, stats, steam, watcher, webhook,
 };
 use anyhow::Context;
 use anyhow::{anyhow, Error};
-use civ5save::{Civ5Save, Civ5SaveReader};
-use directories::{BaseDirs, ProjectDirs};
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use civ5save::{Civ5Save, Civ5SaveReader, Civ5SaveWriter};
+use directories::ProjectDirs;
 use iced::futures::TryFutureExt;
-use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
 use regex::Regex;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use sled::IVec;
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{Cursor, Read};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
-use std::time::{Duration, SystemTime};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::mpsc::error::TryRecvError;
-use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, instrument, trace, trace_span, warn, Instrument};
 
 type Result<T> = anyhow::Result<T>;
 
 const CONFIG_KEY: &str = "config";
-const GAMES_KEY: &str = "games";
-const AUTH_KEY: &str = "auth-key";
+pub(crate) const GAMES_KEY: &str = "games";
+pub(crate) const ACCOUNTS_KEY: &str = "accounts";
+/// The user id of the account currently in use.
 const USER_ID_KEY: &str = "user-id";
+/// In the user tree, present while the account is paused.
+const PAUSED_KEY: &str = "paused";
+/// Saves to move into the archive once Civ V has stopped, as a list of `DeferredArchive`.
+const DEFERRED_ARCHIVES_KEY: &str = "deferred-archives";
+/// Alerts held back during quiet hours, as `HeldAlerts`.
+const HELD_ALERTS_KEY: &str = "held-alerts";
+/// GMR points for the current account, as of the last fetch.
+const TOTAL_POINTS_KEY: &str = "total-points";
+/// When games for the current account were last fetched from GMR, in RFC 3339.
+const GAMES_FETCHED_AT_KEY: &str = "games-fetched-at";
+const USAGE_PREFIX: &str = "usage-";
+/// Roughly the size of a late game save on a large map.
+const DEFAULT_SAVE_SIZE: u64 = 4 * 1024 * 1024;
+const PENDING_SAVE_PREFIX: &str = "pending-save-";
+/// Downloads and uploads running at once. The rest wait their turn, soonest deadline first.
+const MAX_ACTIVE_TRANSFERS: usize = 2;
+
+/// How often frontends should call `Manager::process()`.
+pub const PROCESS_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredPlayer {
@@ -37,67 +84,800 @@ pub struct StoredPlayer {
     last_downloaded: SystemTime,
 }
 
-#[derive(Debug)]
+impl StoredPlayer {
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    /// The avatar as downloaded, usually a JPEG.
+    pub fn image_data(&self) -> &[u8] {
+        &self.image_data
+    }
+
+    pub fn last_downloaded(&self) -> SystemTime {
+        self.last_downloaded
+    }
+}
+
+/// Someone the user shares at least one game with.
+#[derive(Debug, Clone)]
+pub struct KnownPlayer {
+    pub user_id: UserId,
+    /// None until their Steam info has been fetched.
+    pub info: Option<StoredPlayer>,
+    pub shared_games: Vec<GameId>,
+    /// Over every shared game, see `stats::average_turn()`.
+    pub average_turn: Option<chrono::Duration>,
+}
+
+/// An authenticated GMR account. Everything specific to an account is kept in its own sled tree,
+/// so switching accounts doesn't lose anything.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Account {
+    pub user_id: UserId,
+    /// Empty when it's `in_keychain`.
+    auth_key: String,
+    #[serde(default)]
+    in_keychain: bool,
+}
+
+impl std::fmt::Debug for Account {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Account")
+            .field("user_id", &self.user_id)
+            .field("in_keychain", &self.in_keychain)
+            .finish()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TransferState {
     Idle,
     Downloading,
     Downloaded,
+    /// The download didn't make it. It's tried again on the next start or on request.
+    DownloadFailed,
+    /// With `confirm_uploads` on, a matched save waits here until `approve_upload()`.
+    UploadAwaitingApproval,
     UploadQueued,
     Uploading,
     UploadComplete,
+    /// The upload was rejected or lost. The bytes are kept until `retry_upload()` queues it again.
+    UploadFailed,
 }
 
-#[derive(Debug)]
+impl TransferState {
+    /// Using bandwidth right now.
+    pub fn is_active(&self) -> bool {
+        matches!(self, TransferState::Downloading | TransferState::Uploading)
+    }
+}
+
+/// A game's transfer and where it is in the queue. `priority` 0 goes first, see
+/// `MAX_ACTIVE_TRANSFERS`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TransferStatus {
+    pub game_id: GameId,
+    pub state: TransferState,
+    pub priority: usize,
+}
+
+/// How things stand, for `civfun status`. A running instance hands it out through
+/// `instance::request_status()`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    pub signed_in: bool,
+    pub paused: bool,
+    pub games_fetched_at: Option<DateTime<Utc>>,
+    pub save_dir: Option<PathBuf>,
+    /// Why saves can't be picked up from `save_dir`, if they can't.
+    pub save_dir_problem: Option<String>,
+    /// Only while running.
+    pub watching: bool,
+    /// Games that aren't idle, in the order they get bandwidth.
+    pub transfers: Vec<GameTransfer>,
+    /// Saves waiting for the user to pick their game.
+    pub pending_saves: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameTransfer {
+    pub game_id: GameId,
+    pub name: String,
+    pub state: TransferState,
+}
+
+/// What gets written to sled for each game's transfer, so an interrupted transfer can be resumed
+/// on the next start. The turn is kept so that state from an older turn is ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredTransfer {
+    turn_id: TurnId,
+    state: TransferState,
+}
+
+/// A game that a new save might belong to. `difference` is the difference score against the
+/// save downloaded for the game's current turn, lower is closer. It's None when there was
+/// nothing to compare against.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveCandidate {
+    pub game: Game,
+    pub difference: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingSave {
+    pub path: PathBuf,
+    pub candidates: Vec<SaveCandidate>,
+}
+
+/// What `upload_dropped()` did with a save.
+#[derive(Debug, Clone)]
+pub enum DroppedSave {
+    /// Matched to this game and queued for upload.
+    Queued(Game),
+    /// Could be for any of these games, so the user has to pick one.
+    Unclear(PendingSave),
+}
+
+/// A save waiting in `UploadAwaitingApproval`, with what's known about it to help decide.
+#[derive(Debug, Clone)]
+pub struct UploadSummary {
+    pub game: Game,
+    pub path: PathBuf,
+    /// The turn in the save itself, which doesn't always match GMR's.
+    pub turn: u32,
+    /// Whose turn is next, going by the filename.
+    pub leader: Option<String>,
+    /// Against the save downloaded for this turn, like `SaveCandidate::difference`.
+    pub difference: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
 pub enum Event {
     AuthenticationSuccess,
     AuthenticationFailure,
+    /// A different account is now in use. Anything shown for the previous account is stale.
+    AccountSwitched(UserId),
     UpdatedGames(Vec<Game>),
+    /// Settings were changed with `update_config()`.
+    ConfigChanged(Box<Config>),
+    GameSettingsChanged {
+        game_id: GameId,
+        settings: GameSettings,
+    },
+    /// A save was matched to this game but `confirm_uploads` is on. Send it with
+    /// `approve_upload()` or drop it with `reject_upload()`.
+    UploadNeedsApproval {
+        game: Game,
+        path: PathBuf,
+    },
+    /// A save for a turn that was already queued or sent turned up again, e.g. because the user
+    /// saved twice. It wasn't uploaded.
+    DuplicateTurnIgnored {
+        game_id: GameId,
+        path: PathBuf,
+    },
+    /// The stored save for a turn was damaged, so it's being downloaded again.
+    SaveRepaired {
+        game_id: GameId,
+        turn_id: TurnId,
+        problem: String,
+    },
+    /// Records in sled can't be read or decoded. Exporting what's still readable and starting
+    /// over may help.
+    DatabaseCorrupt {
+        message: String,
+    },
+    /// GMR couldn't be reached or gave a bad response. Polling backs off until it works again.
+    FetchGamesFailed {
+        message: String,
+    },
+    /// The save for the first turn of a game doesn't match how GMR set the game up, so it
+    /// wasn't uploaded. See `first_turn_setup()`.
+    FirstTurnSaveRejected {
+        game_id: GameId,
+        path: PathBuf,
+        problems: Vec<SetupProblem>,
+    },
+    /// It has become the user's turn in this game since games were last fetched.
+    NewTurn(Game),
     UpdatedPlayer(StoredPlayer),
+    DownloadStarted(GameId),
+    /// `bytes` is how much has been received so far. See `progress::TransferProgress` for
+    /// speed and time left.
+    DownloadProgress {
+        game_id: GameId,
+        progress: Option<Percentage>,
+        bytes: u64,
+    },
+    DownloadComplete {
+        game_id: GameId,
+        path: PathBuf,
+    },
+    DownloadFailed {
+        game_id: GameId,
+        message: String,
+    },
+    UploadStarted(GameId),
+    UploadProgress {
+        game_id: GameId,
+        progress: Option<Percentage>,
+        bytes: u64,
+    },
+    UploadComplete(GameId),
+    UploadFailed {
+        game_id: GameId,
+        message: String,
+    },
+    /// A new save couldn't be matched to exactly one game. Resolve it with
+    /// `confirm_save_assignment()` or `dismiss_save()`.
+    SaveNeedsConfirmation {
+        path: PathBuf,
+        candidates: Vec<SaveCandidate>,
+    },
+    /// None of the usual places for the hotseat directory exist. Set `save_dir` with
+    /// `update_config()`.
+    SaveDirNotFound {
+        tried: Vec<PathBuf>,
+    },
+    /// The hotseat directory was found but downloading turns into it would fail.
+    SaveDirProblem {
+        path: PathBuf,
+        problem: SaveDirProblem,
+    },
+    /// Another copy of civfun was started and exited, asking this one to be shown instead.
+    AnotherInstanceStarted,
+    /// Civ V started or stopped. While it runs, moving and overwriting saves is put off.
+    GameRunning(bool),
+    /// Background activity was paused or resumed, see `pause()`.
+    Paused(bool),
+    /// Quiet hours are over. Sums up the alerts that were held back from desktop notifications
+    /// and webhooks while they were on.
+    DeferredAlerts(Notification),
+    /// The local clock is off from GMR's by `skew` or more (positive when it's behind), or is
+    /// back within `clock::significant_skew()` of it. Deadlines use GMR's time either way.
+    ClockSkew {
+        skew: chrono::Duration,
+    },
+    /// Part of `process()` failed. When `recoverable` it's tried again on the next call,
+    /// otherwise it failed too many times in a row and is paused for a while.
+    Error {
+        source: Subsystem,
+        message: String,
+        recoverable: bool,
+    },
+    /// It's the user's turn and it expires soon. Sent once for each of the configured
+    /// `reminder_minutes` per turn.
+    TurnDeadlineApproaching {
+        game: Game,
+        remaining: chrono::Duration,
+    },
+    /// The turn is within `skip_warning_hours` of being skipped. Sent again each time the
+    /// escalation goes up.
+    TurnAboutToBeSkipped {
+        game: Game,
+        remaining: chrono::Duration,
+        escalation: Escalation,
+    },
+}
+
+impl Event {
+    /// The game this event is about, if it's about one.
+    pub fn game_id(&self) -> Option<GameId> {
+        match self {
+            Event::NewTurn(game)
+            | Event::TurnDeadlineApproaching { game, .. }
+            | Event::TurnAboutToBeSkipped { game, .. }
+            | Event::UploadNeedsApproval { game, .. } => Some(game.game_id),
+            Event::DownloadStarted(game_id)
+            | Event::DownloadProgress { game_id, .. }
+            | Event::DownloadComplete { game_id, .. }
+            | Event::DownloadFailed { game_id, .. }
+            | Event::UploadStarted(game_id)
+            | Event::UploadProgress { game_id, .. }
+            | Event::UploadComplete(game_id)
+            | Event::UploadFailed { game_id, .. }
+            | Event::GameSettingsChanged { game_id, .. }
+            | Event::SaveRepaired { game_id, .. }
+            | Event::DuplicateTurnIgnored { game_id, .. }
+            | Event::FirstTurnSaveRejected { game_id, .. } => Some(*game_id),
+            _ => None,
+        }
+    }
+
+    /// The game, for events that carry all of it.
+    pub fn game(&self) -> Option<&Game> {
+        match self {
+            Event::NewTurn(game)
+            | Event::TurnDeadlineApproaching { game, .. }
+            | Event::TurnAboutToBeSkipped { game, .. }
+            | Event::UploadNeedsApproval { game, .. } => Some(game),
+            _ => None,
+        }
+    }
+}
+
+/// The independent parts of `process()`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    Authentication,
+    Games,
+    GameProcess,
+    Transfers,
+    Saves,
+    Reminders,
+    Cleanup,
+    IntegrityCheck,
+    Instance,
+}
+
+impl Display for Subsystem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Subsystem::Authentication => "authentication",
+            Subsystem::Games => "refreshing games",
+            Subsystem::GameProcess => "checking for Civ V",
+            Subsystem::Transfers => "transfers",
+            Subsystem::Saves => "new saves",
+            Subsystem::Reminders => "reminders",
+            Subsystem::Cleanup => "cleanup",
+            Subsystem::IntegrityCheck => "integrity check",
+            Subsystem::Instance => "other instances",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 #[derive(Debug)]
 enum FetchGames {
-    Games(Vec<Game>),
+    /// With the user's total points, and how far GMR's clock is ahead of ours when it could be
+    /// worked out.
+    Games(Vec<Game>, u64, Option<chrono::Duration>),
     StoredPlayer(StoredPlayer),
 }
 
-#[derive(Debug)]
-pub struct Manager {
-    db: sled::Db,
+/// Everything waiting on `rx`, and whether the sending side has gone away.
+fn drain<T>(rx: &mut Receiver<T>) -> (Vec<T>, bool) {
+    let mut items = vec![];
+    loop {
+        match rx.try_recv() {
+            Ok(item) => items.push(item),
+            Err(TryRecvError::Empty) => return (items, false),
+            Err(TryRecvError::Disconnected) => return (items, true),
+        }
+    }
+}
+
+/// What's wrong with a save, if anything.
+fn save_problem(bytes: &[u8]) -> Option<String> {
+    if !bytes.starts_with(b"CIV5") {
+        return Some("The save doesn't start with CIV5.".into());
+    }
+    Civ5SaveReader::new(bytes)
+        .parse()
+        .err()
+        .map(|err| format!("The save can't be read: {:#}", err))
+}
+
+/// In-memory state shared by every clone of a `Manager`.
+#[derive(Debug, Default)]
+struct State {
     transfer: HashMap<GameId, TransferState>,
-    auth_rx: Option<oneshot::Receiver<Option<UserId>>>,
+    /// Where each game was in the last `process_transfers()`, by turn deadline.
+    transfer_priority: HashMap<GameId, usize>,
+    auth_rx: Option<oneshot::Receiver<(String, Result<Option<UserId>>)>>,
     fetch_games_rx: Option<mpsc::Receiver<Result<FetchGames>>>,
     download_rx: HashMap<GameId, Receiver<DownloadMessage>>,
     upload_rx: HashMap<GameId, Receiver<UploadMessage>>,
     watch_files_rx: Option<Receiver<String>>,
+    last_reminder_check: Option<DateTime<Utc>>,
+    last_cleanup: Option<DateTime<Utc>>,
+    last_integrity_check: Option<DateTime<Utc>>,
+    poll_delay_tx: Option<watch::Sender<Duration>>,
+    poll_failures: u32,
+    breakers: HashMap<Subsystem, Breaker>,
+    nudge_rx: Option<mpsc::Receiver<()>>,
+    last_email: HashMap<GameId, Instant>,
+    /// Player info read from or written to sled, so lookups don't need to decode it again.
+    players: HashMap<UserId, StoredPlayer>,
+    game_running_rx: Option<mpsc::Receiver<bool>>,
+    game_running: bool,
+    /// How far GMR's clock is ahead of ours, once a response has said.
+    clock_skew: Option<chrono::Duration>,
+    /// Whether `clock_skew` was significant, to only report changes.
+    clock_skewed: bool,
+    /// `play_turn()` was asked for before the save was downloaded. Civ V starts once it is.
+    play_after_download: HashSet<GameId>,
+    /// Civ V was started by `play_turn()` for these games.
+    playing: HashSet<GameId>,
+    /// Uploads that were in flight when civfun stopped, by the turn being uploaded. They could
+    /// have finished, so they aren't sent again until a fetch shows the turn hasn't moved on.
+    interrupted_uploads: HashMap<GameId, TurnId>,
+    /// A scan ran before games were fetched, so it's run again after the next fetch in case it
+    /// skipped saves for turns it didn't know about yet.
+    rescan_saves: bool,
+}
+
+/// A save that has been looked at, stored by its fingerprint.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SeenSave {
+    /// The game it was for, if it was matched to one.
+    game_id: Option<GameId>,
+    /// Files with these bytes that were handled or reported as `Event::DuplicateTurnIgnored`.
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum DeferredArchive {
+    Uploaded(GameId, TurnId),
+    Downloaded(Game),
+}
+
+/// Cheap to clone. Every clone shares the same db, state and event subscribers, so it can be
+/// handed to as many frontends and tasks as needed.
+#[derive(Debug, Clone)]
+pub struct Manager {
+    db: sled::Db,
+    state: Arc<Mutex<State>>,
+    /// Held while `process()` runs so two clones don't act on the same transfer.
+    processing: Arc<Mutex<()>>,
+    events_tx: broadcast::Sender<Event>,
+    event_log: Arc<EventLog>,
+    /// Where GMR is, only changed for tests.
+    api_url: String,
+    clock: Arc<dyn Clock>,
+    /// Overrides `data_dir_path()` for temp files, logs and a portable archive.
+    data_dir: Option<PathBuf>,
+    keys: Arc<dyn KeyStore>,
 }
 
 impl Manager {
-    pub fn new(db: sled::Db) -> Self {
-        Self {
+    /// Brings the database schema up to date before anything else touches it.
+    pub fn new(db: sled::Db) -> Result<Self> {
+        migrations::migrate(&db).context("Migrating database.")?;
+        let (events_tx, _) = broadcast::channel(100);
+        let event_log = Arc::new(EventLog::new(&db).context("Opening event log.")?);
+        Ok(Self {
             db,
-            transfer: Default::default(),
-            auth_rx: None,
-            fetch_games_rx: None,
-            // download_rx: Default::default(),
-            download_rx: Default::default(),
-            upload_rx: Default::default(),
-            watch_files_rx: None,
+            state: Default::default(),
+            processing: Default::default(),
+            events_tx,
+            event_log,
+            api_url: api::GMR_URL.to_string(),
+            clock: Arc::new(SystemClock),
+            data_dir: None,
+            keys: Arc::new(Keychain),
+        })
+    }
+
+    /// Use a different GMR server, e.g. a mock one. Together with `sled::Config::temporary()`
+    /// this runs a whole manager in tests without touching the real service or disk.
+    pub fn with_api_url(mut self, url: &str) -> Self {
+        self.api_url = url.to_string();
+        self
+    }
+
+    /// Tell the time with something other than the system clock, e.g. a `MockClock` in tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Keep temp files and logs in `dir` instead of the data dir shared by the whole process.
+    pub fn with_data_dir(mut self, dir: &Path) -> Self {
+        self.data_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Keep auth keys and the email password somewhere other than the OS keychain, e.g. a
+    /// `MemoryKeyStore` in tests.
+    pub fn with_key_store(mut self, keys: Arc<dyn KeyStore>) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    fn local_now(&self) -> NaiveDateTime {
+        self.now().with_timezone(&Local).naive_local()
+    }
+
+    fn data_path(&self, join: &Path) -> Result<PathBuf> {
+        match &self.data_dir {
+            Some(dir) => Ok(dir.join(join)),
+            None => data_dir_path(join),
+        }
+    }
+
+    fn temp_files(&self) -> Result<TempFiles> {
+        TempFiles::open(&self.db, &self.data_path(Path::new("tmp"))?)
+    }
+
+    /// Keep the guard for as short as possible, and never while calling another method that
+    /// needs it.
+    fn state(&self) -> MutexGuard<'_, State> {
+        // A panic while holding the lock can't leave State half updated in a way that matters
+        // more than stopping altogether.
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Receive every `Event` the manager emits from now on.
+    ///
+    /// Each subscriber gets its own copy of each event. A subscriber that falls too far behind
+    /// will get `RecvError::Lagged` and miss the oldest events.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.events_tx.subscribe()
+    }
+
+    fn emit(&self, event: Event) {
+        trace!(?event, "Emitting.");
+        if let Err(err) = self.log_event(&event) {
+            warn!(?err, "Could not log event.");
+        }
+        if self.defer_alert(&event) {
+            debug!("Quiet hours, alerting later.");
+        } else if let Err(err) = self.send_webhooks(&event) {
+            warn!(?err, "Could not send webhooks.");
+        }
+        if let Err(err) = self.send_email(&event) {
+            warn!(?err, "Could not send email.");
+        }
+        // An error here only means nobody is subscribed yet, which is fine.
+        let _ = self.events_tx.send(event);
+    }
+
+    fn log_event(&self, event: &Event) -> Result<()> {
+        if !event_log::is_logged(event) {
+            return Ok(());
+        }
+        if let Some(game) = event.game() {
+            return self.event_log.add(event, Some(game), self.now());
+        }
+        let game = match event.game_id() {
+            Some(game_id) => self.games()?.into_iter().find(|g| g.game_id == game_id),
+            None => None,
+        };
+        self.event_log.add(event, game.as_ref(), self.now())
+    }
+
+    /// Keeps an alert for later when it's quiet hours and it isn't urgent.
+    fn defer_alert(&self, event: &Event) -> bool {
+        let config = match self.config() {
+            Ok(config) => config,
+            Err(_) => return false,
+        };
+        let notification = match notify::notification_for(event) {
+            Some(notification)
+                if !notify::is_critical(event) && notify::is_quiet(&config, self.local_now()) =>
+            {
+                notification
+            }
+            _ => return false,
+        };
+        let muted = match event.game_id() {
+            Some(game_id) => self
+                .game_settings(&game_id)
+                .map_or(false, |settings| settings.muted),
+            None => false,
+        };
+        if muted {
+            // Dropped later anyway, so there's nothing to hold.
+            return false;
+        }
+        let held = self.held_alerts().and_then(|mut held| {
+            held.push(notification);
+            self.db
+                .insert(HELD_ALERTS_KEY, serde_json::to_vec(&held)?)?;
+            Ok(())
+        });
+        match held {
+            Ok(()) => true,
+            Err(err) => {
+                warn!(?err, "Could not hold back alert.");
+                false
+            }
+        }
+    }
+
+    fn held_alerts(&self) -> Result<HeldAlerts> {
+        match self.db.get(HELD_ALERTS_KEY)? {
+            Some(b) => serde_json::from_slice(&b).context("Decoding held alerts."),
+            None => Ok(HeldAlerts::default()),
+        }
+    }
+
+    /// Sums up whatever was held back once quiet hours are over, as one webhook message and
+    /// `Event::DeferredAlerts` for a desktop notification.
+    fn send_deferred_alerts(&self) -> Result<()> {
+        if !self.db.contains_key(HELD_ALERTS_KEY)?
+            || notify::is_quiet(&self.config()?, self.local_now())
+        {
+            return Ok(());
+        }
+        let held = self.held_alerts()?;
+        self.db.remove(HELD_ALERTS_KEY)?;
+        let summary = match held.summary() {
+            Some(summary) => summary,
+            None => return Ok(()),
+        };
+        info!(
+            count = held.notifications.len() + held.dropped,
+            "Quiet hours are over, sending held back alerts."
+        );
+        for webhook in self.config()?.webhooks {
+            let payload = webhook::summary_payload(webhook.format, &summary);
+            tokio::spawn(async move {
+                if let Err(err) = webhook::send(&webhook.url, &payload).await {
+                    warn!(?err, "Webhook failed.");
+                }
+            });
+        }
+        self.emit(Event::DeferredAlerts(summary));
+        Ok(())
+    }
+
+    fn send_webhooks(&self, event: &Event) -> Result<()> {
+        let webhooks = self.config()?.webhooks;
+        let game_id = match event.game_id() {
+            Some(game_id) if !webhooks.is_empty() => game_id,
+            _ => return Ok(()),
+        };
+        if self.game_settings(&game_id)?.muted {
+            return Ok(());
+        }
+        let game = match self.games()?.into_iter().find(|g| g.game_id == game_id) {
+            Some(game) => game,
+            None => return Ok(()),
+        };
+        let player = match self.user_id()? {
+            Some(user_id) => self.player_name(&user_id),
+            None => None,
+        };
+        for webhook in webhooks {
+            let payload = match webhook::payload(webhook.format, event, &game, player.as_deref()) {
+                Some(payload) => payload,
+                None => continue,
+            };
+            tokio::spawn(async move {
+                if let Err(err) = webhook::send(&webhook.url, &payload).await {
+                    warn!(?err, "Webhook failed.");
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Emails new turns and approaching deadlines, at most once per `min_interval_minutes` for
+    /// each game.
+    fn send_email(&self, event: &Event) -> Result<()> {
+        let settings = match self.email_settings()? {
+            Some(settings) => settings,
+            None => return Ok(()),
+        };
+        let game_id = match event {
+            Event::NewTurn(game) | Event::TurnDeadlineApproaching { game, .. } => game.game_id,
+            Event::TurnAboutToBeSkipped {
+                game, escalation, ..
+            } if *escalation >= Escalation::Email => game.game_id,
+            _ => return Ok(()),
+        };
+        if self.game_settings(&game_id)?.muted {
+            return Ok(());
+        }
+        let notification = match notify::notification_for(event) {
+            Some(notification) => notification,
+            None => return Ok(()),
+        };
+        {
+            let now = Instant::now();
+            let mut state = self.state();
+            if let Some(last) = state.last_email.get(&game_id) {
+                if now.duration_since(*last) < settings.min_interval() {
+                    debug!(?game_id, "Emailed about this game recently.");
+                    return Ok(());
+                }
+            }
+            state.last_email.insert(game_id, now);
+        }
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = email::send(&settings, &notification.summary, &notification.body) {
+                warn!(?err, "Email failed.");
+            }
+        });
+        Ok(())
+    }
+
+    /// Sends an email with the current settings, to check they work.
+    pub async fn send_test_email(&self) -> Result<()> {
+        let settings = self
+            .email_settings()?
+            .ok_or_else(|| anyhow!("Email isn't set up."))?;
+        tokio::task::spawn_blocking(move || {
+            email::send(
+                &settings,
+                "civfun test email",
+                "Turn alerts from civfun will arrive like this.",
+            )
+        })
+        .await?
+    }
+
+    /// Email settings with the password filled in from the keychain.
+    fn email_settings(&self) -> Result<Option<EmailSettings>> {
+        let mut settings = match self.config()?.email {
+            Some(settings) => settings,
+            None => return Ok(None),
+        };
+        if settings.password.is_empty() {
+            settings.password = self
+                .keys
+                .load(keychain::EMAIL_PASSWORD)?
+                .unwrap_or_default();
         }
+        Ok(Some(settings))
+    }
+
+    /// Writes logs, crash reports, settings without secrets and database stats to one gzipped
+    /// JSON file at `path`, for attaching to bug reports.
+    pub fn build_diagnostic_bundle(&self, path: &Path) -> Result<()> {
+        Bundle::new(&self.db, &self.config()?, self.recent_events(100)?)?.write(path)
+    }
+
+    /// The log file currently being written, to attach to bug reports.
+    pub fn log_path(&self) -> Result<PathBuf> {
+        Ok(self
+            .data_path(Path::new(logging::LOG_DIR))?
+            .join(logging::LOG_FILENAME))
+    }
+
+    /// Up to `limit` of the most interesting recent events, newest first, with descriptions
+    /// for an activity feed.
+    pub fn recent_events(&self, limit: usize) -> Result<Vec<LoggedEvent>> {
+        self.event_log.recent(limit)
     }
 
     // TODO: Turn this into a builder pattern so `start()` is a `build()` in a `ManagerBuilder`.
     #[instrument(skip(self))]
-    pub fn start(&mut self) -> Result<()> {
+    pub fn start(&self) -> Result<()> {
         trace!("Setting up manager.");
         self.fill_transfer_states().context("Transfer states.")?;
+        self.move_auth_keys_to_keychain()?;
+        // Email passwords used to be kept in sled.
+        let mut config = self.config()?;
+        if config
+            .email
+            .as_ref()
+            .map_or(false, |e| !e.password.is_empty())
+        {
+            self.save_config(&mut config)
+                .context("Moving the email password to the keychain.")?;
+        }
+        // Nothing is transferring yet, so any temp file is left over from an interrupted run.
+        if let Err(err) = self.temp_files().and_then(|t| t.clean()) {
+            warn!(?err, "Could not clean up temp files.");
+        }
+        if let Err(err) = self.gather_archives() {
+            warn!(
+                ?err,
+                "Could not move archived saves into the portable archive."
+            );
+        }
 
         if let Some(auth_key) = self.auth_key()? {
             debug!("☑ Has auth key.");
             self.authenticate(&auth_key)?;
         }
 
-        if self.user_id()?.is_some() {
+        let paused = self.paused()?;
+        if paused {
+            info!("Starting paused.");
+        }
+
+        if self.user_id()?.is_some() && !paused {
             debug!("☑ Has user_id.");
 
             trace!("Fetching games on startup.");
@@ -105,118 +885,1079 @@ impl Manager {
         }
 
         self.start_watching_saves()?;
+        if !paused {
+            self.scan_for_missed_saves()
+                .context("Scanning for missed saves.")?;
+            self.state().rescan_saves = true;
+        }
+        self.start_polling()?;
+        self.start_watching_game();
+
+        Ok(())
+    }
 
+    /// Emits `Event::AnotherInstanceStarted` when a second copy of civfun nudges this one, and
+    /// answers it with `status()` when it asks.
+    pub fn listen_for_instances(&self, lock: &InstanceLock) -> Result<()> {
+        let (tx, rx) = mpsc::channel(1);
+        let manager = self.clone();
+        lock.listen(tx, move || {
+            manager
+                .status()
+                .and_then(|status| Ok(serde_json::to_string(&status)?))
+                .unwrap_or_else(|err| {
+                    warn!(?err, "Answering a status request.");
+                    String::new()
+                })
+        })?;
+        self.state().nudge_rx = Some(rx);
         Ok(())
     }
 
+    /// Without a running instance, `fill_transfer_states()` first so transfers are known.
     #[instrument(skip(self))]
-    pub fn process(&mut self) -> Result<Vec<Event>> {
-        let mut events = vec![];
-        if let Some(ref mut rx) = self.auth_rx {
-            match rx.try_recv() {
-                Ok(maybe_user_id) => {
-                    if let Some(event) = self
-                        .handle_auth_response(maybe_user_id)
-                        .with_context(|| format!("Handling auth response: {:?}", &maybe_user_id))?
-                    {
-                        events.push(event);
-                    }
+    pub fn status(&self) -> Result<Status> {
+        let signed_in = self.user_id()?.is_some();
+        let (save_dir, save_dir_problem) = match self.save_dir() {
+            Ok(dir) if dir.is_dir() => (Some(dir), None),
+            Ok(dir) => (Some(dir), Some("The folder doesn't exist.".to_string())),
+            Err(err) => (None, Some(format!("{:#}", err))),
+        };
+        let (transfers, pending_saves) = if signed_in {
+            let games = self.games()?;
+            let transfers = self
+                .download_status()
+                .into_iter()
+                .filter(|status| status.state != TransferState::Idle)
+                .filter_map(|status| {
+                    let game = games.iter().find(|g| g.game_id == status.game_id)?;
+                    Some(GameTransfer {
+                        game_id: status.game_id,
+                        name: game.name.clone(),
+                        state: status.state,
+                    })
+                })
+                .collect();
+            let pending = self.pending_saves()?.into_iter().map(|p| p.path).collect();
+            (transfers, pending)
+        } else {
+            (vec![], vec![])
+        };
+        let watching = self.state().watch_files_rx.is_some();
+        Ok(Status {
+            signed_in,
+            paused: self.paused()?,
+            games_fetched_at: self.games_fetched_at()?,
+            save_dir,
+            save_dir_problem,
+            watching,
+            transfers,
+            pending_saves,
+        })
+    }
+
+    fn process_nudges(&self) -> Result<()> {
+        let nudged = self
+            .state()
+            .nudge_rx
+            .as_mut()
+            .map(|rx| !drain(rx).0.is_empty())
+            .unwrap_or(false);
+        if nudged {
+            info!("Another instance was started.");
+            self.emit(Event::AnotherInstanceStarted);
+        }
+        Ok(())
+    }
+
+    /// Keeps track of whether Civ V is running, see `Event::GameRunning`.
+    pub fn start_watching_game(&self) {
+        let (tx, rx) = mpsc::channel(1);
+        game_process::spawn(tx);
+        self.state().game_running_rx = Some(rx);
+    }
+
+    /// Whether Civ V was running when it was last checked.
+    pub fn game_running(&self) -> bool {
+        self.state().game_running
+    }
+
+    fn process_game_running(&self) -> Result<()> {
+        let latest = self
+            .state()
+            .game_running_rx
+            .as_mut()
+            .and_then(|rx| drain(rx).0.pop());
+        if let Some(running) = latest {
+            let changed = std::mem::replace(&mut self.state().game_running, running) != running;
+            if changed {
+                info!(running, "Civ V running changed.");
+                self.emit(Event::GameRunning(running));
+            }
+        }
+
+        if self.game_running() {
+            return Ok(());
+        }
+        let deferred = match self.user_db() {
+            Ok(user_db) => user_db.remove(DEFERRED_ARCHIVES_KEY)?,
+            // Nothing is archived without an account.
+            Err(_) => None,
+        };
+        let deferred: Vec<DeferredArchive> = match deferred {
+            Some(b) => serde_json::from_slice(&b).context("Decoding deferred archives.")?,
+            None => return Ok(()),
+        };
+        for archive in deferred {
+            let result = match &archive {
+                DeferredArchive::Uploaded(game_id, turn_id) => {
+                    self.archive_uploaded_save(game_id, turn_id)
                 }
-                Err(_) => {}
+                DeferredArchive::Downloaded(game) => self.archive_downloaded_save(game),
             };
+            if let Err(err) = result {
+                warn!(?err, ?archive, "Could not archive deferred save.");
+            }
         }
+        Ok(())
+    }
 
-        let mut fetched = vec![];
-        if let Some(ref mut rx) = self.fetch_games_rx {
+    /// Does everything `start()` and `process()` do with no frontend attached, e.g. as a
+    /// background service or on a server syncing saves into a shared folder. Events are only
+    /// logged. Returns if starting fails.
+    pub async fn run_forever(&self) -> Result<()> {
+        self.start()?;
+        let mut events = self.subscribe();
+        let mut interval = tokio::time::interval(PROCESS_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.process()?;
             loop {
-                match rx.try_recv() {
-                    Ok(event) => {
-                        trace!(?event);
-                        fetched.push(event);
-                    }
-                    Err(_) => {
-                        break;
+                match events.try_recv() {
+                    Ok(event) => debug!(?event, "Event."),
+                    Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                        warn!(skipped, "Events were dropped before being logged.")
                     }
+                    Err(_) => break,
                 }
             }
         }
+    }
 
-        for fetch in fetched {
-            match fetch.context("Fetch games.")? {
-                FetchGames::Games(games) => {
-                    self.save_games(&games)?;
-                    events.push(Event::UpdatedGames(games));
-                }
-                FetchGames::StoredPlayer(stored_player) => {
-                    self.save_stored_player(&stored_player)?;
-                    events.push(Event::UpdatedPlayer(stored_player));
+    /// Fetches games every `poll_seconds`, backing off while fetches fail. This runs on its own
+    /// task, results included, so polling doesn't wait for anything to call `process()`.
+    pub fn start_polling(&self) -> Result<()> {
+        let (delay_tx, delay_rx) = watch::channel(self.poll_delay()?);
+        let manager = self.clone();
+        poll::spawn(delay_rx, move || {
+            let manager = manager.clone();
+            async move {
+                if let Err(err) = manager.poll_games().await {
+                    warn!(?err, "Polling games failed.");
                 }
+            }
+        });
+        self.state().poll_delay_tx = Some(delay_tx);
+        Ok(())
+    }
+
+    /// One poll, skipped while paused or signed out. Games and players are stored and their
+    /// events emitted as they arrive.
+    async fn poll_games(&self) -> Result<()> {
+        let user_id = match self.user_id()? {
+            Some(user_id) if !self.paused()? => user_id,
+            _ => return Ok(()),
+        };
+        trace!("Polling games.");
+        let (mut tx, mut rx) = mpsc::channel(5);
+        let api = self.api()?;
+        let db = self.db.clone();
+        let max_age = self.player_cache_age()?;
+        tokio::spawn(async move {
+            if let Err(err) = Self::do_fetch_games(db, api, &mut tx, max_age, vec![]).await {
+                // Only fails when the poll has stopped listening.
+                let _ = tx.send(Err(err)).await;
+            }
+        });
+        while let Some(fetch) = rx.recv().await {
+            if self.user_id()? != Some(user_id) {
+                debug!("The account changed while polling.");
+                return Ok(());
+            }
+            let events = {
+                // Stored games decide what `process()` transfers, so don't change them under it.
+                let _processing = self
+                    .processing
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                self.handle_fetch(fetch)?
             };
+            for event in events {
+                self.emit(event);
+            }
         }
+        Ok(())
+    }
 
-        self.process_transfers()?;
-        self.process_new_saves()?;
+    fn poll_delay(&self) -> Result<Duration> {
+        let interval = Duration::from_secs(self.config()?.poll_seconds);
+        Ok(poll::delay(interval, self.state().poll_failures))
+    }
 
-        if events.len() > 0 {
-            trace!(?events);
+    /// Tells the polling task about a changed interval or failure count.
+    fn update_poll_delay(&self) -> Result<()> {
+        let delay = self.poll_delay()?;
+        if let Some(tx) = &self.state().poll_delay_tx {
+            if *tx.borrow() != delay {
+                debug!(?delay, "Poll delay changed.");
+                // Only fails when polling has stopped.
+                let _ = tx.send(delay);
+            }
         }
-
-        Ok(events)
+        Ok(())
     }
 
+    /// Drives all background work. Resulting events are sent to subscribers, see `subscribe()`.
     #[instrument(skip(self))]
-    pub fn games(&self) -> Result<Vec<Game>> {
-        Ok(match self.db.get(GAMES_KEY)? {
-            Some(b) => serde_json::from_slice(&b)?,
-            None => vec![],
-        })
-    }
+    pub fn process(&self) -> Result<()> {
+        let _processing = match self.processing.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => {
+                trace!("Already processing in another clone.");
+                return Ok(());
+            }
+        };
 
-    #[instrument(skip(self))]
-    fn my_games(&self) -> Result<Vec<Game>> {
-        let user_id = self
-            .user_id()?
-            .ok_or(anyhow!("my_games requested without a valid auth state."))?;
+        self.run(Subsystem::Authentication, Self::process_auth_response);
+        self.run(Subsystem::GameProcess, Self::process_game_running);
+        if !self.paused()? {
+            self.run(Subsystem::Games, Self::process_games);
+            self.run(Subsystem::Transfers, Self::process_transfers);
+            self.run(Subsystem::Saves, Self::process_new_saves);
+        }
+        self.run(Subsystem::Reminders, Self::process_reminders);
+        self.run(Subsystem::Cleanup, Self::process_cleanup);
+        self.run(Subsystem::IntegrityCheck, Self::process_integrity_check);
+        self.run(Subsystem::Instance, Self::process_nudges);
 
-        Ok(self
-            .games()?
-            .into_iter()
-            .filter(|g| g.is_user_id_turn(&user_id))
-            .collect())
+        Ok(())
     }
 
-    #[instrument(skip(self, key))]
-    pub fn authenticate(&mut self, key: &str) -> Result<()> {
-        trace!("Authentication requested.");
+    /// Stops polling for games, picking up new saves and transferring turns until `resume()`,
+    /// e.g. while on a metered connection. Stays paused across restarts.
+    pub fn pause(&self) -> Result<()> {
+        self.set_paused(true)
+    }
+
+    /// Undoes `pause()`, catching up on games and any saves made in the meantime.
+    pub fn resume(&self) -> Result<()> {
+        self.set_paused(false)?;
+        if self.user_id()?.is_some() {
+            self.fetch_games()
+                .context("Fetching games after resuming.")?;
+        }
+        self.scan_for_missed_saves()
+            .context("Scanning for saves after resuming.")?;
+        self.state().rescan_saves = true;
+        Ok(())
+    }
+
+    /// Pausing is per account. Without one there's nothing to pause.
+    pub fn paused(&self) -> Result<bool> {
+        if self.user_id()?.is_none() {
+            return Ok(false);
+        }
+        Ok(self.user_db()?.contains_key(PAUSED_KEY)?)
+    }
+
+    fn set_paused(&self, paused: bool) -> Result<()> {
+        if self.paused()? == paused {
+            return Ok(());
+        }
+        info!(paused, "Pausing background activity.");
+        let user_db = self.user_db()?;
+        if paused {
+            user_db.insert(PAUSED_KEY, vec![])?;
+        } else {
+            user_db.remove(PAUSED_KEY)?;
+        }
+        self.emit(Event::Paused(paused));
+        Ok(())
+    }
+
+    /// Runs one part of `process()`. A failure is sent as `Event::Error` instead of stopping
+    /// everything else, and a subsystem that keeps failing is paused for a while.
+    fn run<F>(&self, subsystem: Subsystem, f: F)
+    where
+        F: FnOnce(&Self) -> Result<()>,
+    {
+        let now = Instant::now();
+        if self.breaker(subsystem, |b| b.is_paused(now)) {
+            trace!(?subsystem, "Paused.");
+            return;
+        }
+        match f(self) {
+            Ok(()) => self.breaker(subsystem, Breaker::success),
+            Err(err) => {
+                let paused = self.breaker(subsystem, |b| b.failure(now));
+                error!(?subsystem, ?err, paused, "Processing failed.");
+                self.emit(Event::Error {
+                    source: subsystem,
+                    message: format!("{:#}", err),
+                    recoverable: !paused,
+                });
+            }
+        }
+    }
+
+    fn breaker<T>(&self, subsystem: Subsystem, f: impl FnOnce(&mut Breaker) -> T) -> T {
+        f(self.state().breakers.entry(subsystem).or_default())
+    }
+
+    fn process_auth_response(&self) -> Result<()> {
+        let auth_response = self
+            .state()
+            .auth_rx
+            .as_mut()
+            .and_then(|rx| rx.try_recv().ok());
+        if let Some((key, response)) = auth_response {
+            let maybe_user_id = response.context("Authenticating.")?;
+            if let Some(event) = self
+                .handle_auth_response(key, maybe_user_id)
+                .with_context(|| format!("Handling auth response: {:?}", &maybe_user_id))?
+            {
+                self.emit(event);
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles games from `fetch_games()`. Polled games are handled by the polling task.
+    fn process_games(&self) -> Result<()> {
+        let fetched = {
+            let mut state = self.state();
+            let (fetched, finished) = state.fetch_games_rx.as_mut().map(drain).unwrap_or_default();
+            if finished {
+                state.fetch_games_rx = None;
+            }
+            fetched
+        };
+        trace!(?fetched);
+
+        let mut events = vec![];
+        for fetch in fetched {
+            events.extend(self.handle_fetch(fetch)?);
+        }
+        for event in events {
+            self.emit(event);
+        }
+        Ok(())
+    }
+
+    /// Stores what a fetch sent, returning the events it causes. A failed fetch backs off polling.
+    fn handle_fetch(&self, fetch: Result<FetchGames>) -> Result<Vec<Event>> {
+        match fetch {
+            Ok(FetchGames::Games(games, points, skew)) => {
+                self.handle_fetched_games(games, points, skew)
+            }
+            Ok(FetchGames::StoredPlayer(stored_player)) => {
+                self.save_stored_player(&stored_player)?;
+                Ok(vec![Event::UpdatedPlayer(stored_player)])
+            }
+            Err(err) => {
+                let failures = {
+                    let mut state = self.state();
+                    state.poll_failures += 1;
+                    state.poll_failures
+                };
+                warn!(?err, failures, "Fetching games failed.");
+                self.update_poll_delay()?;
+                Ok(vec![Event::FetchGamesFailed {
+                    message: format!("{:#}", err),
+                }])
+            }
+        }
+    }
+
+    /// Stores freshly fetched games, returning the events they cause, e.g. `NewTurn`.
+    fn handle_fetched_games(
+        &self,
+        games: Vec<Game>,
+        points: u64,
+        skew: Option<chrono::Duration>,
+    ) -> Result<Vec<Event>> {
+        let mut events = vec![];
+        if let Some(skew) = skew {
+            events.extend(self.update_clock_skew(skew));
+        }
+        let had_failures = std::mem::take(&mut self.state().poll_failures) > 0;
+        if had_failures {
+            self.update_poll_delay()?;
+        }
+        events.extend(self.new_turns(&games)?.into_iter().map(Event::NewTurn));
+        self.confirm_interrupted_uploads(&games)?;
+        self.record_turns(&games)?;
+        self.archive_finished(&games)?;
+        self.save_games(&games)?;
+        if std::mem::take(&mut self.state().rescan_saves) {
+            self.scan_for_missed_saves()
+                .context("Scanning for saves after fetching games.")?;
+        }
+        self.user_db()?
+            .insert(TOTAL_POINTS_KEY, points.to_string().as_str())?;
+        self.user_db()?
+            .insert(GAMES_FETCHED_AT_KEY, self.now().to_rfc3339().as_str())?;
+        events.push(Event::UpdatedGames(games));
+        Ok(events)
+    }
+
+    /// An upload that was cut off by a restart finished if the turn has moved on since. Otherwise
+    /// it's left queued to be sent again.
+    fn confirm_interrupted_uploads(&self, games: &[Game]) -> Result<()> {
+        let interrupted = std::mem::take(&mut self.state().interrupted_uploads);
+        for (game_id, turn_id) in interrupted {
+            let moved_on = games
+                .iter()
+                .find(|g| g.game_id == game_id)
+                .map_or(false, |g| g.current_turn.turn_id != turn_id);
+            if moved_on {
+                info!(?game_id, ?turn_id, "Upload finished before the restart.");
+                self.handle_upload_complete(&game_id, &turn_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Keeps the latest skew for `server_now()`. Returns `Event::ClockSkew` when the local clock
+    /// becomes noticeably wrong, or right again.
+    fn update_clock_skew(&self, skew: chrono::Duration) -> Option<Event> {
+        let significant = clock::is_significant(skew);
+        let mut state = self.state();
+        state.clock_skew = Some(skew);
+        if significant == state.clock_skewed {
+            return None;
+        }
+        state.clock_skewed = significant;
+        if significant {
+            warn!(seconds = skew.num_seconds(), "The local clock is off.");
+        }
+        Some(Event::ClockSkew { skew })
+    }
+
+    /// The time according to GMR, going by the last response. Deadlines are worked out from this
+    /// so a wrong local clock doesn't make reminders early or late.
+    pub fn server_now(&self) -> DateTime<Utc> {
+        let skew = self
+            .state()
+            .clock_skew
+            .unwrap_or_else(chrono::Duration::zero);
+        self.now() + skew
+    }
+
+    /// Archives games the user is no longer a player in. Games that only look finished are left
+    /// for the user to archive. Each game is only archived automatically once, so a game the user
+    /// brings back with `unarchive_game()` stays.
+    fn archive_finished(&self, games: &[Game]) -> Result<()> {
+        let user_id = match self.user_id()? {
+            Some(user_id) => user_id,
+            None => return Ok(()),
+        };
+        let user_db = self.user_db()?;
+        for game in games.iter().filter(|g| g.has_left(&user_id)) {
+            let key = format!("auto-archived-{}", game.game_id);
+            if user_db.contains_key(&key)? {
+                continue;
+            }
+            info!(game_id = ?game.game_id, "No longer in the game, archiving it.");
+            self.archive_game(&game.game_id)?;
+            user_db.insert(key, vec![])?;
+        }
+        Ok(())
+    }
+
+    /// Hides a game and stops transferring anything for it. Emits `GameSettingsChanged`.
+    pub fn archive_game(&self, game_id: &GameId) -> Result<()> {
+        self.update_game_settings(game_id, |s| s.archived = true)?;
+        Ok(())
+    }
+
+    pub fn unarchive_game(&self, game_id: &GameId) -> Result<()> {
+        self.update_game_settings(game_id, |s| s.archived = false)?;
+        Ok(())
+    }
+
+    /// Adds any turns that started since the last fetch to each game's history.
+    fn record_turns(&self, games: &[Game]) -> Result<()> {
+        let user_db = self.user_db()?;
+        for game in games {
+            let key = Self::turn_history_key(&game.game_id);
+            let mut history = self.turn_history(&game.game_id)?;
+            if stats::record(&mut history, &game.current_turn) {
+                user_db.insert(key, serde_json::to_vec(&history)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Every turn seen for the game, oldest first.
+    pub fn turn_history(&self, game_id: &GameId) -> Result<Vec<TurnRecord>> {
+        match self.user_db()?.get(Self::turn_history_key(game_id))? {
+            Some(b) => serde_json::from_slice(&b).context("Decoding turn history."),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn turn_history_key(game_id: &GameId) -> String {
+        format!("turn-history-{}", game_id)
+    }
+
+    /// Turn times, skips and streaks for each player, from the turns seen since civfun started
+    /// following the game.
+    pub fn stats(&self, game_id: &GameId) -> Result<GameStats> {
+        Ok(stats::game_stats(&self.turn_history(game_id)?))
+    }
+
+    /// Turn times over every game for the current account, going back `weeks` weeks for the
+    /// weekly figures.
+    pub fn dashboard(&self, weeks: usize) -> Result<Dashboard> {
+        let user_id = self
+            .user_id()?
+            .ok_or_else(|| anyhow!("No account has been authenticated."))?;
+        let histories = self
+            .games()?
+            .iter()
+            .map(|game| Ok((game.game_id, self.turn_history(&game.game_id)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(stats::dashboard(
+            &user_id,
+            &histories,
+            self.server_now(),
+            weeks,
+        ))
+    }
+
+    /// GMR points for the current account. None until games have been fetched.
+    pub fn total_points(&self) -> Result<Option<u64>> {
+        if self.user_id()?.is_none() {
+            return Ok(None);
+        }
+        self.user_db()?
+            .get(TOTAL_POINTS_KEY)?
+            .map(|iv| -> Result<u64> { Ok(String::from_utf8(iv.to_vec())?.parse()?) })
+            .transpose()
+            .context("Decoding total points.")
+    }
+
+    /// When games were last fetched from GMR. None before the first fetch for this account.
+    pub fn games_fetched_at(&self) -> Result<Option<DateTime<Utc>>> {
+        if self.user_id()?.is_none() {
+            return Ok(None);
+        }
+        self.user_db()?
+            .get(GAMES_FETCHED_AT_KEY)?
+            .map(|iv| -> Result<DateTime<Utc>> {
+                Ok(DateTime::parse_from_rfc3339(std::str::from_utf8(&iv)?)?.with_timezone(&Utc))
+            })
+            .transpose()
+            .context("Decoding when games were fetched.")
+    }
+
+    /// When games were last fetched, if that was long enough ago that what's shown could be out
+    /// of date, e.g. because GMR can't be reached.
+    pub fn games_stale_since(&self) -> Result<Option<DateTime<Utc>>> {
+        let fetched_at = match self.games_fetched_at()? {
+            Some(fetched_at) => fetched_at,
+            None => return Ok(None),
+        };
+        let age = (self.now() - fetched_at).to_std().unwrap_or_default();
+        let interval = Duration::from_secs(self.config()?.poll_seconds);
+        Ok(Some(fetched_at).filter(|_| poll::is_stale(age, interval)))
+    }
+
+    /// Games for the current account. Empty when there isn't one.
+    #[instrument(skip(self))]
+    pub fn games(&self) -> Result<Vec<Game>> {
+        if self.user_id()?.is_none() {
+            return Ok(vec![]);
+        }
+        Ok(match self.user_db()?.get(GAMES_KEY)? {
+            Some(b) => serde_json::from_slice(&b)?,
+            None => vec![],
+        })
+    }
+
+    #[instrument(skip(self))]
+    fn my_games(&self) -> Result<Vec<Game>> {
+        let user_id = self
+            .user_id()?
+            .ok_or(anyhow!("my_games requested without a valid auth state."))?;
+
+        let mut games = vec![];
+        for game in self.games()? {
+            if game.is_user_id_turn(&user_id) && !self.game_settings(&game.game_id)?.archived {
+                games.push(game);
+            }
+        }
+        Ok(games)
+    }
+
+    /// Games in `games` where it's the user's turn, and whose turn wasn't in the stored games.
+    /// The first fetch for an account only has nothing to compare with, so it seeds the stored
+    /// games instead of announcing every waiting turn at once.
+    fn new_turns(&self, games: &[Game]) -> Result<Vec<Game>> {
+        let user_id = match self.user_id()? {
+            Some(user_id) => user_id,
+            None => return Ok(vec![]),
+        };
+        if self.user_db()?.get(GAMES_KEY)?.is_none() {
+            trace!("First fetch for this account, no turns are new.");
+            return Ok(vec![]);
+        }
+        let seen: HashSet<TurnId> = self
+            .games()?
+            .iter()
+            .map(|g| g.current_turn.turn_id)
+            .collect();
+        let mut new_turns = vec![];
+        for game in games {
+            if game.is_user_id_turn(&user_id)
+                && !seen.contains(&game.current_turn.turn_id)
+                && !self.game_settings(&game.game_id)?.archived
+            {
+                new_turns.push(game.clone());
+            }
+        }
+        Ok(new_turns)
+    }
+
+    /// Emits `TurnDeadlineApproaching` for the user's turns that crossed a reminder threshold.
+    /// Which reminders were sent is kept per turn so restarting doesn't repeat them. Turns the
+    /// skip watchdog has warned about get no more reminders, as its warnings say the same.
+    #[instrument(skip(self))]
+    fn process_reminders(&self) -> Result<()> {
+        let now = self.server_now();
+        {
+            let mut state = self.state();
+            if let Some(last) = state.last_reminder_check {
+                if now - last < chrono::Duration::minutes(1) {
+                    return Ok(());
+                }
+            }
+            state.last_reminder_check = Some(now);
+        }
+        self.send_deferred_alerts()?;
+
+        if self.user_id()?.is_none() {
+            return Ok(());
+        }
+        self.check_skip_warnings(now)?;
+        let thresholds = self.config()?.reminder_minutes;
+        if thresholds.is_empty() {
+            return Ok(());
+        }
+
+        for game in self.my_games()? {
+            let remaining = match game.current_turn.time_remaining_at(now) {
+                Some(remaining) => remaining,
+                None => continue,
+            };
+            let key = Self::reminded_key(&game.game_id, &game.current_turn.turn_id);
+            let user_db = self.user_db()?;
+            let mut sent: Vec<u32> = match user_db.get(&key)? {
+                Some(b) => serde_json::from_slice(&b).context("Decoding sent reminders.")?,
+                None => vec![],
+            };
+            let due = reminders::due(remaining, &thresholds, &sent);
+            if due.is_empty() {
+                continue;
+            }
+
+            sent.extend(due);
+            user_db.insert(&key, serde_json::to_vec(&sent)?)?;
+            let skip_warned_key = Self::skip_warned_key(&game.game_id, &game.current_turn.turn_id);
+            if user_db.contains_key(skip_warned_key)? {
+                debug!(game_id = ?game.game_id, "Already warned about being skipped.");
+                continue;
+            }
+            debug!(game_id = ?game.game_id, "Turn deadline approaching.");
+            self.emit(Event::TurnDeadlineApproaching { game, remaining });
+        }
+        Ok(())
+    }
+
+    /// Emits `TurnAboutToBeSkipped` when a turn reaches a higher escalation than before.
+    fn check_skip_warnings(&self, now: DateTime<Utc>) -> Result<()> {
+        let warning = match self.config()?.skip_warning_hours {
+            Some(hours) => chrono::Duration::hours(hours as i64),
+            None => return Ok(()),
+        };
+        let user_db = self.user_db()?;
+        for game in self.my_games()? {
+            let remaining = match game.current_turn.time_remaining_at(now) {
+                Some(remaining) => remaining,
+                None => continue,
+            };
+            let escalation = match watchdog::escalation(remaining, warning) {
+                Some(escalation) => escalation,
+                None => continue,
+            };
+            let key = Self::skip_warned_key(&game.game_id, &game.current_turn.turn_id);
+            let warned: Option<Escalation> = match user_db.get(&key)? {
+                Some(b) => Some(serde_json::from_slice(&b).context("Decoding skip warning.")?),
+                None => None,
+            };
+            if warned >= Some(escalation) {
+                continue;
+            }
+            warn!(game_id = ?game.game_id, ?remaining, ?escalation, "Turn is about to be skipped.");
+            user_db.insert(&key, serde_json::to_vec(&escalation)?)?;
+            self.emit(Event::TurnAboutToBeSkipped {
+                game,
+                remaining,
+                escalation,
+            });
+        }
+        Ok(())
+    }
+
+    fn skip_warned_key(game_id: &GameId, turn_id: &TurnId) -> String {
+        format!("skip-warned-{}-{}", game_id, turn_id)
+    }
+
+    fn reminded_key(game_id: &GameId, turn_id: &TurnId) -> String {
+        format!("reminded-{}-{}", game_id, turn_id)
+    }
+
+    /// Runs `cleanup()` once an hour.
+    fn process_cleanup(&self) -> Result<()> {
+        let now = self.now();
+        {
+            let mut state = self.state();
+            if let Some(last) = state.last_cleanup {
+                if now - last < chrono::Duration::hours(1) {
+                    return Ok(());
+                }
+            }
+            state.last_cleanup = Some(now);
+        }
+        if self.user_id()?.is_none() {
+            return Ok(());
+        }
+        self.cleanup().context("Cleaning up old saves.")?;
+        Ok(())
+    }
+
+    /// Deletes stored saves and archived files for old turns, following `keep_turns` and
+    /// `max_storage_mib`. The current turn of each game is always kept. Returns how many turns
+    /// were removed.
+    #[instrument(skip(self))]
+    pub fn cleanup(&self) -> Result<usize> {
+        let config = self.config()?;
+        if config.keep_turns.is_none() && config.max_storage_mib.is_none() {
+            return Ok(0);
+        }
+        let protected = self
+            .games()?
+            .iter()
+            .map(|g| (g.game_id, g.current_turn.turn_id))
+            .collect();
+        let prune = retention::turns_to_prune(
+            &self.stored_turns()?,
+            config.keep_turns,
+            config.max_storage_mib.map(|mib| mib * 1024 * 1024),
+            &protected,
+        );
+        for (game_id, turn_id) in &prune {
+            self.remove_turn(game_id, turn_id)?;
+        }
+        if !prune.is_empty() {
+            info!(turns = prune.len(), "Removed old turns.");
+        }
+        Ok(prune.len())
+    }
+
+    /// Runs `check_integrity()` every six hours.
+    fn process_integrity_check(&self) -> Result<()> {
+        let now = self.now();
+        {
+            let mut state = self.state();
+            if let Some(last) = state.last_integrity_check {
+                if now - last < chrono::Duration::hours(6) {
+                    return Ok(());
+                }
+            }
+            state.last_integrity_check = Some(now);
+        }
+        if self.user_id()?.is_none() {
+            return Ok(());
+        }
+        self.check_integrity().context("Checking integrity.")?;
+        Ok(())
+    }
+
+    /// Checks sled and the stored saves for the user's current turns. A damaged download is
+    /// thrown away and downloaded again. A damaged save waiting to be uploaded can't be fetched
+    /// again, so that upload fails instead. Returns how many problems were found.
+    #[instrument(skip(self))]
+    pub fn check_integrity(&self) -> Result<usize> {
+        let corrupt = self.check_db()?;
+        if !corrupt.is_empty() {
+            error!(?corrupt, "Database is corrupt.");
+            self.emit(Event::DatabaseCorrupt {
+                message: corrupt.join(" "),
+            });
+            return Ok(corrupt.len());
+        }
+
+        let mut problems = 0;
+        let user_db = self.user_db()?;
+        for game in self.my_games()? {
+            let game_id = game.game_id;
+            let turn_id = game.current_turn.turn_id;
+            match self.transfer_state(&game_id) {
+                TransferState::Downloaded => {
+                    let bytes = user_db.get(Self::saved_bytes_db_key(&game_id, &turn_id))?;
+                    let problem = match &bytes {
+                        // First turns don't have a save.
+                        None if game.current_turn.is_first_turn => None,
+                        None => Some("The downloaded save is missing.".to_string()),
+                        Some(bytes) => save_problem(bytes).or_else(|| {
+                            self.analysed(&game_id, &turn_id)
+                                .err()
+                                .map(|err| format!("The save analysis is unreadable: {:#}", err))
+                        }),
+                    };
+                    if let Some(problem) = problem {
+                        warn!(?game_id, ?turn_id, ?problem, "Downloading again.");
+                        self.forget_download(&game_id, &turn_id)?;
+                        self.emit(Event::SaveRepaired {
+                            game_id,
+                            turn_id,
+                            problem,
+                        });
+                        problems += 1;
+                    }
+                }
+                TransferState::UploadQueued => {
+                    let bytes = user_db.get(Self::upload_bytes_db_key(&game_id, &turn_id))?;
+                    let problem = match &bytes {
+                        None => Some("The save to upload has gone missing.".to_string()),
+                        Some(bytes) => save_problem(bytes),
+                    };
+                    if let Some(problem) = problem {
+                        warn!(?game_id, ?turn_id, ?problem, "Can't upload.");
+                        self.set_transfer_state(&game_id, &turn_id, TransferState::UploadFailed)?;
+                        self.emit(Event::UploadFailed {
+                            game_id,
+                            message: format!("{} Please save your turn again.", problem),
+                        });
+                        problems += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Reads every record in every tree, decoding the ones in a known format. Returns a
+    /// description of each record that can't be read or decoded.
+    fn check_db(&self) -> Result<Vec<String>> {
+        let mut corrupt = vec![];
+        for name in self.db.tree_names() {
+            let tree_name = String::from_utf8_lossy(&name).to_string();
+            for item in self.db.open_tree(&name)?.iter() {
+                let (key, value) = match item {
+                    Ok(item) => item,
+                    Err(err) => {
+                        // The rest of the tree is unlikely to read any better.
+                        corrupt.push(format!("The {} tree can't be read: {}.", tree_name, err));
+                        break;
+                    }
+                };
+                let key = String::from_utf8_lossy(&key);
+                if let Err(err) = Self::decode_record(&tree_name, &key, &value) {
+                    corrupt.push(format!(
+                        "{} in {} can't be decoded: {}.",
+                        key, tree_name, err
+                    ));
+                }
+            }
+        }
+        Ok(corrupt)
+    }
+
+    /// Decodes a record as whatever is stored under its key. Saves, strings and trees owned by
+    /// other modules aren't decoded.
+    fn decode_record(tree_name: &str, key: &str, value: &[u8]) -> serde_json::Result<()> {
+        fn decode<T: DeserializeOwned>(value: &[u8]) -> serde_json::Result<()> {
+            serde_json::from_slice::<T>(value).map(drop)
+        }
+        if tree_name == event_log::TREE_NAME {
+            return decode::<LoggedEvent>(value);
+        }
+        match key {
+            CONFIG_KEY => decode::<Config>(value),
+            ACCOUNTS_KEY => decode::<Vec<Account>>(value),
+            GAMES_KEY => decode::<Vec<Game>>(value),
+            DEFERRED_ARCHIVES_KEY => decode::<Vec<DeferredArchive>>(value),
+            HELD_ALERTS_KEY => decode::<HeldAlerts>(value),
+            _ if key.starts_with("game-settings-") => decode::<GameSettings>(value),
+            _ if key.starts_with("player-info-") => decode::<StoredPlayer>(value),
+            _ if key.starts_with("transfer-") => decode::<StoredTransfer>(value),
+            _ if key.starts_with("turn-history-") => decode::<Vec<TurnRecord>>(value),
+            _ if key.starts_with("analysed-") => decode::<Civ5Save>(value),
+            _ => Ok(()),
+        }
+    }
+
+    /// Everything stored for a turn, in sled and in the archive.
+    fn remove_turn(&self, game_id: &GameId, turn_id: &TurnId) -> Result<()> {
+        debug!(?game_id, ?turn_id, "Removing turn.");
+        let user_db = self.user_db()?;
+        user_db.remove(Self::saved_bytes_db_key(game_id, turn_id))?;
+        user_db.remove(Self::analysed_game_key(game_id, turn_id))?;
+        user_db.remove(Self::upload_bytes_db_key(game_id, turn_id))?;
+        user_db.remove(Self::upload_path_db_key(game_id, turn_id))?;
+        user_db.remove(Self::reminded_key(game_id, turn_id))?;
+        user_db.remove(Self::skip_warned_key(game_id, turn_id))?;
+        if let Ok(archive) = self.archive() {
+            archive.remove_turn(game_id, turn_id)?;
+        }
+        Ok(())
+    }
+
+    /// How much is stored for each turn, in sled and in the archive.
+    fn stored_turns(&self) -> Result<Vec<StoredTurn>> {
+        let mut bytes: HashMap<(GameId, TurnId), u64> = self.save_bytes_by_turn()?;
+        if let Ok(archive) = self.archive() {
+            for entry in archive.all_entries()? {
+                let size = std::fs::metadata(&entry.path).map(|m| m.len()).unwrap_or(0);
+                *bytes.entry((entry.game_id, entry.turn_id)).or_default() += size;
+            }
+        }
+        Ok(bytes
+            .into_iter()
+            .map(|((game_id, turn_id), bytes)| StoredTurn {
+                game_id,
+                turn_id,
+                bytes,
+            })
+            .collect())
+    }
+
+    fn save_bytes_by_turn(&self) -> Result<HashMap<(GameId, TurnId), u64>> {
+        let mut bytes = HashMap::new();
+        let user_db = self.user_db()?;
+        for prefix in &["saved-bytes-", "upload-bytes-"] {
+            for item in user_db.scan_prefix(prefix) {
+                let (key, value) = item?;
+                if let Some(turn) = Self::turn_from_key(&key, prefix) {
+                    *bytes.entry(turn).or_default() += value.len() as u64;
+                }
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// The inverse of keys like `saved_bytes_db_key()`.
+    fn turn_from_key(key: &[u8], prefix: &str) -> Option<(GameId, TurnId)> {
+        let rest = std::str::from_utf8(key).ok()?.strip_prefix(prefix)?;
+        let (game_id, turn_id) = rest.split_once('-')?;
+        Some((
+            game_id.parse::<u32>().ok()?.into(),
+            turn_id.parse::<u64>().ok()?.into(),
+        ))
+    }
+
+    /// Save bytes downloaded and uploaded by the current account, for each game and in total.
+    pub fn usage(&self) -> Result<Usage> {
+        if self.user_id()?.is_none() {
+            return Ok(Usage::default());
+        }
+        let mut games = HashMap::new();
+        for item in self.user_db()?.scan_prefix(USAGE_PREFIX) {
+            let (key, value) = item?;
+            let game_id = std::str::from_utf8(&key)?
+                .strip_prefix(USAGE_PREFIX)
+                .and_then(|id| id.parse::<u32>().ok())
+                .ok_or_else(|| anyhow!("Bad usage key: {:?}", key))?;
+            games.insert(GameId::from(game_id), serde_json::from_slice(&value)?);
+        }
+        Ok(Usage::new(games))
+    }
+
+    fn record_usage(&self, game_id: &GameId, f: impl FnOnce(&mut Transferred)) -> Result<()> {
+        let user_db = self.user_db()?;
+        let key = format!("{}{}", USAGE_PREFIX, game_id);
+        let mut transferred: Transferred = match user_db.get(&key)? {
+            Some(b) => serde_json::from_slice(&b)?,
+            None => Default::default(),
+        };
+        f(&mut transferred);
+        user_db.insert(key, serde_json::to_vec(&transferred)?)?;
+        Ok(())
+    }
+
+    /// Space used by the db and the archive.
+    pub fn storage_usage(&self) -> Result<StorageUsage> {
+        let mut usage = StorageUsage {
+            db_bytes: self.db.size_on_disk()?,
+            ..Default::default()
+        };
+        if self.user_id()?.is_none() {
+            return Ok(usage);
+        }
+        usage.save_bytes = self.save_bytes_by_turn()?.values().sum();
+        if let Ok(archive) = self.archive() {
+            for entry in archive.all_entries()? {
+                if let Ok(metadata) = std::fs::metadata(&entry.path) {
+                    usage.archive_bytes += metadata.len();
+                    usage.archive_files += 1;
+                }
+            }
+        }
+        usage.turns = self.stored_turns()?.len();
+        Ok(usage)
+    }
+
+    /// Checks the key with GMR. On success it's added as an account and becomes the current one.
+    #[instrument(skip(self, key))]
+    pub fn authenticate(&self, key: &str) -> Result<()> {
+        trace!("Authentication requested.");
+        let key = key.trim().to_owned();
+        api::validate_auth_key(&key)?;
         let (tx, rx) = oneshot::channel();
-        self.auth_rx = Some(rx);
-        self.save_auth_key(key)?;
-        let api = self.api()?;
+        self.state().auth_rx = Some(rx);
+        let api = Api::new(&key).with_base_url(&self.api_url);
 
         tokio::spawn(async move {
             trace!("Sending authentication request.");
-            let maybe_user_id = api.authenticate_user().await.unwrap();
-            debug!(?maybe_user_id, "User ID response.");
-            tx.send(maybe_user_id).unwrap();
+            let response = api.authenticate_user().await;
+            debug!(?response, "User ID response.");
+            // Only fails when another authentication has replaced this one.
+            let _ = tx.send((key, response));
         });
 
         Ok(())
     }
 
-    #[instrument(skip(self))]
-    fn handle_auth_response(&mut self, maybe_user_id: Option<UserId>) -> Result<Option<Event>> {
+    #[instrument(skip(self, key))]
+    fn handle_auth_response(
+        &self,
+        key: String,
+        maybe_user_id: Option<UserId>,
+    ) -> Result<Option<Event>> {
         trace!("Handling auth response.");
 
-        let previous_user_id = self.user_id()?;
         if let Some(user_id) = maybe_user_id {
-            self.save_user_id(&user_id)?;
-            let mut should_clear = false;
+            self.add_account(user_id, key)?;
 
-            if let Some(previous_user_id) = previous_user_id {
-                if previous_user_id != user_id {
-                    info!("Clearing games because user_id is different");
-                    self.clear_games().context("Clear games.")?;
-                }
+            if self.user_id()? == Some(user_id) {
+                trace!("Same account as before.");
+            } else {
+                info!(?user_id, "Switching to newly authenticated account.");
+                self.switch_account(&user_id)?;
             }
 
             Ok(Some(Event::AuthenticationSuccess))
@@ -226,35 +1967,323 @@ impl Manager {
         }
     }
 
-    /// This will eventually fetch a second time if the players shown don't exist in the db.
+    /// Every account that has been authenticated on this machine.
+    pub fn accounts(&self) -> Result<Vec<Account>> {
+        Ok(match self.db.get(ACCOUNTS_KEY)? {
+            Some(b) => serde_json::from_slice(&b).context("Decoding accounts.")?,
+            None => vec![],
+        })
+    }
+
+    /// Auth keys used to be kept in sled, as `auth-key` and then in the account list. Any still
+    /// there move into the keychain, if there is one now.
+    fn move_auth_keys_to_keychain(&self) -> Result<()> {
+        for account in self.accounts()? {
+            if account.in_keychain || account.auth_key.is_empty() {
+                continue;
+            }
+            debug!(user_id = ?account.user_id, "Moving auth key to the keychain.");
+            self.add_account(account.user_id, account.auth_key)?;
+        }
+        // Only an interrupted migration could leave this behind.
+        self.db.remove("auth-key")?;
+        Ok(())
+    }
+
+    /// Adds the account, or replaces the auth key of an existing one.
+    /// The key goes into the OS keychain when there is one, otherwise into sled.
+    fn add_account(&self, user_id: UserId, auth_key: String) -> Result<()> {
+        let in_keychain = match self
+            .keys
+            .store(&keychain::auth_key_account(&user_id), &auth_key)
+        {
+            Ok(stored) => stored,
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Could not use the keychain, keeping the auth key in the db."
+                );
+                false
+            }
+        };
+        let account = Account {
+            user_id,
+            auth_key: if in_keychain { String::new() } else { auth_key },
+            in_keychain,
+        };
+        self.save_account(account)
+    }
+
+    fn save_account(&self, account: Account) -> Result<()> {
+        let mut accounts = self.accounts()?;
+        match accounts.iter_mut().find(|a| a.user_id == account.user_id) {
+            Some(existing) => *existing = account,
+            None => accounts.push(account),
+        }
+        self.db
+            .insert(ACCOUNTS_KEY, serde_json::to_vec(&accounts)?)?;
+        Ok(())
+    }
+
+    /// Forget an account and its auth key. The current account can't be removed, switch to
+    /// another one first. Its games and saves stay in the db in case it's added again.
+    #[instrument(skip(self))]
+    pub fn remove_account(&self, user_id: &UserId) -> Result<()> {
+        if self.user_id()?.as_ref() == Some(user_id) {
+            return Err(anyhow!(
+                "Switch to another account before removing this one."
+            ));
+        }
+        let mut accounts = self.accounts()?;
+        accounts.retain(|a| &a.user_id != user_id);
+        self.db
+            .insert(ACCOUNTS_KEY, serde_json::to_vec(&accounts)?)?;
+        self.keys.remove(&keychain::auth_key_account(user_id))?;
+        Ok(())
+    }
+
+    /// Forgets the current account: its auth key, games, history and transfers. Another known
+    /// account becomes the current one if there is one, otherwise there's no account until a key
+    /// is entered again. Downloaded saves and the archive are left alone.
+    #[instrument(skip(self))]
+    pub fn log_out(&self) -> Result<()> {
+        let user_id = match self.user_id()? {
+            Some(user_id) => user_id,
+            None => return Ok(()),
+        };
+        info!(?user_id, "Logging out.");
+
+        {
+            let mut state = self.state();
+            state.transfer.clear();
+            state.transfer_priority.clear();
+            state.fetch_games_rx = None;
+            state.download_rx.clear();
+            state.upload_rx.clear();
+        }
+        self.db.remove(USER_ID_KEY)?;
+        self.remove_account(&user_id)?;
+        self.db.drop_tree(Self::user_tree_name(&user_id))?;
+
+        match self.accounts()?.first() {
+            Some(account) => self.switch_account(&account.user_id),
+            None => {
+                self.emit(Event::UpdatedGames(vec![]));
+                Ok(())
+            }
+        }
+    }
+
+    /// Make another known account the current one. Its games and transfers pick up from where
+    /// they were left.
+    #[instrument(skip(self))]
+    pub fn switch_account(&self, user_id: &UserId) -> Result<()> {
+        if !self.accounts()?.iter().any(|a| &a.user_id == user_id) {
+            return Err(anyhow!("No account for user {}.", user_id));
+        }
+
+        self.save_user_id(user_id)?;
+
+        // Anything in flight belongs to the previous account.
+        {
+            let mut state = self.state();
+            state.transfer.clear();
+            state.fetch_games_rx = None;
+            state.download_rx.clear();
+            state.upload_rx.clear();
+        }
+        self.fill_transfer_states()
+            .context("Transfer states for switched account.")?;
+
+        self.emit(Event::AccountSwitched(*user_id));
+        // Each account is paused on its own.
+        self.emit(Event::Paused(self.paused()?));
+        self.emit(Event::UpdatedGames(self.games()?));
+        self.fetch_games()
+            .context("Fetching games for switched account.")
+    }
+
+    /// Starts Civ V through Steam with the DirectX version and arguments from the config.
+    pub fn launch_game(&self) -> Result<()> {
+        let config = self.config()?;
+        steam::launch(config.directx, &config.launch_args)?;
+        Ok(())
+    }
+
+    /// One click to play a turn: downloads the save if it isn't there yet, then starts Civ V.
+    /// Hotseat passwords are cleared on the way when `clear_hotseat_passwords` is on. Until a
+    /// save for the turn turns up, `awaiting_save()` is true for the game.
+    #[instrument(skip(self))]
+    pub fn play_turn(&self, game_id: &GameId) -> Result<()> {
+        match self.transfer_state(game_id) {
+            TransferState::Downloaded => self.launch_turn(game_id),
+            TransferState::Idle | TransferState::DownloadFailed => {
+                self.download(game_id)?;
+                self.state().play_after_download.insert(*game_id);
+                Ok(())
+            }
+            TransferState::Downloading => {
+                self.state().play_after_download.insert(*game_id);
+                Ok(())
+            }
+            state => Err(anyhow!("Can't play the turn while {:?}.", state)),
+        }
+    }
+
+    fn launch_turn(&self, game_id: &GameId) -> Result<()> {
+        self.launch_game()?;
+        self.state().playing.insert(*game_id);
+        Ok(())
+    }
+
+    /// Civ V was started with `play_turn()` and the save for the turn hasn't turned up yet.
+    pub fn awaiting_save(&self, game_id: &GameId) -> bool {
+        let playing = self.state().playing.contains(game_id);
+        playing && self.transfer_state(game_id) == TransferState::Downloaded
+    }
+
+    /// Writes everything civfun knows to a single file, to be imported on another machine.
+    pub fn export(&self, path: &Path, options: &ExportOptions) -> Result<ExportSummary> {
+        export::export(&self.db, path, options)
+    }
+
+    /// Brings in an export from `export()`. Accounts already on this machine are kept as they
+    /// are and only new ones are added. The current account and config come from the export,
+    /// apart from folders that don't exist on this machine.
     #[instrument(skip(self))]
-    pub fn fetch_games(&mut self) -> Result<()> {
+    pub fn import(&self, path: &Path) -> Result<ExportSummary> {
+        let mut accounts = self.accounts()?;
+        let old_config = self.config()?;
+        let summary = export::import(&self.db, path)?;
+        self.state().players.clear();
+
+        // Accounts already here keep their auth keys, which can be in this machine's keychain.
+        for account in self.accounts()? {
+            if !accounts.iter().any(|a| a.user_id == account.user_id) {
+                accounts.push(account);
+            }
+        }
+        self.db
+            .insert(ACCOUNTS_KEY, serde_json::to_vec(&accounts)?)?;
+
+        // Folders belong to the other machine, so ones that don't exist here are left as they
+        // were.
+        let mut config = self.config()?;
+        if config.save_dir.as_ref().map_or(false, |dir| !dir.is_dir()) {
+            warn!(save_dir = ?config.save_dir, "Imported save dir doesn't exist here.");
+            config.save_dir = old_config.save_dir.clone();
+        }
+        if config.sync_dir.as_ref().map_or(false, |dir| !dir.is_dir()) {
+            warn!(sync_dir = ?config.sync_dir, "Imported sync dir doesn't exist here.");
+            config.sync_dir = old_config.sync_dir;
+        }
+        self.save_config(&mut config)?;
+
+        self.emit(Event::ConfigChanged(Box::new(self.config()?)));
+        self.start_watching_saves()?;
+        match self.user_id()? {
+            Some(user_id) if self.auth_key()?.is_some() => self.switch_account(&user_id)?,
+            _ => {
+                // The auth key was left out, so the user needs to enter it again.
+                self.state().transfer.clear();
+                self.emit(Event::UpdatedGames(vec![]));
+            }
+        }
+        Ok(summary)
+    }
+
+    /// The sled tree holding everything for the current account.
+    fn user_db(&self) -> Result<sled::Tree> {
+        let user_id = self
+            .user_id()?
+            .ok_or_else(|| anyhow!("No account has been authenticated."))?;
+        Ok(self.db.open_tree(Self::user_tree_name(&user_id))?)
+    }
+
+    fn user_tree_name(user_id: &UserId) -> String {
+        format!("user-{}", user_id)
+    }
+
+    /// This will eventually fetch a second time if the players shown don't exist in the db, or
+    /// were stored more than `player_cache_hours` ago.
+    #[instrument(skip(self))]
+    pub fn fetch_games(&self) -> Result<()> {
+        self.start_fetch(vec![])
+    }
+
+    /// Fetches the current account's games from GMR and waits for them, for the command line.
+    /// Nothing is stored, so the app still notices any new turns next time it polls.
+    #[instrument(skip(self))]
+    pub async fn fetch_games_now(&self) -> Result<Vec<Game>> {
+        Ok(self.api()?.get_games_and_players(&[]).await?.games)
+    }
+
+    /// Like a poll, but waits for the games instead of handing them to `process()`. They're
+    /// stored and their events emitted as if they had been polled.
+    #[instrument(skip(self))]
+    pub async fn refresh_games_now(&self) -> Result<Vec<Game>> {
+        let (games, skew) = self.api()?.get_games_and_players_with_skew(&[]).await?;
+        for event in
+            self.handle_fetched_games(games.games.clone(), games.current_total_points, skew)?
+        {
+            self.emit(event);
+        }
+        Ok(games.games)
+    }
+
+    /// Fetch a player's Steam name and avatar again, however recently they were stored. The
+    /// result arrives as `Event::UpdatedPlayer`.
+    #[instrument(skip(self))]
+    pub fn refresh_player(&self, user_id: &UserId) -> Result<()> {
+        self.start_fetch(vec![*user_id])
+    }
+
+    /// Fetches games, then any players in `force` along with the unknown and stale ones.
+    fn start_fetch(&self, force: Vec<UserId>) -> Result<()> {
         trace!("Fetching games.");
         let (mut tx, rx) = mpsc::channel(5);
-        self.fetch_games_rx = Some(rx);
+        self.state().fetch_games_rx = Some(rx);
         let api = self.api()?;
         let db = self.db.clone();
+        let max_age = self.player_cache_age()?;
         tokio::spawn(async move {
-            if let Err(err) = Self::do_fetch_games(db, api, &mut tx).await {
-                tx.send(Err(err)).await.unwrap();
+            if let Err(err) = Self::do_fetch_games(db, api, &mut tx, max_age, force).await {
+                // Only fails when a newer fetch has replaced this one.
+                let _ = tx.send(Err(err)).await;
             }
         });
         Ok(())
     }
 
+    /// Players stored longer ago than this are fetched again.
+    fn player_cache_age(&self) -> Result<Duration> {
+        Ok(Duration::from_secs(
+            self.config()?.player_cache_hours as u64 * 60 * 60,
+        ))
+    }
+
     async fn do_fetch_games(
         db: sled::Db,
         api: Api,
         tx: &mut mpsc::Sender<Result<FetchGames>>,
+        max_age: Duration,
+        force: Vec<UserId>,
     ) -> Result<()> {
-        let games = api.get_games_and_players(&[]).await?;
-        tx.send(Ok(FetchGames::Games(games.games.clone())))
-            .await
-            .unwrap();
-
-        let unknown_players =
-            Self::filter_unknown_players(&db, &games).context("Filter unknown players.")?;
-        if unknown_players.len() == 0 {
+        let (games, skew) = api.get_games_and_players_with_skew(&[]).await?;
+        tx.send(Ok(FetchGames::Games(
+            games.games.clone(),
+            games.current_total_points,
+            skew,
+        )))
+        .await
+        .context("Sending games.")?;
+
+        let mut unknown_players = Self::filter_unknown_players(&db, &games, max_age)
+            .context("Filter unknown players.")?;
+        unknown_players.extend(force);
+        unknown_players.sort();
+        unknown_players.dedup();
+        if unknown_players.is_empty() {
             return Ok(());
         }
 
@@ -269,9 +2298,9 @@ impl Manager {
             let player = player.clone();
             tokio::spawn(async move {
                 let result = Self::fetch_avatar(player, db_).await;
-                tx_.send(result.map(|sp| FetchGames::StoredPlayer(sp)))
-                    .await
-                    .unwrap();
+                let _ = tx_
+                    .send(result.map(|sp| FetchGames::StoredPlayer(sp)))
+                    .await;
             });
         }
 
@@ -286,13 +2315,26 @@ impl Manager {
 
     #[instrument(skip(db))]
     async fn fetch_avatar(player: Player, db: sled::Db) -> Result<StoredPlayer> {
-        let image_data = reqwest::get(&player.avatar_url)
-            .await
-            .unwrap()
-            .bytes()
-            .await
-            .unwrap()
-            .to_vec();
+        // Steam gives changed avatars a new URL, so the same URL means the same image.
+        let stored = db
+            .get(Self::player_info_key(&player.steam_id))?
+            .and_then(|b| serde_json::from_slice::<StoredPlayer>(&b).ok())
+            .filter(|stored| {
+                stored.player.avatar_url == player.avatar_url && !stored.image_data.is_empty()
+            });
+        let image_data = match stored {
+            Some(stored) => {
+                trace!("Avatar hasn't changed.");
+                stored.image_data
+            }
+            None => reqwest::get(&player.avatar_url)
+                .await
+                .context("Requesting avatar.")?
+                .bytes()
+                .await
+                .context("Downloading avatar.")?
+                .to_vec(),
+        };
 
         let stored_player = StoredPlayer {
             player,
@@ -303,7 +2345,12 @@ impl Manager {
         Ok(stored_player)
     }
 
-    fn filter_unknown_players(db: &sled::Db, games: &GetGamesAndPlayers) -> Result<Vec<UserId>> {
+    /// Players in `games` that aren't stored, or were stored more than `max_age` ago.
+    fn filter_unknown_players(
+        db: &sled::Db,
+        games: &GetGamesAndPlayers,
+        max_age: Duration,
+    ) -> Result<Vec<UserId>> {
         let mut players: Vec<UserId> = games
             .games
             .iter()
@@ -322,7 +2369,19 @@ impl Manager {
 
             match data {
                 Some(u) => {
-                    // TODO: Check the age of the avatar, e.g. 24 hours and add to needs_request.
+                    let stored: StoredPlayer = match serde_json::from_slice(&u) {
+                        Ok(stored) => stored,
+                        Err(err) => {
+                            warn!(?err, ?user_id, "Unreadable player info, fetching again.");
+                            needs_request.push(user_id);
+                            continue;
+                        }
+                    };
+                    let age = stored.last_downloaded.elapsed().unwrap_or_default();
+                    if age > max_age {
+                        trace!(?user_id, ?age, "Player info is stale.");
+                        needs_request.push(user_id);
+                    }
                 }
                 None => {
                     needs_request.push(user_id);
@@ -344,6 +2403,14 @@ impl Manager {
         format!("player-info-{}", user_id)
     }
 
+    fn pending_save_key(path: &Path) -> String {
+        format!("{}{}", PENDING_SAVE_PREFIX, path.to_string_lossy())
+    }
+
+    fn transfer_key(game_id: &GameId) -> String {
+        format!("transfer-{}", game_id)
+    }
+
     fn saved_bytes_db_key(game_id: &GameId, turn_id: &TurnId) -> String {
         format!("saved-bytes-{}-{}", game_id, turn_id)
     }
@@ -356,43 +2423,170 @@ impl Manager {
         format!("upload-bytes-{}-{}", game_id, turn_id)
     }
 
-    /// Windows: ~\Documents\My Games\Sid Meier's Civilization 5\Saves\hotseat\
-    /// OS X: ~/Documents/Aspyr/Sid Meier's Civilization 5/Saves/hotseat/
-    /// Linux: ~/.local/share/Aspyr/Sid Meier's Civilization 5/Saves/hotseat/
-    fn save_dir() -> Result<PathBuf> {
-        let base_dirs = BaseDirs::new().ok_or(anyhow!("Could not work out basedir."))?;
-        let home = base_dirs.home_dir();
-        let suffix = PathBuf::from("Sid Meier's Civilization 5")
-            .join("Saves")
-            .join("hotseat");
-        // Can't use the `directories` crate because these paths are inconsistent between OS's.
-        let middle = if cfg!(windows) {
-            PathBuf::from("Documents").join("My Games")
-        } else if cfg!(target_os = "macos") {
-            PathBuf::from("Documents").join("Aspyr")
-        } else if cfg!(unix) {
-            PathBuf::from(".local").join("share").join("Aspyr")
-        } else {
-            return Err(anyhow!("Unhandled operating system for save_dir."));
-        };
-        Ok(home.join(middle).join(suffix))
+    fn upload_path_db_key(game_id: &GameId, turn_id: &TurnId) -> String {
+        format!("upload-path-{}-{}", game_id, turn_id)
     }
 
-    fn filename(game: &Game) -> Result<PathBuf> {
-        let cleaner_name: String = game
-            .name
-            .chars()
-            .map(|c| match "./\\\"<>|:*?".contains(c) {
-                true => '_',
-                false => c,
-            })
-            .collect();
-        Ok(format!("(civfun {}) {}.Civ5Save", game.game_id, cleaner_name).into())
+    /// The hotseat directory, either from the config or detected.
+    pub fn save_dir(&self) -> Result<PathBuf> {
+        if let Some(save_dir) = self.config()?.save_dir {
+            return Ok(save_dir);
+        }
+        let save_dir::Detection { tried, found } = save_dir::detect()?;
+        found.ok_or_else(|| {
+            anyhow!(
+                "Could not find the hotseat save directory. Tried: {:?}",
+                tried.iter().map(|(p, _)| p).collect::<Vec<_>>()
+            )
+        })
+    }
+
+    /// Where we looked for the hotseat directory, for showing to the user when it can't be found.
+    pub fn detect_save_dir(&self) -> Result<save_dir::Detection> {
+        save_dir::detect()
+    }
+
+    pub fn config(&self) -> Result<Config> {
+        match self.db.get(CONFIG_KEY)? {
+            Some(b) => serde_json::from_slice(&b).context("Decoding config."),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Change settings with `f`. The result is validated before it's saved, and anything that
+    /// depends on a changed setting is restarted. Emits `ConfigChanged` if anything changed.
+    ///
+    /// ```ignore
+    /// manager.update_config(|config| config.poll_seconds = 120)?;
+    /// ```
+    #[instrument(skip(self, f))]
+    pub fn update_config<F>(&self, f: F) -> Result<Config>
+    where
+        F: FnOnce(&mut Config),
+    {
+        let old = self.config()?;
+        let mut config = old.clone();
+        f(&mut config);
+        if config == old {
+            return Ok(config);
+        }
+        config.validate(&old)?;
+        debug!(?config, "Config changed.");
+        self.save_config(&mut config)?;
+        if config.email.is_none() && old.email.is_some() {
+            self.keys.remove(keychain::EMAIL_PASSWORD)?;
+        }
+
+        if config.save_dir != old.save_dir {
+            self.start_watching_saves()?;
+        }
+        if config.poll_seconds != old.poll_seconds {
+            self.update_poll_delay()?;
+        }
+        if config.sync_dir.is_some() && config.sync_dir != old.sync_dir && self.user_id()?.is_some()
+        {
+            self.sync_mirror().context("Filling the sync folder.")?;
+        }
+
+        self.emit(Event::ConfigChanged(Box::new(config.clone())));
+        Ok(config)
+    }
+
+    /// Override the hotseat directory. None goes back to detecting it.
+    #[instrument(skip(self))]
+    pub fn set_save_dir(&self, save_dir: Option<PathBuf>) -> Result<()> {
+        self.update_config(|config| config.save_dir = save_dir)?;
+        Ok(())
+    }
+
+    /// Turn desktop notifications on or off.
+    pub fn set_notifications(&self, enabled: bool) -> Result<()> {
+        self.update_config(|config| config.notifications = enabled)?;
+        Ok(())
+    }
+
+    pub fn set_quiet_hours(&self, quiet_hours: Option<QuietHours>) -> Result<()> {
+        self.update_config(|config| config.quiet_hours = quiet_hours)?;
+        Ok(())
+    }
+
+    /// How often to check GMR for games. Takes effect straight away.
+    pub fn set_poll_seconds(&self, seconds: u64) -> Result<()> {
+        self.update_config(|config| config.poll_seconds = seconds)?;
+        Ok(())
+    }
+
+    /// Minutes before a turn expires to remind the user. An empty list turns reminders off.
+    pub fn set_reminder_minutes(&self, minutes: Vec<u32>) -> Result<()> {
+        self.update_config(|config| config.reminder_minutes = minutes)?;
+        Ok(())
+    }
+
+    /// A new email password is moved into the keychain when there is one, leaving
+    /// `config.email.password` empty.
+    fn save_config(&self, config: &mut Config) -> Result<()> {
+        if let Some(email) = &mut config.email {
+            if !email.password.is_empty() {
+                match self.keys.store(keychain::EMAIL_PASSWORD, &email.password) {
+                    Ok(true) => email.password.clear(),
+                    Ok(false) => {}
+                    Err(err) => warn!(
+                        ?err,
+                        "Could not use the keychain, keeping the email password in the db."
+                    ),
+                }
+            }
+        }
+        self.db.insert(CONFIG_KEY, serde_json::to_vec(config)?)?;
+        Ok(())
+    }
+
+    /// Where the save for this game's current turn is downloaded to.
+    pub fn save_path(&self, game: &Game) -> Result<PathBuf> {
+        let settings = self.game_settings(&game.game_id)?;
+        Ok(self.save_dir()?.join(settings.filename(game)))
+    }
+
+    pub fn game_settings(&self, game_id: &GameId) -> Result<GameSettings> {
+        match self.user_db()?.get(Self::game_settings_key(game_id))? {
+            Some(b) => serde_json::from_slice(&b).context("Decoding game settings."),
+            None => Ok(GameSettings::default()),
+        }
+    }
+
+    /// Like `update_config()`, for a single game. Emits `GameSettingsChanged` if anything
+    /// changed.
+    #[instrument(skip(self, f))]
+    pub fn update_game_settings<F>(&self, game_id: &GameId, f: F) -> Result<GameSettings>
+    where
+        F: FnOnce(&mut GameSettings),
+    {
+        let old = self.game_settings(game_id)?;
+        let mut settings = old.clone();
+        f(&mut settings);
+        if settings == old {
+            return Ok(settings);
+        }
+        settings.validate()?;
+        debug!(?settings, "Game settings changed.");
+        self.user_db()?.insert(
+            Self::game_settings_key(game_id),
+            serde_json::to_vec(&settings)?,
+        )?;
+        self.emit(Event::GameSettingsChanged {
+            game_id: *game_id,
+            settings: settings.clone(),
+        });
+        Ok(settings)
+    }
+
+    fn game_settings_key(game_id: &GameId) -> String {
+        format!("game-settings-{}", game_id)
     }
 
     #[instrument(skip(self))]
     fn store_downloaded_save(
-        &mut self,
+        &self,
         game_id: &GameId,
         turn_id: &TurnId,
         path: &PathBuf,
@@ -401,288 +2595,1265 @@ impl Manager {
         let mut fp = File::open(&path)?;
         let mut data = Vec::with_capacity(1_000_000);
         fp.read_to_end(&mut data)?;
-        self.db.insert(
+        if self.config()?.clear_hotseat_passwords {
+            data = Civ5SaveWriter::new(&data)
+                .clear_passwords()
+                .context("Clearing hotseat passwords.")?;
+            std::fs::write(path, &data).context("Writing save without passwords.")?;
+        }
+        self.user_db()?.insert(
             Self::saved_bytes_db_key(&game_id, &turn_id),
             data.as_slice(),
         )?;
-        self.transfer
-            .insert(game_id.clone(), TransferState::Downloaded);
+        self.set_transfer_state(game_id, turn_id, TransferState::Downloaded)?;
+        // A new save is a new turn, which hasn't been played unless it was asked for.
+        self.state().playing.remove(game_id);
+        let play = self.state().play_after_download.remove(game_id);
+        if play {
+            if let Err(err) = self.launch_turn(game_id) {
+                warn!(?err, "Launching Civ V after downloading.");
+                self.emit(Event::Error {
+                    source: Subsystem::GameProcess,
+                    message: format!("Starting Civ V: {:#}", err),
+                    recoverable: true,
+                });
+            }
+        }
 
         self.analyse(game_id, turn_id, &data)?;
+        if let Err(err) = self.mirror_save(game_id, turn_id, ArchiveKind::Downloaded, &data) {
+            warn!(?err, "Could not copy save to the sync folder.");
+        }
 
         Ok(())
     }
 
     #[instrument(skip(self, data))]
-    fn analyse(&mut self, game_id: &GameId, turn_id: &TurnId, data: &[u8]) -> Result<()> {
+    fn analyse(&self, game_id: &GameId, turn_id: &TurnId, data: &[u8]) -> Result<()> {
         trace!(data_len = ?data.len(), "Analysing save.");
         let civ5save = Civ5SaveReader::new(&data).parse()?;
         trace!(?civ5save);
 
         let key = Self::analysed_game_key(game_id, turn_id);
         let encoded = serde_json::to_vec(&civ5save)?;
-        self.db.insert(key, encoded)?;
+        self.user_db()?.insert(key, encoded)?;
         Ok(())
     }
 
     #[instrument(skip(self))]
     fn analysed(&self, game_id: &GameId, turn_id: &TurnId) -> Result<Option<Civ5Save>> {
         let key = Self::analysed_game_key(game_id, turn_id);
-        let bytes = self.db.get(key).context("Fetching analysed")?;
+        let bytes = self.user_db()?.get(key).context("Fetching analysed")?;
         match bytes {
             None => Ok(None),
             Some(b) => Ok(Some(serde_json::from_slice(&b)?)),
         }
     }
 
-    pub fn download_status(&self) -> Vec<TransferState> {
-        todo!()
+    /// Whether games are being fetched from GMR right now.
+    pub fn fetching(&self) -> bool {
+        self.state().fetch_games_rx.is_some()
+    }
+
+    /// Whether new saves in the save dir are being noticed. False when there's no save dir or
+    /// the watcher has stopped.
+    pub fn watching_saves(&self) -> bool {
+        self.state().watch_files_rx.is_some()
+    }
+
+    /// Idle for games that nothing has happened to yet.
+    pub fn transfer_state(&self, game_id: &GameId) -> TransferState {
+        self.state()
+            .transfer
+            .get(game_id)
+            .copied()
+            .unwrap_or(TransferState::Idle)
+    }
+
+    /// Each game's transfer, in the order they get bandwidth.
+    pub fn download_status(&self) -> Vec<TransferStatus> {
+        let state = self.state();
+        let mut statuses: Vec<_> = state
+            .transfer
+            .iter()
+            .map(|(game_id, transfer)| TransferStatus {
+                game_id: *game_id,
+                state: *transfer,
+                priority: state
+                    .transfer_priority
+                    .get(game_id)
+                    .copied()
+                    .unwrap_or(usize::MAX),
+            })
+            .collect();
+        statuses.sort_by_key(|s| s.priority);
+        statuses
+    }
+
+    /// None until the game has been through `process_transfers()`.
+    pub fn transfer_priority(&self, game_id: &GameId) -> Option<usize> {
+        self.state().transfer_priority.get(game_id).copied()
+    }
+
+    /// How much space the next download might need, going by the biggest save kept so far and
+    /// allowing for the temporary copy made while downloading.
+    fn largest_save_size(&self) -> Result<u64> {
+        let mut largest = DEFAULT_SAVE_SIZE;
+        if self.user_id()?.is_some() {
+            for item in self.user_db()?.scan_prefix("saved-bytes-") {
+                let (_, bytes) = item?;
+                largest = largest.max(bytes.len() as u64);
+            }
+        }
+        Ok(largest * 2)
+    }
+
+    /// Emits `Event::SaveDirNotFound` instead of failing when there's no hotseat directory, so
+    /// the UI can ask the user where it is.
+    #[instrument(skip(self))]
+    pub fn start_watching_saves(&self) -> Result<()> {
+        let save_dir = match self.save_dir() {
+            Ok(save_dir) => save_dir,
+            Err(err) => {
+                warn!(?err, "Not watching for saves.");
+                self.state().watch_files_rx = None;
+                let tried = self
+                    .detect_save_dir()?
+                    .tried
+                    .into_iter()
+                    .map(|(path, _)| path)
+                    .collect();
+                self.emit(Event::SaveDirNotFound { tried });
+                return Ok(());
+            }
+        };
+        debug!(?save_dir);
+
+        let problems = save_dir::preflight(&save_dir, self.largest_save_size()?);
+        let missing = problems.contains(&SaveDirProblem::Missing);
+        for problem in problems {
+            warn!(?save_dir, ?problem, "Problem with the save dir.");
+            self.emit(Event::SaveDirProblem {
+                path: save_dir.clone(),
+                problem,
+            });
+        }
+        if missing {
+            self.state().watch_files_rx = None;
+            return Ok(());
+        }
+
+        let (tx, rx) = mpsc::channel(10);
+        self.state().watch_files_rx = Some(rx);
+
+        watcher::spawn(save_dir, tx)?;
+        Ok(())
+    }
+
+    pub fn process_new_saves(&self) -> Result<()> {
+        let (found, finished) = match self.state().watch_files_rx.as_mut() {
+            Some(rx) => drain(rx),
+            None => {
+                trace!("Not watching for saves.");
+                return Ok(());
+            }
+        };
+        if finished {
+            warn!("The save watcher stopped.");
+            self.state().watch_files_rx = None;
+        }
+        for file in found {
+            if !self.save_dir()?.join(&file).exists() {
+                // Renamed or removed again before we got to it.
+                trace!(?file, "Save has gone away.");
+                continue;
+            }
+            self.handle_save(&file, false).context(file)?;
+        }
+
+        Ok(())
+    }
+
+    /// Example filename: Casimir III_0028 BC-2320.Civ5Save
+    /// [Next turn's leader]_[Turn number] [(BC|AD)-Year].Civ5Save
+    /// Filter current games:
+    ///  - When turn number is the same or +1.
+    ///  - Verify the difference between the downloaded save and the new file is low.
+    /// If there isn't exactly one clear match, park the save and ask the user about it with
+    /// `Event::SaveNeedsConfirmation`.
+    /// Otherwise:
+    ///  - Move the originally downloaded file to `civfun Archive/[game_id]_[turn]_[dn]_[original name]`.
+    ///  - Copy the file bytes into the DB and queue for upload.
+    ///  - Move the uploaded file to `civfun Archive/[game_id]_[turn]_[up]_[original name]`
+    ///
+    /// A save is only looked at once, going by its fingerprint. When `scanning`, a save that
+    /// doesn't look like any current game is ignored instead of asking the user about it, and is
+    /// looked at again by the next scan.
+    #[instrument(skip(self))]
+    fn handle_save(&self, filename: &str, scanning: bool) -> Result<bool> {
+        // let turn = Self::turn_from_filename(filename)?;
+        // let turn = match turn {
+        //     Some(turn) => turn,
+        //     None => return Ok(false),
+        // };
+
+        let full_path = self.save_dir()?.join(filename);
+        trace!(?full_path);
+        for game in self.my_games()? {
+            if self.save_path(&game)? == full_path {
+                trace!(game_id = ?game.game_id, "Our own download, not a played turn.");
+                return Ok(false);
+            }
+        }
+        let mut fp = File::open(&full_path).context("Opening save")?;
+        let mut bytes = Vec::with_capacity(1_000_000);
+        fp.read_to_end(&mut bytes)?;
+        drop(fp);
+
+        let seen_key = Self::seen_save_key(&bytes);
+        let user_db = self.user_db()?;
+        if let Some(mut seen) = self.seen_save(&seen_key)? {
+            trace!("Already seen this save.");
+            // The same save as another file is reported once, not on every scan.
+            if let Some(game_id) = seen.game_id {
+                if !seen.paths.contains(&full_path) {
+                    seen.paths.push(full_path.clone());
+                    user_db.insert(&seen_key, serde_json::to_vec(&seen)?)?;
+                    self.duplicate_ignored(game_id, full_path);
+                }
+            }
+            return Ok(false);
+        }
+        let new_parsed_save = Civ5SaveReader::new(&bytes).parse()?;
+
+        let candidates = self.save_candidates(&new_parsed_save)?;
+        let winner = match Self::clear_winner(&candidates).cloned() {
+            Some(game) => Some(game),
+            None => self.remembered_match(filename, &candidates)?,
+        };
+        if let Some(game) = winner {
+            trace!(game_id = ?game.game_id, "Found game for save.");
+            let seen = SeenSave {
+                game_id: Some(game.game_id),
+                paths: vec![full_path.clone()],
+            };
+            let problems = self.first_turn_problems(&game, &new_parsed_save)?;
+            let queued = match self.transfer_state(&game.game_id) {
+                _ if !problems.is_empty() => {
+                    warn!(game_id = ?game.game_id, ?problems, "First turn save rejected.");
+                    self.emit(Event::FirstTurnSaveRejected {
+                        game_id: game.game_id,
+                        path: full_path,
+                        problems,
+                    });
+                    false
+                }
+                TransferState::Uploading | TransferState::UploadComplete => {
+                    // A turn can only be submitted once. Anything that comes after is a re-save.
+                    self.duplicate_ignored(game.game_id, full_path);
+                    false
+                }
+                // Anything else either hasn't been sent yet or failed, so the newest save wins.
+                _ => {
+                    self.queue_upload(&game, &full_path, bytes, false)?;
+                    true
+                }
+            };
+            user_db.insert(seen_key, serde_json::to_vec(&seen)?)?;
+            return Ok(queued);
+        }
+        if scanning && candidates.is_empty() {
+            // Not marked as seen, it could be for a turn that hasn't been fetched yet.
+            trace!("Old save.");
+            return Ok(false);
+        }
+
+        let candidates = if candidates.is_empty() {
+            // Nothing looks like it, so let the user choose from everything waiting on them.
+            self.my_games()?
+                .into_iter()
+                .map(|game| SaveCandidate {
+                    game,
+                    difference: None,
+                })
+                .collect()
+        } else {
+            candidates
+        };
+        info!(
+            candidates = candidates.len(),
+            "Save needs to be assigned by the user."
+        );
+        let pending = PendingSave {
+            path: full_path,
+            candidates,
+        };
+        self.user_db()?.insert(
+            Self::pending_save_key(&pending.path),
+            serde_json::to_vec(&pending)?,
+        )?;
+        self.emit(Event::SaveNeedsConfirmation {
+            path: pending.path,
+            candidates: pending.candidates,
+        });
+        user_db.insert(seen_key, vec![])?;
+
+        Ok(true)
+    }
+
+    fn duplicate_ignored(&self, game_id: GameId, path: PathBuf) {
+        info!(
+            ?game_id,
+            ?path,
+            "Ignoring a save for a turn that's already been handled."
+        );
+        self.emit(Event::DuplicateTurnIgnored { game_id, path });
+    }
+
+    fn seen_save_key(bytes: &[u8]) -> String {
+        format!("seen-save-{}", watcher::fingerprint(bytes))
+    }
+
+    fn seen_save(&self, key: &str) -> Result<Option<SeenSave>> {
+        let b = match self.user_db()?.get(key)? {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+        // Saves seen before games were recorded have an empty value.
+        if b.is_empty() {
+            return Ok(Some(SeenSave::default()));
+        }
+        Ok(Some(
+            serde_json::from_slice(&b).context("Decoding seen save.")?,
+        ))
+    }
+
+    /// Finds turns played while civfun wasn't running, which the watcher never saw. Returns how
+    /// many saves were queued or need the user to pick a game.
+    #[instrument(skip(self))]
+    pub fn scan_for_missed_saves(&self) -> Result<usize> {
+        if self.user_id()?.is_none() {
+            return Ok(0);
+        }
+        let save_dir = match self.save_dir() {
+            Ok(save_dir) => save_dir,
+            Err(err) => {
+                trace!(?err, "No save dir to scan.");
+                return Ok(0);
+            }
+        };
+
+        let mut found = 0;
+        for entry in std::fs::read_dir(&save_dir).context("Scanning save dir.")? {
+            let path = entry?.path();
+            if !path.is_file() || !watcher::is_save(&path) {
+                continue;
+            }
+            let filename = match path.file_name() {
+                Some(filename) => filename.to_string_lossy().to_string(),
+                None => continue,
+            };
+            match self.handle_save(&filename, true) {
+                Ok(true) => found += 1,
+                Ok(false) => {}
+                // Anything can be lying around in there, so one bad file shouldn't stop the rest.
+                Err(err) => warn!(?filename, ?err, "Skipping save."),
+            }
+        }
+        debug!(found, "Scanned for missed saves.");
+        Ok(found)
+    }
+
+    /// Unless `approved`, the upload waits for `approve_upload()` when `confirm_uploads` is on.
+    fn queue_upload(&self, game: &Game, path: &Path, bytes: Vec<u8>, approved: bool) -> Result<()> {
+        let game_id = game.game_id;
+        let turn_id = &game.current_turn.turn_id;
+        self.state().playing.remove(&game_id);
+        let user_db = self.user_db()?;
+        user_db.insert(Self::upload_bytes_db_key(&game_id, turn_id), bytes)?;
+        user_db.insert(
+            Self::upload_path_db_key(&game_id, turn_id),
+            path.to_string_lossy().as_bytes(),
+        )?;
+        if !approved && self.config()?.confirm_uploads {
+            info!(?game_id, "Upload needs approval.");
+            self.set_transfer_state(&game_id, turn_id, TransferState::UploadAwaitingApproval)?;
+            self.emit(Event::UploadNeedsApproval {
+                game: game.clone(),
+                path: path.to_path_buf(),
+            });
+        } else {
+            self.set_transfer_state(&game_id, turn_id, TransferState::UploadQueued)?;
+        }
+
+        if let Err(err) = self.archive_downloaded_save(game) {
+            warn!(?err, "Could not archive downloaded save.");
+        }
+        Ok(())
+    }
+
+    /// Send a save that was waiting in `UploadAwaitingApproval`.
+    #[instrument(skip(self))]
+    pub fn approve_upload(&self, game_id: &GameId) -> Result<()> {
+        let turn_id = self.awaiting_approval(game_id)?;
+        self.set_transfer_state(game_id, &turn_id, TransferState::UploadQueued)
+    }
+
+    /// Send a save again after `Event::UploadFailed`, rather than waiting for the next start.
+    #[instrument(skip(self))]
+    pub fn retry_upload(&self, game_id: &GameId) -> Result<()> {
+        if self.transfer_state(game_id) != TransferState::UploadFailed {
+            return Err(anyhow!("No upload has failed in game {}.", game_id));
+        }
+        let turn_id = self
+            .my_games()?
+            .into_iter()
+            .find(|g| &g.game_id == game_id)
+            .map(|g| g.current_turn.turn_id)
+            .ok_or_else(|| anyhow!("It isn't your turn in game {}.", game_id))?;
+        self.set_transfer_state(game_id, &turn_id, TransferState::UploadQueued)
+    }
+
+    /// Throw away a save that was waiting in `UploadAwaitingApproval`, e.g. because it was the
+    /// wrong game. The turn goes back to waiting for a save.
+    #[instrument(skip(self))]
+    pub fn reject_upload(&self, game_id: &GameId) -> Result<()> {
+        let turn_id = self.awaiting_approval(game_id)?;
+        let user_db = self.user_db()?;
+        user_db.remove(Self::upload_bytes_db_key(game_id, &turn_id))?;
+        user_db.remove(Self::upload_path_db_key(game_id, &turn_id))?;
+        self.set_transfer_state(game_id, &turn_id, TransferState::Downloaded)
+    }
+
+    /// Everything waiting for `approve_upload()` or `reject_upload()`.
+    pub fn uploads_awaiting_approval(&self) -> Result<Vec<UploadSummary>> {
+        let user_db = self.user_db()?;
+        let mut summaries = vec![];
+        for game in self.my_games()? {
+            if self.transfer_state(&game.game_id) != TransferState::UploadAwaitingApproval {
+                continue;
+            }
+            let turn_id = &game.current_turn.turn_id;
+            let (bytes, path) = match (
+                user_db.get(Self::upload_bytes_db_key(&game.game_id, turn_id))?,
+                user_db.get(Self::upload_path_db_key(&game.game_id, turn_id))?,
+            ) {
+                (Some(bytes), Some(path)) => (bytes, path),
+                _ => {
+                    warn!(game_id = ?game.game_id, "Upload awaiting approval has no save.");
+                    continue;
+                }
+            };
+            let path = PathBuf::from(String::from_utf8_lossy(&path).to_string());
+            let save = Civ5SaveReader::new(&bytes).parse()?;
+            let difference = match self.analysed(&game.game_id, turn_id)? {
+                Some(downloaded) => Some(save.difference_score(&downloaded)?),
+                None => None,
+            };
+            let leader = path
+                .file_name()
+                .and_then(|name| Self::leader_from_filename(&name.to_string_lossy()));
+            summaries.push(UploadSummary {
+                turn: save.header.turn,
+                leader,
+                difference,
+                path,
+                game,
+            });
+        }
+        Ok(summaries)
+    }
+
+    fn awaiting_approval(&self, game_id: &GameId) -> Result<TurnId> {
+        if self.transfer_state(game_id) != TransferState::UploadAwaitingApproval {
+            return Err(anyhow!(
+                "No upload is waiting for approval in game {}.",
+                game_id
+            ));
+        }
+        self.my_games()?
+            .into_iter()
+            .find(|g| &g.game_id == game_id)
+            .map(|g| g.current_turn.turn_id)
+            .ok_or_else(|| anyhow!("It isn't your turn in game {}.", game_id))
+    }
+
+    /// Saves that couldn't be matched to a game automatically and are waiting on the user.
+    pub fn pending_saves(&self) -> Result<Vec<PendingSave>> {
+        let mut pending = vec![];
+        for item in self.user_db()?.scan_prefix(PENDING_SAVE_PREFIX) {
+            let (_, value) = item?;
+            pending.push(serde_json::from_slice(&value).context("Decoding pending save.")?);
+        }
+        Ok(pending)
+    }
+
+    /// The user has told us which game a pending save belongs to.
+    #[instrument(skip(self))]
+    pub fn confirm_save_assignment(&self, path: &Path, game_id: &GameId) -> Result<()> {
+        self.upload_file(game_id, path)?;
+        let user_db = self.user_db()?;
+        user_db.remove(Self::pending_save_key(path))?;
+        if let Some(leader) = path
+            .file_name()
+            .and_then(|name| Self::leader_from_filename(&name.to_string_lossy()))
+        {
+            debug!(?leader, ?game_id, "Remembering the game for this leader.");
+            user_db.insert(Self::match_key(&leader), serde_json::to_vec(game_id)?)?;
+        }
+        Ok(())
+    }
+
+    /// When matching by difference is ambiguous, picks the game the user chose last time for
+    /// a save with the same leader, as long as it's still one of the candidates.
+    fn remembered_match(
+        &self,
+        filename: &str,
+        candidates: &[SaveCandidate],
+    ) -> Result<Option<Game>> {
+        let leader = match Self::leader_from_filename(filename) {
+            Some(leader) => leader,
+            None => return Ok(None),
+        };
+        let game_id: GameId = match self.user_db()?.get(Self::match_key(&leader))? {
+            Some(data) => serde_json::from_slice(&data)?,
+            None => return Ok(None),
+        };
+        let game = candidates
+            .iter()
+            .find(|candidate| candidate.game.game_id == game_id)
+            .map(|candidate| candidate.game.clone());
+        if game.is_some() {
+            debug!(
+                ?leader,
+                ?game_id,
+                "Using the remembered game for this leader."
+            );
+        }
+        Ok(game)
+    }
+
+    fn match_key(leader: &str) -> String {
+        format!("match-leader-{}", leader)
+    }
+
+    /// Uploads any save file for a game, without matching it to a game first. For saves kept
+    /// outside the hotseat directory, which are left where they are after uploading.
+    #[instrument(skip(self))]
+    pub fn upload_file(&self, game_id: &GameId, path: &Path) -> Result<()> {
+        let game = self
+            .my_games()?
+            .into_iter()
+            .find(|g| &g.game_id == game_id)
+            .ok_or_else(|| anyhow!("It's not your turn in game {}.", game_id))?;
+        match self.transfer_state(game_id) {
+            TransferState::Uploading | TransferState::UploadComplete => {
+                return Err(anyhow!("This turn of {} has already been sent.", game.name));
+            }
+            _ => {}
+        }
+        let bytes = std::fs::read(path).with_context(|| format!("Reading {:?}", path))?;
+        let save = Civ5SaveReader::new(&bytes)
+            .parse()
+            .with_context(|| format!("{:?} isn't a Civ V save.", path))?;
+        let problems = self.first_turn_problems(&game, &save)?;
+        if let Some(problem) = problems.first() {
+            return Err(anyhow!("{}", problem));
+        }
+
+        // So the watcher doesn't pick it up again if it's in the hotseat directory.
+        let seen = SeenSave {
+            game_id: Some(*game_id),
+            paths: vec![path.to_path_buf()],
+        };
+        self.user_db()?
+            .insert(Self::seen_save_key(&bytes), serde_json::to_vec(&seen)?)?;
+        // Picking the game is approval enough.
+        self.queue_upload(&game, path, bytes, true)
+    }
+
+    /// Uploads a save dropped onto the window or given on the command line, matching it to a
+    /// game the way the watcher does. When that's not clear, returns the games it could be for
+    /// so the user can pick one with `confirm_save_assignment`. It isn't kept as pending, it can
+    /// always be dropped again.
+    #[instrument(skip(self))]
+    pub fn upload_dropped(&self, path: &Path) -> Result<DroppedSave> {
+        if !watcher::is_save(path) {
+            return Err(anyhow!("{:?} isn't a .Civ5Save file.", path));
+        }
+        let bytes = std::fs::read(path).with_context(|| format!("Reading {:?}", path))?;
+        let save = Civ5SaveReader::new(&bytes)
+            .parse()
+            .with_context(|| format!("{:?} isn't a Civ V save.", path))?;
+
+        let candidates = self.save_candidates(&save)?;
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let winner = match Self::clear_winner(&candidates).cloned() {
+            Some(game) => Some(game),
+            None => self.remembered_match(&filename, &candidates)?,
+        };
+        if let Some(game) = winner {
+            debug!(game_id = ?game.game_id, "Found game for dropped save.");
+            self.upload_file(&game.game_id, path)?;
+            return Ok(DroppedSave::Queued(game));
+        }
+
+        let candidates = if candidates.is_empty() {
+            self.my_games()?
+                .into_iter()
+                .map(|game| SaveCandidate {
+                    game,
+                    difference: None,
+                })
+                .collect()
+        } else {
+            candidates
+        };
+        if candidates.is_empty() {
+            return Err(anyhow!("None of your games are waiting on your turn."));
+        }
+        Ok(DroppedSave::Unclear(PendingSave {
+            path: path.to_path_buf(),
+            candidates,
+        }))
+    }
+
+    /// How the first save of a game needs to be set up, for games waiting on their first turn.
+    pub fn first_turn_setup(&self, game_id: &GameId) -> Result<ExpectedSetup> {
+        let game = self
+            .my_games()?
+            .into_iter()
+            .find(|g| &g.game_id == game_id && g.current_turn.is_first_turn)
+            .ok_or_else(|| anyhow!("Game {} isn't waiting on you for its first turn.", game_id))?;
+        Ok(ExpectedSetup::new(&game, |user_id| {
+            self.player_name(user_id)
+        }))
     }
 
-    #[instrument(skip(self))]
-    pub fn start_watching_saves(&mut self) -> Result<()> {
-        let save_dir = Self::save_dir().unwrap();
-        debug!(?save_dir);
-
-        let (tx, rx) = mpsc::channel(10);
-        self.watch_files_rx = Some(rx);
-
-        let (watch_tx, watch_rx) = std::sync::mpsc::channel();
-        let mut watcher: RecommendedWatcher = Watcher::new(watch_tx, Duration::from_millis(250))?;
-        watcher.watch(save_dir, RecursiveMode::NonRecursive)?;
-
-        tokio::spawn(async move {
-            // Move watcher into here, since it would be dropped otherwise and then the channel
-            // would be dropped.
-            let _ = watcher;
+    /// Checks a save made for the first turn of a game before it's uploaded.
+    pub fn check_first_turn_save(
+        &self,
+        game_id: &GameId,
+        path: &Path,
+    ) -> Result<Vec<SetupProblem>> {
+        let expected = self.first_turn_setup(game_id)?;
+        let bytes = std::fs::read(path).with_context(|| format!("Reading {:?}", path))?;
+        let save = Civ5SaveReader::new(&bytes).parse()?;
+        Ok(first_turn::check(&save, &expected))
+    }
 
-            Self::watch_loop(watch_rx, tx).await;
-        });
+    /// Always empty for games past their first turn.
+    fn first_turn_problems(&self, game: &Game, save: &Civ5Save) -> Result<Vec<SetupProblem>> {
+        if !game.current_turn.is_first_turn {
+            return Ok(vec![]);
+        }
+        let expected = ExpectedSetup::new(game, |user_id| self.player_name(user_id));
+        Ok(first_turn::check(save, &expected))
+    }
 
-        Ok(())
+    fn player_name(&self, user_id: &UserId) -> Option<String> {
+        let stored_player = self.stored_player(user_id).ok()??;
+        Some(stored_player.player.persona_name)
     }
 
-    async fn watch_loop(watch_rx: std::sync::mpsc::Receiver<DebouncedEvent>, tx: Sender<String>) {
-        trace!("Loop started.");
-        loop {
-            let event = watch_rx.try_recv();
-            match event {
-                Ok(event) => {
-                    info!(?event);
-                    if let DebouncedEvent::Create(path) = event {
-                        let filename = path.file_name().unwrap().to_str().unwrap().into();
-                        tx.send(filename).await.unwrap();
-                    }
-                }
-                Err(std::sync::mpsc::TryRecvError::Empty) => {}
-                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                    warn!("Disconnected");
-                    return;
+    /// Everyone the current account plays with, most shared games first.
+    pub fn players(&self) -> Result<Vec<KnownPlayer>> {
+        let me = self.user_id()?;
+        let games = self.games()?;
+        let histories = games
+            .iter()
+            .map(|game| self.turn_history(&game.game_id))
+            .collect::<Result<Vec<_>>>()?;
+        let mut shared: HashMap<UserId, Vec<GameId>> = HashMap::new();
+        for game in games {
+            for player in &game.players {
+                if Some(player.user_id) != me {
+                    shared.entry(player.user_id).or_default().push(game.game_id);
                 }
             }
-
-            tokio::task::yield_now().await;
         }
-    }
 
-    pub fn process_new_saves(&mut self) -> Result<()> {
-        let rx = match self.watch_files_rx {
-            Some(ref mut rx) => rx,
-            None => {
-                warn!("Receiver is None for watch_files_rx.");
-                return Ok(());
-            }
-        };
+        let mut players = shared
+            .into_iter()
+            .map(|(user_id, shared_games)| {
+                Ok(KnownPlayer {
+                    user_id,
+                    info: self.stored_player(&user_id)?,
+                    shared_games,
+                    average_turn: stats::average_turn(&user_id, &histories),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        players.sort_by_key(|p| (Reverse(p.shared_games.len()), p.user_id));
+        Ok(players)
+    }
 
-        let mut found = vec![];
-        while let Ok(file) = rx.try_recv() {
-            found.push(file);
-        }
-        for file in found {
-            self.handle_save(&file).context(file)?;
+    /// Steam name and avatar, once the player has been seen in a game.
+    pub fn stored_player(&self, user_id: &UserId) -> Result<Option<StoredPlayer>> {
+        if let Some(stored_player) = self.state().players.get(user_id) {
+            return Ok(Some(stored_player.clone()));
         }
+        let stored_player: StoredPlayer = match self.db.get(Self::player_info_key(user_id))? {
+            Some(b) => serde_json::from_slice(&b).context("Decoding player info.")?,
+            None => return Ok(None),
+        };
+        self.state().players.insert(*user_id, stored_player.clone());
+        Ok(Some(stored_player))
+    }
 
+    /// Forget about a pending save. The file is left alone.
+    #[instrument(skip(self))]
+    pub fn dismiss_save(&self, path: &Path) -> Result<()> {
+        self.user_db()?.remove(Self::pending_save_key(path))?;
         Ok(())
     }
 
-    /// Example filename: Casimir III_0028 BC-2320.Civ5Save
-    /// [Next turn's leader]_[Turn number] [(BC|AD)-Year].Civ5Save
-    /// Filter current games:
-    ///  - When turn number is the same or +1.
-    ///  - Verify the difference between the downloaded save and the new file is low.
-    /// If there's more than one, there's something really wrong, so abort for now.
-    /// Otherwise:
-    ///  - Move the originally downloaded file to `civfun Archive/[game_id]_[turn]_[dn]_[original name]`.
-    ///  - Copy the file bytes into the DB and queue for upload.
-    ///  - Move the uploaded file to `civfun Archive/[game_id]_[turn]_[up]_[original name]`
     #[instrument(skip(self))]
-    fn handle_save(&mut self, filename: &str) -> Result<bool> {
-        // let turn = Self::turn_from_filename(filename)?;
-        // let turn = match turn {
-        //     Some(turn) => turn,
-        //     None => return Ok(false),
-        // };
-
-        let full_path = Self::save_dir()?.join(filename);
-        trace!(?full_path);
-        let mut fp = File::open(&full_path).context("Opening save")?;
-        let mut bytes = Vec::with_capacity(1_000_000);
-        fp.read_to_end(&mut bytes)?;
-        drop(fp);
-        let new_parsed_save = Civ5SaveReader::new(&bytes).parse()?;
-
-        let potential_games = self.find_game_for_save(&new_parsed_save)?;
-        if potential_games.len() == 0 {
-            todo!("New save file has no potential matches. Ask user about it?");
-        } else if potential_games.len() == 1 {
-            let game = &potential_games[0];
-            let turn_id = &game.current_turn.turn_id;
-            let game_id = game.game_id;
-            trace!(?game_id, "Found game for save.");
-            self.db
-                .insert(Self::upload_bytes_db_key(&game_id, &turn_id), bytes)
-                .unwrap();
-            self.transfer.insert(game_id, TransferState::UploadQueued);
-        } else {
-            todo!("Multiple potential saves. Ask the user about it?");
+    pub fn process_transfers(&self) -> Result<()> {
+        if self.user_id()?.is_none() {
+            trace!("Not signed in, nothing to transfer.");
+            return Ok(());
         }
+        let mut games = self.my_games()?;
+        sort_by_deadline(&mut games);
+        self.state().transfer_priority = games
+            .iter()
+            .enumerate()
+            .map(|(priority, game)| (game.game_id, priority))
+            .collect();
 
-        Ok(true)
-    }
-
-    #[instrument(skip(self))]
-    pub fn process_transfers(&mut self) -> Result<()> {
-        for game in self.my_games()? {
+        let mut active = self
+            .state()
+            .transfer
+            .values()
+            .filter(|s| s.is_active())
+            .count();
+        for game in games {
             let game_id = &game.game_id;
             let turn_id = &game.current_turn.turn_id;
 
-            let state = self
+            let state = *self
+                .state()
                 .transfer
-                .entry(game.game_id.clone())
+                .entry(game.game_id)
                 .or_insert(TransferState::Idle);
 
-            trace!(?game_id, ?state);
+            trace!(?game_id, ?state, active);
 
             match state {
-                TransferState::Idle => self.process_idle_state(game)?,
+                TransferState::Idle | TransferState::UploadQueued
+                    if active >= MAX_ACTIVE_TRANSFERS =>
+                {
+                    trace!("Waiting for a more urgent transfer to finish.");
+                }
+                TransferState::Idle => {
+                    self.process_idle_state(game.clone())?;
+                    if self.transfer_state(game_id).is_active() {
+                        active += 1;
+                    }
+                }
                 TransferState::Downloading => self.process_downloading_state(&game_id, &turn_id)?,
                 TransferState::Downloaded => {}
-                TransferState::UploadQueued => self.process_upload_queued(game)?,
-                // State::Uploading => self.handle_uploading(game)?,
-                // State::UploadComplete => self.handle_upload_complete(game).await?,
-                _ => todo!("{:?}", state),
+                TransferState::DownloadFailed => {}
+                TransferState::UploadQueued => {
+                    self.process_upload_queued(game.clone())?;
+                    if self.transfer_state(game_id).is_active() {
+                        active += 1;
+                    }
+                }
+                TransferState::Uploading => self.process_uploading_state(game_id, turn_id)?,
+                TransferState::UploadComplete => {}
+                TransferState::UploadFailed => {}
+                TransferState::UploadAwaitingApproval => {}
             }
         }
         Ok(())
     }
 
     #[instrument(skip(self, game))]
-    fn process_idle_state(&mut self, game: Game) -> Result<()> {
+    fn process_idle_state(&self, game: Game) -> Result<()> {
+        if !self.game_settings(&game.game_id)?.auto_download {
+            return Ok(());
+        }
+        if self.would_overwrite_in_game(&game)? {
+            // Stays idle, so this is tried again once the game has stopped.
+            trace!("Civ V is running, not overwriting the save yet.");
+            return Ok(());
+        }
+        self.start_download(game)
+    }
+
+    /// Download the save for a game now, even if auto download is off for it.
+    #[instrument(skip(self))]
+    pub fn download(&self, game_id: &GameId) -> Result<()> {
+        let game = self
+            .my_games()?
+            .into_iter()
+            .find(|g| &g.game_id == game_id)
+            .ok_or_else(|| anyhow!("It isn't your turn in game {}.", game_id))?;
+        if self.would_overwrite_in_game(&game)? {
+            return Err(anyhow!(
+                "Close Civ V first, the save for {} would be overwritten while it's open.",
+                game.name
+            ));
+        }
+        match self.transfer_state(game_id) {
+            TransferState::Idle | TransferState::DownloadFailed => self.start_download(game),
+            state => Err(anyhow!("Can't download while {:?}.", state)),
+        }
+    }
+
+    /// Downloads the save for a game to `path` instead of the save folder. It's only a copy, so
+    /// the turn's transfer state and stored save are left alone.
+    #[instrument(skip(self))]
+    pub fn download_to(&self, game_id: &GameId, path: &Path) -> Result<Receiver<DownloadMessage>> {
+        let game = self
+            .my_games()?
+            .into_iter()
+            .find(|g| &g.game_id == game_id)
+            .ok_or_else(|| anyhow!("It isn't your turn in game {}.", game_id))?;
+        if game.current_turn.is_first_turn {
+            return Err(anyhow!("There's no save to download for the first turn."));
+        }
+        self.api()?
+            .get_latest_save_file_bytes(game_id, &path.to_path_buf())
+    }
+
+    /// Civ V may have the existing save open, and overwriting it can corrupt it on Windows.
+    fn would_overwrite_in_game(&self, game: &Game) -> Result<bool> {
+        Ok(self.game_running() && self.save_path(game)?.exists())
+    }
+
+    /// Throw away the downloaded save for the current turn and download it again, e.g. after the
+    /// file was deleted or damaged.
+    #[instrument(skip(self))]
+    pub fn redownload(&self, game_id: &GameId) -> Result<()> {
+        let game = self
+            .my_games()?
+            .into_iter()
+            .find(|g| &g.game_id == game_id)
+            .ok_or_else(|| anyhow!("It isn't your turn in game {}.", game_id))?;
+        if game.current_turn.is_first_turn {
+            return Err(anyhow!("There's no save to download for the first turn."));
+        }
+        match self.transfer_state(game_id) {
+            TransferState::Idle | TransferState::Downloaded | TransferState::DownloadFailed => {}
+            state => return Err(anyhow!("Can't download again while {:?}.", state)),
+        }
+        if self.would_overwrite_in_game(&game)? {
+            return Err(anyhow!(
+                "Close Civ V first, the save for {} would be overwritten while it's open.",
+                game.name
+            ));
+        }
+        self.forget_download(game_id, &game.current_turn.turn_id)?;
+        self.start_download(game)
+    }
+
+    /// Back to Idle, without the stored save or its analysis.
+    fn forget_download(&self, game_id: &GameId, turn_id: &TurnId) -> Result<()> {
+        let user_db = self.user_db()?;
+        user_db.remove(Self::saved_bytes_db_key(game_id, turn_id))?;
+        user_db.remove(Self::analysed_game_key(game_id, turn_id))?;
+        self.set_transfer_state(game_id, turn_id, TransferState::Idle)
+    }
+
+    fn start_download(&self, game: Game) -> Result<()> {
         if game.current_turn.is_first_turn {
             // No save for first turn.
             trace!("First turn. Marking as downloaded.");
-            self.transfer
-                .insert(game.game_id, TransferState::Downloaded);
+            self.set_transfer_state(
+                &game.game_id,
+                &game.current_turn.turn_id,
+                TransferState::Downloaded,
+            )?;
             return Ok(());
         }
 
-        let path = Self::save_dir()?.join(Self::filename(&game)?);
+        let path = self.save_path(&game)?;
         trace!(?path, "Downloading.");
         let rx = self
             .api()?
             .get_latest_save_file_bytes(&game.game_id, &path)?;
 
-        self.transfer
-            .insert(game.game_id, TransferState::Downloading);
-        self.download_rx.insert(game.game_id, rx);
+        self.set_transfer_state(
+            &game.game_id,
+            &game.current_turn.turn_id,
+            TransferState::Downloading,
+        )?;
+        self.state().download_rx.insert(game.game_id, rx);
         Ok(())
     }
 
     #[instrument(skip(self))]
-    fn process_downloading_state(&mut self, game_id: &GameId, turn_id: &TurnId) -> Result<()> {
-        let rx: &mut Receiver<DownloadMessage> = self.download_rx.get_mut(game_id).unwrap();
+    fn process_downloading_state(&self, game_id: &GameId, turn_id: &TurnId) -> Result<()> {
+        let drained = self.state().download_rx.get_mut(game_id).map(drain);
+        let (messages, finished) = match drained {
+            Some(drained) => drained,
+            None => {
+                // We were downloading before a restart. Start again.
+                trace!("No download receiver, starting again.");
+                return self.set_transfer_state(game_id, turn_id, TransferState::Idle);
+            }
+        };
 
-        let mut completed_download = None;
-        loop {
-            let msg = match rx.try_recv() {
-                Ok(msg) => msg,
-                Err(TryRecvError::Empty) => break,
-                Err(err) => panic!("{:?}", err),
-            };
+        let mut result = None;
+        for msg in messages {
             match msg {
-                DownloadMessage::Error(e) => {
-                    error!(?e, "Download");
-                }
                 DownloadMessage::Started(size) => {
                     trace!(?size, "Started");
+                    self.emit(Event::DownloadStarted(*game_id));
                 }
-                DownloadMessage::Chunk(percentage) => {
-                    trace!(?percentage, "Download progress");
-                }
-                DownloadMessage::Done(path) => {
-                    trace!("Done!");
-                    // Use update_state variable because we need to modify
-                    // `self.download_state` which is currently borrowed.
-                    completed_download = Some(path);
-                    break;
+                DownloadMessage::Chunk { progress, bytes } => self.emit(Event::DownloadProgress {
+                    game_id: *game_id,
+                    progress,
+                    bytes,
+                }),
+                DownloadMessage::Done(path) => result = Some(Ok(path)),
+                DownloadMessage::Error(message) => result = Some(Err(message)),
+                DownloadMessage::Paused => {
+                    debug!("Paused part way, downloading again after resuming.");
+                    self.state().download_rx.remove(game_id);
+                    return self.set_transfer_state(game_id, turn_id, TransferState::Idle);
                 }
             }
         }
-        if let Some(path) = completed_download {
-            // Save the file into the DB because:
-            // 1) The user might delete the file in the future
-            // 2) Be able to analyse the file and compare when the user uploads their turn.
-            self.store_downloaded_save(&game_id, &turn_id, &path)
-                .unwrap();
-            self.transfer
-                .insert(game_id.clone(), TransferState::Downloaded);
+        if finished && result.is_none() {
+            result = Some(Err("Download stopped unexpectedly.".into()));
         }
+
+        match result {
+            None => {}
+            Some(Ok(path)) => {
+                trace!("Done!");
+                self.state().download_rx.remove(game_id);
+                let size = std::fs::metadata(&path)
+                    .map(|m| m.len())
+                    .unwrap_or_default();
+                self.record_usage(game_id, |t| t.add_download(size))?;
+                // Save the file into the DB because:
+                // 1) The user might delete the file in the future
+                // 2) Be able to analyse the file and compare when the user uploads their turn.
+                match self.store_downloaded_save(game_id, turn_id, &path) {
+                    Ok(()) => self.emit(Event::DownloadComplete {
+                        game_id: *game_id,
+                        path,
+                    }),
+                    Err(err) => self.download_failed(game_id, turn_id, format!("{:#}", err))?,
+                }
+            }
+            Some(Err(message)) => self.download_failed(game_id, turn_id, message)?,
+        }
+        Ok(())
+    }
+
+    fn download_failed(&self, game_id: &GameId, turn_id: &TurnId, message: String) -> Result<()> {
+        error!(?game_id, ?message, "Download failed.");
+        self.state().download_rx.remove(game_id);
+        self.state().play_after_download.remove(game_id);
+        self.set_transfer_state(game_id, turn_id, TransferState::DownloadFailed)?;
+        self.emit(Event::DownloadFailed {
+            game_id: *game_id,
+            message,
+        });
         Ok(())
     }
 
     #[instrument(skip(self, game))]
-    fn process_upload_queued(&mut self, game: Game) -> Result<()> {
+    fn process_upload_queued(&self, game: Game) -> Result<()> {
         let game_id = game.game_id;
         let turn_id = game.current_turn.turn_id;
-        info!(?game_id);
-
-        self.transfer.insert(game_id, TransferState::Uploading);
+        if self.state().interrupted_uploads.contains_key(&game_id) {
+            trace!("Waiting for games to be fetched before uploading again.");
+            return Ok(());
+        }
 
-        // TODO: Second unwrap is for an empty entry.
-        // We're assuming the key exists if we've gone into this state.
-        let bytes = self
-            .db
-            .get(Self::upload_bytes_db_key(&game_id, &turn_id))
-            .unwrap()
-            .unwrap();
+        let bytes = match self
+            .user_db()?
+            .get(Self::upload_bytes_db_key(&game_id, &turn_id))?
+        {
+            Some(bytes) => bytes,
+            None => {
+                // Nothing to send. This happens when the stored state is out of sync with the db.
+                warn!(?game_id, ?turn_id, "Upload queued without any bytes.");
+                self.set_transfer_state(&game_id, &turn_id, TransferState::UploadFailed)?;
+                self.emit(Event::UploadFailed {
+                    game_id,
+                    message: "The save to upload has gone missing.".into(),
+                });
+                return Ok(());
+            }
+        };
 
         info!(?game_id, ?turn_id, "Uploading.");
-        let rx = self
-            .api()?
-            .upload_save_client(turn_id, bytes.to_vec())
-            .unwrap();
+        let rx = self.api()?.upload_save_client(turn_id, bytes.to_vec())?;
+        self.state().upload_rx.insert(game_id, rx);
+        self.set_transfer_state(&game_id, &turn_id, TransferState::Uploading)?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    fn process_uploading_state(&self, game_id: &GameId, turn_id: &TurnId) -> Result<()> {
+        let drained = self.state().upload_rx.get_mut(game_id).map(drain);
+        let (messages, finished) = match drained {
+            Some(drained) => drained,
+            None => {
+                // We were uploading before a restart. Start again.
+                trace!("No upload receiver, queueing again.");
+                return self.set_transfer_state(game_id, turn_id, TransferState::UploadQueued);
+            }
+        };
+
+        let mut result = None;
+        for msg in messages {
+            match msg {
+                UploadMessage::Started => self.emit(Event::UploadStarted(*game_id)),
+                UploadMessage::Chunk { progress, bytes } => self.emit(Event::UploadProgress {
+                    game_id: *game_id,
+                    progress,
+                    bytes,
+                }),
+                UploadMessage::Done => result = Some(Ok(())),
+                UploadMessage::Error(message) => result = Some(Err(message)),
+                UploadMessage::Paused => {
+                    debug!("Paused part way, uploading again after resuming.");
+                    self.state().upload_rx.remove(game_id);
+                    return self.set_transfer_state(game_id, turn_id, TransferState::UploadQueued);
+                }
+            }
+        }
+        if finished && result.is_none() {
+            result = Some(Err("Upload stopped unexpectedly.".into()));
+        }
+
+        match result {
+            None => {}
+            Some(Ok(())) => self.handle_upload_complete(game_id, turn_id)?,
+            Some(Err(message)) => {
+                error!(?message, "Upload failed.");
+                self.state().upload_rx.remove(game_id);
+                self.set_transfer_state(game_id, turn_id, TransferState::UploadFailed)?;
+                self.emit(Event::UploadFailed {
+                    game_id: *game_id,
+                    message,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    fn handle_upload_complete(&self, game_id: &GameId, turn_id: &TurnId) -> Result<()> {
+        info!("Upload complete.");
+        self.state().upload_rx.remove(game_id);
+        self.set_transfer_state(game_id, turn_id, TransferState::UploadComplete)?;
+        self.emit(Event::UploadComplete(*game_id));
+
+        if let Some(bytes) = self
+            .user_db()?
+            .get(Self::upload_bytes_db_key(game_id, turn_id))?
+        {
+            self.record_usage(game_id, |t| t.add_upload(bytes.len() as u64))?;
+            if let Err(err) = self.mirror_save(game_id, turn_id, ArchiveKind::Uploaded, &bytes) {
+                warn!(?err, "Could not copy save to the sync folder.");
+            }
+        }
+
+        if let Err(err) = self.archive_uploaded_save(game_id, turn_id) {
+            // The turn has been submitted, so a failed move isn't worth stopping for.
+            warn!(?err, "Could not archive uploaded save.");
+        }
+
+        // The current turn has moved on, so get the new state of the games.
+        self.fetch_games().context("Refreshing games after upload.")
+    }
+
+    /// Moves the uploaded save out of the hotseat directory so it isn't mistaken for a new turn
+    /// and the directory doesn't fill up.
+    fn archive_uploaded_save(&self, game_id: &GameId, turn_id: &TurnId) -> Result<()> {
+        if self.game_running() {
+            return self.defer_archive(DeferredArchive::Uploaded(*game_id, *turn_id));
+        }
+        let key = Self::upload_path_db_key(game_id, turn_id);
+        let path = match self.user_db()?.get(&key)? {
+            Some(path) => PathBuf::from(String::from_utf8(path.to_vec())?),
+            None => return Ok(()),
+        };
+        // Files uploaded from elsewhere with `upload_file()` are the user's to keep.
+        if path.starts_with(self.save_dir()?) {
+            self.archive()?
+                .add(&path, game_id, turn_id, ArchiveKind::Uploaded)?;
+        }
+        self.user_db()?.remove(key)?;
+        Ok(())
+    }
+
+    /// Moves the save we downloaded for this turn into the archive, now that it's been played.
+    fn archive_downloaded_save(&self, game: &Game) -> Result<()> {
+        if self.game_running() {
+            return self.defer_archive(DeferredArchive::Downloaded(game.clone()));
+        }
+        let path = self.save_path(game)?;
+        self.archive()?.add(
+            &path,
+            &game.game_id,
+            &game.current_turn.turn_id,
+            ArchiveKind::Downloaded,
+        )?;
+        Ok(())
+    }
+
+    /// Kept in sled so saves are still archived if we're closed before Civ V is.
+    fn defer_archive(&self, archive: DeferredArchive) -> Result<()> {
+        debug!(?archive, "Civ V is running, archiving later.");
+        let user_db = self.user_db()?;
+        let mut deferred: Vec<DeferredArchive> = match user_db.get(DEFERRED_ARCHIVES_KEY)? {
+            Some(b) => serde_json::from_slice(&b).context("Decoding deferred archives.")?,
+            None => vec![],
+        };
+        deferred.push(archive);
+        user_db.insert(DEFERRED_ARCHIVES_KEY, serde_json::to_vec(&deferred)?)?;
+        Ok(())
+    }
+
+    /// Copies a save into `sync_dir`, when there is one.
+    fn mirror_save(
+        &self,
+        game_id: &GameId,
+        turn_id: &TurnId,
+        kind: ArchiveKind,
+        bytes: &[u8],
+    ) -> Result<()> {
+        let sync_dir = match self.config()?.sync_dir {
+            Some(sync_dir) => sync_dir,
+            None => return Ok(()),
+        };
+        let game_name = self
+            .games()?
+            .into_iter()
+            .find(|g| &g.game_id == game_id)
+            .map(|g| g.name);
+        Mirror::new(&sync_dir).add(game_id, game_name.as_deref(), turn_id, kind, bytes)?;
+        Ok(())
+    }
+
+    /// Copies every stored save into `sync_dir`, e.g. after it was first set. Returns how many
+    /// weren't there yet.
+    #[instrument(skip(self))]
+    pub fn sync_mirror(&self) -> Result<usize> {
+        let sync_dir = self
+            .config()?
+            .sync_dir
+            .ok_or_else(|| anyhow!("No sync folder has been set."))?;
+        let mirror = Mirror::new(&sync_dir);
+        let names: HashMap<GameId, String> = self
+            .games()?
+            .into_iter()
+            .map(|g| (g.game_id, g.name))
+            .collect();
+        let mut added = 0;
+        let user_db = self.user_db()?;
+        for (prefix, kind) in &[
+            ("saved-bytes-", ArchiveKind::Downloaded),
+            ("upload-bytes-", ArchiveKind::Uploaded),
+        ] {
+            for item in user_db.scan_prefix(prefix) {
+                let (key, bytes) = item?;
+                let (game_id, turn_id) = match Self::turn_from_key(&key, prefix) {
+                    Some(turn) => turn,
+                    None => continue,
+                };
+                let name = names.get(&game_id).map(|n| n.as_str());
+                if mirror
+                    .add(&game_id, name, &turn_id, *kind, &bytes)?
+                    .is_some()
+                {
+                    added += 1;
+                }
+            }
+        }
+        Ok(added)
+    }
 
-        self.upload_rx.insert(game_id, rx);
+    fn archive(&self) -> Result<Archive> {
+        // A portable install keeps everything it makes next to itself.
+        let dir = if is_portable() {
+            self.data_path(Path::new(""))?
+        } else {
+            self.save_dir()?
+        };
+        Ok(Archive::new(self.user_db()?, &dir))
+    }
 
+    /// Saves archived in the save dir before civfun was made portable move into the data
+    /// dir, for every account.
+    fn gather_archives(&self) -> Result<()> {
+        if !is_portable() {
+            return Ok(());
+        }
+        let dir = self.data_path(Path::new(""))?;
+        for account in self.accounts()? {
+            let db = self.db.open_tree(Self::user_tree_name(&account.user_id))?;
+            let moved = Archive::new(db, &dir).gather()?;
+            if moved > 0 {
+                info!(moved, user_id = ?account.user_id, "Moved archived saves into the data dir.");
+            }
+        }
         Ok(())
     }
 
+    /// Every save that has been moved into the archive for a game, oldest first.
+    pub fn archive_entries(&self, game_id: &GameId) -> Result<Vec<ArchiveEntry>> {
+        self.archive()?.entries(game_id)
+    }
+
+    /// Every archived save for every game, newest first.
+    pub fn archived_saves(&self) -> Result<Vec<ArchiveEntry>> {
+        let mut entries = self.archive()?.all_entries()?;
+        entries.sort_by_key(|e| std::cmp::Reverse(e.archived_at));
+        Ok(entries)
+    }
+
+    /// Where archived saves are kept, in the save dir, or in the data dir when portable.
+    pub fn archive_dir(&self) -> Result<PathBuf> {
+        Ok(self.archive()?.dir().to_path_buf())
+    }
+
+    /// Copies an archived save back into the save dir to replay a turn. It's marked as seen
+    /// first so the watcher doesn't take it for a newly played turn and upload it.
+    #[instrument(skip(self))]
+    pub fn restore_archived(&self, entry: &ArchiveEntry) -> Result<PathBuf> {
+        let bytes = std::fs::read(&entry.path)
+            .with_context(|| format!("Reading archived save {:?}", entry.path))?;
+        self.user_db()?
+            .insert(Self::seen_save_key(&bytes), vec![])?;
+        self.archive()?.restore(entry, &self.save_dir()?)
+    }
+
+    /// Every game the new save could belong to, best match first.
     #[instrument(skip(self, new_parsed_save))]
-    fn find_game_for_save(&self, new_parsed_save: &Civ5Save) -> Result<Vec<Game>> {
+    fn save_candidates(&self, new_parsed_save: &Civ5Save) -> Result<Vec<SaveCandidate>> {
         let new_turn = new_parsed_save.header.turn;
 
         // We're at the first turn. Only look for games that GMR say is the first turn.
-        let mut suspects = vec![];
         if new_turn == 0 {
-            for game in self.my_games()? {
-                if game.current_turn.is_first_turn {
-                    suspects.push(game);
-                }
-            }
-            return Ok(suspects);
+            return Ok(self
+                .my_games()?
+                .into_iter()
+                .filter(|game| game.current_turn.is_first_turn)
+                .map(|game| SaveCandidate {
+                    game,
+                    difference: None,
+                })
+                .collect());
         }
 
-        let mut smallest_diff: Option<(u32, Game)> = None;
+        let mut candidates = vec![];
         for game in self.my_games()? {
             let game_id = &game.game_id;
             trace!(?game_id);
@@ -716,30 +3887,43 @@ impl Manager {
 
             let diff = new_parsed_save.difference_score(&last_parsed_save)?;
             trace!(diff);
-            smallest_diff = match smallest_diff {
-                Some((sd, game)) => {
-                    if diff < sd {
-                        Some((diff, game.clone()))
-                    } else {
-                        Some((sd, game))
-                    }
-                }
-                None => Some((diff, game.clone())),
-            };
+            candidates.push(SaveCandidate {
+                game,
+                difference: Some(diff),
+            });
         }
 
-        match smallest_diff {
-            Some((_, game)) => {
-                info!(game_id = ?game.game_id, "Smallest diff found.");
-                Ok(vec![game])
-            }
-            None => {
-                warn!("No games found to compare.");
-                Ok(vec![])
-            }
+        if candidates.is_empty() {
+            warn!("No games found to compare.");
+        }
+        candidates.sort_by_key(|c| c.difference);
+        Ok(candidates)
+    }
+
+    /// Picks a game only when there's no real doubt about it: either it's the only candidate, or
+    /// its difference score is less than half of the runner up. Saves from unrelated games are
+    /// hundreds of bytes apart, while consecutive turns of the same game are usually a handful.
+    fn clear_winner(candidates: &[SaveCandidate]) -> Option<&Game> {
+        match candidates {
+            [only] => Some(&only.game),
+            [best, runner_up, ..] => match (best.difference, runner_up.difference) {
+                (Some(best_diff), Some(runner_up_diff)) if best_diff * 2 < runner_up_diff => {
+                    Some(&best.game)
+                }
+                _ => None,
+            },
+            [] => None,
         }
     }
 
+    /// The leader whose turn is next, e.g. `Casimir III`. None when the filename isn't in the
+    /// usual format.
+    fn leader_from_filename(filename: &str) -> Option<String> {
+        let re = Regex::new(r"^(?P<leader>.+?)_\d{4} .*\.Civ5Save$").unwrap();
+        let captures = re.captures(filename)?;
+        Some(captures.name("leader")?.as_str().to_string())
+    }
+
     /// Returns Ok(None) when the filename is invalid.
     fn turn_from_filename(filename: &str) -> Result<Option<u64>> {
         // TODO: once_cell
@@ -754,21 +3938,22 @@ impl Manager {
         Ok(Some(turn))
     }
 
-    /// This is private. Use `authenticate()` to set a key instead. It has extra logic for deleting
-    /// existing state if the user has changed.
-    fn save_auth_key(&self, key: &str) -> Result<()> {
-        self.db.insert(AUTH_KEY, key)?;
-        Ok(())
-    }
-
+    /// The auth key of the current account.
     pub fn auth_key(&self) -> Result<Option<String>> {
-        self.db
-            .get(AUTH_KEY)?
-            .map(|iv| String::from_utf8(iv.to_vec()).with_context(|| format!("Parsing {:?}", iv)))
-            .transpose()
+        let user_id = match self.user_id()? {
+            Some(user_id) => user_id,
+            None => return Ok(None),
+        };
+        match self.accounts()?.into_iter().find(|a| a.user_id == user_id) {
+            Some(account) if account.in_keychain => {
+                self.keys.load(&keychain::auth_key_account(&user_id))
+            }
+            Some(account) => Ok(Some(account.auth_key)),
+            None => Ok(None),
+        }
     }
 
-    pub fn save_user_id(&self, user_id: &UserId) -> Result<()> {
+    fn save_user_id(&self, user_id: &UserId) -> Result<()> {
         self.db
             .insert(USER_ID_KEY, format!("{}", user_id).as_str())?;
         Ok(())
@@ -788,38 +3973,95 @@ impl Manager {
         Ok(n.into())
     }
 
+    /// Restore transfer states for the current turn of each game.
+    ///
+    /// Persisted states are preferred. A transfer that was in flight when the app stopped can't
+    /// be continued, so a download starts again from scratch and an upload is queued again, to be
+    /// sent once `confirm_interrupted_uploads()` has checked it didn't finish. A failed upload
+    /// stays failed until `retry_upload()`, so a rejected save isn't sent on every start.
+    /// Without a persisted state, fall back to working it out from the stored save bytes.
     #[instrument(skip(self))]
-    pub fn fill_transfer_states(&mut self) -> Result<()> {
+    pub fn fill_transfer_states(&self) -> Result<()> {
         for game in self.games()? {
             let game_id = game.game_id;
             let turn_id = game.current_turn.turn_id;
 
-            if self
-                .db
+            if let Some(state) = self.stored_transfer_state(&game_id, &turn_id)? {
+                let state = match state {
+                    TransferState::Downloading => TransferState::Idle,
+                    TransferState::DownloadFailed => TransferState::Idle,
+                    TransferState::Uploading => {
+                        self.state().interrupted_uploads.insert(game_id, turn_id);
+                        TransferState::UploadQueued
+                    }
+                    state => state,
+                };
+                trace!(?game_id, ?state, "Resuming transfer state.");
+                self.state().transfer.insert(game_id, state);
+            } else if self
+                .user_db()?
                 .contains_key(Self::upload_bytes_db_key(&game_id, &turn_id))?
             {
                 trace!(?game_id, "Marking game as ready to upload.");
-                self.transfer.insert(game_id, TransferState::UploadQueued);
+                self.state()
+                    .transfer
+                    .insert(game_id, TransferState::UploadQueued);
             } else if self
-                .db
+                .user_db()?
                 .contains_key(Self::saved_bytes_db_key(&game_id, &turn_id))?
             {
                 trace!(?game_id, "Marking game as already downloaded.");
-                self.transfer.insert(game_id, TransferState::Downloaded);
+                self.state()
+                    .transfer
+                    .insert(game_id, TransferState::Downloaded);
             }
         }
 
         Ok(())
     }
 
+    fn stored_transfer_state(
+        &self,
+        game_id: &GameId,
+        turn_id: &TurnId,
+    ) -> Result<Option<TransferState>> {
+        let stored: Option<StoredTransfer> =
+            match self.user_db()?.get(Self::transfer_key(game_id))? {
+                Some(b) => Some(serde_json::from_slice(&b).context("Decoding transfer state.")?),
+                None => None,
+            };
+        Ok(stored
+            .filter(|stored| &stored.turn_id == turn_id)
+            .map(|stored| stored.state))
+    }
+
+    /// Sets the in-memory state and persists it so it survives a restart.
+    fn set_transfer_state(
+        &self,
+        game_id: &GameId,
+        turn_id: &TurnId,
+        state: TransferState,
+    ) -> Result<()> {
+        let stored = StoredTransfer {
+            turn_id: *turn_id,
+            state,
+        };
+        let encoded = serde_json::to_vec(&stored)?;
+        self.user_db()?
+            .insert(Self::transfer_key(game_id), encoded)
+            .context("Saving transfer state.")?;
+        self.state().transfer.insert(*game_id, state);
+        Ok(())
+    }
+
     pub fn save_games(&self, games: &[Game]) -> Result<()> {
         let encoded = serde_json::to_vec(games)?;
-        self.db.insert(GAMES_KEY, encoded.as_slice())?;
+        self.user_db()?.insert(GAMES_KEY, encoded.as_slice())?;
         Ok(())
     }
 
     pub fn clear_games(&self) -> Result<()> {
-        self.db.remove(GAMES_KEY)?;
+        self.user_db()?.remove(GAMES_KEY)?;
         Ok(())
     }
 
@@ -828,12 +4070,23 @@ impl Manager {
         let json = serde_json::to_vec(&stored_player).context("Encoding player info.")?;
         trace!(?key, ?json, "Saving player info.");
         self.db.insert(key, json).context("Saving player info.")?;
+        self.state()
+            .players
+            .insert(stored_player.player.steam_id, stored_player.clone());
         Ok(())
     }
 
     fn api(&self) -> Result<Api> {
         match &self.auth_key()? {
-            Some(auth_key) => Ok(Api::new(auth_key)),
+            Some(auth_key) => {
+                let config = self.config()?;
+                let manager = self.clone();
+                Ok(Api::new(auth_key)
+                    .with_base_url(&self.api_url)
+                    .with_temp_files(self.temp_files()?)
+                    .with_limits(config.download_limit_kib, config.upload_limit_kib)
+                    .with_pause(move || manager.paused().unwrap_or(false)))
+            }
             None => Err(anyhow!("Attempt to access API without auth key.")),
         }
     }
@@ -843,6 +4096,36 @@ pub fn project_dirs() -> anyhow::Result<ProjectDirs> {
     Ok(ProjectDirs::from("", "civ.fun", "gmr").context("Could not determine ProjectDirs.")?)
 }
 
+/// Set once at startup, before anything asks for `data_dir_path()`.
+static DATA_DIR: OnceLock<DataDir> = OnceLock::new();
+
+/// Somewhere other than the usual place for the OS to keep the db, logs and temp files.
+#[derive(Debug, Clone)]
+pub enum DataDir {
+    /// From `--data-dir` or `CIVFUN_DATA_DIR`.
+    Custom(PathBuf),
+    /// In a folder next to the executable, with archived saves too, e.g. to run civfun from a USB
+    /// stick.
+    Portable(PathBuf),
+}
+
+pub fn set_data_dir(dir: DataDir) -> anyhow::Result<()> {
+    debug!(?dir, "Data dir overridden.");
+    DATA_DIR.set(dir).map_err(|dir| {
+        anyhow!(
+            "The data dir was already set, not changing it to {:?}.",
+            dir
+        )
+    })
+}
+
+pub fn is_portable() -> bool {
+    matches!(DATA_DIR.get(), Some(DataDir::Portable(_)))
+}
+
 pub fn data_dir_path(join: &Path) -> anyhow::Result<PathBuf> {
-    Ok(project_dirs()?.data_dir().join(join))
+    match DATA_DIR.get() {
+        Some(DataDir::Custom(dir)) | Some(DataDir::Portable(dir)) => Ok(dir.join(join)),
+        None => Ok(project_dirs()?.data_dir().join(join)),
+    }
 }
@@ -0,0 +1,301 @@
+use crate::api::parse_gmr_date;
+use crate::manager::GAMES_KEY;
+use anyhow::{anyhow, Context};
+use tracing::{info, instrument, trace};
+
+type Result<T> = anyhow::Result<T>;
+
+pub const SCHEMA_VERSION_KEY: &str = "schema-version";
+
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    run: fn(&sled::Db) -> Result<()>,
+}
+
+/// Every change to what's stored in sled needs a migration here, in order, with the next version
+/// number. Each one runs exactly once per database.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Initial layout",
+        run: |_| Ok(()),
+    },
+    Migration {
+        version: 2,
+        description: "Store game dates as RFC 3339",
+        run: rewrite_games,
+    },
+    Migration {
+        version: 3,
+        description: "Keep each account's data in its own tree",
+        run: split_accounts,
+    },
+    Migration {
+        version: 4,
+        description: "Pause each account on its own",
+        run: per_account_pause,
+    },
+];
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Databases from before versioning existed have no version key, which is treated as 0.
+pub fn schema_version(db: &sled::Db) -> Result<u32> {
+    match db.get(SCHEMA_VERSION_KEY)? {
+        Some(iv) => {
+            let s = String::from_utf8(iv.to_vec()).context("Decoding schema version.")?;
+            Ok(s.parse()
+                .with_context(|| format!("Parsing schema version {:?}", s))?)
+        }
+        None => Ok(0),
+    }
+}
+
+/// Brings the database up to `latest_version()`. Returns the versions that were applied.
+#[instrument(skip(db))]
+pub fn migrate(db: &sled::Db) -> Result<Vec<u32>> {
+    let current = schema_version(db)?;
+    let latest = latest_version();
+    trace!(current, latest);
+    if current > latest {
+        return Err(anyhow!(
+            "The database is version {} but this version of civfun only understands up to {}. \
+            Please upgrade civfun.",
+            current,
+            latest
+        ));
+    }
+
+    let mut applied = vec![];
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        info!(
+            version = migration.version,
+            description = migration.description,
+            "Migrating database."
+        );
+        (migration.run)(db).with_context(|| {
+            format!("Migration {}: {}", migration.version, migration.description)
+        })?;
+        db.insert(SCHEMA_VERSION_KEY, migration.version.to_string().as_str())?;
+        applied.push(migration.version);
+    }
+    db.flush()?;
+    Ok(applied)
+}
+
+/// Games used to be stored with GMR's raw date strings, e.g. `/Date(1633842243123)/`. These are
+/// rewritten as RFC 3339, which is how they're written now. Only the dates are touched, working
+/// on the JSON rather than `Game` so this keeps doing the same thing if `Game` changes later.
+fn rewrite_games(db: &sled::Db) -> Result<()> {
+    let b = match db.get(GAMES_KEY)? {
+        Some(b) => b,
+        None => return Ok(()),
+    };
+    let mut games: serde_json::Value = serde_json::from_slice(&b).context("Decoding games.")?;
+    let turns = games
+        .as_array_mut()
+        .into_iter()
+        .flatten()
+        .filter_map(|game| game.get_mut("CurrentTurn"));
+    for turn in turns {
+        for field in &["Started", "Expires"] {
+            let value = match turn.get_mut(*field) {
+                Some(value) => value,
+                None => continue,
+            };
+            // Expires can be null.
+            if let Some(raw) = value.as_str() {
+                *value = parse_gmr_date(raw)?.to_rfc3339().into();
+            }
+        }
+    }
+    db.insert(GAMES_KEY, serde_json::to_vec(&games)?)?;
+    Ok(())
+}
+
+/// Keys that belonged to the single account before multiple accounts were supported.
+const ACCOUNT_KEY_PREFIXES: &[&str] = &[
+    "saved-bytes-",
+    "analysed-",
+    "upload-bytes-",
+    "upload-path-",
+    "transfer-",
+    "pending-save-",
+    "archive-",
+];
+
+/// Moves the auth key into the account list and everything for that account into `user-{id}`.
+///
+/// Key names are spelled out rather than shared with the manager so this keeps doing the same
+/// thing if they change later.
+fn split_accounts(db: &sled::Db) -> Result<()> {
+    let user_id = match db.get("user-id")? {
+        Some(iv) => String::from_utf8(iv.to_vec())
+            .context("Decoding user id.")?
+            .parse::<u64>()
+            .context("Parsing user id.")?,
+        None => {
+            // A key without a user id never authenticated, and accounts need a user id. Leaving
+            // it would keep an unused secret around, so the user is asked for the key again.
+            if db.remove("auth-key")?.is_some() {
+                info!("Removed an auth key that never authenticated.");
+            } else {
+                trace!("Never authenticated. Nothing to move.");
+            }
+            return Ok(());
+        }
+    };
+
+    if let Some(auth_key) = db.get("auth-key")? {
+        let auth_key = String::from_utf8(auth_key.to_vec()).context("Decoding auth key.")?;
+        let accounts = serde_json::json!([{ "user_id": user_id, "auth_key": auth_key }]);
+        db.insert("accounts", serde_json::to_vec(&accounts)?)?;
+        db.remove("auth-key")?;
+    }
+
+    let tree = db.open_tree(format!("user-{}", user_id))?;
+    if let Some(games) = db.remove("games")? {
+        tree.insert("games", games)?;
+    }
+    for prefix in ACCOUNT_KEY_PREFIXES {
+        for item in db.scan_prefix(prefix) {
+            let (key, value) = item?;
+            tree.insert(&key, value)?;
+            db.remove(&key)?;
+        }
+    }
+    Ok(())
+}
+
+/// Pausing used to cover every account. It's kept for the account that was current and dropped
+/// when there wasn't one.
+fn per_account_pause(db: &sled::Db) -> Result<()> {
+    let paused = match db.remove("paused")? {
+        Some(paused) => paused,
+        None => return Ok(()),
+    };
+    if let Some(user_id) = db.get("user-id")? {
+        let user_id = String::from_utf8(user_id.to_vec()).context("Decoding user id.")?;
+        db.open_tree(format!("user-{}", user_id))?
+            .insert("paused", paused)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Game;
+    use chrono::{TimeZone, Utc};
+
+    fn temporary_db() -> sled::Db {
+        sled::Config::new().temporary(true).open().unwrap()
+    }
+
+    #[test]
+    fn versions_are_in_order() {
+        for (idx, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, idx as u32 + 1);
+        }
+    }
+
+    #[test]
+    fn empty_db() {
+        let db = temporary_db();
+        assert_eq!(schema_version(&db).unwrap(), 0);
+        assert_eq!(migrate(&db).unwrap(), vec![1, 2, 3, 4]);
+        assert_eq!(schema_version(&db).unwrap(), latest_version());
+        assert!(migrate(&db).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unversioned_db_with_raw_dates() {
+        let db = temporary_db();
+        let games = r#"[{"Name":"Marathon Madness","GameId":1,"Players":[],"Type":0,
+            "CurrentTurn":{"TurnId":2,"Number":43,"UserId":3,"Started":"2021-10-10T05:04:03.123",
+            "Expires":"/Date(1634015043123)/","Skipped":false,"PlayerNumber":0,
+            "IsFirstTurn":false}}]"#;
+        db.insert(GAMES_KEY, games).unwrap();
+
+        migrate(&db).unwrap();
+
+        let stored = db.get(GAMES_KEY).unwrap().unwrap();
+        let stored = std::str::from_utf8(&stored).unwrap();
+        assert!(stored.contains("2021-10-10T05:04:03.123+00:00"));
+        assert!(stored.contains("2021-10-12T05:04:03.123+00:00"));
+        assert!(!stored.contains("/Date("));
+        let games: Vec<Game> = serde_json::from_str(stored).unwrap();
+        assert_eq!(
+            games[0].current_turn.expires,
+            Some(Utc.ymd(2021, 10, 12).and_hms_milli(5, 4, 3, 123))
+        );
+    }
+
+    #[test]
+    fn newer_db() {
+        let db = temporary_db();
+        db.insert(
+            SCHEMA_VERSION_KEY,
+            (latest_version() + 1).to_string().as_str(),
+        )
+        .unwrap();
+        assert!(migrate(&db).is_err());
+    }
+
+    #[test]
+    fn single_account_moves_to_its_tree() {
+        let db = temporary_db();
+        db.insert(SCHEMA_VERSION_KEY, "2").unwrap();
+        db.insert("user-id", "123").unwrap();
+        db.insert("auth-key", "secret").unwrap();
+        db.insert("games", "[]").unwrap();
+        db.insert("saved-bytes-1-2", "bytes").unwrap();
+        db.insert("player-info-123", "{}").unwrap();
+
+        assert_eq!(migrate(&db).unwrap(), vec![3, 4]);
+
+        let tree = db.open_tree("user-123").unwrap();
+        assert_eq!(tree.get("games").unwrap().unwrap(), "[]");
+        assert_eq!(tree.get("saved-bytes-1-2").unwrap().unwrap(), "bytes");
+        assert!(db.get("games").unwrap().is_none());
+        assert!(db.get("saved-bytes-1-2").unwrap().is_none());
+        assert!(db.get("auth-key").unwrap().is_none());
+        assert!(db.get("player-info-123").unwrap().is_some());
+
+        let accounts: serde_json::Value =
+            serde_json::from_slice(&db.get("accounts").unwrap().unwrap()).unwrap();
+        assert_eq!(
+            accounts,
+            serde_json::json!([{ "user_id": 123, "auth_key": "secret" }])
+        );
+    }
+
+    #[test]
+    fn auth_key_without_user_id_is_removed() {
+        let db = temporary_db();
+        db.insert(SCHEMA_VERSION_KEY, "2").unwrap();
+        db.insert("auth-key", "secret").unwrap();
+
+        assert_eq!(migrate(&db).unwrap(), vec![3, 4]);
+
+        assert!(db.get("auth-key").unwrap().is_none());
+        assert!(db.get("accounts").unwrap().is_none());
+    }
+
+    #[test]
+    fn pause_moves_to_current_account() {
+        let db = temporary_db();
+        db.insert(SCHEMA_VERSION_KEY, "3").unwrap();
+        db.insert("user-id", "123").unwrap();
+        db.insert("paused", vec![]).unwrap();
+
+        assert_eq!(migrate(&db).unwrap(), vec![4]);
+
+        assert!(db.get("paused").unwrap().is_none());
+        let tree = db.open_tree("user-123").unwrap();
+        assert!(tree.get("paused").unwrap().is_some());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::api::{GameId, TurnId};
+use crate::archive::{unique_path, ArchiveKind};
+use crate::config::clean_filename;
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+use tracing::{debug, instrument, trace};
+
+type Result<T> = anyhow::Result<T>;
+
+/// Copies of saves in a folder synced by Dropbox, OneDrive or similar, so every machine the
+/// player uses has the same turn history.
+///
+/// Saves go in a folder per game, named `[turn_id]_[dn|up].Civ5Save`. A file is never
+/// overwritten with different content, so two machines writing the same turn end up with two
+/// files instead of a sync conflict.
+pub struct Mirror {
+    dir: PathBuf,
+}
+
+impl Mirror {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Returns None if an identical copy is already there.
+    #[instrument(skip(self, bytes))]
+    pub fn add(
+        &self,
+        game_id: &GameId,
+        game_name: Option<&str>,
+        turn_id: &TurnId,
+        kind: ArchiveKind,
+        bytes: &[u8],
+    ) -> Result<Option<PathBuf>> {
+        let game_dir = self.dir.join(game_dir_name(game_id, game_name));
+        std::fs::create_dir_all(&game_dir).context("Creating sync folder.")?;
+        let mut path = game_dir.join(format!("{}_{}.Civ5Save", turn_id, kind.tag()));
+        if path.exists() {
+            if std::fs::read(&path)? == bytes {
+                trace!(?path, "Already synced.");
+                return Ok(None);
+            }
+            path = unique_path(&path);
+        }
+
+        // Sync clients can pick up a file while it's being written, so only move it into place
+        // once it's complete.
+        let tmp = game_dir.join(format!(".{}.tmp", turn_id));
+        std::fs::write(&tmp, bytes).with_context(|| format!("Writing {:?}", tmp))?;
+        std::fs::rename(&tmp, &path).with_context(|| format!("Moving {:?} to {:?}", tmp, path))?;
+        debug!(?path, "Synced.");
+        Ok(Some(path))
+    }
+}
+
+fn game_dir_name(game_id: &GameId, game_name: Option<&str>) -> String {
+    match game_name {
+        Some(name) => format!("{} ({})", clean_filename(name), game_id),
+        None => game_id.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mirror = Mirror::new(dir.path());
+        let game_id = GameId::from(7);
+        let turn_id = TurnId::from(100);
+        let add = |bytes: &[u8]| {
+            mirror
+                .add(
+                    &game_id,
+                    Some("A/B"),
+                    &turn_id,
+                    ArchiveKind::Uploaded,
+                    bytes,
+                )
+                .unwrap()
+        };
+
+        let first = add(b"one").unwrap();
+        assert_eq!(first, dir.path().join("A_B (7)").join("100_up.Civ5Save"));
+        assert_eq!(add(b"one"), None);
+
+        let second = add(b"two").unwrap();
+        assert_eq!(
+            second,
+            dir.path().join("A_B (7)").join("100_up (2).Civ5Save")
+        );
+        assert_eq!(std::fs::read(first).unwrap(), b"one");
+    }
+}
@@ -0,0 +1,321 @@
+use crate::config::{Config, GameSettings};
+use crate::manager::Event;
+use anyhow::Context;
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+type Result<T> = anyhow::Result<T>;
+
+const APP_NAME: &str = "civ.fun's Multiplayer Robot";
+
+/// A time of day range where no desktop notifications are shown. It can go past midnight, e.g.
+/// 22:00 to 07:00. The same start and end means no quiet time during the day, which is useful
+/// with `weekends`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Quiet all day on Saturdays and Sundays too.
+    #[serde(default)]
+    pub weekends: bool,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    pub fn contains_at(&self, now: NaiveDateTime) -> bool {
+        let weekend = matches!(now.weekday(), Weekday::Sat | Weekday::Sun);
+        (self.weekends && weekend) || self.contains(now.time())
+    }
+}
+
+/// Whether alerts should wait, going by the local time.
+pub fn is_quiet(config: &Config, now: NaiveDateTime) -> bool {
+    config
+        .quiet_hours
+        .as_ref()
+        .map(|quiet_hours| quiet_hours.contains_at(now))
+        .unwrap_or(false)
+}
+
+/// Alerts that can't wait for quiet hours to end.
+pub fn is_critical(event: &Event) -> bool {
+    matches!(event, Event::TurnAboutToBeSkipped { .. })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+}
+
+/// At most this many alerts are kept during quiet hours, the newest ones.
+pub const MAX_HELD_ALERTS: usize = 20;
+
+/// Alerts held back during quiet hours, kept in sled until they're over.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeldAlerts {
+    pub notifications: Vec<Notification>,
+    /// Older alerts that didn't fit in `MAX_HELD_ALERTS`.
+    pub dropped: usize,
+}
+
+impl HeldAlerts {
+    pub fn push(&mut self, notification: Notification) {
+        self.notifications.push(notification);
+        if self.notifications.len() > MAX_HELD_ALERTS {
+            self.notifications.remove(0);
+            self.dropped += 1;
+        }
+    }
+
+    /// One alert for everything held back, so they don't arrive as a burst.
+    pub fn summary(&self) -> Option<Notification> {
+        match (self.notifications.as_slice(), self.dropped) {
+            ([], _) => None,
+            ([notification], 0) => Some(notification.clone()),
+            (notifications, dropped) => {
+                let mut lines: Vec<String> =
+                    notifications.iter().map(|n| n.summary.clone()).collect();
+                if dropped > 0 {
+                    lines.push(format!("…and {} earlier", dropped));
+                }
+                Some(Notification {
+                    summary: format!(
+                        "{} alerts during quiet hours",
+                        notifications.len() + dropped
+                    ),
+                    body: lines.join("\n"),
+                })
+            }
+        }
+    }
+}
+
+/// The notification to show for a manager event, if any.
+pub fn notification_for(event: &Event) -> Option<Notification> {
+    match event {
+        Event::NewTurn(game) => Some(Notification {
+            summary: format!("It's your turn in {}", game.name),
+            body: format!("Turn {} is ready to download.", game.current_turn.number),
+        }),
+        Event::UploadComplete(_) => Some(Notification {
+            summary: "Turn uploaded successfully".into(),
+            body: "Your turn has been sent to GMR.".into(),
+        }),
+        Event::UploadNeedsApproval { game, .. } => Some(Notification {
+            summary: format!("Upload your turn in {}?", game.name),
+            body: "Your save is waiting for you to approve it.".into(),
+        }),
+        Event::UploadFailed { message, .. } => Some(Notification {
+            summary: "Turn upload failed".into(),
+            body: message.clone(),
+        }),
+        Event::TurnDeadlineApproaching { game, remaining } => Some(Notification {
+            summary: format!("Your turn in {} is running out", game.name),
+            body: format!(
+                "{} left before the turn expires.",
+                hours_minutes(*remaining)
+            ),
+        }),
+        Event::TurnAboutToBeSkipped {
+            game, remaining, ..
+        } => Some(Notification {
+            summary: format!("Your turn in {} is about to be skipped", game.name),
+            body: format!(
+                "Only {} left. Play it now or GMR will skip you.",
+                hours_minutes(*remaining)
+            ),
+        }),
+        Event::DeferredAlerts(notification) => Some(notification.clone()),
+        _ => None,
+    }
+}
+
+/// The desktop notification for the event, unless notifications are turned off, the game is
+/// muted or it's during quiet hours. The manager sends `Event::DeferredAlerts` with a summary of
+/// what was held back once quiet hours are over.
+#[instrument(skip(event, config, game_settings))]
+pub fn to_show(
+    event: &Event,
+    config: &Config,
+    game_settings: Option<&GameSettings>,
+    now: NaiveDateTime,
+) -> Option<Notification> {
+    if !config.notifications {
+        return None;
+    }
+    if game_settings.map(|s| s.muted).unwrap_or(false) {
+        debug!("Game is muted.");
+        return None;
+    }
+    if is_quiet(config, now) && !is_critical(event) {
+        debug!("Quiet hours.");
+        return None;
+    }
+    notification_for(event)
+}
+
+/// Whether to flash the window for an event, see `attention::request()`. Only for new turns,
+/// and not for muted games or during quiet hours.
+pub fn wants_attention(
+    event: &Event,
+    config: &Config,
+    game_settings: Option<&GameSettings>,
+    now: NaiveDateTime,
+) -> bool {
+    matches!(event, Event::NewTurn(_))
+        && config.request_attention
+        && !game_settings.map(|s| s.muted).unwrap_or(false)
+        && !is_quiet(config, now)
+}
+
+/// Shows the notification on a blocking thread, since some platforms wait for the OS to
+/// answer.
+pub async fn show(notification: Notification) -> Result<()> {
+    debug!(?notification, "Showing notification.");
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        notify_rust::Notification::new()
+            .appname(APP_NAME)
+            .summary(&notification.summary)
+            .body(&notification.body)
+            .show()
+            .context("Showing notification.")?;
+        Ok(())
+    })
+    .await?
+}
+
+/// e.g. `2h 5m`. Deadlines that have passed are `0h 0m`.
+fn hours_minutes(remaining: chrono::Duration) -> String {
+    let remaining = remaining.max(chrono::Duration::zero());
+    format!(
+        "{}h {}m",
+        remaining.num_hours(),
+        remaining.num_minutes() % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn hm(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms(h, m, 0)
+    }
+
+    #[test]
+    fn quiet_hours_same_day() {
+        let quiet = QuietHours {
+            start: hm(13, 0),
+            end: hm(14, 30),
+            weekends: false,
+        };
+        assert!(!quiet.contains(hm(12, 59)));
+        assert!(quiet.contains(hm(13, 0)));
+        assert!(quiet.contains(hm(14, 29)));
+        assert!(!quiet.contains(hm(14, 30)));
+    }
+
+    #[test]
+    fn quiet_hours_overnight() {
+        let quiet = QuietHours {
+            start: hm(22, 0),
+            end: hm(7, 0),
+            weekends: false,
+        };
+        assert!(quiet.contains(hm(23, 0)));
+        assert!(quiet.contains(hm(0, 0)));
+        assert!(quiet.contains(hm(6, 59)));
+        assert!(!quiet.contains(hm(7, 0)));
+        assert!(!quiet.contains(hm(12, 0)));
+    }
+
+    #[test]
+    fn quiet_weekends() {
+        let quiet = QuietHours {
+            start: hm(0, 0),
+            end: hm(0, 0),
+            weekends: true,
+        };
+        let saturday = NaiveDate::from_ymd(2021, 10, 16).and_hms(12, 0, 0);
+        let monday = NaiveDate::from_ymd(2021, 10, 18).and_hms(12, 0, 0);
+        assert!(quiet.contains_at(saturday));
+        assert!(!quiet.contains_at(monday));
+        assert!(!QuietHours {
+            weekends: false,
+            ..quiet
+        }
+        .contains_at(saturday));
+    }
+
+    #[test]
+    fn remaining_time() {
+        assert_eq!(hours_minutes(chrono::Duration::minutes(125)), "2h 5m");
+        assert_eq!(hours_minutes(chrono::Duration::minutes(-5)), "0h 0m");
+    }
+
+    #[test]
+    fn attention() {
+        let event = Event::NewTurn(Default::default());
+        let monday = NaiveDate::from_ymd(2021, 10, 18).and_hms(12, 0, 0);
+        let mut config = Config::default();
+        assert!(wants_attention(&event, &config, None, monday));
+        assert!(!wants_attention(
+            &Event::UploadComplete(Default::default()),
+            &config,
+            None,
+            monday
+        ));
+
+        let muted = GameSettings {
+            muted: true,
+            ..Default::default()
+        };
+        assert!(!wants_attention(&event, &config, Some(&muted), monday));
+
+        config.quiet_hours = Some(QuietHours {
+            start: hm(0, 0),
+            end: hm(0, 0),
+            weekends: true,
+        });
+        let saturday = NaiveDate::from_ymd(2021, 10, 16).and_hms(12, 0, 0);
+        assert!(!wants_attention(&event, &config, None, saturday));
+
+        config.request_attention = false;
+        assert!(!wants_attention(&event, &config, None, monday));
+    }
+
+    #[test]
+    fn held_alerts_summary() {
+        let alert = |n: usize| Notification {
+            summary: format!("Alert {}", n),
+            body: "Body".into(),
+        };
+        let mut held = HeldAlerts::default();
+        assert_eq!(held.summary(), None);
+        held.push(alert(0));
+        assert_eq!(held.summary(), Some(alert(0)));
+
+        for n in 1..MAX_HELD_ALERTS + 2 {
+            held.push(alert(n));
+        }
+        assert_eq!(held.notifications.len(), MAX_HELD_ALERTS);
+        assert_eq!(held.dropped, 2);
+        let summary = held.summary().unwrap();
+        assert_eq!(
+            summary.summary,
+            format!("{} alerts during quiet hours", MAX_HELD_ALERTS + 2)
+        );
+        assert!(summary.body.starts_with("Alert 2\n"));
+        assert!(summary.body.ends_with("…and 2 earlier"));
+    }
+}
@@ -0,0 +1,80 @@
+use std::process::Command;
+use tracing::debug;
+
+/// Whether the OS is set to a dark theme. None when it can't be told, e.g. on Linux desktops
+/// that don't say.
+///
+/// Windows has it in the registry, macOS in the global defaults, and GTK desktops usually
+/// in `GTK_THEME`.
+pub fn prefers_dark() -> Option<bool> {
+    let dark = if cfg!(windows) {
+        command_output(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                "/v",
+                "AppsUseLightTheme",
+            ],
+        )
+        .and_then(|output| windows_prefers_dark(&output))
+    } else if cfg!(target_os = "macos") {
+        // Fails when the key isn't there, which is how light mode is stored.
+        Some(
+            command_output("defaults", &["read", "-g", "AppleInterfaceStyle"])
+                .is_some_and(|output| output.trim() == "Dark"),
+        )
+    } else {
+        std::env::var("GTK_THEME")
+            .ok()
+            .map(|t| gtk_theme_is_dark(&t))
+    };
+    debug!(?dark, "OS theme.");
+    dark
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    match Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).to_string())
+        }
+        _ => None,
+    }
+}
+
+/// e.g. `AppsUseLightTheme    REG_DWORD    0x0` from `reg query`.
+fn windows_prefers_dark(output: &str) -> Option<bool> {
+    let line = output
+        .lines()
+        .find(|line| line.trim_start().starts_with("AppsUseLightTheme"))?;
+    match line.split_whitespace().last()? {
+        "0x0" => Some(true),
+        "0x1" => Some(false),
+        _ => None,
+    }
+}
+
+/// e.g. `Adwaita:dark` or `Arc-Dark`.
+fn gtk_theme_is_dark(theme: &str) -> bool {
+    theme.to_lowercase().contains("dark")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_os_settings() {
+        let reg = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize\r\n    AppsUseLightTheme    REG_DWORD    0x0\r\n\r\n";
+        assert_eq!(windows_prefers_dark(reg), Some(true));
+        assert_eq!(
+            windows_prefers_dark(&reg.replace("0x0", "0x1")),
+            Some(false)
+        );
+        assert_eq!(windows_prefers_dark(""), None);
+
+        assert!(gtk_theme_is_dark("Adwaita:dark"));
+        assert!(gtk_theme_is_dark("Arc-Dark"));
+        assert!(!gtk_theme_is_dark("Adwaita"));
+    }
+}
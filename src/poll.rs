@@ -0,0 +1,97 @@
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::trace;
+
+/// Never wait longer than this between polls, no matter how many have failed.
+pub const MAX_DELAY: Duration = Duration::from_secs(30 * 60);
+
+/// Each poll is moved by up to this fraction of the delay, so clients started at the same time
+/// don't all hit GMR together.
+const JITTER: f64 = 0.1;
+
+/// Doubles the interval for each failure in a row, up to `MAX_DELAY` or the interval itself if
+/// that's longer.
+pub fn delay(interval: Duration, failures: u32) -> Duration {
+    let cap = MAX_DELAY.max(interval);
+    let factor = 2u32.saturating_pow(failures.min(16));
+    interval.checked_mul(factor).unwrap_or(cap).min(cap)
+}
+
+/// Games fetched longer ago than this many poll intervals are out of date. Missing a poll or two
+/// is normal when GMR is slow.
+const STALE_POLLS: u32 = 3;
+/// Short poll intervals still get this long before games count as out of date.
+const MIN_STALE_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// Whether games fetched `age` ago are too old to trust, polling every `interval`.
+pub fn is_stale(age: Duration, interval: Duration) -> bool {
+    age > (interval * STALE_POLLS).max(MIN_STALE_AGE)
+}
+
+fn jitter(delay: Duration) -> Duration {
+    let fraction = rand::thread_rng().gen_range(-JITTER..=JITTER);
+    delay.mul_f64(1.0 + fraction)
+}
+
+/// Runs `poll` every time it's time to poll, waiting for it to finish before the next wait. The
+/// delay can be changed at any time through `delay_rx`, which also restarts the wait. Stops when
+/// the sending side of `delay_rx` is dropped.
+pub fn spawn<F, Fut>(mut delay_rx: watch::Receiver<Duration>, mut poll: F)
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    tokio::spawn(async move {
+        loop {
+            let wait = jitter(*delay_rx.borrow());
+            trace!(?wait, "Waiting to poll.");
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => poll().await,
+                changed = delay_rx.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff() {
+        let minute = Duration::from_secs(60);
+        assert_eq!(delay(minute, 0), minute);
+        assert_eq!(delay(minute, 1), minute * 2);
+        assert_eq!(delay(minute, 3), minute * 8);
+        assert_eq!(delay(minute, 5), MAX_DELAY);
+        assert_eq!(delay(minute, u32::MAX), MAX_DELAY);
+
+        let hour = Duration::from_secs(60 * 60);
+        assert_eq!(delay(hour, 2), hour);
+    }
+
+    #[test]
+    fn stale() {
+        let minute = Duration::from_secs(60);
+        assert!(!is_stale(minute * 5, minute));
+        assert!(is_stale(minute * 11, minute));
+        assert!(!is_stale(minute * 20, minute * 10));
+        assert!(is_stale(minute * 31, minute * 10));
+    }
+
+    #[test]
+    fn jitter_stays_close() {
+        let minute = Duration::from_secs(60);
+        for _ in 0..100 {
+            let jittered = jitter(minute);
+            assert!(jittered >= Duration::from_secs(54));
+            assert!(jittered <= Duration::from_secs(66));
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::api::Percentage;
+use std::time::{Duration, Instant};
+
+/// Speed and time left for a download or upload, worked out from its progress events.
+///
+/// The speed is averaged over the whole transfer, which is steadier than the last chunk and
+/// close enough for transfers that take seconds.
+#[derive(Debug, Clone)]
+pub struct TransferProgress {
+    started: Instant,
+    /// Between 0.0 and 1.0. None when the size isn't known.
+    pub fraction: Option<f32>,
+    pub bytes: u64,
+}
+
+impl TransferProgress {
+    pub fn new(started: Instant) -> Self {
+        Self {
+            started,
+            fraction: None,
+            bytes: 0,
+        }
+    }
+
+    pub fn update(&mut self, progress: Option<&Percentage>, bytes: u64) {
+        self.fraction = progress.map(Percentage::value);
+        self.bytes = bytes;
+    }
+
+    /// Bytes per second. None until there's been time to tell.
+    pub fn speed_at(&self, now: Instant) -> Option<f64> {
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f64();
+        if elapsed < 0.5 || self.bytes == 0 {
+            return None;
+        }
+        Some(self.bytes as f64 / elapsed)
+    }
+
+    pub fn time_left_at(&self, now: Instant) -> Option<Duration> {
+        let fraction = self.fraction.filter(|f| *f > 0.0)?;
+        let total = self.bytes as f64 / fraction as f64;
+        let remaining = (total - self.bytes as f64).max(0.0);
+        Some(Duration::from_secs_f64(remaining / self.speed_at(now)?))
+    }
+
+    /// e.g. `1.2 MB/s, 5s left`.
+    pub fn describe_at(&self, now: Instant) -> String {
+        let speed = match self.speed_at(now) {
+            Some(speed) => speed,
+            None => return "Starting".to_string(),
+        };
+        match self.time_left_at(now) {
+            Some(left) => format!("{}/s, {}s left", format_bytes(speed), left.as_secs()),
+            None => format!("{}/s", format_bytes(speed)),
+        }
+    }
+}
+
+pub fn format_bytes(bytes: f64) -> String {
+    if bytes >= 1_000_000.0 {
+        format!("{:.1} MB", bytes / 1_000_000.0)
+    } else if bytes >= 1_000.0 {
+        format!("{:.0} KB", bytes / 1_000.0)
+    } else {
+        format!("{:.0} B", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn speed_and_time_left() {
+        let started = Instant::now();
+        let mut progress = TransferProgress::new(started);
+        assert_eq!(progress.describe_at(started), "Starting");
+
+        let quarter: Percentage = 0.25f32.try_into().unwrap();
+        progress.update(Some(&quarter), 1_000_000);
+        let now = started + Duration::from_secs(2);
+        assert_eq!(progress.speed_at(now), Some(500_000.0));
+        assert_eq!(progress.time_left_at(now), Some(Duration::from_secs(6)));
+        assert_eq!(progress.describe_at(now), "500 KB/s, 6s left");
+
+        progress.update(None, 1_000_000);
+        assert_eq!(progress.time_left_at(now), None);
+        assert_eq!(progress.describe_at(now), "500 KB/s");
+    }
+}
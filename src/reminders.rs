@@ -0,0 +1,47 @@
+use chrono::Duration;
+
+/// Thresholds, in minutes, that `remaining` has dropped below and that haven't been sent yet.
+///
+/// When several are crossed at once, e.g. the app was closed for a day, they're all returned so
+/// they can be marked as sent, but only one reminder should be shown.
+pub fn due(remaining: Duration, thresholds: &[u32], sent: &[u32]) -> Vec<u32> {
+    let mut due: Vec<u32> = thresholds
+        .iter()
+        .copied()
+        .filter(|t| remaining <= Duration::minutes(*t as i64))
+        .filter(|t| !sent.contains(t))
+        .collect();
+    due.sort_unstable();
+    due.dedup();
+    due
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THRESHOLDS: &[u32] = &[24 * 60, 3 * 60, 30];
+
+    #[test]
+    fn nothing_due() {
+        assert!(due(Duration::hours(25), THRESHOLDS, &[]).is_empty());
+    }
+
+    #[test]
+    fn crossing_one_at_a_time() {
+        assert_eq!(due(Duration::hours(23), THRESHOLDS, &[]), vec![24 * 60]);
+        assert!(due(Duration::hours(23), THRESHOLDS, &[24 * 60]).is_empty());
+        assert_eq!(
+            due(Duration::minutes(29), THRESHOLDS, &[24 * 60, 3 * 60]),
+            vec![30]
+        );
+    }
+
+    #[test]
+    fn crossing_several_at_once() {
+        assert_eq!(
+            due(Duration::minutes(-5), THRESHOLDS, &[]),
+            vec![30, 3 * 60, 24 * 60]
+        );
+    }
+}
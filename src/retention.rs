@@ -0,0 +1,123 @@
+use crate::api::{GameId, TurnId};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+/// Everything kept for one turn of one game: save bytes in sled and files in the archive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredTurn {
+    pub game_id: GameId,
+    pub turn_id: TurnId,
+    pub bytes: u64,
+}
+
+/// How much space civfun is using, for the settings screen.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageUsage {
+    /// The whole sled database on disk.
+    pub db_bytes: u64,
+    /// Downloaded and uploaded save bytes kept in sled.
+    pub save_bytes: u64,
+    /// Files in the archive directory.
+    pub archive_bytes: u64,
+    pub archive_files: usize,
+    /// Turns with anything stored for them.
+    pub turns: usize,
+}
+
+/// Which turns to delete so each game keeps at most `keep_turns` turns and everything adds up
+/// to at most `max_bytes`. The oldest turns go first. `protected` turns are never deleted, even
+/// if that means going over the limits.
+pub fn turns_to_prune(
+    turns: &[StoredTurn],
+    keep_turns: Option<u32>,
+    max_bytes: Option<u64>,
+    protected: &HashSet<(GameId, TurnId)>,
+) -> Vec<(GameId, TurnId)> {
+    let mut newest_first: Vec<&StoredTurn> = turns.iter().collect();
+    newest_first.sort_by_key(|t| Reverse(t.turn_id));
+
+    let mut prune = vec![];
+    let mut kept = vec![];
+    let mut per_game: HashMap<GameId, u32> = HashMap::new();
+    for turn in newest_first {
+        let key = (turn.game_id, turn.turn_id);
+        if protected.contains(&key) {
+            kept.push(turn);
+            continue;
+        }
+        let count = per_game.entry(turn.game_id).or_default();
+        *count += 1;
+        match keep_turns {
+            Some(keep) if *count > keep => prune.push(key),
+            _ => kept.push(turn),
+        }
+    }
+
+    if let Some(max_bytes) = max_bytes {
+        let mut total: u64 = kept.iter().map(|t| t.bytes).sum();
+        for turn in kept.iter().rev() {
+            if total <= max_bytes {
+                break;
+            }
+            let key = (turn.game_id, turn.turn_id);
+            if protected.contains(&key) {
+                continue;
+            }
+            total -= turn.bytes;
+            prune.push(key);
+        }
+    }
+
+    prune
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(game_id: u32, turn_id: u64, bytes: u64) -> StoredTurn {
+        StoredTurn {
+            game_id: game_id.into(),
+            turn_id: turn_id.into(),
+            bytes,
+        }
+    }
+
+    fn key(game_id: u32, turn_id: u64) -> (GameId, TurnId) {
+        (game_id.into(), turn_id.into())
+    }
+
+    #[test]
+    fn keep_turns_per_game() {
+        let turns = vec![
+            turn(1, 10, 1),
+            turn(1, 20, 1),
+            turn(1, 30, 1),
+            turn(2, 15, 1),
+        ];
+        let prune = turns_to_prune(&turns, Some(2), None, &HashSet::new());
+        assert_eq!(prune, vec![key(1, 10)]);
+    }
+
+    #[test]
+    fn max_bytes_removes_oldest() {
+        let turns = vec![turn(1, 10, 100), turn(2, 20, 100), turn(1, 30, 100)];
+        let prune = turns_to_prune(&turns, None, Some(150), &HashSet::new());
+        assert_eq!(prune, vec![key(1, 10), key(2, 20)]);
+    }
+
+    #[test]
+    fn protected_turns_stay() {
+        let turns = vec![turn(1, 10, 100), turn(1, 20, 100)];
+        let protected = vec![key(1, 10)].into_iter().collect();
+        let prune = turns_to_prune(&turns, Some(0), Some(0), &protected);
+        assert_eq!(prune, vec![key(1, 20)]);
+    }
+
+    #[test]
+    fn no_limits() {
+        let turns = vec![turn(1, 10, 100), turn(1, 20, 100)];
+        assert!(turns_to_prune(&turns, None, None, &HashSet::new()).is_empty());
+    }
+}
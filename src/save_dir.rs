@@ -0,0 +1,166 @@
+use anyhow::anyhow;
+use directories::{BaseDirs, UserDirs};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+type Result<T> = anyhow::Result<T>;
+
+/// The result of looking for the hotseat directory, kept around so the UI can show the user
+/// where we looked.
+#[derive(Debug, Clone)]
+pub struct Detection {
+    /// Every path that was tried, in order, and whether it exists.
+    pub tried: Vec<(PathBuf, bool)>,
+    pub found: Option<PathBuf>,
+}
+
+/// Tries each candidate in order and picks the first one that exists.
+pub fn detect() -> Result<Detection> {
+    let tried: Vec<(PathBuf, bool)> = candidates()?
+        .into_iter()
+        .map(|path| {
+            let exists = path.is_dir();
+            (path, exists)
+        })
+        .collect();
+    let found = tried
+        .iter()
+        .find(|(_, exists)| *exists)
+        .map(|(path, _)| path.clone());
+    Ok(Detection { tried, found })
+}
+
+/// Places the hotseat directory is usually found, most likely first.
+///
+/// Windows: ~\Documents\My Games\Sid Meier's Civilization 5\Saves\hotseat\
+/// OS X: ~/Documents/Aspyr/Sid Meier's Civilization 5/Saves/hotseat/
+/// Linux: ~/.local/share/Aspyr/Sid Meier's Civilization 5/Saves/hotseat/
+///
+/// The Documents folder can be moved, e.g. by OneDrive on Windows, so the OS's idea of where
+/// Documents lives is tried as well as the default.
+pub fn candidates() -> Result<Vec<PathBuf>> {
+    let base_dirs = BaseDirs::new().ok_or_else(|| anyhow!("Could not work out basedir."))?;
+    let home = base_dirs.home_dir();
+    let documents = UserDirs::new().and_then(|u| u.document_dir().map(Path::to_path_buf));
+
+    // Can't only use the `directories` crate because these paths are inconsistent between OS's.
+    let mut parents = vec![];
+    if cfg!(windows) {
+        if let Some(documents) = documents {
+            parents.push(documents.join("My Games"));
+        }
+        parents.push(home.join("Documents").join("My Games"));
+        parents.push(home.join("OneDrive").join("Documents").join("My Games"));
+    } else if cfg!(target_os = "macos") {
+        if let Some(documents) = documents {
+            parents.push(documents.join("Aspyr"));
+        }
+        parents.push(home.join("Documents").join("Aspyr"));
+    } else if cfg!(unix) {
+        parents.push(home.join(".local").join("share").join("Aspyr"));
+        // Running the Windows version through Proton.
+        parents.push(
+            home.join(".steam/steam/steamapps/compatdata/8930/pfx/drive_c/users/steamuser")
+                .join("Documents")
+                .join("My Games"),
+        );
+    } else {
+        return Err(anyhow!("Unhandled operating system for save_dir."));
+    }
+
+    let mut candidates: Vec<PathBuf> = vec![];
+    for parent in parents {
+        let path = parent
+            .join("Sid Meier's Civilization 5")
+            .join("Saves")
+            .join("hotseat");
+        if !candidates.contains(&path) {
+            candidates.push(path);
+        }
+    }
+    Ok(candidates)
+}
+
+/// Something about the hotseat directory that would make downloading a turn fail.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SaveDirProblem {
+    Missing,
+    NotWritable { message: String },
+    LowSpace { available: u64, needed: u64 },
+}
+
+impl fmt::Display for SaveDirProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveDirProblem::Missing => write!(f, "The hotseat directory doesn't exist."),
+            SaveDirProblem::NotWritable { message } if cfg!(windows) => write!(
+                f,
+                "Saves can't be written to the hotseat directory ({}). The Documents folder may \
+                 be read-only, or Windows' controlled folder access may be blocking civfun.",
+                message
+            ),
+            SaveDirProblem::NotWritable { message } => write!(
+                f,
+                "Saves can't be written to the hotseat directory ({}). Check its permissions.",
+                message
+            ),
+            SaveDirProblem::LowSpace { available, needed } => write!(
+                f,
+                "Only {} KiB of disk space is left for saves, at least {} KiB is needed.",
+                available / 1024,
+                needed / 1024
+            ),
+        }
+    }
+}
+
+/// Checks a download of up to `needed` bytes can be written to `dir`, by actually writing a
+/// small file since permissions alone don't show things like controlled folder access.
+pub fn preflight(dir: &Path, needed: u64) -> Vec<SaveDirProblem> {
+    if !dir.is_dir() {
+        return vec![SaveDirProblem::Missing];
+    }
+    let mut problems = vec![];
+    if let Err(err) = tempfile::Builder::new()
+        .prefix(".civfun-preflight")
+        .tempfile_in(dir)
+        .and_then(|mut file| file.write_all(b"civfun"))
+    {
+        problems.push(SaveDirProblem::NotWritable {
+            message: err.to_string(),
+        });
+    }
+    match fs2::available_space(dir) {
+        Ok(available) if available < needed => {
+            problems.push(SaveDirProblem::LowSpace { available, needed })
+        }
+        Ok(_) => {}
+        // Not knowing is no reason to stop.
+        Err(err) => warn!(?err, "Could not check free space."),
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preflight_problems() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(preflight(dir.path(), 1), vec![]);
+        assert_eq!(
+            preflight(&dir.path().join("nope"), 1),
+            vec![SaveDirProblem::Missing]
+        );
+        assert!(matches!(
+            preflight(dir.path(), u64::MAX)[..],
+            [SaveDirProblem::LowSpace { .. }]
+        ));
+        // The test file is cleaned up.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
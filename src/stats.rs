@@ -0,0 +1,330 @@
+use crate::api::{CurrentTurn, GameId, TurnId, UserId};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One turn of a game as seen in the games payload. `ended` is filled in when the next turn
+/// shows up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnRecord {
+    pub turn_id: TurnId,
+    pub number: u64,
+    pub user_id: UserId,
+    pub started: DateTime<Utc>,
+    pub expires: Option<DateTime<Utc>>,
+    pub ended: Option<DateTime<Utc>>,
+    pub skipped: bool,
+}
+
+impl TurnRecord {
+    pub fn new(turn: &CurrentTurn) -> Self {
+        Self {
+            turn_id: turn.turn_id,
+            number: turn.number,
+            user_id: turn.user_id,
+            started: turn.started,
+            expires: turn.expires,
+            ended: None,
+            skipped: turn.skipped,
+        }
+    }
+
+    /// GMR moves on to the next player when a turn expires, so a turn that ended after it
+    /// expired was skipped even if we never saw the flag.
+    pub fn was_skipped(&self) -> bool {
+        match (self.ended, self.expires) {
+            (Some(ended), Some(expires)) => self.skipped || ended >= expires,
+            _ => self.skipped,
+        }
+    }
+
+    /// How long the turn took. None while it's still going.
+    pub fn duration(&self) -> Option<Duration> {
+        self.ended.map(|ended| ended - self.started)
+    }
+}
+
+/// Adds `turn` to the history if it's new, ending the previous turn when it started. Returns
+/// whether anything changed.
+pub fn record(history: &mut Vec<TurnRecord>, turn: &CurrentTurn) -> bool {
+    match history.last_mut() {
+        Some(last) if last.turn_id == turn.turn_id => {
+            let changed = last.skipped != turn.skipped || last.expires != turn.expires;
+            last.skipped = turn.skipped;
+            last.expires = turn.expires;
+            changed
+        }
+        last => {
+            if let Some(last) = last {
+                last.ended = Some(turn.started);
+            }
+            history.push(TurnRecord::new(turn));
+            true
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerStats {
+    pub user_id: UserId,
+    /// Finished turns, including skipped ones.
+    pub turns: u32,
+    pub skips: u32,
+    /// Mean time taken over turns that weren't skipped.
+    pub average: Option<Duration>,
+    pub longest: Option<Duration>,
+    /// Most turns in a row played without being skipped.
+    pub longest_streak: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameStats {
+    /// Every player who has had a turn, by user id.
+    pub players: Vec<PlayerStats>,
+    /// Mean time for any player's turn that wasn't skipped.
+    pub average: Option<Duration>,
+    /// Turns seen since civfun started recording, including the current one.
+    pub turns_recorded: usize,
+}
+
+pub fn game_stats(history: &[TurnRecord]) -> GameStats {
+    let mut by_player: BTreeMap<UserId, Vec<&TurnRecord>> = BTreeMap::new();
+    for turn in history.iter().filter(|t| t.ended.is_some()) {
+        by_player.entry(turn.user_id).or_default().push(turn);
+    }
+
+    let players = by_player
+        .into_iter()
+        .map(|(user_id, turns)| player_stats(user_id, &turns))
+        .collect();
+    let played: Vec<Duration> = history
+        .iter()
+        .filter(|t| !t.was_skipped())
+        .filter_map(TurnRecord::duration)
+        .collect();
+
+    GameStats {
+        players,
+        average: average(&played),
+        turns_recorded: history.len(),
+    }
+}
+
+/// Mean time `user_id` took over turns that weren't skipped, across every game in `histories`.
+pub fn average_turn(user_id: &UserId, histories: &[Vec<TurnRecord>]) -> Option<Duration> {
+    let played: Vec<Duration> = histories
+        .iter()
+        .flatten()
+        .filter(|t| &t.user_id == user_id && !t.was_skipped())
+        .filter_map(TurnRecord::duration)
+        .collect();
+    average(&played)
+}
+
+/// Turn times across every game, from the point of view of one player.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Dashboard {
+    /// Everyone else's mean turn time over shared games, slowest first.
+    pub opponents: Vec<(UserId, Duration)>,
+    /// The player's mean turn time for each of the last weeks, oldest first. None for weeks
+    /// without a finished turn.
+    pub response_trend: Vec<Option<Duration>>,
+    /// Turns the player finished in each of the last weeks, oldest first.
+    pub turns_per_week: Vec<u32>,
+    /// The game with the longest mean turn time.
+    pub slowest_game: Option<(GameId, Duration)>,
+}
+
+/// Works out the dashboard for `me` over the last `weeks` weeks up to `now`.
+pub fn dashboard(
+    me: &UserId,
+    histories: &[(GameId, Vec<TurnRecord>)],
+    now: DateTime<Utc>,
+    weeks: usize,
+) -> Dashboard {
+    let mut by_opponent: BTreeMap<UserId, Vec<Duration>> = BTreeMap::new();
+    let mut by_week: Vec<Vec<Duration>> = vec![vec![]; weeks];
+    let mut turns_per_week = vec![0; weeks];
+    for turn in histories.iter().flat_map(|(_, history)| history) {
+        let (ended, duration) = match (turn.ended, turn.duration()) {
+            (Some(ended), Some(duration)) => (ended, duration),
+            _ => continue,
+        };
+        if &turn.user_id != me {
+            if !turn.was_skipped() {
+                by_opponent.entry(turn.user_id).or_default().push(duration);
+            }
+            continue;
+        }
+        let ago = (now - ended).num_weeks();
+        if ago < 0 || ago as usize >= weeks {
+            continue;
+        }
+        let week = weeks - 1 - ago as usize;
+        turns_per_week[week] += 1;
+        if !turn.was_skipped() {
+            by_week[week].push(duration);
+        }
+    }
+
+    let mut opponents: Vec<(UserId, Duration)> = by_opponent
+        .into_iter()
+        .filter_map(|(user_id, played)| Some((user_id, average(&played)?)))
+        .collect();
+    opponents.sort_by_key(|(_, average)| std::cmp::Reverse(*average));
+    let slowest_game = histories
+        .iter()
+        .filter_map(|(game_id, history)| Some((*game_id, game_stats(history).average?)))
+        .max_by_key(|(_, average)| *average);
+
+    Dashboard {
+        opponents,
+        response_trend: by_week.iter().map(|played| average(played)).collect(),
+        turns_per_week,
+        slowest_game,
+    }
+}
+
+fn player_stats(user_id: UserId, turns: &[&TurnRecord]) -> PlayerStats {
+    let mut skips = 0;
+    let mut streak = 0;
+    let mut longest_streak = 0;
+    let mut played = vec![];
+    for turn in turns {
+        if turn.was_skipped() {
+            skips += 1;
+            streak = 0;
+        } else {
+            streak += 1;
+            longest_streak = longest_streak.max(streak);
+            played.extend(turn.duration());
+        }
+    }
+    PlayerStats {
+        user_id,
+        turns: turns.len() as u32,
+        skips,
+        average: average(&played),
+        longest: played.iter().max().copied(),
+        longest_streak,
+    }
+}
+
+fn average(durations: &[Duration]) -> Option<Duration> {
+    if durations.is_empty() {
+        return None;
+    }
+    let total: i64 = durations.iter().map(|d| d.num_seconds()).sum();
+    Some(Duration::seconds(total / durations.len() as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn turn(id: u64, user: u64, started_hour: u32, expires_hour: Option<u32>) -> CurrentTurn {
+        let at = |hour| Utc.ymd(2021, 10, 1).and_hms(0, 0, 0) + Duration::hours(hour as i64);
+        CurrentTurn {
+            turn_id: TurnId::from(id),
+            number: id,
+            user_id: UserId::from(user),
+            started: at(started_hour),
+            expires: expires_hour.map(at),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn recording() {
+        let mut history = vec![];
+        assert!(record(&mut history, &turn(1, 10, 0, None)));
+        assert!(!record(&mut history, &turn(1, 10, 0, None)));
+        assert!(record(&mut history, &turn(2, 20, 3, None)));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].duration(), Some(Duration::hours(3)));
+        assert_eq!(history[1].ended, None);
+    }
+
+    #[test]
+    fn stats() {
+        let mut history = vec![];
+        for t in &[
+            turn(1, 10, 0, Some(24)),
+            turn(2, 20, 2, Some(26)),
+            // Player 10 runs out of time.
+            turn(3, 10, 6, Some(30)),
+            turn(4, 20, 30, Some(54)),
+            turn(5, 10, 34, Some(58)),
+            turn(6, 20, 35, None),
+        ] {
+            record(&mut history, t);
+        }
+        let stats = game_stats(&history);
+        assert_eq!(stats.turns_recorded, 6);
+        assert_eq!(stats.players.len(), 2);
+
+        let first = &stats.players[0];
+        assert_eq!(first.user_id, UserId::from(10));
+        assert_eq!(first.turns, 3);
+        assert_eq!(first.skips, 1);
+        assert_eq!(first.average, Some(Duration::minutes(90)));
+        assert_eq!(first.longest_streak, 1);
+
+        let second = &stats.players[1];
+        assert_eq!(second.turns, 2);
+        assert_eq!(second.skips, 0);
+        assert_eq!(second.average, Some(Duration::hours(4)));
+        assert_eq!(second.longest, Some(Duration::hours(4)));
+        assert_eq!(second.longest_streak, 2);
+
+        let mut other_game = vec![];
+        record(&mut other_game, &turn(7, 20, 0, None));
+        record(&mut other_game, &turn(8, 10, 8, None));
+        let histories = vec![history, other_game];
+        assert_eq!(
+            average_turn(&UserId::from(20), &histories),
+            Some(Duration::hours(16) / 3)
+        );
+        assert_eq!(average_turn(&UserId::from(30), &histories), None);
+    }
+
+    #[test]
+    fn dashboard_weeks() {
+        let mut history = vec![];
+        for t in &[
+            turn(1, 10, 0, Some(24)),
+            turn(2, 20, 2, Some(26)),
+            turn(3, 10, 6, Some(30)),
+            turn(4, 20, 30, Some(54)),
+            turn(5, 10, 34, Some(58)),
+            turn(6, 20, 35, None),
+        ] {
+            record(&mut history, t);
+        }
+        let mut slow_game = vec![];
+        record(&mut slow_game, &turn(7, 30, 0, None));
+        record(&mut slow_game, &turn(8, 10, 48, None));
+        let histories = vec![(GameId::from(1), history), (GameId::from(2), slow_game)];
+        let now = Utc.ymd(2021, 10, 15).and_hms(0, 0, 0);
+
+        let dashboard = dashboard(&UserId::from(10), &histories, now, 3);
+        assert_eq!(
+            dashboard.opponents,
+            vec![
+                (UserId::from(30), Duration::hours(48)),
+                (UserId::from(20), Duration::hours(4)),
+            ]
+        );
+        // Three turns were finished a week ago, one of them skipped.
+        assert_eq!(dashboard.turns_per_week, vec![0, 3, 0]);
+        assert_eq!(
+            dashboard.response_trend,
+            vec![None, Some(Duration::minutes(90)), None]
+        );
+        assert_eq!(
+            dashboard.slowest_game,
+            Some((GameId::from(2), Duration::hours(48)))
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use crate::config::DirectXVersion;
+use directories::BaseDirs;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use tracing::{debug, instrument};
+
+pub const CIV5_APP_ID: u32 = 8930;
+
+#[derive(Debug)]
+pub enum LaunchError {
+    /// None of the usual Steam directories exist.
+    SteamNotFound { tried: Vec<PathBuf> },
+    /// Steam is there but Civ V isn't installed in any of its libraries.
+    Civ5NotInstalled { libraries: Vec<PathBuf> },
+    /// The OS couldn't open the steam:// URL.
+    Open(std::io::Error),
+}
+
+impl Display for LaunchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LaunchError::SteamNotFound { .. } => write!(f, "Steam doesn't seem to be installed."),
+            LaunchError::Civ5NotInstalled { .. } => {
+                write!(f, "Civilization V isn't installed in Steam.")
+            }
+            LaunchError::Open(err) => write!(f, "Couldn't start Steam: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for LaunchError {}
+
+/// Checks Civ V is installed, then asks Steam to start it.
+#[instrument]
+pub fn launch(directx: DirectXVersion, args: &[String]) -> Result<(), LaunchError> {
+    let tried = steam_dirs();
+    let steam_dir =
+        tried
+            .iter()
+            .find(|dir| dir.is_dir())
+            .ok_or_else(|| LaunchError::SteamNotFound {
+                tried: tried.clone(),
+            })?;
+    let libraries = library_dirs(steam_dir);
+    let installed = libraries.iter().any(|library| {
+        library
+            .join("steamapps")
+            .join(format!("appmanifest_{}.acf", CIV5_APP_ID))
+            .is_file()
+    });
+    if !installed {
+        return Err(LaunchError::Civ5NotInstalled { libraries });
+    }
+
+    let url = launch_url(directx, args);
+    debug!(?url, "Launching.");
+    open::that(&url).map_err(LaunchError::Open)?;
+    Ok(())
+}
+
+/// e.g. `steam://rungameid/8930//%5Cdx9` for DirectX 9 without extra arguments.
+pub fn launch_url(directx: DirectXVersion, args: &[String]) -> String {
+    let mut all_args = vec![];
+    match directx {
+        DirectXVersion::Auto => {}
+        DirectXVersion::Dx9 => all_args.push("\\dx9".to_string()),
+        DirectXVersion::Dx11 => all_args.push("\\dx11".to_string()),
+    }
+    all_args.extend(args.iter().cloned());
+
+    let mut url = format!("steam://rungameid/{}", CIV5_APP_ID);
+    if !all_args.is_empty() {
+        url.push_str("//");
+        url.push_str(&percent_encode(&all_args.join(" ")));
+    }
+    url
+}
+
+pub(crate) fn percent_encode(s: &str) -> String {
+    let mut encoded = String::new();
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Where Steam is usually installed, most likely first. On Windows that's wherever the registry
+/// says, falling back to the default install folders.
+pub fn steam_dirs() -> Vec<PathBuf> {
+    let home = match BaseDirs::new() {
+        Some(base_dirs) => base_dirs.home_dir().to_path_buf(),
+        None => return vec![],
+    };
+    if cfg!(windows) {
+        let mut dirs: Vec<PathBuf> = registry_steam_dir().into_iter().collect();
+        for dir in &[r"C:\Program Files (x86)\Steam", r"C:\Program Files\Steam"] {
+            let dir = PathBuf::from(dir);
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        dirs
+    } else if cfg!(target_os = "macos") {
+        vec![home.join("Library/Application Support/Steam")]
+    } else {
+        vec![
+            home.join(".steam/steam"),
+            home.join(".local/share/Steam"),
+            home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+        ]
+    }
+}
+
+/// Steam keeps where it's installed in `HKCU\Software\Valve\Steam\SteamPath`.
+#[cfg(windows)]
+fn registry_steam_dir() -> Option<PathBuf> {
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    let read = || -> std::io::Result<String> {
+        RegKey::predef(HKEY_CURRENT_USER)
+            .open_subkey(r"Software\Valve\Steam")?
+            .get_value("SteamPath")
+    };
+    match read() {
+        Ok(path) => Some(PathBuf::from(path)),
+        Err(err) => {
+            debug!(?err, "Steam isn't in the registry.");
+            None
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn registry_steam_dir() -> Option<PathBuf> {
+    None
+}
+
+/// The Steam directory itself plus any other libraries listed in `libraryfolders.vdf`.
+pub fn library_dirs(steam_dir: &Path) -> Vec<PathBuf> {
+    let mut libraries = vec![steam_dir.to_path_buf()];
+    let vdf = steam_dir.join("steamapps").join("libraryfolders.vdf");
+    if let Ok(contents) = std::fs::read_to_string(&vdf) {
+        for library in parse_library_folders(&contents) {
+            if !libraries.contains(&library) {
+                libraries.push(library);
+            }
+        }
+    }
+    libraries
+}
+
+/// Handles both layouts of the file: `"path" "D:\\SteamLibrary"` inside a block per library,
+/// and the older `"1" "D:\\SteamLibrary"`.
+fn parse_library_folders(contents: &str) -> Vec<PathBuf> {
+    let mut libraries = vec![];
+    for line in contents.lines() {
+        let parts: Vec<&str> = line.split('"').collect();
+        // A `"key"   "value"` line splits into ["", key, whitespace, value, ""].
+        if parts.len() != 5 {
+            continue;
+        }
+        let (key, value) = (parts[1], parts[3]);
+        let is_path_key = key == "path" || key.chars().all(|c| c.is_ascii_digit());
+        if is_path_key && (value.contains('/') || value.contains('\\')) {
+            libraries.push(PathBuf::from(value.replace("\\\\", "\\")));
+        }
+    }
+    libraries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            launch_url(DirectXVersion::Dx9, &[]),
+            "steam://rungameid/8930//%5Cdx9"
+        );
+        assert_eq!(
+            launch_url(DirectXVersion::Auto, &[]),
+            "steam://rungameid/8930"
+        );
+        assert_eq!(
+            launch_url(DirectXVersion::Dx11, &["-windowed".into()]),
+            "steam://rungameid/8930//%5Cdx11%20-windowed"
+        );
+    }
+
+    #[test]
+    fn library_folders() {
+        let new = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"C:\\Program Files (x86)\\Steam"
+		"label"		""
+	}
+	"1"
+	{
+		"path"		"/mnt/games/SteamLibrary"
+	}
+}"#;
+        assert_eq!(
+            parse_library_folders(new),
+            vec![
+                PathBuf::from(r"C:\Program Files (x86)\Steam"),
+                PathBuf::from("/mnt/games/SteamLibrary"),
+            ]
+        );
+
+        let old = r#"
+"LibraryFolders"
+{
+	"TimeNextStatsReport"		"1633842243"
+	"ContentStatsID"		"-1234"
+	"1"		"D:\\SteamLibrary"
+}"#;
+        assert_eq!(
+            parse_library_folders(old),
+            vec![PathBuf::from(r"D:\SteamLibrary")]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Temp files live in the folder they're opened with, usually `tmp/` inside the data dir, and each
+//! one is tracked in sled until it's persisted or dropped. Anything still tracked on startup, or
+//! still in the folder with our prefix, was left behind by a killed or crashed run and is removed
+//! by `clean()`.
+use anyhow::Context;
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+use tracing::{debug, instrument, warn};
+
+type Result<T> = anyhow::Result<T>;
+
+const TREE: &str = "temp-files";
+
+/// Starts the name of every temp file, so `clean()` leaves anything else in the folder alone.
+const FILE_PREFIX: &str = "civfun-";
+
+#[derive(Debug, Clone)]
+pub struct TempFiles {
+    dir: PathBuf,
+    tree: sled::Tree,
+}
+
+impl TempFiles {
+    /// Temp files in `dir`, tracked in `db`.
+    pub fn open(db: &sled::Db, dir: &Path) -> Result<Self> {
+        Ok(Self::new(dir, db.open_tree(TREE)?))
+    }
+
+    pub fn new(dir: &Path, tree: sled::Tree) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            tree,
+        }
+    }
+
+    /// A new temp file, tracked until `forget()` is called with its path.
+    pub fn create(&self, prefix: &str) -> Result<NamedTempFile> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Creating temp dir {:?}", self.dir))?;
+        let file = tempfile::Builder::new()
+            .prefix(&format!("{}{}", FILE_PREFIX, prefix))
+            .tempfile_in(&self.dir)
+            .with_context(|| format!("Creating temp file in {:?}", self.dir))?;
+        self.tree.insert(
+            file.path().to_string_lossy().as_bytes(),
+            Utc::now().to_rfc3339().as_bytes(),
+        )?;
+        Ok(file)
+    }
+
+    /// The temp file is gone, either persisted somewhere else or deleted.
+    pub fn forget(&self, path: &Path) -> Result<()> {
+        self.tree.remove(path.to_string_lossy().as_bytes())?;
+        Ok(())
+    }
+
+    /// Removes every tracked temp file and any untracked one of ours in the temp dir. Only call
+    /// this when nothing is being transferred, i.e. on start. Returns how many files were removed.
+    #[instrument(skip(self))]
+    pub fn clean(&self) -> Result<usize> {
+        let mut paths = vec![];
+        for item in self.tree.iter() {
+            let (key, _) = item?;
+            paths.push(PathBuf::from(String::from_utf8_lossy(&key).to_string()));
+        }
+        if self.dir.is_dir() {
+            for entry in std::fs::read_dir(&self.dir)? {
+                let entry = entry?;
+                if entry.file_name().to_string_lossy().starts_with(FILE_PREFIX) {
+                    paths.push(entry.path());
+                }
+            }
+        }
+        paths.sort();
+        paths.dedup();
+
+        let mut removed = 0;
+        for path in paths {
+            if path.is_file() {
+                match std::fs::remove_file(&path) {
+                    Ok(()) => removed += 1,
+                    Err(err) => {
+                        // Probably still open somewhere. Try again next time.
+                        warn!(?path, ?err, "Could not remove stale temp file.");
+                        continue;
+                    }
+                }
+            }
+            self.forget(&path)?;
+        }
+        debug!(removed, "Cleaned temp files.");
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleans_leftovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let temp_files = TempFiles::new(&dir.path().join("tmp"), db.open_tree(TREE).unwrap());
+
+        // Finished normally.
+        let done = temp_files.create("download-").unwrap();
+        temp_files.forget(done.path()).unwrap();
+        drop(done);
+
+        // The app was killed before the file could be dropped.
+        let (_, orphan) = temp_files.create("download-").unwrap().keep().unwrap();
+        // Killed before it was tracked.
+        let stray = dir.path().join("tmp").join("civfun-download-stray");
+        std::fs::write(&stray, b"?").unwrap();
+        // Somebody else's.
+        let other = dir.path().join("tmp").join("notes.txt");
+        std::fs::write(&other, b"?").unwrap();
+
+        assert_eq!(temp_files.clean().unwrap(), 2);
+        assert!(!orphan.exists());
+        assert!(!stray.exists());
+        assert!(other.exists());
+        assert!(temp_files.tree.is_empty());
+        assert_eq!(temp_files.clean().unwrap(), 0);
+    }
+}
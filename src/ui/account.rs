@@ -0,0 +1,199 @@
+use iced::{button, Column, Element, Length, Row, Space, VerticalAlignment};
+use tracing::warn;
+
+use crate::ui::avatars::Avatar;
+use crate::ui::confirm::Confirmation;
+use crate::ui::style::{
+    done_icon, ButtonView, Theme, NORMAL_ICON_SIZE, RELAXED_PADDING, ROW_HEIGHT,
+};
+use crate::ui::{Message, Screen};
+use civfun_gmr::api::UserId;
+use civfun_gmr::manager::Manager;
+
+const AVATAR_SIZE: u16 = 64;
+
+/// Who is signed in, their points, and the other accounts on this machine.
+#[derive(Default, Debug)]
+pub struct AccountScreen {
+    user_id: Option<UserId>,
+    name: Option<String>,
+    avatar: Option<Avatar>,
+    points: Option<u64>,
+    accounts: Vec<AccountRow>,
+    error: Option<String>,
+
+    log_out_button_state: button::State,
+    add_account_button_state: button::State,
+    done_button_state: button::State,
+}
+
+#[derive(Debug)]
+struct AccountRow {
+    user_id: UserId,
+    name: String,
+    switch_button_state: button::State,
+    remove_button_state: button::State,
+}
+
+#[derive(Debug, Clone)]
+pub enum AccountMessage {
+    LogOut,
+    Switch(UserId),
+    Remove(UserId),
+}
+
+impl AccountScreen {
+    pub fn load(&mut self, manager: &Manager) {
+        self.user_id = manager.user_id().unwrap_or_default();
+        let player = match self.user_id {
+            Some(user_id) => manager.stored_player(&user_id).unwrap_or_else(|err| {
+                warn!(?err, "Loading the current player.");
+                None
+            }),
+            None => None,
+        };
+        self.name = player.as_ref().map(|p| p.player().persona_name.clone());
+        self.avatar = player
+            .as_ref()
+            .filter(|p| !p.image_data().is_empty())
+            .map(|p| Avatar::from_image_data(p.image_data()));
+        self.points = manager.total_points().unwrap_or_else(|err| {
+            warn!(?err, "Loading total points.");
+            None
+        });
+
+        self.accounts = manager
+            .accounts()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|account| Some(account.user_id) != self.user_id)
+            .map(|account| AccountRow {
+                user_id: account.user_id,
+                name: match manager.stored_player(&account.user_id) {
+                    Ok(Some(player)) => player.player().persona_name.clone(),
+                    _ => account.user_id.to_string(),
+                },
+                switch_button_state: Default::default(),
+                remove_button_state: Default::default(),
+            })
+            .collect();
+    }
+
+    pub fn update(&mut self, manager: &Manager, message: AccountMessage) {
+        use AccountMessage::*;
+        let result = match message {
+            LogOut => manager.log_out(),
+            Switch(user_id) => manager.switch_account(&user_id),
+            Remove(user_id) => manager.remove_account(&user_id),
+        };
+        match result {
+            Ok(()) => {
+                self.load(manager);
+                self.error = None;
+            }
+            Err(err) => self.error = Some(format!("{:#}", err)),
+        }
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<'_, Message> {
+        let Self {
+            user_id,
+            name,
+            avatar,
+            points,
+            accounts,
+            error,
+            log_out_button_state,
+            add_account_button_state,
+            done_button_state,
+        } = self;
+
+        let mut column = Column::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(theme.title_text("Account"));
+        if let Some(error) = error {
+            column = column.push(theme.normal_text(&format!("⚠ {}", error)));
+        }
+
+        if let Some(user_id) = user_id {
+            let avatar: Element<_> = match avatar {
+                Some(avatar) => avatar.view(AVATAR_SIZE),
+                None => Space::new(Length::Units(AVATAR_SIZE), Length::Units(AVATAR_SIZE)).into(),
+            };
+            let points = match points {
+                Some(points) => format!("{} points", points),
+                None => "Points aren't known yet".to_string(),
+            };
+            column =
+                column.push(
+                    Row::new().spacing(RELAXED_PADDING / 2).push(avatar).push(
+                        Column::new()
+                            .push(theme.normal_text(
+                                name.as_deref().unwrap_or(&format!("Player {}", user_id)),
+                            ))
+                            .push(theme.normal_text(&points).size(16)),
+                    ),
+                );
+
+            column = column.push(theme.action_button(
+                ButtonView::Text("Log out"),
+                Message::Confirm(Confirmation::new(
+                    "Log out?",
+                    "This forgets the auth key, games and turn history for this account. \
+                     Saves on disk are kept.",
+                    "Log out",
+                    Message::Account(AccountMessage::LogOut),
+                )),
+                log_out_button_state,
+            ));
+        } else {
+            column = column.push(theme.normal_text("Not signed in."));
+        }
+
+        if !accounts.is_empty() {
+            column = column.push(theme.normal_text("Other accounts").size(24));
+        }
+        for account in accounts.iter_mut() {
+            column = column.push(
+                Row::new()
+                    .height(Length::Units(ROW_HEIGHT))
+                    .push(
+                        theme
+                            .normal_text(&account.name)
+                            .width(Length::Fill)
+                            .vertical_alignment(VerticalAlignment::Center),
+                    )
+                    .push(theme.action_button(
+                        ButtonView::Text("Switch"),
+                        Message::Account(AccountMessage::Switch(account.user_id)),
+                        &mut account.switch_button_state,
+                    ))
+                    .push(theme.action_button(
+                        ButtonView::Text("Remove"),
+                        Message::Confirm(Confirmation::new(
+                            &format!("Remove {}?", account.name),
+                            "This forgets its auth key. Its games and saves are kept in case it's \
+                             added again.",
+                            "Remove",
+                            Message::Account(AccountMessage::Remove(account.user_id)),
+                        )),
+                        &mut account.remove_button_state,
+                    )),
+            );
+        }
+
+        column
+            .push(theme.action_button(
+                ButtonView::Text("Add account"),
+                Message::SetScreen(Screen::AuthKeyInput),
+                add_account_button_state,
+            ))
+            .push(Space::new(Length::Fill, Length::Units(RELAXED_PADDING)))
+            .push(theme.action_button(
+                ButtonView::TextIcon("Done", done_icon(NORMAL_ICON_SIZE)),
+                Message::SetScreen(Screen::Games),
+                done_button_state,
+            ))
+            .into()
+    }
+}
@@ -1,36 +1,111 @@
-use iced::{button, Button, Element, HorizontalAlignment, Length, Row, Text, VerticalAlignment};
+use iced::{button, Button, Element, HorizontalAlignment, Length, Row, Text};
+use std::time::{Duration, Instant};
 
+use crate::ui::games_list::short_duration;
 use crate::ui::style::{
-    action_button, cog_icon, normal_text, steam_icon, ActionButtonStyle, ButtonView,
-    NORMAL_ICON_SIZE, ROW_HEIGHT,
+    cog_icon, steam_icon, ActionButtonStyle, ButtonView, Theme, NORMAL_ICON_SIZE, ROW_HEIGHT,
 };
-use crate::ui::Message;
+use crate::ui::{Message, Screen};
+use civfun_gmr::manager::{Event, Manager};
+
+/// Clicking refresh again within this long of the last click does nothing.
+const REFRESH_DEBOUNCE: Duration = Duration::from_secs(5);
 
 #[derive(Default, Debug, Clone)]
 pub struct Actions {
     start_button_state: button::State,
+    pause_button_state: button::State,
+    refresh_button_state: button::State,
+    activity_button_state: button::State,
+    stats_button_state: button::State,
+    pub paused: bool,
+    fetching: bool,
+    last_refresh: Option<Instant>,
+    refresh_requested: Option<Instant>,
 }
 
 impl Actions {
-    pub fn view(&mut self) -> Element<Message> {
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Event::UpdatedGames(_) = event {
+            self.last_refresh = Some(Instant::now());
+        }
+    }
+
+    /// Picks up whether a fetch is running. Called as often as the manager is processed.
+    pub fn poll(&mut self, manager: &Manager) {
+        self.fetching = manager.fetching();
+    }
+
+    /// Whether a refresh asked for at `now` should go ahead. It shouldn't while one is running
+    /// or straight after the last click.
+    pub fn request_refresh(&mut self, now: Instant) -> bool {
+        let recently = self
+            .refresh_requested
+            .is_some_and(|at| now.saturating_duration_since(at) < REFRESH_DEBOUNCE);
+        if self.fetching || recently {
+            return false;
+        }
+        self.refresh_requested = Some(now);
+        self.fetching = true;
+        true
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<Message> {
         // let start_button = Button::new(
         //     &mut self.start_button_state,
         //     button_row(Some(steam_icon(20)), Some("Play")),
         // )
         // .on_press(Message::PlayCiv)
         // .style(ActionButtonStyle);
-        let mut start_button = action_button(
+        let mut start_button = theme.action_button(
             ButtonView::TextIcon("Play", steam_icon(NORMAL_ICON_SIZE)),
             Message::PlayCiv,
             &mut self.start_button_state,
         );
 
-        let status = normal_text("testing").vertical_alignment(VerticalAlignment::Center);
+        let pause_button = theme.action_button(
+            ButtonView::Text(if self.paused { "Resume" } else { "Pause" }),
+            Message::TogglePause,
+            &mut self.pause_button_state,
+        );
+
+        let refresh_label = if self.fetching {
+            "Refreshing…".to_string()
+        } else {
+            match self.last_refresh.map(|at| at.elapsed()) {
+                Some(elapsed) if elapsed >= Duration::from_secs(60) => format!(
+                    "Refresh ({} ago)",
+                    short_duration(chrono::Duration::seconds(elapsed.as_secs() as i64))
+                ),
+                Some(_) => "Refresh (just now)".to_string(),
+                None => "Refresh".to_string(),
+            }
+        };
+        let refresh_button = theme.action_button(
+            ButtonView::Text(&refresh_label),
+            Message::RequestRefresh,
+            &mut self.refresh_button_state,
+        );
+
+        let activity_button = theme.action_button(
+            ButtonView::Text("Activity"),
+            Message::SetScreen(Screen::Activity),
+            &mut self.activity_button_state,
+        );
+
+        let stats_button = theme.action_button(
+            ButtonView::Text("Stats"),
+            Message::SetScreen(Screen::Stats),
+            &mut self.stats_button_state,
+        );
 
         Row::new()
             .height(Length::Units(ROW_HEIGHT))
             .push(start_button.width(Length::Shrink))
-            .push(status.width(Length::Fill))
+            .push(pause_button.width(Length::Shrink))
+            .push(refresh_button.width(Length::Shrink))
+            .push(activity_button.width(Length::Shrink))
+            .push(stats_button.width(Length::Shrink))
             .into()
     }
 }
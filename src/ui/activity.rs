@@ -0,0 +1,222 @@
+use iced::{
+    button, scrollable, text_input, Clipboard, Column, Element, Length, Row, Scrollable, Space,
+    TextInput,
+};
+use tracing::warn;
+
+use crate::ui::style::{done_icon, ButtonView, Theme, NORMAL_ICON_SIZE, RELAXED_PADDING};
+use crate::ui::{Message, Screen};
+use chrono::Local;
+use civfun_gmr::diagnostics;
+use civfun_gmr::event_log::{ActivityKind, LoggedEvent};
+use civfun_gmr::logging;
+use civfun_gmr::manager::Manager;
+
+/// Events and log lines each, before merging.
+const MAX_ENTRIES: usize = 200;
+
+/// A timeline of what civfun has been doing, from the event log and the log files, newest
+/// first.
+#[derive(Default, Debug)]
+pub struct ActivityScreen {
+    entries: Vec<LoggedEvent>,
+    /// None shows every kind.
+    kind: Option<ActivityKind>,
+    search: String,
+    status: Option<String>,
+
+    scroll_state: scrollable::State,
+    search_input_state: text_input::State,
+    all_button_state: button::State,
+    kind_button_states: [button::State; 4],
+    refresh_button_state: button::State,
+    copy_button_state: button::State,
+    done_button_state: button::State,
+}
+
+#[derive(Debug, Clone)]
+pub enum ActivityMessage {
+    Kind(Option<ActivityKind>),
+    SearchChanged(String),
+    Refresh,
+    CopyDiagnostics,
+}
+
+impl ActivityScreen {
+    pub fn load(&mut self, manager: &Manager) {
+        self.status = None;
+        let mut entries = manager.recent_events(MAX_ENTRIES).unwrap_or_else(|err| {
+            warn!(?err, "Loading recent events.");
+            self.status = Some(format!("⚠ {:#}", err));
+            vec![]
+        });
+        match logging::log_dir().and_then(|dir| logging::recent_problems(&dir, MAX_ENTRIES)) {
+            Ok(problems) => entries.extend(problems),
+            Err(err) => warn!(?err, "Reading the log file."),
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.at));
+        self.entries = entries;
+    }
+
+    pub fn update(
+        &mut self,
+        manager: &Manager,
+        message: ActivityMessage,
+        clipboard: &mut Clipboard,
+    ) {
+        use ActivityMessage::*;
+        match message {
+            Kind(kind) => self.kind = kind,
+            SearchChanged(s) => self.search = s,
+            Refresh => self.load(manager),
+            CopyDiagnostics => {
+                let visible: Vec<_> = self.visible().cloned().collect();
+                clipboard.write(diagnostics::summary(&visible));
+                self.status = Some(format!(
+                    "Copied {} entries, ready to paste into a bug report.",
+                    visible.len()
+                ));
+            }
+        }
+    }
+
+    fn visible(&self) -> impl Iterator<Item = &LoggedEvent> {
+        let search = self.search.trim().to_lowercase();
+        let kind = self.kind;
+        self.entries
+            .iter()
+            .filter(move |entry| matches(entry, kind, &search))
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<'_, Message> {
+        let Self {
+            entries,
+            kind,
+            search,
+            status,
+            scroll_state,
+            search_input_state,
+            all_button_state,
+            kind_button_states,
+            refresh_button_state,
+            copy_button_state,
+            done_button_state,
+        } = self;
+
+        let mut filters = Row::new().spacing(RELAXED_PADDING / 4).push(filter_button(
+            theme,
+            "All",
+            None,
+            *kind,
+            all_button_state,
+        ));
+        for (filter, state) in ActivityKind::ALL.iter().zip(kind_button_states.iter_mut()) {
+            filters = filters.push(filter_button(
+                theme,
+                &filter.to_string(),
+                Some(*filter),
+                *kind,
+                state,
+            ));
+        }
+
+        let search_text = search.trim().to_lowercase();
+        let search = TextInput::new(search_input_state, "Search", search, |s| {
+            Message::Activity(ActivityMessage::SearchChanged(s))
+        })
+        .padding(10);
+
+        let mut timeline = Column::new().spacing(4);
+        let mut shown = 0;
+        for entry in entries
+            .iter()
+            .filter(|entry| matches(entry, *kind, &search_text))
+        {
+            shown += 1;
+            let at = entry.at.with_timezone(&Local).format("%m-%d %H:%M");
+            timeline = timeline.push(
+                Row::new()
+                    .spacing(RELAXED_PADDING / 2)
+                    .push(
+                        theme
+                            .normal_text(&at.to_string())
+                            .size(16)
+                            .width(Length::Units(100)),
+                    )
+                    .push(
+                        theme
+                            .normal_text(&entry.description)
+                            .size(16)
+                            .width(Length::Fill),
+                    ),
+            );
+        }
+        if shown == 0 {
+            timeline = timeline.push(theme.normal_text("Nothing to show.").size(16));
+        }
+
+        let mut column = Column::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(theme.title_text("Activity"))
+            .push(filters)
+            .push(search);
+        if let Some(status) = status {
+            column = column.push(theme.normal_text(status).size(16));
+        }
+        column
+            .push(
+                Scrollable::new(scroll_state)
+                    .height(Length::Fill)
+                    .push(timeline),
+            )
+            .push(
+                Row::new()
+                    .spacing(RELAXED_PADDING / 2)
+                    .push(theme.action_button(
+                        ButtonView::Text("Refresh"),
+                        Message::Activity(ActivityMessage::Refresh),
+                        refresh_button_state,
+                    ))
+                    .push(theme.action_button(
+                        ButtonView::Text("Copy diagnostics"),
+                        Message::Activity(ActivityMessage::CopyDiagnostics),
+                        copy_button_state,
+                    ))
+                    .push(Space::new(Length::Fill, Length::Shrink))
+                    .push(theme.action_button(
+                        ButtonView::TextIcon("Done", done_icon(NORMAL_ICON_SIZE)),
+                        Message::SetScreen(Screen::Games),
+                        done_button_state,
+                    )),
+            )
+            .into()
+    }
+}
+
+/// `search` is lower case.
+fn matches(entry: &LoggedEvent, kind: Option<ActivityKind>, search: &str) -> bool {
+    kind.map_or(true, |kind| entry.kind == kind)
+        && entry.description.to_lowercase().contains(search)
+}
+
+/// The selected filter is shown in brackets, since buttons can't be toggled.
+fn filter_button<'a>(
+    theme: Theme,
+    label: &str,
+    kind: Option<ActivityKind>,
+    selected: Option<ActivityKind>,
+    state: &'a mut button::State,
+) -> Element<'a, Message> {
+    let label = if kind == selected {
+        format!("[{}]", label)
+    } else {
+        label.to_string()
+    };
+    theme
+        .action_button(
+            ButtonView::Text(&label),
+            Message::Activity(ActivityMessage::Kind(kind)),
+            state,
+        )
+        .into()
+}
@@ -0,0 +1,187 @@
+use iced::{
+    button, scrollable, Column, Element, Length, Row, Scrollable, Space, VerticalAlignment,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::ui::style::{ButtonView, Theme, RELAXED_PADDING, ROW_HEIGHT};
+use crate::ui::{Message, Screen};
+use civfun_gmr::api::GameId;
+use civfun_gmr::archive::{ArchiveEntry, ArchiveKind};
+use civfun_gmr::manager::Manager;
+
+/// Every archived save grouped by game, to find an old turn and put it back in the hotseat
+/// folder. Opened from settings.
+#[derive(Default, Debug)]
+pub struct ArchiveScreen {
+    /// Games with the newest archived save first.
+    games: Vec<(String, Vec<EntryRow>)>,
+    dir: Option<PathBuf>,
+    /// What the last restore did, or what went wrong.
+    message: Option<String>,
+
+    scroll_state: scrollable::State,
+    folder_button_state: button::State,
+    back_button_state: button::State,
+}
+
+#[derive(Debug)]
+struct EntryRow {
+    entry: ArchiveEntry,
+    reveal_button_state: button::State,
+    restore_button_state: button::State,
+}
+
+#[derive(Debug, Clone)]
+pub enum ArchiveMessage {
+    OpenFolder,
+    Reveal(PathBuf),
+    Restore(ArchiveEntry),
+}
+
+impl ArchiveScreen {
+    pub fn load(&mut self, manager: &Manager) {
+        self.message = None;
+        self.dir = manager.archive_dir().ok();
+        let entries = match manager.archived_saves() {
+            Ok(entries) => entries,
+            Err(err) => {
+                self.games = vec![];
+                self.message = Some(format!("⚠ {:#}", err));
+                return;
+            }
+        };
+        // Archived games can have dropped out of the games list, so the id will have to do.
+        let names: HashMap<GameId, String> = manager
+            .games()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|game| (game.game_id, game.name))
+            .collect();
+
+        self.games = vec![];
+        for entry in entries {
+            let name = names
+                .get(&entry.game_id)
+                .cloned()
+                .unwrap_or_else(|| format!("Game {}", entry.game_id));
+            let row = EntryRow {
+                entry,
+                reveal_button_state: Default::default(),
+                restore_button_state: Default::default(),
+            };
+            let game_id = row.entry.game_id;
+            match self
+                .games
+                .iter_mut()
+                .find(|(_, rows)| rows[0].entry.game_id == game_id)
+            {
+                Some((_, rows)) => rows.push(row),
+                None => self.games.push((name, vec![row])),
+            }
+        }
+    }
+
+    pub fn update(&mut self, manager: &Manager, message: ArchiveMessage) {
+        use ArchiveMessage::*;
+        let result = match message {
+            OpenFolder => match &self.dir {
+                Some(dir) => open::that(dir).map_err(anyhow::Error::from),
+                None => Err(anyhow::anyhow!("There's no save dir yet.")),
+            },
+            // The file managers can't be asked to highlight a file, so open its folder.
+            Reveal(path) => match path.parent() {
+                Some(dir) => open::that(dir).map_err(anyhow::Error::from),
+                None => Ok(()),
+            },
+            Restore(entry) => manager.restore_archived(&entry).map(|path| {
+                self.message = Some(format!(
+                    "Restored to {}. Load it from Civ V's hotseat games.",
+                    path.to_string_lossy()
+                ));
+            }),
+        };
+        if let Err(err) = result {
+            self.message = Some(format!("⚠ {:#}", err));
+        }
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<'_, Message> {
+        let Self {
+            games,
+            dir,
+            message,
+            scroll_state,
+            folder_button_state,
+            back_button_state,
+        } = self;
+
+        let mut column = Column::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(theme.title_text("Archived saves"));
+        if let Some(message) = message {
+            column = column.push(theme.normal_text(message).size(16));
+        }
+        if dir.is_some() {
+            column = column.push(theme.action_button(
+                ButtonView::Text("Open archive folder"),
+                Message::Archive(ArchiveMessage::OpenFolder),
+                folder_button_state,
+            ));
+        }
+        if games.is_empty() {
+            column = column.push(theme.normal_text("Nothing has been archived yet."));
+        }
+
+        for (name, rows) in games.iter_mut() {
+            column = column.push(theme.normal_text(name).size(24));
+            for row in rows.iter_mut() {
+                let kind = match row.entry.kind {
+                    ArchiveKind::Downloaded => "Downloaded",
+                    ArchiveKind::Uploaded => "Uploaded",
+                };
+                let text = format!(
+                    "{} {}: {}",
+                    row.entry.archived_at.format("%Y-%m-%d %H:%M"),
+                    kind,
+                    row.entry.original_name
+                );
+                column = column.push(
+                    Row::new()
+                        .height(Length::Units(ROW_HEIGHT))
+                        .spacing(RELAXED_PADDING / 2)
+                        .push(
+                            theme
+                                .normal_text(&text)
+                                .size(16)
+                                .width(Length::Fill)
+                                .vertical_alignment(VerticalAlignment::Center),
+                        )
+                        .push(theme.action_button(
+                            ButtonView::Text("Show"),
+                            Message::Archive(ArchiveMessage::Reveal(row.entry.path.clone())),
+                            &mut row.reveal_button_state,
+                        ))
+                        .push(theme.action_button(
+                            ButtonView::Text("Restore"),
+                            Message::Archive(ArchiveMessage::Restore(row.entry.clone())),
+                            &mut row.restore_button_state,
+                        )),
+                );
+            }
+        }
+
+        column = column
+            .push(Space::new(Length::Fill, Length::Units(RELAXED_PADDING)))
+            .push(theme.action_button(
+                ButtonView::Text("Back"),
+                Message::SetScreen(Screen::Settings),
+                back_button_state,
+            ));
+
+        Scrollable::new(scroll_state)
+            .width(Length::Fill)
+            .push(column)
+            .into()
+    }
+}
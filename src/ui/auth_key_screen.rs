@@ -1,25 +1,33 @@
 use iced::{
-    button, text_input, Align, Button, Clipboard, Color, Column, Command, Element,
-    HorizontalAlignment, Length, Row, Space, Text, TextInput, VerticalAlignment,
+    button, text_input, Clipboard, Color, Column, Command, Element, Length, Row, TextInput,
 };
-use tracing::error;
 
 use crate::ui::style::{
-    action_button, centered_column, normal_text, title_text, vertically_centered_content,
-    ButtonView, ROW_HEIGHT,
+    centered_column, vertically_centered_content, ButtonView, Theme, ROW_HEIGHT,
 };
-use crate::ui::{Message, Screen};
+use crate::ui::Message;
+use civfun_gmr::api::{validate_auth_key, AUTH_KEY_URL};
 
 #[derive(Default, Debug)]
 pub struct AuthKeyScreen {
     input_state: text_input::State,
     input_value: String,
+    /// The key is hidden unless asked for, in case someone is watching or streaming.
+    show_key: bool,
+    /// Why the key in the input can't be right, if it can't.
+    problem: Option<String>,
     button_state: button::State,
+    paste_button_state: button::State,
+    show_button_state: button::State,
+    open_gmr_button_state: button::State,
 }
 
 #[derive(Clone, Debug)]
 pub enum AuthKeyMessage {
     InputChanged(String),
+    Paste,
+    ToggleShow,
+    OpenGmr,
     Save,
 }
 
@@ -27,26 +35,49 @@ impl AuthKeyScreen {
     pub fn update(
         &mut self,
         message: AuthKeyMessage,
-        _clipboard: &mut Clipboard,
+        clipboard: &mut Clipboard,
     ) -> Command<Message> {
         use AuthKeyMessage::*;
         match message {
-            InputChanged(s) => {
-                self.input_value = s;
+            InputChanged(s) => self.set_input(s),
+            Paste => match clipboard.read() {
+                Some(s) => self.set_input(s),
+                None => self.problem = Some("There's nothing to paste.".to_string()),
+            },
+            ToggleShow => self.show_key = !self.show_key,
+            OpenGmr => {
+                if let Err(err) = open::that(AUTH_KEY_URL) {
+                    self.problem = Some(format!("Couldn't open {}: {}", AUTH_KEY_URL, err));
+                }
             }
             Save => {
                 let s = self.input_value.trim().to_string();
+                if let Err(err) = validate_auth_key(&s) {
+                    self.problem = Some(err.to_string());
+                    return Command::none();
+                }
                 return Command::perform(async { s }, Message::AuthKeySave);
             }
         }
         Command::none()
     }
 
-    pub fn view(&mut self) -> Element<AuthKeyMessage> {
-        let title = title_text("Authentication");
-        let message = normal_text("Please enter your Authentication Key below.");
+    /// Checks the key as it's typed. Nothing is said about an empty input until Save.
+    fn set_input(&mut self, s: String) {
+        self.input_value = s;
+        let key = self.input_value.trim();
+        self.problem = if key.is_empty() {
+            None
+        } else {
+            validate_auth_key(key).err().map(|err| err.to_string())
+        };
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<AuthKeyMessage> {
+        let title = theme.title_text("Authentication");
+        let message = theme.normal_text("Please enter your Authentication Key below.");
 
-        let input = TextInput::new(
+        let mut input = TextInput::new(
             &mut self.input_state,
             "",
             &self.input_value,
@@ -54,8 +85,11 @@ impl AuthKeyScreen {
         )
         .padding(10)
         .size(20);
+        if !self.show_key {
+            input = input.password();
+        }
 
-        let button = action_button(
+        let button = theme.action_button(
             ButtonView::Text("Save"),
             AuthKeyMessage::Save,
             &mut self.button_state,
@@ -67,8 +101,42 @@ impl AuthKeyScreen {
             .push(input)
             .push(button);
 
-        vertically_centered_content(centered_column().push(title).push(message).push(input_row))
-            .into()
+        let tools_row = Row::new()
+            .height(Length::Units(ROW_HEIGHT))
+            .push(theme.action_button(
+                ButtonView::Text("Paste"),
+                AuthKeyMessage::Paste,
+                &mut self.paste_button_state,
+            ))
+            .push(theme.action_button(
+                ButtonView::Text(if self.show_key { "Hide" } else { "Show" }),
+                AuthKeyMessage::ToggleShow,
+                &mut self.show_button_state,
+            ));
+
+        let mut column = centered_column()
+            .push(title)
+            .push(message)
+            .push(input_row)
+            .push(tools_row);
+        if let Some(problem) = &self.problem {
+            column = column.push(theme.normal_text(&format!("⚠ {}", problem)).size(16));
+        }
+        column = column.push(
+            Column::new()
+                .push(
+                    theme
+                        .normal_text("Your key is on the GMR download page once you've signed in.")
+                        .size(16),
+                )
+                .push(theme.action_button(
+                    ButtonView::Text("Get my key from GMR"),
+                    AuthKeyMessage::OpenGmr,
+                    &mut self.open_gmr_button_state,
+                )),
+        );
+
+        vertically_centered_content(column).into()
     }
 
     fn background_color(&self) -> Color {
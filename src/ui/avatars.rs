@@ -0,0 +1,139 @@
+use iced::{image, svg, Element, Image, Length, Svg};
+use std::collections::HashMap;
+
+use civfun_gmr::api::UserId;
+use civfun_gmr::manager::StoredPlayer;
+
+/// Cells along each side of a placeholder. The left half and middle are picked from the user
+/// id and mirrored onto the right.
+const IDENTICON_CELLS: u32 = 5;
+
+/// Avatar images by player, made once so iced doesn't have to rasterise new ones every frame.
+/// Players start with a placeholder made from their user id, which is swapped for their Steam
+/// avatar once it has been downloaded.
+#[derive(Default, Debug)]
+pub struct Avatars {
+    handles: HashMap<UserId, (Source, Avatar)>,
+}
+
+/// Placeholders are drawn as SVG, downloaded avatars are JPEG or PNG.
+#[derive(Debug, Clone)]
+pub enum Avatar {
+    Placeholder(svg::Handle),
+    Image(image::Handle),
+}
+
+impl Avatar {
+    pub fn from_image_data(image_data: &[u8]) -> Self {
+        Avatar::Image(image::Handle::from_memory(image_data.to_vec()))
+    }
+
+    /// A square `size` units across.
+    pub fn view<'a, M: 'a>(&self, size: u16) -> Element<'a, M> {
+        match self {
+            Avatar::Placeholder(handle) => Svg::new(handle.clone())
+                .width(Length::Units(size))
+                .height(Length::Units(size))
+                .into(),
+            Avatar::Image(handle) => Image::new(handle.clone())
+                .width(Length::Units(size))
+                .height(Length::Units(size))
+                .into(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Source {
+    Placeholder,
+    /// The Steam avatar from this URL, so a new one is only made when it changes.
+    Url(String),
+}
+
+impl Avatars {
+    /// Makes a placeholder for the player if there's nothing to show for them yet.
+    pub fn ensure(&mut self, user_id: &UserId) {
+        self.handles.entry(*user_id).or_insert_with(|| {
+            (
+                Source::Placeholder,
+                Avatar::Placeholder(svg::Handle::from_memory(identicon_svg(user_id))),
+            )
+        });
+    }
+
+    /// Swaps in the player's downloaded avatar, unless it's the one already shown.
+    pub fn update(&mut self, stored: &StoredPlayer) {
+        let user_id = stored.player().steam_id;
+        if stored.image_data().is_empty() {
+            self.ensure(&user_id);
+            return;
+        }
+        let source = Source::Url(stored.player().avatar_url.clone());
+        if matches!(self.handles.get(&user_id), Some((shown, _)) if *shown == source) {
+            return;
+        }
+        let avatar = Avatar::from_image_data(stored.image_data());
+        self.handles.insert(user_id, (source, avatar));
+    }
+
+    pub fn get(&self, user_id: &UserId) -> Option<&Avatar> {
+        self.handles.get(user_id).map(|(_, avatar)| avatar)
+    }
+}
+
+/// A symmetric pattern of squares in a colour, both picked from the user id so the same player
+/// always looks the same.
+fn identicon_svg(user_id: &UserId) -> Vec<u8> {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(user_id.to_string().as_bytes());
+    let hash = hasher.finalize();
+    let hue = (hash >> 16) % 360;
+    let half = (IDENTICON_CELLS + 1) / 2;
+    let size = 64 / IDENTICON_CELLS;
+
+    let mut cells = String::new();
+    for y in 0..IDENTICON_CELLS {
+        for x in 0..half {
+            if hash & (1 << (y * half + x)) == 0 {
+                continue;
+            }
+            for column in [x, IDENTICON_CELLS - 1 - x] {
+                cells.push_str(&format!(
+                    r#"<rect x="{}" y="{}" width="{}" height="{}"/>"#,
+                    column * size + 2,
+                    y * size + 2,
+                    size,
+                    size
+                ));
+                if column == IDENTICON_CELLS - 1 - column {
+                    break;
+                }
+            }
+        }
+    }
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64"><rect width="64" height="64" fill="{}"/><g fill="{}">{}</g></svg>"#,
+        hsl(hue, 0.3, 0.85),
+        hsl(hue, 0.55, 0.45),
+        cells
+    )
+    .into_bytes()
+}
+
+/// e.g. `#a1b2c3`. The SVG renderer doesn't understand `hsl()`.
+fn hsl(hue: u32, saturation: f32, lightness: f32) -> String {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let h = hue as f32 / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let byte = |c: f32| ((c + m) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(r), byte(g), byte(b))
+}
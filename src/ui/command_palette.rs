@@ -0,0 +1,178 @@
+use iced::{button, text_input, Column, Container, Element, Length, TextInput};
+
+use crate::ui::style::{ButtonView, PanelStyle, Theme, RELAXED_PADDING};
+use crate::ui::{Message, Screen};
+use civfun_gmr::api::{Game, UserId};
+use civfun_gmr::fuzzy;
+
+/// Results shown at once. Typing more narrows them down.
+const MAX_RESULTS: usize = 8;
+
+/// Ctrl+K (⌘K on macOS) brings up a search over everything the client can do, so it can be
+/// driven from the keyboard. Enter runs the best match.
+#[derive(Default, Debug)]
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    /// Built when opened, since the games they mention change.
+    commands: Vec<(String, Message)>,
+
+    input_state: text_input::State,
+    result_button_states: Vec<button::State>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PaletteMessage {
+    Toggle,
+    Close,
+    QueryChanged(String),
+    /// Runs the best match.
+    Submit,
+    /// Runs the match at this position in the results.
+    Run(usize),
+}
+
+impl CommandPalette {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Returns the message to run when a command is picked.
+    pub fn update(
+        &mut self,
+        message: PaletteMessage,
+        games: &[Game],
+        me: Option<UserId>,
+    ) -> Option<Message> {
+        match message {
+            PaletteMessage::Toggle if !self.open => {
+                self.open = true;
+                self.query.clear();
+                self.commands = commands(games, me);
+                self.input_state = text_input::State::focused();
+                None
+            }
+            PaletteMessage::Toggle | PaletteMessage::Close => {
+                self.open = false;
+                None
+            }
+            PaletteMessage::QueryChanged(query) => {
+                self.query = query;
+                None
+            }
+            PaletteMessage::Submit => self.run(0),
+            PaletteMessage::Run(index) => self.run(index),
+        }
+    }
+
+    fn results(&self) -> Vec<&(String, Message)> {
+        let mut results = fuzzy::filter(&self.query, &self.commands, |(label, _)| label);
+        results.truncate(MAX_RESULTS);
+        results
+    }
+
+    fn run(&mut self, index: usize) -> Option<Message> {
+        let message = self
+            .results()
+            .get(index)
+            .map(|(_, message)| message.clone());
+        if message.is_some() {
+            self.open = false;
+        }
+        message
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<'_, Message> {
+        let results: Vec<(usize, String)> = self
+            .results()
+            .into_iter()
+            .map(|(label, _)| label.clone())
+            .enumerate()
+            .collect();
+        let Self {
+            query,
+            input_state,
+            result_button_states,
+            ..
+        } = self;
+
+        let mut column = Column::new().spacing(4).push(
+            TextInput::new(input_state, "Type a command…", query, |s| {
+                Message::Palette(PaletteMessage::QueryChanged(s))
+            })
+            .on_submit(Message::Palette(PaletteMessage::Submit))
+            .padding(10),
+        );
+        if results.is_empty() {
+            column = column.push(theme.normal_text("Nothing matches.").size(16));
+        }
+        result_button_states.resize_with(results.len(), Default::default);
+        for ((index, label), state) in results.iter().zip(result_button_states.iter_mut()) {
+            column = column.push(
+                theme
+                    .action_button(
+                        ButtonView::Text(label),
+                        Message::Palette(PaletteMessage::Run(*index)),
+                        state,
+                    )
+                    .width(Length::Fill),
+            );
+        }
+
+        Container::new(column)
+            .width(Length::Fill)
+            .padding(RELAXED_PADDING / 2)
+            .style(PanelStyle(theme))
+            .into()
+    }
+}
+
+/// Everything the palette can do, with a few for each game.
+fn commands(games: &[Game], me: Option<UserId>) -> Vec<(String, Message)> {
+    let mut commands = vec![
+        ("Refresh games".to_string(), Message::RequestRefresh),
+        ("Play Civ V".to_string(), Message::PlayCiv),
+        ("Open save folder".to_string(), Message::OpenSaveDir),
+        (
+            "Open settings".to_string(),
+            Message::SetScreen(Screen::Settings),
+        ),
+        (
+            "Open account".to_string(),
+            Message::SetScreen(Screen::Account),
+        ),
+        (
+            "Open activity".to_string(),
+            Message::SetScreen(Screen::Activity),
+        ),
+        ("Open stats".to_string(), Message::SetScreen(Screen::Stats)),
+        (
+            "Browse archived saves".to_string(),
+            Message::SetScreen(Screen::Archive),
+        ),
+        ("Pause or resume".to_string(), Message::TogglePause),
+        ("Open logs".to_string(), Message::OpenLogs),
+    ];
+    for game in games {
+        if Some(game.current_turn.user_id) == me {
+            commands.push((
+                format!("Play {}", game.name),
+                Message::PlayTurn(game.game_id),
+            ));
+            commands.push((
+                format!("Download {}", game.name),
+                Message::Download(game.game_id),
+            ));
+            // The game's screen has the field for picking a save.
+            commands.push((
+                format!("Upload a save for {}", game.name),
+                Message::SetScreen(Screen::GameDetail(game.game_id)),
+            ));
+        }
+        commands.push((
+            format!("Open {}", game.name),
+            Message::SetScreen(Screen::GameDetail(game.game_id)),
+        ));
+    }
+    commands
+}
@@ -0,0 +1,86 @@
+use iced::{button, Element, Row};
+
+use crate::ui::style::{
+    centered_column, vertically_centered_content, ButtonView, Theme, RELAXED_PADDING,
+};
+use crate::ui::Message;
+
+/// Something that can't be undone, waiting for the user to say yes. Send it in
+/// `Message::Confirm` instead of the message that does it.
+#[derive(Debug, Clone)]
+pub struct Confirmation {
+    title: String,
+    /// What will be lost.
+    detail: String,
+    /// e.g. "Log out", rather than a plain "Yes".
+    confirm_label: String,
+    message: Box<Message>,
+}
+
+impl Confirmation {
+    pub fn new(title: &str, detail: &str, confirm_label: &str, message: Message) -> Self {
+        Self {
+            title: title.to_string(),
+            detail: detail.to_string(),
+            confirm_label: confirm_label.to_string(),
+            message: Box::new(message),
+        }
+    }
+}
+
+/// Covers the current screen while a `Confirmation` is waiting, so nothing else can be done
+/// until it's answered.
+#[derive(Default, Debug)]
+pub struct ConfirmDialog {
+    pending: Option<Confirmation>,
+    confirm_button_state: button::State,
+    cancel_button_state: button::State,
+}
+
+impl ConfirmDialog {
+    pub fn ask(&mut self, confirmation: Confirmation) {
+        self.pending = Some(confirmation);
+    }
+
+    /// The message to run now it's been confirmed.
+    pub fn confirmed(&mut self) -> Option<Message> {
+        self.pending
+            .take()
+            .map(|confirmation| *confirmation.message)
+    }
+
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<'_, Message> {
+        let confirmation = match &self.pending {
+            Some(confirmation) => confirmation,
+            None => return theme.normal_text("Nothing to confirm.").into(),
+        };
+        let buttons = Row::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(theme.action_button(
+                ButtonView::Text(&confirmation.confirm_label),
+                Message::Confirmed,
+                &mut self.confirm_button_state,
+            ))
+            .push(theme.action_button(
+                ButtonView::Text("Cancel"),
+                Message::CancelConfirm,
+                &mut self.cancel_button_state,
+            ));
+
+        vertically_centered_content(
+            centered_column()
+                .push(theme.title_text(&confirmation.title))
+                .push(theme.normal_text(&confirmation.detail))
+                .push(buttons),
+        )
+        .into()
+    }
+}
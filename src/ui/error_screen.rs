@@ -1,31 +1,110 @@
 use crate::ui::style::{
-    action_button, centered_column, normal_text, title_text, vertically_centered_content,
-    ButtonView, RELAXED_PADDING,
+    centered_column, vertically_centered_content, ButtonView, Theme, RELAXED_PADDING,
 };
 use crate::ui::{Message, Screen};
-use iced::{button, Align, Column, Container, Element, HorizontalAlignment, Length};
+use crate::VERSION;
+use civfun_gmr::api::GameId;
+use iced::{button, Column, Element, Row};
+
+/// What failed, so the error screen can try it again.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Retry {
+    FetchGames,
+    Download(GameId),
+    PlayTurn(GameId),
+    Upload(GameId),
+    LaunchGame,
+}
+
+impl Retry {
+    fn message(&self) -> Message {
+        match self {
+            Retry::FetchGames => Message::RequestRefresh,
+            Retry::Download(game_id) => Message::Download(*game_id),
+            Retry::PlayTurn(game_id) => Message::PlayTurn(*game_id),
+            Retry::Upload(game_id) => Message::RetryUpload(*game_id),
+            Retry::LaunchGame => Message::PlayCiv,
+        }
+    }
+}
+
+/// The lines of an error's chain after the first, which are the causes of it.
+pub fn causes(err: &anyhow::Error) -> Vec<String> {
+    err.chain().skip(1).map(|cause| cause.to_string()).collect()
+}
 
 #[derive(Debug, Default)]
 pub struct ErrorScreen {
     close_button_state: button::State,
+    retry_button_state: button::State,
+    copy_button_state: button::State,
+    logs_button_state: button::State,
 }
 
 impl ErrorScreen {
-    pub fn view(&mut self, text: &str, next: Screen) -> Element<Message> {
-        let title = title_text("Oh no!");
-        let message = normal_text(text);
-        let close_button = action_button(
+    pub fn view(
+        &mut self,
+        theme: Theme,
+        text: &str,
+        details: &[String],
+        retry: Option<&Retry>,
+        next: Screen,
+    ) -> Element<Message> {
+        let title = theme.title_text("Oh no!");
+        let message = theme.normal_text(text);
+        let mut causes = Column::new().spacing(4);
+        for detail in details {
+            causes = causes.push(
+                theme
+                    .normal_text(&format!("Caused by: {}", detail))
+                    .size(16),
+            );
+        }
+
+        let mut buttons = Row::new().spacing(RELAXED_PADDING / 2);
+        if let Some(retry) = retry {
+            buttons = buttons.push(theme.action_button(
+                ButtonView::Text("Try again"),
+                retry.message(),
+                &mut self.retry_button_state,
+            ));
+        }
+        buttons = buttons.push(theme.action_button(
             ButtonView::Text("Okay, thanks."),
             Message::SetScreen(next),
             &mut self.close_button_state,
-        );
+        ));
+
+        let help = Row::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(theme.action_button(
+                ButtonView::Text("Copy details"),
+                Message::CopyErrorDetails(report(text, details)),
+                &mut self.copy_button_state,
+            ))
+            .push(theme.action_button(
+                ButtonView::Text("Open logs"),
+                Message::OpenLogs,
+                &mut self.logs_button_state,
+            ));
 
         vertically_centered_content(
             centered_column()
                 .push(title)
                 .push(message)
-                .push(close_button),
+                .push(causes)
+                .push(buttons)
+                .push(help),
         )
         .into()
     }
 }
+
+/// Plain text for pasting into a bug report.
+fn report(text: &str, details: &[String]) -> String {
+    let mut report = format!("civfun v{}\n{}\n", VERSION, text);
+    for detail in details {
+        report.push_str(&format!("Caused by: {}\n", detail));
+    }
+    report
+}
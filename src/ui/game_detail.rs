@@ -0,0 +1,434 @@
+use chrono::{Local, Utc};
+use iced::{
+    button, scrollable, text_input, tooltip, Column, Container, Element, Length, Row, Scrollable,
+    Space, TextInput, Tooltip, VerticalAlignment,
+};
+use std::path::PathBuf;
+
+use crate::ui::games_list::{short_duration, GamesList};
+use crate::ui::style::{
+    player_colour, BarStyle, ButtonView, SegmentStyle, Theme, TooltipStyle, RELAXED_PADDING,
+    ROW_HEIGHT,
+};
+use crate::ui::{Message, Screen};
+use civfun_gmr::api::{Game, GameId, UserId};
+use civfun_gmr::archive::{ArchiveEntry, ArchiveKind};
+use civfun_gmr::config::GameSettings;
+use civfun_gmr::manager::Manager;
+use civfun_gmr::stats::{GameStats, TurnRecord};
+
+const AVATAR_SIZE: u16 = 30;
+/// Only the most recent turns fit in the graph.
+const GRAPH_TURNS: usize = 40;
+const GRAPH_HEIGHT: u16 = 80;
+/// Turns in the timeline, including the one being played.
+const TIMELINE_TURNS: usize = 30;
+const TIMELINE_HEIGHT: u16 = 24;
+
+/// Everything about one game, loaded when the screen is opened with `load()`.
+#[derive(Default, Debug)]
+pub struct GameDetail {
+    game: Option<Game>,
+    settings: GameSettings,
+    history: Vec<TurnRecord>,
+    stats: Option<GameStats>,
+    entries: Vec<ArchiveEntry>,
+    upload_path: String,
+    error: Option<String>,
+
+    scroll_state: scrollable::State,
+    upload_input_state: text_input::State,
+    back_button_state: button::State,
+    redownload_button_state: button::State,
+    upload_button_state: button::State,
+    mute_button_state: button::State,
+    archive_button_state: button::State,
+}
+
+#[derive(Debug, Clone)]
+pub enum GameDetailMessage {
+    Redownload,
+    UploadPathChanged(String),
+    Upload,
+    ToggleMute,
+    ToggleArchive,
+}
+
+impl GameDetail {
+    pub fn load(&mut self, manager: &Manager, game_id: &GameId) {
+        self.error = None;
+        let load = || -> anyhow::Result<_> {
+            let game = manager.games()?.into_iter().find(|g| &g.game_id == game_id);
+            Ok((
+                game,
+                manager.game_settings(game_id)?,
+                manager.turn_history(game_id)?,
+                manager.stats(game_id)?,
+            ))
+        };
+        match load() {
+            Ok((game, settings, history, stats)) => {
+                self.game = game;
+                self.settings = settings;
+                self.history = history;
+                self.stats = Some(stats);
+            }
+            Err(err) => self.error = Some(format!("{:#}", err)),
+        }
+        // There's no archive until a save dir is found, which is fine.
+        self.entries = manager.archive_entries(game_id).unwrap_or_default();
+        if self.upload_path.is_empty() {
+            if let Ok(save_dir) = manager.save_dir() {
+                self.upload_path = save_dir.to_string_lossy().to_string();
+            }
+        }
+    }
+
+    pub fn update(&mut self, manager: &Manager, message: GameDetailMessage) {
+        let game_id = match &self.game {
+            Some(game) => game.game_id,
+            None => return,
+        };
+        use GameDetailMessage::*;
+        let result = match message {
+            Redownload => manager.redownload(&game_id),
+            UploadPathChanged(s) => {
+                self.upload_path = s;
+                Ok(())
+            }
+            Upload => manager.upload_file(&game_id, &PathBuf::from(self.upload_path.trim())),
+            ToggleMute => manager
+                .update_game_settings(&game_id, |s| s.muted = !s.muted)
+                .map(|_| ()),
+            ToggleArchive => {
+                if self.settings.archived {
+                    manager.unarchive_game(&game_id)
+                } else {
+                    manager.archive_game(&game_id)
+                }
+            }
+        };
+        if let Err(err) = result {
+            self.error = Some(format!("{:#}", err));
+            return;
+        }
+        self.load(manager, &game_id);
+    }
+
+    pub fn view<'a>(&'a mut self, theme: Theme, games_list: &GamesList) -> Element<'a, Message> {
+        let Self {
+            game,
+            settings,
+            history,
+            stats,
+            entries,
+            upload_path,
+            error,
+            scroll_state,
+            upload_input_state,
+            back_button_state,
+            redownload_button_state,
+            upload_button_state,
+            mute_button_state,
+            archive_button_state,
+        } = self;
+
+        let back_button = theme.action_button(
+            ButtonView::Text("Back"),
+            Message::SetScreen(Screen::Games),
+            back_button_state,
+        );
+        let game = match game {
+            Some(game) => game,
+            None => {
+                return Column::new()
+                    .push(theme.normal_text("This game is gone."))
+                    .push(back_button)
+                    .into()
+            }
+        };
+
+        let mut column = Column::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(theme.title_text(&game.name).size(30));
+        if let Some(error) = error {
+            column = column.push(theme.normal_text(&format!("⚠ {}", error)));
+        }
+
+        let turn = &game.current_turn;
+        let mut timer = format!(
+            "Turn {}, {}'s turn for {}",
+            turn.number,
+            games_list.name(&turn.user_id),
+            short_duration(turn.elapsed())
+        );
+        if let Some(remaining) = turn.time_remaining() {
+            timer.push_str(&format!(", {} left", short_duration(remaining)));
+        }
+        column = column.push(theme.normal_text(&timer));
+        if game.looks_finished() && !settings.archived {
+            column = column.push(theme.normal_text(
+                "Nobody has a turn, so this game looks finished. Archive it to hide it.",
+            ));
+        }
+
+        column = column.push(heading(theme, "Players"));
+        let mut players = game.players.clone();
+        players.sort_by_key(|p| p.turn_order);
+        for player in players {
+            let player_stats = stats
+                .as_ref()
+                .and_then(|s| s.players.iter().find(|p| p.user_id == player.user_id));
+            let mut text = format!(
+                "{}. {}",
+                player.turn_order + 1,
+                games_list.name(&player.user_id)
+            );
+            if let Some(player_stats) = player_stats {
+                if let Some(average) = player_stats.average {
+                    text.push_str(&format!(", {} a turn", short_duration(average)));
+                }
+                if player_stats.skips > 0 {
+                    text.push_str(&format!(", skipped {}", player_stats.skips));
+                }
+            }
+            column = column.push(
+                Row::new()
+                    .spacing(RELAXED_PADDING / 2)
+                    .height(Length::Units(AVATAR_SIZE))
+                    .push(games_list.avatar(theme, &player.user_id, AVATAR_SIZE))
+                    .push(
+                        theme
+                            .normal_text(&text)
+                            .vertical_alignment(VerticalAlignment::Center),
+                    ),
+            );
+        }
+
+        column = column
+            .push(heading(theme, "Turn history"))
+            .push(turn_graph(theme, history))
+            .push(heading(theme, "Timeline"))
+            .push(turn_timeline(theme, game, history, games_list));
+
+        column = column.push(heading(theme, "Archived saves"));
+        if entries.is_empty() {
+            column = column.push(theme.normal_text("None yet.").size(16));
+        }
+        for entry in entries.iter().rev() {
+            let kind = match entry.kind {
+                ArchiveKind::Downloaded => "Downloaded",
+                ArchiveKind::Uploaded => "Uploaded",
+            };
+            column = column.push(
+                theme
+                    .normal_text(&format!(
+                        "{} {}: {}",
+                        entry.archived_at.format("%Y-%m-%d %H:%M"),
+                        kind,
+                        entry.original_name
+                    ))
+                    .size(16),
+            );
+        }
+
+        column = column
+            .push(heading(theme, "Actions"))
+            .push(
+                Row::new()
+                    .spacing(RELAXED_PADDING / 2)
+                    .push(theme.action_button(
+                        ButtonView::Text("Download again"),
+                        Message::GameDetail(GameDetailMessage::Redownload),
+                        redownload_button_state,
+                    ))
+                    .push(theme.action_button(
+                        ButtonView::Text(if settings.muted { "Unmute" } else { "Mute" }),
+                        Message::GameDetail(GameDetailMessage::ToggleMute),
+                        mute_button_state,
+                    ))
+                    .push(theme.action_button(
+                        ButtonView::Text(if settings.archived {
+                            "Unarchive"
+                        } else {
+                            "Archive"
+                        }),
+                        Message::GameDetail(GameDetailMessage::ToggleArchive),
+                        archive_button_state,
+                    )),
+            )
+            .push(theme.normal_text("Upload a save for this turn:").size(16))
+            .push(
+                Row::new()
+                    .height(Length::Units(ROW_HEIGHT))
+                    .push(
+                        TextInput::new(upload_input_state, "", upload_path, |s| {
+                            Message::GameDetail(GameDetailMessage::UploadPathChanged(s))
+                        })
+                        .padding(10),
+                    )
+                    .push(theme.action_button(
+                        ButtonView::Text("Upload"),
+                        Message::GameDetail(GameDetailMessage::Upload),
+                        upload_button_state,
+                    )),
+            )
+            .push(Space::new(Length::Fill, Length::Units(RELAXED_PADDING)))
+            .push(back_button);
+
+        Scrollable::new(scroll_state)
+            .width(Length::Fill)
+            .push(column)
+            .into()
+    }
+}
+
+fn heading<'a>(theme: Theme, s: &str) -> Element<'a, Message> {
+    theme.normal_text(s).size(24).into()
+}
+
+/// The most recent turns end to end, each as wide as it took and in its player's colour, to show
+/// who the game spends its time waiting on. Hovering a turn says who, when and how long.
+fn turn_timeline<'a>(
+    theme: Theme,
+    game: &Game,
+    history: &[TurnRecord],
+    games_list: &GamesList,
+) -> Element<'a, Message> {
+    let turns = &history[history.len().saturating_sub(TIMELINE_TURNS)..];
+    if turns.is_empty() {
+        return theme.normal_text("No turns recorded yet.").size(16).into();
+    }
+    let now = Utc::now();
+    // The turn being played so far counts too, it's often the one holding things up.
+    let minutes: Vec<i64> = turns
+        .iter()
+        .map(|turn| {
+            turn.duration()
+                .unwrap_or_else(|| now - turn.started)
+                .num_minutes()
+                .max(1)
+        })
+        .collect();
+    let total: i64 = minutes.iter().sum();
+    let colour = |user_id: &UserId| {
+        let index = game
+            .players
+            .iter()
+            .find(|p| &p.user_id == user_id)
+            .map_or(game.players.len(), |p| p.turn_order as usize);
+        player_colour(index)
+    };
+
+    let mut timeline = Row::new().height(Length::Units(TIMELINE_HEIGHT));
+    for (turn, minutes) in turns.iter().zip(minutes) {
+        let portion = (minutes * 1000 / total).max(1) as u16;
+        let mut text = format!(
+            "{}, turn {}\n{}, ",
+            games_list.name(&turn.user_id),
+            turn.number,
+            turn.started.with_timezone(&Local).format("%a %e %b %H:%M")
+        );
+        match turn.duration() {
+            Some(duration) => text.push_str(&format!("took {}", short_duration(duration))),
+            None => text.push_str(&format!(
+                "playing for {}",
+                short_duration(now - turn.started)
+            )),
+        }
+        if turn.was_skipped() {
+            text.push_str(", skipped");
+        }
+        let segment = Container::new(Space::new(Length::Fill, Length::Fill))
+            .width(Length::FillPortion(portion))
+            .height(Length::Fill)
+            .style(SegmentStyle {
+                theme,
+                colour: colour(&turn.user_id),
+                skipped: turn.was_skipped(),
+            });
+        timeline = timeline.push(
+            Tooltip::new(segment, text, tooltip::Position::Bottom)
+                .size(16)
+                .padding(6)
+                .style(TooltipStyle(theme)),
+        );
+    }
+
+    let first = turns[0].started.with_timezone(&Local);
+    let axis = Row::new()
+        .push(
+            theme
+                .normal_text(&first.format("%e %b").to_string())
+                .size(14)
+                .width(Length::Fill),
+        )
+        .push(theme.normal_text("Now").size(14));
+
+    let mut players = game.players.clone();
+    players.sort_by_key(|p| p.turn_order);
+    let mut legend = Row::new().spacing(RELAXED_PADDING / 2);
+    for player in players {
+        legend = legend.push(
+            Row::new()
+                .spacing(4)
+                .push(
+                    Container::new(Space::new(Length::Fill, Length::Fill))
+                        .width(Length::Units(12))
+                        .height(Length::Units(12))
+                        .style(SegmentStyle {
+                            theme,
+                            colour: colour(&player.user_id),
+                            skipped: false,
+                        }),
+                )
+                .push(
+                    theme
+                        .normal_text(&games_list.name(&player.user_id))
+                        .size(14),
+                ),
+        );
+    }
+
+    Column::new()
+        .spacing(4)
+        .push(timeline)
+        .push(axis)
+        .push(legend)
+        .into()
+}
+
+/// A bar for each recent finished turn, as tall as the turn took. Skipped turns are red.
+fn turn_graph<'a>(theme: Theme, history: &[TurnRecord]) -> Element<'a, Message> {
+    let finished: Vec<_> = history.iter().filter(|t| t.duration().is_some()).collect();
+    let turns = &finished[finished.len().saturating_sub(GRAPH_TURNS)..];
+    let longest = turns
+        .iter()
+        .filter_map(|t| t.duration())
+        .map(|d| d.num_minutes())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    if turns.is_empty() {
+        return theme.normal_text("No finished turns yet.").size(16).into();
+    }
+
+    let mut row = Row::new()
+        .spacing(2)
+        .height(Length::Units(GRAPH_HEIGHT))
+        .align_items(iced::Align::End);
+    for turn in turns.iter() {
+        let minutes = turn.duration().map(|d| d.num_minutes()).unwrap_or(0);
+        let height = (minutes * GRAPH_HEIGHT as i64 / longest).max(1) as u16;
+        row = row.push(
+            Container::new(Space::new(Length::Fill, Length::Fill))
+                .width(Length::Units(6))
+                .height(Length::Units(height))
+                .style(BarStyle {
+                    theme,
+                    highlight: turn.was_skipped(),
+                }),
+        );
+    }
+    row.into()
+}
@@ -1,19 +1,265 @@
-use iced::{Column, Element, Length, Row, Text};
+use chrono::{DateTime, Duration, Utc};
+use iced::{
+    button, scrollable, tooltip, Button, Checkbox, Column, Container, Element, Length, ProgressBar,
+    Rectangle, Row, Scrollable, Size, Space, Text, Tooltip, VerticalAlignment,
+};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::time::Instant;
+use tracing::warn;
 
-use crate::ui::Message;
-use civfun_gmr::api::Game;
+use crate::ui::avatars::Avatars;
+use crate::ui::style::{
+    bell_icon, bell_slash_icon, ButtonView, Theme, NORMAL_ICON_SIZE, RELAXED_PADDING,
+};
+use crate::ui::style::{ActionButtonStyle, BadgeStyle, TooltipStyle};
+use crate::ui::{Message, Screen};
+use civfun_gmr::api::{Game, GameId, Urgency, UserId};
+use civfun_gmr::config::Config;
+use civfun_gmr::manager::{Event, KnownPlayer, Manager, TransferState};
+use civfun_gmr::progress::TransferProgress;
+
+const AVATAR_SIZE: u16 = 50;
+const SMALL_AVATAR_SIZE: u16 = 20;
+/// Every game gets the same height, so rows scrolled out of view can be left as blank space
+/// without the scrollbar jumping about. Fits the action buttons and a progress bar.
+const GAME_ROW_HEIGHT: u16 = 110;
+const GAME_ROW_SPACING: u16 = RELAXED_PADDING / 2;
+/// Rows built either side of the ones in view, so nothing blank shows while scrolling.
+const OVERSCAN_ROWS: usize = 2;
+
+#[derive(Default, Debug)]
+pub struct GamesList {
+    me: Option<UserId>,
+    rows: Vec<GameRow>,
+    names: HashMap<UserId, String>,
+    avatars: Avatars,
+    /// Shown when hovering over a player's avatar.
+    profiles: HashMap<UserId, String>,
+    /// GMR's time when the countdowns were last worked out, see `tick()`. None until the
+    /// first refresh.
+    now: Option<DateTime<Utc>>,
+    /// Transfers that have started since the app did.
+    progress: HashMap<GameId, TransferProgress>,
+    /// When the games were last fetched, if that's long enough ago that the turns shown might
+    /// not be current.
+    stale_since: Option<DateTime<Utc>>,
+    /// Ticked games, for the bulk actions.
+    selected: HashSet<GameId>,
+    /// For the sort and filter controls.
+    config: Config,
+    /// The window's height in UI units, which is a little more than the list gets. Zero until
+    /// it's known, which builds every row.
+    viewport_height: u32,
+    sort_button_state: button::State,
+    find_games_button_state: button::State,
+    bulk_button_states: [button::State; 3],
+    clear_selection_button_state: button::State,
+    scroll_state: scrollable::State,
+}
+
+/// Done to every selected game at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BulkAction {
+    Mute,
+    Archive,
+    Redownload,
+}
+
+impl BulkAction {
+    pub const ALL: [BulkAction; 3] = [
+        BulkAction::Mute,
+        BulkAction::Archive,
+        BulkAction::Redownload,
+    ];
+}
+
+impl Display for BulkAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            BulkAction::Mute => "Mute",
+            BulkAction::Archive => "Archive",
+            BulkAction::Redownload => "Download again",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug)]
+struct GameRow {
+    game: Game,
+    transfer: TransferState,
+    /// Civ V was started for this turn and the save hasn't turned up yet.
+    awaiting_save: bool,
+    /// From the game's settings, for the bell.
+    muted: bool,
+    open_button_state: button::State,
+    mute_button_state: button::State,
+    buttons: ActionButtons,
+}
 
 #[derive(Default, Debug)]
-pub struct GamesList {}
+struct ActionButtons {
+    play: button::State,
+    download: button::State,
+    upload: button::State,
+    open_folder: button::State,
+}
 
 impl GamesList {
-    pub fn view(&mut self, games: &[Game]) -> Element<Message> {
-        let mut column = Column::new();
-        for game in games {
-            let el = Self::game(game.clone());
-            column = column.push(el)
+    /// Picks up games, transfer states and players from the manager. Button states are kept for
+    /// games that are still there.
+    pub fn refresh(&mut self, manager: &Manager, games: &[Game]) {
+        self.me = manager.user_id().unwrap_or_default();
+        self.now = Some(manager.server_now());
+        self.config = manager.config().unwrap_or_default();
+
+        let mut old: HashMap<_, _> = self
+            .rows
+            .drain(..)
+            .map(|row| (row.game.game_id, row))
+            .collect();
+        self.rows = games
+            .iter()
+            .map(|game| {
+                let transfer = manager.transfer_state(&game.game_id);
+                let awaiting_save = manager.awaiting_save(&game.game_id);
+                let muted = match manager.game_settings(&game.game_id) {
+                    Ok(settings) => settings.muted,
+                    Err(err) => {
+                        warn!(?err, "Loading game settings.");
+                        false
+                    }
+                };
+                match old.remove(&game.game_id) {
+                    Some(row) => GameRow {
+                        game: game.clone(),
+                        transfer,
+                        awaiting_save,
+                        muted,
+                        ..row
+                    },
+                    None => GameRow {
+                        game: game.clone(),
+                        transfer,
+                        awaiting_save,
+                        muted,
+                        open_button_state: Default::default(),
+                        mute_button_state: Default::default(),
+                        buttons: Default::default(),
+                    },
+                }
+            })
+            .collect();
+        let rows = &self.rows;
+        self.selected
+            .retain(|game_id| rows.iter().any(|row| &row.game.game_id == game_id));
+
+        // Something to show straight away, even for players who haven't been fetched yet.
+        for player in games.iter().flat_map(|game| &game.players) {
+            self.avatars.ensure(&player.user_id);
+        }
+
+        let players = match manager.players() {
+            Ok(players) => players,
+            Err(err) => {
+                warn!(?err, "Loading players.");
+                return;
+            }
+        };
+        for player in players {
+            self.profiles
+                .insert(player.user_id, profile(&player, games));
+            let info = match player.info {
+                Some(info) => info,
+                None => continue,
+            };
+            self.names
+                .insert(player.user_id, info.player().persona_name.clone());
+            self.avatars.update(&info);
+        }
+    }
+
+    pub fn select(&mut self, game_id: GameId, on: bool) {
+        if on {
+            self.selected.insert(game_id);
+        } else {
+            self.selected.remove(&game_id);
+        }
+    }
+
+    /// The selected games, clearing the selection.
+    pub fn take_selected(&mut self) -> Vec<GameId> {
+        self.selected.drain().collect()
+    }
+
+    pub fn set_viewport_height(&mut self, height: u32) {
+        self.viewport_height = height;
+    }
+
+    /// The rows that could be on screen going by how far the list is scrolled.
+    fn visible_rows(&self) -> Range<usize> {
+        let count = self.rows.len();
+        if self.viewport_height == 0 {
+            return 0..count;
+        }
+        let pitch = (GAME_ROW_HEIGHT + GAME_ROW_SPACING) as u32;
+        let content_height = (count as u32 * pitch).saturating_sub(GAME_ROW_SPACING as u32);
+        let offset = self.scroll_state.offset(
+            Rectangle::with_size(Size::new(0.0, self.viewport_height as f32)),
+            Rectangle::with_size(Size::new(0.0, content_height as f32)),
+        );
+        let first = (offset / pitch) as usize;
+        let last = ((offset + self.viewport_height) / pitch) as usize + 1;
+        first.saturating_sub(OVERSCAN_ROWS)..(last + OVERSCAN_ROWS).min(count)
+    }
+
+    /// Moves the countdowns on without reloading anything. Called every minute.
+    pub fn tick(&mut self, now: DateTime<Utc>) {
+        self.now = Some(now);
+    }
+
+    pub fn set_stale_since(&mut self, stale_since: Option<DateTime<Utc>>) {
+        self.stale_since = stale_since;
+    }
+
+    /// Keeps track of transfer progress, and swaps in avatars as they arrive.
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::UpdatedPlayer(stored) => {
+                let player = stored.player();
+                self.names
+                    .insert(player.steam_id, player.persona_name.clone());
+                self.avatars.update(stored);
+            }
+            Event::DownloadStarted(game_id) | Event::UploadStarted(game_id) => {
+                self.progress
+                    .insert(*game_id, TransferProgress::new(Instant::now()));
+            }
+            Event::DownloadProgress {
+                game_id,
+                progress,
+                bytes,
+            }
+            | Event::UploadProgress {
+                game_id,
+                progress,
+                bytes,
+            } => {
+                self.progress
+                    .entry(*game_id)
+                    .or_insert_with(|| TransferProgress::new(Instant::now()))
+                    .update(progress.as_ref(), *bytes);
+            }
+            Event::DownloadComplete { game_id, .. }
+            | Event::DownloadFailed { game_id, .. }
+            | Event::UploadComplete(game_id)
+            | Event::UploadFailed { game_id, .. } => {
+                self.progress.remove(game_id);
+            }
+            _ => {}
         }
-        column.into()
     }
 
     /*
@@ -23,25 +269,403 @@ impl GamesList {
     | [     ] | [ ] [ ] [ ] [ ]      |            |
     +------+-------------------------+------------|
      */
-    fn game(game: Game) -> Element<'static, Message> {
-        Row::new()
-            .push(Self::avatar(game.clone()))
-            .push(Self::title_and_players(game.clone()))
-            .push(Self::actions(game.clone()))
+    pub fn view(&mut self, theme: Theme) -> Element<Message> {
+        let visible = self.visible_rows();
+        let Self {
+            me,
+            rows,
+            names,
+            avatars,
+            profiles,
+            now,
+            progress,
+            stale_since,
+            selected,
+            config,
+            viewport_height: _,
+            sort_button_state,
+            find_games_button_state,
+            bulk_button_states,
+            clear_selection_button_state,
+            scroll_state,
+        } = self;
+        let now = now.unwrap_or_else(Utc::now);
+        // Times here are the app's, not GMR's, so compare with the local clock.
+        let stale = stale_since.map(|at| {
+            format!(
+                "⚠ Last updated {} ago",
+                short_duration(Utc::now().signed_duration_since(at))
+            )
+        });
+
+        let controls = Row::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(theme.action_button(
+                ButtonView::Text(&format!("Sort: {}", config.games_sort)),
+                Message::SortGames,
+                sort_button_state,
+            ))
+            .push(theme.labelled(
+                Checkbox::new(config.my_turn_only, "", Message::MyTurnOnly),
+                "My turn",
+            ))
+            .push(theme.labelled(
+                Checkbox::new(config.hide_finished, "", Message::HideFinished),
+                "Hide finished",
+            ))
+            .push(Space::new(Length::Fill, Length::Shrink))
+            .push(theme.action_button(
+                ButtonView::Text("Find or create games"),
+                Message::OpenGmr,
+                find_games_button_state,
+            ));
+
+        let mut column = Column::new().spacing(GAME_ROW_SPACING);
+        for (i, row) in rows.iter_mut().enumerate() {
+            if !visible.contains(&i) {
+                column = column.push(Space::new(Length::Fill, Length::Units(GAME_ROW_HEIGHT)));
+                continue;
+            }
+            let game_id = row.game.game_id;
+            let progress = match progress.get(&row.game.game_id) {
+                Some(progress) if row.transfer.is_active() => Some(Self::progress(theme, progress)),
+                _ => None,
+            };
+            let mut game_column = Column::new().height(Length::Units(GAME_ROW_HEIGHT)).push(
+                Row::new()
+                    .spacing(RELAXED_PADDING / 2)
+                    .push(Checkbox::new(
+                        selected.contains(&row.game.game_id),
+                        "",
+                        move |on| Message::SelectGame(game_id, on),
+                    ))
+                    .push(avatar(
+                        theme,
+                        avatars,
+                        profiles,
+                        &row.game.current_turn.user_id,
+                        AVATAR_SIZE,
+                    ))
+                    .push(
+                        Button::new(
+                            &mut row.open_button_state,
+                            Self::title_and_players(
+                                theme, &row.game, now, me, names, avatars, profiles,
+                            ),
+                        )
+                        .width(Length::Fill)
+                        .on_press(Message::SetScreen(Screen::GameDetail(row.game.game_id)))
+                        .style(ActionButtonStyle(theme)),
+                    )
+                    .push(
+                        Tooltip::new(
+                            theme.action_button(
+                                ButtonView::Icon(if row.muted {
+                                    bell_slash_icon(NORMAL_ICON_SIZE)
+                                } else {
+                                    bell_icon(NORMAL_ICON_SIZE)
+                                }),
+                                Message::MuteGame(game_id, !row.muted),
+                                &mut row.mute_button_state,
+                            ),
+                            if row.muted {
+                                "Notifications are off for this game"
+                            } else {
+                                "Turn off notifications for this game"
+                            },
+                            tooltip::Position::Bottom,
+                        )
+                        .size(16)
+                        .padding(6)
+                        .style(TooltipStyle(theme)),
+                    )
+                    .push(Self::actions(
+                        theme,
+                        &row.game,
+                        row.transfer,
+                        row.awaiting_save,
+                        &mut row.buttons,
+                        me,
+                    )),
+            );
+            if let Some(progress) = progress {
+                game_column = game_column.push(progress);
+            } else if let Some(stale) = &stale {
+                game_column = game_column.push(theme.normal_text(stale).size(14));
+            }
+            column = column.push(game_column);
+        }
+        let mut layout = Column::new().spacing(RELAXED_PADDING / 2).push(controls);
+        if !selected.is_empty() {
+            let mut bar = Row::new()
+                .spacing(RELAXED_PADDING / 2)
+                .push(theme.normal_text(&format!("{} selected", selected.len())));
+            for (action, state) in BulkAction::ALL.iter().zip(bulk_button_states.iter_mut()) {
+                bar = bar.push(theme.action_button(
+                    ButtonView::Text(&action.to_string()),
+                    Message::Bulk(*action),
+                    state,
+                ));
+            }
+            layout = layout.push(bar.push(theme.action_button(
+                ButtonView::Text("Clear"),
+                Message::ClearSelection,
+                clear_selection_button_state,
+            )));
+        }
+        layout
+            .push(
+                Scrollable::new(scroll_state)
+                    .width(Length::Fill)
+                    .push(column),
+            )
             .into()
     }
 
-    fn avatar(info: Game) -> Element<'static, Message> {
-        Text::new("AVATAR").width(Length::Units(50)).into()
-    }
-    fn title_and_players(game: Game) -> Element<'static, Message> {
+    fn title_and_players<'a>(
+        theme: Theme,
+        game: &Game,
+        now: DateTime<Utc>,
+        me: &Option<UserId>,
+        names: &HashMap<UserId, String>,
+        avatars: &Avatars,
+        profiles: &HashMap<UserId, String>,
+    ) -> Element<'a, Message> {
+        let turn = &game.current_turn;
+        let mut status = if Some(turn.user_id) == *me {
+            "Your turn".to_string()
+        } else {
+            format!("Waiting on {}", name(names, &turn.user_id))
+        };
+        status.push_str(&format!(", {} ago", short_duration(turn.elapsed_at(now))));
+        let mut status_row = Row::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(theme.normal_text(&status).size(16));
+        if let Some(remaining) = turn.time_remaining_at(now) {
+            status_row = status_row.push(countdown(remaining));
+        }
+
+        let mut players = game.players.clone();
+        players.sort_by_key(|p| p.turn_order);
+        let mut players_row = Row::new().spacing(2);
+        for player in players {
+            players_row = players_row.push(avatar(
+                theme,
+                avatars,
+                profiles,
+                &player.user_id,
+                SMALL_AVATAR_SIZE,
+            ));
+        }
+
         Column::new()
-            .push(Text::new(game.name))
-            .push(Text::new("PLAYERS PLAYER PLAYERS"))
+            .push(theme.normal_text(&game.name))
+            .push(status_row)
+            .push(players_row)
             .width(Length::Fill)
             .into()
     }
-    fn actions(info: Game) -> Element<'static, Message> {
-        Text::new("ACTIONS").into()
+
+    fn progress<'a>(theme: Theme, progress: &TransferProgress) -> Element<'a, Message> {
+        Row::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(
+                ProgressBar::new(0.0..=1.0, progress.fraction.unwrap_or(0.0))
+                    .height(Length::Units(16)),
+            )
+            .push(
+                theme
+                    .normal_text(&progress.describe_at(Instant::now()))
+                    .size(16),
+            )
+            .into()
+    }
+
+    fn actions<'a>(
+        theme: Theme,
+        game: &Game,
+        transfer: TransferState,
+        awaiting_save: bool,
+        buttons: &'a mut ActionButtons,
+        me: &Option<UserId>,
+    ) -> Element<'a, Message> {
+        let game_id = game.game_id;
+        let mut column = Column::new().spacing(2);
+        if Some(game.current_turn.user_id) != *me {
+            return column.into();
+        }
+        match transfer {
+            TransferState::Idle => {
+                column = column.push(
+                    Row::new()
+                        .spacing(2)
+                        .push(theme.action_button(
+                            ButtonView::Text("Play"),
+                            Message::PlayTurn(game_id),
+                            &mut buttons.play,
+                        ))
+                        .push(theme.action_button(
+                            ButtonView::Text("Download"),
+                            Message::Download(game_id),
+                            &mut buttons.download,
+                        )),
+                );
+            }
+            TransferState::Downloaded if awaiting_save => {
+                column = column.push(
+                    theme
+                        .normal_text("Awaiting your save")
+                        .size(16)
+                        .vertical_alignment(VerticalAlignment::Center),
+                );
+            }
+            TransferState::Downloaded => {
+                column = column.push(theme.action_button(
+                    ButtonView::Text("Play"),
+                    Message::PlayTurn(game_id),
+                    &mut buttons.play,
+                ));
+            }
+            TransferState::DownloadFailed => {
+                column = column.push(theme.action_button(
+                    ButtonView::Text("Retry"),
+                    Message::Download(game_id),
+                    &mut buttons.download,
+                ));
+            }
+            TransferState::UploadAwaitingApproval => {
+                column = column.push(theme.action_button(
+                    ButtonView::Text("Upload"),
+                    Message::Upload(game_id),
+                    &mut buttons.upload,
+                ));
+            }
+            state => {
+                column = column.push(
+                    theme
+                        .normal_text(transfer_text(state))
+                        .size(16)
+                        .vertical_alignment(VerticalAlignment::Center),
+                );
+            }
+        }
+        column
+            .push(theme.action_button(
+                ButtonView::Text("Folder"),
+                Message::OpenSaveDir,
+                &mut buttons.open_folder,
+            ))
+            .into()
+    }
+}
+
+fn transfer_text(state: TransferState) -> &'static str {
+    match state {
+        TransferState::Idle => "",
+        TransferState::Downloading => "Downloading",
+        TransferState::Downloaded => "Ready to play",
+        TransferState::DownloadFailed => "Download failed",
+        TransferState::UploadAwaitingApproval => "Waiting for approval",
+        TransferState::UploadQueued => "Waiting to upload",
+        TransferState::Uploading => "Uploading",
+        TransferState::UploadComplete => "Uploaded",
+        TransferState::UploadFailed => "Upload failed",
+    }
+}
+
+impl GamesList {
+    /// The player's Steam name, once it's known.
+    pub fn name(&self, user_id: &UserId) -> String {
+        name(&self.names, user_id)
+    }
+
+    pub fn avatar<'a>(&self, theme: Theme, user_id: &UserId, size: u16) -> Element<'a, Message> {
+        avatar(theme, &self.avatars, &self.profiles, user_id, size)
+    }
+}
+
+fn name(names: &HashMap<UserId, String>, user_id: &UserId) -> String {
+    names
+        .get(user_id)
+        .cloned()
+        .unwrap_or_else(|| format!("player {}", user_id))
+}
+
+/// A blank space the same size for players that aren't known at all. Hovering shows the
+/// player's profile, once there is one.
+fn avatar<'a>(
+    theme: Theme,
+    avatars: &Avatars,
+    profiles: &HashMap<UserId, String>,
+    user_id: &UserId,
+    size: u16,
+) -> Element<'a, Message> {
+    let image: Element<_> = match avatars.get(user_id) {
+        Some(avatar) => avatar.view(size),
+        None => Space::new(Length::Units(size), Length::Units(size)).into(),
+    };
+    match profiles.get(user_id) {
+        Some(profile) => Tooltip::new(image, profile, tooltip::Position::Bottom)
+            .size(16)
+            .padding(6)
+            .style(TooltipStyle(theme))
+            .into(),
+        None => image,
+    }
+}
+
+/// e.g. "Alice\nOnline, as of 2h ago\nTakes 3h 20m a turn\nGames together: Game A, Game B".
+fn profile(player: &KnownPlayer, games: &[Game]) -> String {
+    let mut lines = vec![];
+    match &player.info {
+        Some(info) => {
+            lines.push(info.player().persona_name.clone());
+            let age = info.last_downloaded().elapsed().unwrap_or_default();
+            let age = Duration::from_std(age).unwrap_or_else(|_| Duration::zero());
+            lines.push(format!(
+                "{}, as of {} ago",
+                info.player().online_state(),
+                short_duration(age)
+            ));
+        }
+        None => lines.push(format!("Player {}", player.user_id)),
+    }
+    if let Some(average) = player.average_turn {
+        lines.push(format!("Takes {} a turn", short_duration(average)));
+    }
+    let shared: Vec<&str> = games
+        .iter()
+        .filter(|g| player.shared_games.contains(&g.game_id))
+        .map(|g| g.name.as_str())
+        .collect();
+    if !shared.is_empty() {
+        lines.push(format!("Games together: {}", shared.join(", ")));
+    }
+    lines.join("\n")
+}
+
+/// Time left before the turn is skipped, coloured by `Urgency`.
+fn countdown<'a>(remaining: Duration) -> Element<'a, Message> {
+    let text = if remaining < Duration::zero() {
+        "Expired".to_string()
+    } else {
+        format!("{} left", short_duration(remaining))
+    };
+    Container::new(Text::new(text).size(14))
+        .padding(2)
+        .style(BadgeStyle(Urgency::of(remaining)))
+        .into()
+}
+
+/// e.g. `5d 2h`, `3h 20m` or `12m`.
+pub fn short_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
     }
 }
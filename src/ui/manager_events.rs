@@ -0,0 +1,54 @@
+use civfun_gmr::manager::Event;
+use iced_futures::futures::stream::{self, BoxStream, StreamExt};
+use iced_futures::subscription::Recipe;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tracing::warn;
+
+/// The one `Manager::subscribe()` receiver for the UI, taken before `Manager::start()` so no
+/// events are missed. iced only runs the stream once while the subscription stays the same,
+/// which is when the receiver is taken out.
+pub type SharedReceiver = Arc<Mutex<Option<Receiver<Event>>>>;
+
+/// Turns the UI's `Manager::subscribe()` receiver into an iced subscription.
+pub struct ManagerEvents(pub SharedReceiver);
+
+impl<H, I> Recipe<H, I> for ManagerEvents
+where
+    H: Hasher,
+{
+    type Output = Event;
+
+    fn hash(&self, state: &mut H) {
+        // There's only ever one manager, so the type is enough to identify the subscription.
+        std::any::TypeId::of::<Self>().hash(state);
+    }
+
+    fn stream(self: Box<Self>, _input: BoxStream<'static, I>) -> BoxStream<'static, Self::Output> {
+        let rx = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        let rx = match rx {
+            Some(rx) => rx,
+            None => {
+                warn!("Manager events were already subscribed to.");
+                return stream::empty().boxed();
+            }
+        };
+        Box::pin(stream::unfold(rx, |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => return Some((event, rx)),
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(?skipped, "UI fell behind on manager events.");
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }))
+    }
+}
@@ -1,41 +1,94 @@
 use crate::ui::auth_key_screen::AuthKeyMessage;
-use crate::ui::style::{action_button, ButtonView, NORMAL_ICON_SIZE};
+use crate::ui::style::{ButtonView, Theme, NORMAL_ICON_SIZE};
 use crate::{TITLE, VERSION};
+use account::{AccountMessage, AccountScreen};
 use actions::Actions;
+use activity::{ActivityMessage, ActivityScreen};
+use archive_browser::{ArchiveMessage, ArchiveScreen};
 use auth_key_screen::AuthKeyScreen;
-use civfun_gmr::api::{Game, GetGamesAndPlayers, Player, UserId};
-use civfun_gmr::manager::{Event, Manager};
-use error_screen::ErrorScreen;
-use games_list::GamesList;
+use chrono::Local;
+use civfun_gmr::api::{Game, GameId, GetGamesAndPlayers, Player, UserId, GMR_URL};
+use civfun_gmr::config::GamesSort;
+use civfun_gmr::manager::{DroppedSave, Event, Manager, PendingSave, PROCESS_INTERVAL};
+use civfun_gmr::update::{self, Release};
+use civfun_gmr::{attention, logging};
+use command_palette::{CommandPalette, PaletteMessage};
+use confirm::{ConfirmDialog, Confirmation};
+use error_screen::{causes, ErrorScreen, Retry};
+use game_detail::{GameDetail, GameDetailMessage};
+use games_list::{BulkAction, GamesList};
 use iced::container::{Style, StyleSheet};
 use iced::svg::Handle;
 use iced::window::Mode;
 use iced::{
-    button, container, executor, scrollable, text_input, time, window, Align, Application,
-    Background, Button, Clipboard, Color, Column, Command, Container, Element, Font,
+    button, container, executor, keyboard, scrollable, text_input, time, window, Align,
+    Application, Background, Button, Clipboard, Color, Column, Command, Container, Element, Font,
     HorizontalAlignment, Image, Length, Row, Rule, Scrollable, Settings, Space, Subscription, Svg,
     Text, TextInput, VerticalAlignment,
 };
+use manager_events::{ManagerEvents, SharedReceiver};
 use notify::DebouncedEvent;
-use prefs::Prefs;
+use offline_banner::OfflineBanner;
+use prefs::{Prefs, PrefsMessage};
+use save_match_dialog::SaveMatchDialog;
+use stats_screen::StatsScreen;
+use status_bar::StatusBar;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use style::{cog_icon, done_icon, normal_text, steam_icon, title, ActionButtonStyle, ROW_HEIGHT};
+use style::{
+    cog_icon, done_icon, steam_icon, user_icon, ActionButtonStyle, RELAXED_PADDING, ROW_HEIGHT,
+};
+use toasts::Toasts;
 use tokio::task::spawn_blocking;
 use tokio::time::Instant;
-use tracing::{debug, error, info, instrument, trace, warn};
+use tracing::{error, instrument, trace, warn};
+use update_banner::{UpdateBanner, UpdateMessage};
+use upload_approval::UploadApproval;
 
+mod account;
 mod actions;
+mod activity;
+mod archive_browser;
 mod auth_key_screen;
+mod avatars;
+mod command_palette;
+mod confirm;
 mod error_screen;
+mod game_detail;
 mod games_list;
+mod manager_events;
+mod offline_banner;
 mod prefs;
+mod save_match_dialog;
+mod stats_screen;
+mod status_bar;
 mod style;
+mod toasts;
+mod update_banner;
+mod upload_approval;
+
+/// Before the UI scale, which the window grows with.
+const DEFAULT_WINDOW_SIZE: (u32, u32) = (480, 640);
+const MIN_WINDOW_SIZE: (u32, u32) = (400, 200);
+/// The countdowns only show minutes, so there's no point redrawing them more often.
+const COUNTDOWN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// A window being resized sends lots of events, so its size is only saved once it has stayed the
+/// same for this long.
+const WINDOW_SAVE_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
 
 pub fn run(manager: Manager) -> anyhow::Result<()> {
+    // The window is in logical pixels, so it needs to grow with the UI.
+    let config = manager.config()?;
+    let scale = config.ui_scale();
+    let scaled = |size: u32| (size as f64 * scale) as u32;
+    let min_size = (scaled(MIN_WINDOW_SIZE.0), scaled(MIN_WINDOW_SIZE.1));
+    let (width, height) = config
+        .window_size
+        .unwrap_or((scaled(DEFAULT_WINDOW_SIZE.0), scaled(DEFAULT_WINDOW_SIZE.1)));
     let settings = Settings {
         window: window::Settings {
-            size: (400, 400),
-            min_size: Some((400, 200)),
+            size: (width.max(min_size.0), height.max(min_size.1)),
+            min_size: Some(min_size),
             ..Default::default()
         },
         flags: manager,
@@ -51,9 +104,24 @@ pub fn run(manager: Manager) -> anyhow::Result<()> {
 #[derive(PartialEq, Debug, Clone)]
 pub enum Screen {
     NothingYet,
-    Error { message: String, next: Box<Screen> },
+    Error {
+        message: String,
+        /// What caused it, from the error chain.
+        details: Vec<String>,
+        retry: Option<Retry>,
+        next: Box<Screen>,
+    },
     AuthKeyInput,
     Games,
+    GameDetail(GameId),
+    Account,
+    Activity,
+    /// Turn times over every game, opened from the actions bar.
+    Stats,
+    /// Old saves, opened from settings.
+    Archive,
+    /// A save needs to be matched to a game by hand.
+    ConfirmSave,
     Settings,
 }
 
@@ -76,30 +144,211 @@ impl Default for Screen {
 #[derive(Debug)]
 pub struct CivFunUi {
     manager: Manager,
+    /// Subscribed to before the manager starts, for `subscription()`.
+    events: SharedReceiver,
     games: Vec<Game>,
 
     screen: Screen,
-    status_text: String,
+    theme: Theme,
+    /// From the config, kept here since it's asked for on every frame.
+    ui_scale: f64,
+    /// The new window size in logical pixels, and when it was resized to it. Waiting for
+    /// `WINDOW_SAVE_DELAY` before going into the config.
+    resized: Option<((u32, u32), std::time::Instant)>,
+    /// Whether the window is in front, so new turns only flash it when it isn't.
+    focused: bool,
+    status: StatusBar,
+    toasts: Toasts,
+    save_match: SaveMatchDialog,
+    upload_approval: UploadApproval,
+    update_banner: UpdateBanner,
+    offline_banner: OfflineBanner,
+    palette: CommandPalette,
+    confirm: ConfirmDialog,
     settings_button_state: button::State,
+    account_button_state: button::State,
 
     actions: Actions,
     error: ErrorScreen,
     prefs: Prefs,
+    account: AccountScreen,
+    archive: ArchiveScreen,
+    activity: ActivityScreen,
+    stats: StatsScreen,
     enter_auth_key: AuthKeyScreen,
     games_list: GamesList,
+    game_detail: GameDetail,
 
     scroll_state: scrollable::State,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    GetManagerEvents,
+    ProcessManager,
+    /// Once a minute, to move the turn countdowns on.
+    Tick,
+    /// Once a day, and at startup.
+    CheckForUpdate,
+    /// A newer release, if there is one.
+    UpdateChecked(Result<Option<Release>, String>),
+    Update(UpdateMessage),
+    ManagerEvent(Event),
+    /// A desktop notification was shown, or couldn't be.
+    Notified(Result<(), String>),
     SetScreen(Screen),
-    RequestRefresh,
     PlayCiv,
+    /// Downloads the save if needed and starts Civ V for it.
+    PlayTurn(GameId),
+    TogglePause,
+    /// Fetch games now rather than waiting for the next poll.
+    RequestRefresh,
+    SortGames,
+    MyTurnOnly(bool),
+    HideFinished(bool),
+    /// Turns notifications for a game off, or back on.
+    MuteGame(GameId, bool),
+    SelectGame(GameId, bool),
+    ClearSelection,
+    /// Applies to every selected game.
+    Bulk(BulkAction),
+    Download(GameId),
+    /// Approves an upload waiting in `UploadAwaitingApproval`.
+    Upload(GameId),
+    DiscardUpload(GameId),
+    /// Sends a save again after it failed to upload.
+    RetryUpload(GameId),
+    CopyErrorDetails(String),
+    OpenLogs,
+    OpenSaveDir,
+    /// The GMR website, for what the API can't do like starting and joining games.
+    OpenGmr,
+    DismissToast(u64),
+    ConfirmSave {
+        path: PathBuf,
+        game_id: GameId,
+    },
+    DismissSave(PathBuf),
+    /// A file dropped onto the window.
+    SaveDropped(PathBuf),
+    /// In UI units, which are logical pixels divided by the UI scale.
+    WindowResized {
+        width: u32,
+        height: u32,
+    },
+    WindowFocused(bool),
+    Palette(PaletteMessage),
+    /// Asks before running the confirmation's message.
+    Confirm(Confirmation),
+    Confirmed,
+    CancelConfirm,
 
     AuthKeyMessage(AuthKeyMessage),
     AuthKeySave(String),
+
+    Prefs(PrefsMessage),
+    GameDetail(GameDetailMessage),
+    Account(AccountMessage),
+    Archive(ArchiveMessage),
+    Activity(ActivityMessage),
+}
+
+impl CivFunUi {
+    /// Games to list, filtered and sorted going by the config.
+    fn refresh_games(&mut self) {
+        let games = match self.manager.games() {
+            Ok(games) => games,
+            Err(err) => {
+                error!(?err, "Loading games.");
+                return;
+            }
+        };
+        let config = self.manager.config().unwrap_or_default();
+        let me = self.manager.user_id().unwrap_or_default();
+        self.games = games
+            .into_iter()
+            .filter(|game| !config.my_turn_only || Some(game.current_turn.user_id) == me)
+            .filter(|game| match self.manager.game_settings(&game.game_id) {
+                Ok(settings) => !(config.hide_finished && settings.archived),
+                Err(err) => {
+                    warn!(?err, "Loading game settings.");
+                    true
+                }
+            })
+            .collect();
+        config.games_sort.sort(&mut self.games);
+        self.games_list.refresh(&self.manager, &self.games);
+        self.upload_approval.load(&self.manager);
+    }
+
+    /// Whether the games shown are out of date, for the banner and the rows.
+    fn refresh_staleness(&mut self) {
+        self.offline_banner.refresh(&self.manager);
+        self.games_list
+            .set_stale_since(self.offline_banner.stale_since());
+    }
+
+    /// On to the next save to match, or back to the games.
+    fn save_resolved(&mut self, path: &Path) {
+        self.save_match.resolved(path);
+        if self.save_match.is_empty() {
+            self.screen = Screen::Games;
+        }
+    }
+
+    /// Looks for a newer release in the background, unless turned off.
+    fn check_for_update(&self) -> Command<Message> {
+        if !self.manager.config().unwrap_or_default().check_for_updates {
+            return Command::none();
+        }
+        Command::perform(update::check(VERSION), |result| {
+            Message::UpdateChecked(result.map_err(|err| format!("{:#}", err)))
+        })
+    }
+
+    /// Shows a desktop notification for the event in the background, and flashes the window for
+    /// new turns while it isn't in front.
+    fn alert(&self, event: &Event) -> Command<Message> {
+        let alert = || -> anyhow::Result<Command<Message>> {
+            let config = self.manager.config()?;
+            let game_settings = match event.game_id() {
+                Some(game_id) => Some(self.manager.game_settings(&game_id)?),
+                None => None,
+            };
+            let now = Local::now().naive_local();
+            if !self.focused
+                && civfun_gmr::notify::wants_attention(event, &config, game_settings.as_ref(), now)
+            {
+                attention::request(&self.title());
+            }
+            let notification =
+                match civfun_gmr::notify::to_show(event, &config, game_settings.as_ref(), now) {
+                    Some(notification) => notification,
+                    None => return Ok(Command::none()),
+                };
+            Ok(Command::perform(
+                civfun_gmr::notify::show(notification),
+                |result| Message::Notified(result.map_err(|err| format!("{:#}", err))),
+            ))
+        };
+        alert().unwrap_or_else(|err| {
+            warn!(?err, "Desktop notification failed.");
+            Command::none()
+        })
+    }
+
+    /// Shows what went wrong and comes back to the current screen.
+    fn show_error(&mut self, err: anyhow::Error) {
+        self.show_error_with_retry(err, None);
+    }
+
+    fn show_error_with_retry(&mut self, err: anyhow::Error, retry: Option<Retry>) {
+        self.screen = Screen::Error {
+            message: err.to_string(),
+            details: causes(&err),
+            retry,
+            next: Box::new(self.screen.clone()),
+        };
+    }
 }
 
 impl Application for CivFunUi {
@@ -108,21 +357,54 @@ impl Application for CivFunUi {
     type Flags = Manager;
 
     fn new(manager: Manager) -> (CivFunUi, Command<Self::Message>) {
+        let events = Arc::new(std::sync::Mutex::new(Some(manager.subscribe())));
         let mut civfun = CivFunUi {
             manager,
+            events,
             games: vec![],
             screen: Default::default(),
-            status_text: "".to_string(),
+            theme: Default::default(),
+            ui_scale: 1.0,
+            resized: None,
+            focused: true,
+            status: Default::default(),
+            toasts: Default::default(),
+            save_match: Default::default(),
+            upload_approval: Default::default(),
+            update_banner: Default::default(),
+            offline_banner: Default::default(),
+            palette: Default::default(),
+            confirm: Default::default(),
             error: Default::default(),
             actions: Default::default(),
             prefs: Default::default(),
+            account: Default::default(),
+            archive: Default::default(),
+            activity: Default::default(),
+            stats: Default::default(),
             enter_auth_key: Default::default(),
             games_list: Default::default(),
+            game_detail: Default::default(),
             scroll_state: Default::default(),
             settings_button_state: Default::default(),
+            account_button_state: Default::default(),
         };
 
         civfun.manager.start().unwrap();
+        let config = civfun.manager.config().unwrap_or_default();
+        civfun.theme = Theme::from_config(&config);
+        civfun.ui_scale = config.ui_scale();
+        let height = match config.window_size {
+            Some((_, height)) => (height as f64 / civfun.ui_scale) as u32,
+            None => DEFAULT_WINDOW_SIZE.1,
+        };
+        civfun.games_list.set_viewport_height(height);
+        civfun.actions.paused = civfun.manager.paused().unwrap_or_else(|err| {
+            warn!(?err, "Reading whether paused.");
+            false
+        });
+        civfun.refresh_games();
+        civfun.refresh_staleness();
 
         if civfun.manager.auth_key().unwrap().is_some() {
             // civfun.status_text = "Refreshing...".into();
@@ -132,67 +414,436 @@ impl Application for CivFunUi {
             //     // Command::perform(authenticate(manager.clone()), AuthResponse),
             // ]);
             civfun.screen = Screen::Games;
+            match civfun.manager.pending_saves() {
+                Ok(pending) => civfun.save_match.load(pending),
+                Err(err) => warn!(?err, "Loading saves waiting to be matched."),
+            }
+            if !civfun.save_match.is_empty() {
+                civfun.screen = Screen::ConfirmSave;
+            }
         } else {
             civfun.screen = Screen::AuthKeyInput;
         }
 
-        (civfun, Command::none())
+        let command = civfun.check_for_update();
+        (civfun, command)
     }
 
     fn title(&self) -> String {
         format!("{} v{}", TITLE, VERSION)
     }
 
-    #[instrument(skip(self, _clipboard))]
+    #[instrument(skip(self, clipboard))]
     fn update(
         &mut self,
         message: Self::Message,
-        _clipboard: &mut Clipboard,
+        clipboard: &mut Clipboard,
     ) -> Command<Self::Message> {
         use Message::*;
         match message {
-            GetManagerEvents => {
-                for event in self.manager.process().unwrap() {
-                    trace!(?event);
-                    match event {
-                        Event::AuthenticationSuccess => {
-                            self.status_text = "Authentication Successful".to_string();
-                        }
-                        Event::AuthenticationFailure => {
-                            self.screen = Screen::Error {
-                                message: "Authentication Key error".to_string(),
-                                next: Box::new(Screen::AuthKeyInput),
-                            };
-                        }
-                        Event::UpdatedGames(games) => {
-                            self.games = games;
+            ProcessManager => {
+                if let Err(err) = self.manager.process() {
+                    error!(?err, "Processing.");
+                }
+                self.status.poll(&self.manager);
+                self.actions.poll(&self.manager);
+                self.toasts.expire(std::time::Instant::now());
+                if let Some((size, at)) = self.resized {
+                    if at.elapsed() >= WINDOW_SAVE_DELAY {
+                        self.resized = None;
+                        if let Err(err) = self.manager.update_config(|c| c.window_size = Some(size))
+                        {
+                            warn!(?err, "Saving the window size.");
                         }
-                        x => todo!("{:?}", x),
                     }
                 }
             }
 
-            AuthKeyMessage(message) => return self.enter_auth_key.update(message, _clipboard),
+            Tick => {
+                self.games_list.tick(self.manager.server_now());
+                self.refresh_staleness();
+            }
+            CheckForUpdate => return self.check_for_update(),
+            UpdateChecked(Ok(Some(release))) => self.update_banner.found(release),
+            UpdateChecked(Ok(None)) => {}
+            // Nothing the user can do about it, and it'll be tried again tomorrow.
+            UpdateChecked(Err(err)) => warn!(?err, "Checking for updates."),
+            Update(message) => {
+                if let Err(err) = self.update_banner.update(message) {
+                    self.show_error(err);
+                }
+            }
+            ManagerEvent(event) => {
+                trace!(?event);
+                self.games_list.handle_event(&event);
+                self.status.handle_event(&event);
+                self.actions.handle_event(&event);
+                self.toasts.handle_event(&event, &self.games);
+                self.offline_banner.handle_event(&event);
+                let alert = self.alert(&event);
+                match event {
+                    Event::ConfigChanged(config) => {
+                        self.theme = Theme::from_config(&config);
+                        self.ui_scale = config.ui_scale();
+                    }
+                    Event::AuthenticationSuccess => {
+                        self.status.set_message("Authentication Successful");
+                    }
+                    Event::AuthenticationFailure => {
+                        self.screen = Screen::Error {
+                            message: "Authentication Key error".to_string(),
+                            details: vec![],
+                            retry: None,
+                            next: Box::new(Screen::AuthKeyInput),
+                        };
+                    }
+                    Event::AccountSwitched(user_id) => {
+                        self.status
+                            .set_message(format!("Switched to account {}", user_id));
+                    }
+                    Event::TurnDeadlineApproaching { game, remaining } => {
+                        self.status.set_message(format!(
+                            "Your turn in {} expires in {} minutes",
+                            game.name,
+                            remaining.num_minutes()
+                        ));
+                    }
+                    Event::TurnAboutToBeSkipped {
+                        game, remaining, ..
+                    } => {
+                        self.status.set_message(format!(
+                            "⚠ Your turn in {} will be skipped in {} minutes!",
+                            game.name,
+                            remaining.num_minutes()
+                        ));
+                    }
+                    Event::FetchGamesFailed { message } => {
+                        self.status
+                            .set_message(format!("Couldn't refresh games: {}", message));
+                        self.refresh_staleness();
+                    }
+                    Event::UpdatedGames(_) => {
+                        self.refresh_games();
+                        self.refresh_staleness();
+                    }
+                    Event::GameSettingsChanged { .. } => self.refresh_games(),
+                    Event::UploadComplete(_) => {
+                        self.status.set_message("Turn uploaded");
+                    }
+                    Event::UploadFailed { game_id, message } => {
+                        self.screen = Screen::Error {
+                            message: format!("Upload failed: {}", message),
+                            details: vec![],
+                            retry: Some(Retry::Upload(game_id)),
+                            next: Box::new(Screen::Games),
+                        };
+                    }
+                    Event::UploadNeedsApproval { game, .. } => {
+                        self.upload_approval.load(&self.manager);
+                        self.status
+                            .set_message(format!("Turn in {} is waiting for approval", game.name));
+                    }
+                    Event::AnotherInstanceStarted => {
+                        // iced can't raise the window, so at least show the main screen.
+                        self.screen = Screen::Games;
+                        self.status.set_message("civfun is already running here");
+                    }
+                    Event::ClockSkew { skew } if civfun_gmr::clock::is_significant(skew) => {
+                        self.status.set_message(format!(
+                            "Your clock is {} minutes off, deadlines use GMR's time",
+                            skew.num_minutes().abs()
+                        ));
+                    }
+                    Event::Paused(paused) => {
+                        self.actions.paused = paused;
+                        self.status.set_message(if paused {
+                            "Paused".to_string()
+                        } else {
+                            "Resumed".to_string()
+                        });
+                    }
+                    Event::GameRunning(running) => {
+                        self.status.set_message(if running {
+                            "Civ V is running".to_string()
+                        } else {
+                            "Civ V has stopped".to_string()
+                        });
+                    }
+                    Event::Error {
+                        source,
+                        message,
+                        recoverable,
+                    } => {
+                        self.status.set_message(if recoverable {
+                            format!("Problem with {}: {}", source, message)
+                        } else {
+                            format!("Paused {} for a while: {}", source, message)
+                        });
+                    }
+                    Event::FirstTurnSaveRejected { problems, .. } => {
+                        let problems: Vec<String> =
+                            problems.iter().map(|p| p.to_string()).collect();
+                        self.screen = Screen::Error {
+                            message: format!(
+                                "The first turn save can't be used. {}",
+                                problems.join(" ")
+                            ),
+                            details: vec![],
+                            retry: None,
+                            next: Box::new(Screen::Games),
+                        };
+                    }
+                    Event::SaveRepaired { problem, .. } => {
+                        self.status
+                            .set_message(format!("Downloading a save again: {}", problem));
+                    }
+                    Event::SaveDirProblem { problem, .. } => {
+                        self.screen = Screen::Error {
+                            message: problem.to_string(),
+                            details: vec![],
+                            retry: None,
+                            next: Box::new(Screen::Games),
+                        };
+                    }
+                    Event::SaveNeedsConfirmation { path, candidates } => {
+                        self.save_match.add(PendingSave { path, candidates });
+                        self.screen = Screen::ConfirmSave;
+                    }
+                    Event::DatabaseCorrupt { message } => {
+                        self.screen = Screen::Error {
+                            message: format!("The civfun database is damaged: {}", message),
+                            details: vec![],
+                            retry: None,
+                            next: Box::new(Screen::Games),
+                        };
+                    }
+                    _ => {}
+                }
+                // Transfer states and players change with most events.
+                self.games_list.refresh(&self.manager, &self.games);
+                return alert;
+            }
+            Notified(Ok(())) => {}
+            Notified(Err(err)) => warn!(?err, "Desktop notification failed."),
+
+            AuthKeyMessage(message) => return self.enter_auth_key.update(message, clipboard),
 
             AuthKeySave(auth_key) => {
+                if let Err(err) = self.manager.authenticate(&auth_key) {
+                    self.screen = Screen::Error {
+                        message: err.to_string(),
+                        details: causes(&err),
+                        retry: None,
+                        next: Box::new(Screen::AuthKeyInput),
+                    };
+                    return Command::none();
+                }
                 self.screen = Screen::Games;
-                self.status_text = "Authenticating".to_string();
-                self.manager.authenticate(&auth_key).unwrap();
+                self.status.set_message("Authenticating");
             }
 
             SetScreen(screen) => {
+                match &screen {
+                    Screen::Settings => self.prefs.load(&self.manager),
+                    Screen::Account => self.account.load(&self.manager),
+                    Screen::Archive => self.archive.load(&self.manager),
+                    Screen::Activity => self.activity.load(&self.manager),
+                    Screen::Stats => self.stats.load(&self.manager),
+                    Screen::GameDetail(game_id) => self.game_detail.load(&self.manager, game_id),
+                    _ => {}
+                }
                 self.screen = screen;
             }
-            RequestRefresh => {
-                debug!("RequestRefresh");
-                // todo!();
-                self.status_text = "Refreshing...".into();
-                warn!("RequestRefresh TODO!");
-                // return fetch_cmd(&self.manager);
+            Prefs(message) => self.prefs.update(&self.manager, message),
+            GameDetail(message) => self.game_detail.update(&self.manager, message),
+            Activity(message) => self.activity.update(&self.manager, message, clipboard),
+            Archive(message) => self.archive.update(&self.manager, message),
+            Account(message) => {
+                self.account.update(&self.manager, message);
+                self.refresh_games();
+                if let Ok(None) = self.manager.user_id() {
+                    // Logged out of the last account.
+                    self.screen = Screen::AuthKeyInput;
+                }
             }
             PlayCiv => {
-                // TODO: DX version from settings.
-                open::that("steam://rungameid/8930//%5Cdx9").unwrap(); // TODO: unwrap
+                if let Err(err) = self.manager.launch_game() {
+                    error!(?err, "Launching Civ V.");
+                    self.show_error_with_retry(err, Some(Retry::LaunchGame));
+                }
+            }
+            SortGames => {
+                let result = self.manager.update_config(|c| {
+                    let i = GamesSort::ALL.iter().position(|s| *s == c.games_sort);
+                    c.games_sort = GamesSort::ALL[i.map_or(0, |i| i + 1) % GamesSort::ALL.len()];
+                });
+                if let Err(err) = result {
+                    self.show_error(err);
+                }
+                self.refresh_games();
+            }
+            MyTurnOnly(on) => {
+                if let Err(err) = self.manager.update_config(|c| c.my_turn_only = on) {
+                    self.show_error(err);
+                }
+                self.refresh_games();
+            }
+            HideFinished(on) => {
+                if let Err(err) = self.manager.update_config(|c| c.hide_finished = on) {
+                    self.show_error(err);
+                }
+                self.refresh_games();
+            }
+            MuteGame(game_id, muted) => {
+                if let Err(err) = self
+                    .manager
+                    .update_game_settings(&game_id, |s| s.muted = muted)
+                {
+                    self.show_error(err);
+                }
+            }
+            SelectGame(game_id, on) => self.games_list.select(game_id, on),
+            ClearSelection => {
+                self.games_list.take_selected();
+            }
+            Bulk(action) => {
+                let game_ids = self.games_list.take_selected();
+                let mut failed = vec![];
+                for game_id in &game_ids {
+                    let result = match action {
+                        BulkAction::Mute => self
+                            .manager
+                            .update_game_settings(game_id, |s| s.muted = true)
+                            .map(|_| ()),
+                        BulkAction::Archive => self.manager.archive_game(game_id),
+                        BulkAction::Redownload => self.manager.redownload(game_id),
+                    };
+                    // Keep going, one game being busy shouldn't hold up the rest.
+                    if let Err(err) = result {
+                        warn!(?err, ?game_id, ?action, "Bulk action.");
+                        failed.push(format!("{:#}", err));
+                    }
+                }
+                self.status.set_message(if failed.is_empty() {
+                    format!("{}: done for {} games", action, game_ids.len())
+                } else {
+                    format!(
+                        "{}: {} of {} games failed. {}",
+                        action,
+                        failed.len(),
+                        game_ids.len(),
+                        failed.join(" ")
+                    )
+                });
+                self.refresh_games();
+            }
+            PlayTurn(game_id) => {
+                if let Err(err) = self.manager.play_turn(&game_id) {
+                    self.show_error_with_retry(err, Some(Retry::PlayTurn(game_id)));
+                }
+                self.games_list.refresh(&self.manager, &self.games);
+            }
+            Download(game_id) => {
+                if let Err(err) = self.manager.download(&game_id) {
+                    self.show_error_with_retry(err, Some(Retry::Download(game_id)));
+                }
+                self.games_list.refresh(&self.manager, &self.games);
+            }
+            Upload(game_id) => {
+                if let Err(err) = self.manager.approve_upload(&game_id) {
+                    self.show_error(err);
+                }
+                self.games_list.refresh(&self.manager, &self.games);
+                self.upload_approval.load(&self.manager);
+            }
+            RetryUpload(game_id) => {
+                if let Err(err) = self.manager.retry_upload(&game_id) {
+                    self.show_error(err);
+                } else {
+                    self.screen = Screen::Games;
+                }
+                self.games_list.refresh(&self.manager, &self.games);
+            }
+            DiscardUpload(game_id) => {
+                if let Err(err) = self.manager.reject_upload(&game_id) {
+                    self.show_error(err);
+                }
+                self.games_list.refresh(&self.manager, &self.games);
+                self.upload_approval.load(&self.manager);
+            }
+            DismissToast(id) => self.toasts.dismiss(id),
+            WindowFocused(focused) => self.focused = focused,
+            Confirm(confirmation) => self.confirm.ask(confirmation),
+            Confirmed => {
+                if let Some(message) = self.confirm.confirmed() {
+                    return self.update(message, clipboard);
+                }
+            }
+            CancelConfirm => self.confirm.cancel(),
+            Palette(message) => {
+                let me = self.manager.user_id().unwrap_or_default();
+                if let Some(message) = self.palette.update(message, &self.games, me) {
+                    return self.update(message, clipboard);
+                }
+            }
+            WindowResized { width, height } => {
+                self.games_list.set_viewport_height(height);
+                let logical = |size: u32| (size as f64 * self.ui_scale).round() as u32;
+                self.resized = Some(((logical(width), logical(height)), std::time::Instant::now()));
+            }
+            ConfirmSave { path, game_id } => {
+                match self.manager.confirm_save_assignment(&path, &game_id) {
+                    Ok(()) => self.save_resolved(&path),
+                    Err(err) => self.show_error(err),
+                }
+            }
+            DismissSave(path) => match self.manager.dismiss_save(&path) {
+                Ok(()) => self.save_resolved(&path),
+                Err(err) => self.show_error(err),
+            },
+            SaveDropped(path) => match self.manager.upload_dropped(&path) {
+                Ok(DroppedSave::Queued(_)) => self.status.set_message("Uploading the dropped save"),
+                Ok(DroppedSave::Unclear(pending)) => {
+                    self.save_match.add(pending);
+                    self.screen = Screen::ConfirmSave;
+                }
+                Err(err) => self.show_error(err),
+            },
+            CopyErrorDetails(details) => {
+                clipboard.write(details);
+                self.status.set_message("Copied the error details");
+            }
+            OpenLogs => {
+                if let Err(err) = logging::log_dir().and_then(|dir| Ok(open::that(dir)?)) {
+                    self.show_error(err);
+                }
+            }
+            OpenGmr => {
+                if let Err(err) = open::that(GMR_URL) {
+                    self.show_error(err.into());
+                }
+            }
+            OpenSaveDir => {
+                if let Err(err) = self.manager.save_dir().and_then(|dir| Ok(open::that(dir)?)) {
+                    self.show_error(err);
+                }
+            }
+            TogglePause => {
+                let result = if self.actions.paused {
+                    self.manager.resume()
+                } else {
+                    self.manager.pause()
+                };
+                if let Err(err) = result {
+                    error!(?err, "Pausing or resuming.");
+                }
+            }
+            RequestRefresh => {
+                if self.actions.request_refresh(std::time::Instant::now()) {
+                    if let Err(err) = self.manager.fetch_games() {
+                        self.show_error_with_retry(err, Some(Retry::FetchGames));
+                    }
+                }
             }
         }
         Command::none()
@@ -200,8 +851,37 @@ impl Application for CivFunUi {
 
     fn subscription(&self) -> Subscription<Message> {
         Subscription::batch([
-            time::every(std::time::Duration::from_secs(60)).map(|_| Message::RequestRefresh),
-            time::every(std::time::Duration::from_millis(1000)).map(|_| Message::GetManagerEvents),
+            time::every(PROCESS_INTERVAL).map(|_| Message::ProcessManager),
+            time::every(COUNTDOWN_INTERVAL).map(|_| Message::Tick),
+            time::every(update::CHECK_INTERVAL).map(|_| Message::CheckForUpdate),
+            Subscription::from_recipe(ManagerEvents(self.events.clone()))
+                .map(Message::ManagerEvent),
+            iced_native::subscription::events_with(|event, _| match event {
+                iced_native::Event::Window(iced_native::window::Event::Resized {
+                    width,
+                    height,
+                }) => Some(Message::WindowResized { width, height }),
+                iced_native::Event::Keyboard(keyboard::Event::KeyPressed {
+                    key_code: keyboard::KeyCode::K,
+                    modifiers,
+                }) if modifiers.is_command_pressed() => {
+                    Some(Message::Palette(PaletteMessage::Toggle))
+                }
+                iced_native::Event::Keyboard(keyboard::Event::KeyPressed {
+                    key_code: keyboard::KeyCode::Escape,
+                    ..
+                }) => Some(Message::Palette(PaletteMessage::Close)),
+                iced_native::Event::Window(iced_native::window::Event::Focused) => {
+                    Some(Message::WindowFocused(true))
+                }
+                iced_native::Event::Window(iced_native::window::Event::Unfocused) => {
+                    Some(Message::WindowFocused(false))
+                }
+                iced_native::Event::Window(iced_native::window::Event::FileDropped(path)) => {
+                    Some(Message::SaveDropped(path))
+                }
+                _ => None,
+            }),
         ])
     }
 
@@ -209,25 +889,54 @@ impl Application for CivFunUi {
         let Self {
             manager,
             screen,
+            theme,
             error,
             actions,
             prefs: settings,
+            account,
+            archive,
+            activity,
+            stats,
             scroll_state,
             enter_auth_key,
             games_list,
+            game_detail,
+            status,
+            toasts,
+            save_match,
+            upload_approval,
+            update_banner,
+            offline_banner,
+            palette,
+            confirm,
             ref mut settings_button_state,
+            ref mut account_button_state,
             ..
         } = self;
 
+        let theme = *theme;
         let mut content = match screen {
-            Screen::NothingYet => normal_text("Loading...").into(),
-            Screen::AuthKeyInput => enter_auth_key.view().map(Message::AuthKeyMessage),
-            Screen::Games => games_list.view(&self.games),
-            Screen::Settings => settings.view(),
+            _ if confirm.is_open() => confirm.view(theme),
+            Screen::NothingYet => theme.normal_text("Loading...").into(),
+            Screen::AuthKeyInput => enter_auth_key.view(theme).map(Message::AuthKeyMessage),
+            Screen::Games => Column::new()
+                .spacing(RELAXED_PADDING / 2)
+                .push(upload_approval.view(theme))
+                .push(games_list.view(theme))
+                .into(),
+            Screen::GameDetail(_) => game_detail.view(theme, games_list),
+            Screen::ConfirmSave => save_match.view(theme),
+            Screen::Settings => settings.view(theme),
+            Screen::Account => account.view(theme),
+            Screen::Archive => archive.view(theme),
+            Screen::Activity => activity.view(theme),
+            Screen::Stats => stats.view(theme, games_list),
             Screen::Error {
                 message: text,
+                details,
+                retry,
                 next,
-            } => error.view(&text, *next.clone()),
+            } => error.view(theme, &text, details, retry.as_ref(), *next.clone()),
         };
 
         // // TODO: Turn content to scrollable
@@ -243,24 +952,44 @@ impl Application for CivFunUi {
         // .on_press(Message::SetScreen(Screen::Settings))
         // .style(ActionButtonStyle);
 
-        let settings_button = action_button(
+        let settings_button = theme.action_button(
             ButtonView::Icon(cog_icon(NORMAL_ICON_SIZE)),
             Message::SetScreen(Screen::Settings),
             settings_button_state,
         );
 
+        let account_button = theme.action_button(
+            ButtonView::Icon(user_icon(NORMAL_ICON_SIZE)),
+            Message::SetScreen(Screen::Account),
+            account_button_state,
+        );
+
         let title_row = Row::new()
             .height(Length::Units(ROW_HEIGHT))
-            .push(title())
+            .push(theme.title())
+            .push(account_button)
             .push(settings_button);
 
         let actions = if screen.should_show_actions() {
-            actions.view()
+            actions.view(theme)
         } else {
             Space::new(Length::Shrink, Length::Shrink).into()
         };
 
-        let layout = Column::new().push(title_row).push(actions).push(content);
+        let content = Container::new(content).height(Length::Fill);
+        let layout = Column::new()
+            .push(title_row)
+            .push(update_banner.view(theme))
+            .push(offline_banner.view(theme))
+            .push(if palette.is_open() {
+                palette.view(theme)
+            } else {
+                Space::new(Length::Shrink, Length::Shrink).into()
+            })
+            .push(actions)
+            .push(content)
+            .push(toasts.view(theme))
+            .push(status.view(theme));
 
         let outside = Container::new(layout)
             .width(Length::Fill)
@@ -298,6 +1027,10 @@ impl Application for CivFunUi {
     }
 
     fn background_color(&self) -> Color {
-        style::background_color().into()
+        self.theme.background
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.ui_scale
     }
 }
@@ -0,0 +1,74 @@
+use chrono::{DateTime, Local, Utc};
+use iced::{button, Container, Element, Length, Row, Space, VerticalAlignment};
+use tracing::warn;
+
+use crate::ui::style::{ButtonView, PanelStyle, Theme, RELAXED_PADDING};
+use crate::ui::Message;
+use civfun_gmr::manager::{Event, Manager};
+
+/// Stays under the title while GMR can't be reached or the games are out of date, so old turn
+/// states aren't mistaken for current ones.
+#[derive(Default, Debug)]
+pub struct OfflineBanner {
+    /// The last fetch failed, until one works again.
+    offline: bool,
+    /// When games were last fetched, if that was too long ago.
+    stale_since: Option<DateTime<Utc>>,
+    retry_button_state: button::State,
+}
+
+impl OfflineBanner {
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::UpdatedGames(_) => self.offline = false,
+            Event::FetchGamesFailed { .. } => self.offline = true,
+            _ => {}
+        }
+    }
+
+    /// Works out whether the games are out of date. Called when games are fetched and every
+    /// minute, since that's as fast as they go stale.
+    pub fn refresh(&mut self, manager: &Manager) {
+        self.stale_since = manager.games_stale_since().unwrap_or_else(|err| {
+            warn!(?err, "Checking whether games are out of date.");
+            None
+        });
+    }
+
+    pub fn stale_since(&self) -> Option<DateTime<Utc>> {
+        self.stale_since
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<'_, Message> {
+        let as_of = self
+            .stale_since
+            .map(|at| at.with_timezone(&Local).format("%a %H:%M").to_string());
+        let text = match (self.offline, as_of) {
+            (true, Some(as_of)) => format!("⚠ Offline, showing games as of {}", as_of),
+            (true, None) => "⚠ Offline, GMR can't be reached".to_string(),
+            (false, Some(as_of)) => format!("⚠ Games haven't been updated since {}", as_of),
+            (false, None) => return Space::new(Length::Shrink, Length::Shrink).into(),
+        };
+
+        Container::new(
+            Row::new()
+                .spacing(RELAXED_PADDING / 2)
+                .push(
+                    theme
+                        .normal_text(&text)
+                        .size(16)
+                        .width(Length::Fill)
+                        .vertical_alignment(VerticalAlignment::Center),
+                )
+                .push(theme.action_button(
+                    ButtonView::Text("Try now"),
+                    Message::RequestRefresh,
+                    &mut self.retry_button_state,
+                )),
+        )
+        .width(Length::Fill)
+        .padding(RELAXED_PADDING / 4)
+        .style(PanelStyle(theme))
+        .into()
+    }
+}
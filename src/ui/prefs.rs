@@ -1,22 +1,446 @@
-use iced::{button, Button, Element};
+use iced::{
+    button, scrollable, text_input, Checkbox, Column, Element, Length, Radio, Row, Scrollable,
+    Space, TextInput,
+};
+use tracing::{error, warn};
 
-use crate::ui::style::{action_button, done_icon, ActionButtonStyle, ButtonView, NORMAL_ICON_SIZE};
+use crate::ui::style::{
+    done_icon, ButtonView, Theme, NORMAL_ICON_SIZE, RELAXED_PADDING, ROW_HEIGHT,
+};
 use crate::ui::{Message, Screen};
+use chrono::NaiveTime;
+use civfun_gmr::config::{Accent, Config, DirectXVersion, ThemeChoice, UI_SCALES};
+use civfun_gmr::manager::Manager;
+use civfun_gmr::notify::QuietHours;
 
+/// Edits the `Config`. Every change is validated and saved straight away with
+/// `Manager::update_config()`. Text that doesn't make a valid setting yet stays in the input,
+/// with the problem shown underneath, until it does. The save folder is the exception: it's only
+/// used once it's submitted, so a half typed path doesn't get checked and watched.
 #[derive(Default, Debug)]
 pub struct Prefs {
+    config: Config,
+    detected_save_dir: String,
+    error: Option<String>,
+
+    save_dir: String,
+    poll_seconds: String,
+    download_limit: String,
+    upload_limit: String,
+
+    scroll_state: scrollable::State,
+    save_dir_input_state: text_input::State,
+    use_save_dir_button_state: button::State,
+    open_save_dir_button_state: button::State,
+    poll_input_state: text_input::State,
+    download_limit_input_state: text_input::State,
+    upload_limit_input_state: text_input::State,
+    account_button_state: button::State,
+    archive_button_state: button::State,
+    reset_window_button_state: button::State,
     close_settings_button_state: button::State,
-    open_folder_button_state: button::State,
+}
+
+#[derive(Debug, Clone)]
+pub enum PrefsMessage {
+    SaveDirChanged(String),
+    SaveDirSubmitted,
+    OpenSaveDir,
+    DirectX(DirectXVersion),
+    Theme(ThemeChoice),
+    Accent(Accent),
+    HighContrast(bool),
+    ReducedMotion(bool),
+    UiScale(u16),
+    ResetWindow,
+    PollSecondsChanged(String),
+    Notifications(bool),
+    RequestAttention(bool),
+    QuietNights(bool),
+    QuietWeekends(bool),
+    CheckForUpdates(bool),
+    DownloadLimitChanged(String),
+    UploadLimitChanged(String),
+}
+
+/// Blank is no limit.
+fn parse_limit(s: &str) -> Result<Option<u32>, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(None);
+    }
+    s.parse()
+        .map(Some)
+        .map_err(|_| format!("{:?} isn't a number of KiB/s.", s))
+}
+
+fn limit_text(limit: Option<u32>) -> String {
+    limit.map(|l| l.to_string()).unwrap_or_default()
 }
 
 impl Prefs {
-    pub fn view(&mut self) -> Element<Message> {
-        let close_button = action_button(
+    /// Picks up the current config, when the screen is opened.
+    pub fn load(&mut self, manager: &Manager) {
+        let config = match manager.config() {
+            Ok(config) => config,
+            Err(err) => {
+                error!(?err, "Loading config.");
+                self.error = Some(format!("{:#}", err));
+                return;
+            }
+        };
+        self.save_dir = config
+            .save_dir
+            .as_ref()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.poll_seconds = config.poll_seconds.to_string();
+        self.download_limit = limit_text(config.download_limit_kib);
+        self.upload_limit = limit_text(config.upload_limit_kib);
+        self.config = config;
+        self.error = None;
+
+        self.detected_save_dir = match manager.detect_save_dir() {
+            Ok(detection) => detection
+                .found
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            Err(err) => {
+                warn!(?err, "Detecting save dir.");
+                String::new()
+            }
+        };
+    }
+
+    pub fn update(&mut self, manager: &Manager, message: PrefsMessage) {
+        use PrefsMessage::*;
+        let result = match message {
+            SaveDirChanged(s) => {
+                self.save_dir = s;
+                Ok(())
+            }
+            SaveDirSubmitted => {
+                let save_dir = match self.save_dir.trim() {
+                    "" => None,
+                    s => Some(s.into()),
+                };
+                manager.update_config(|c| c.save_dir = save_dir).map(|_| ())
+            }
+            OpenSaveDir => manager
+                .save_dir()
+                .and_then(|dir| Ok(open::that(dir)?))
+                .map(|_| ()),
+            DirectX(directx) => manager.update_config(|c| c.directx = directx).map(|_| ()),
+            Theme(choice) => manager.update_config(|c| c.theme = choice).map(|_| ()),
+            Accent(accent) => manager.update_config(|c| c.accent = accent).map(|_| ()),
+            HighContrast(on) => manager.update_config(|c| c.high_contrast = on).map(|_| ()),
+            ReducedMotion(on) => manager.update_config(|c| c.reduced_motion = on).map(|_| ()),
+            ResetWindow => manager.update_config(|c| c.window_size = None).map(|_| ()),
+            UiScale(percent) => manager
+                .update_config(|c| c.ui_scale_percent = percent)
+                .map(|_| ()),
+            PollSecondsChanged(s) => {
+                self.poll_seconds = s;
+                match self.poll_seconds.trim().parse() {
+                    Ok(poll_seconds) => manager
+                        .update_config(|c| c.poll_seconds = poll_seconds)
+                        .map(|_| ()),
+                    Err(_) => Err(anyhow::anyhow!(
+                        "{:?} isn't a number of seconds.",
+                        self.poll_seconds
+                    )),
+                }
+            }
+            Notifications(on) => manager.update_config(|c| c.notifications = on).map(|_| ()),
+            RequestAttention(on) => manager
+                .update_config(|c| c.request_attention = on)
+                .map(|_| ()),
+            CheckForUpdates(on) => manager
+                .update_config(|c| c.check_for_updates = on)
+                .map(|_| ()),
+            QuietNights(on) => manager
+                .update_config(|c| {
+                    let weekends = c.quiet_hours.as_ref().is_some_and(|q| q.weekends);
+                    c.quiet_hours = quiet_hours(on, weekends);
+                })
+                .map(|_| ()),
+            QuietWeekends(on) => manager
+                .update_config(|c| match &mut c.quiet_hours {
+                    // Keep the hours, which may have been set by hand.
+                    Some(quiet_hours) if quiet_hours.start != quiet_hours.end => {
+                        quiet_hours.weekends = on
+                    }
+                    _ => c.quiet_hours = quiet_hours(false, on),
+                })
+                .map(|_| ()),
+            DownloadLimitChanged(s) => {
+                self.download_limit = s;
+                match parse_limit(&self.download_limit) {
+                    Ok(limit) => manager
+                        .update_config(|c| c.download_limit_kib = limit)
+                        .map(|_| ()),
+                    Err(err) => Err(anyhow::anyhow!(err)),
+                }
+            }
+            UploadLimitChanged(s) => {
+                self.upload_limit = s;
+                match parse_limit(&self.upload_limit) {
+                    Ok(limit) => manager
+                        .update_config(|c| c.upload_limit_kib = limit)
+                        .map(|_| ()),
+                    Err(err) => Err(anyhow::anyhow!(err)),
+                }
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                // Keep whatever is being typed, only refresh the rest.
+                if let Ok(config) = manager.config() {
+                    self.config = config;
+                }
+                self.error = None;
+            }
+            Err(err) => {
+                self.error = Some(format!("{:#}", err));
+            }
+        }
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<Message> {
+        let Self {
+            config,
+            detected_save_dir,
+            error,
+            save_dir,
+            poll_seconds,
+            download_limit,
+            upload_limit,
+            scroll_state,
+            save_dir_input_state,
+            use_save_dir_button_state,
+            open_save_dir_button_state,
+            poll_input_state,
+            download_limit_input_state,
+            upload_limit_input_state,
+            account_button_state,
+            archive_button_state,
+            reset_window_button_state,
+            close_settings_button_state,
+        } = self;
+
+        let close_button = theme.action_button(
             ButtonView::TextIcon("Done", done_icon(NORMAL_ICON_SIZE)),
-            Message::SetScreen(Screen::NothingYet),
-            &mut self.close_settings_button_state,
+            Message::SetScreen(Screen::Games),
+            close_settings_button_state,
         );
 
-        close_button.into()
+        let mut column = Column::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(theme.title_text("Settings"));
+        if let Some(error) = error {
+            column = column.push(theme.normal_text(&format!("⚠ {}", error)));
+        }
+
+        column = column
+            .push(heading(theme, "Hotseat save folder"))
+            .push(
+                Row::new()
+                    .height(Length::Units(ROW_HEIGHT))
+                    .push(
+                        TextInput::new(save_dir_input_state, detected_save_dir, save_dir, |s| {
+                            Message::Prefs(PrefsMessage::SaveDirChanged(s))
+                        })
+                        .on_submit(Message::Prefs(PrefsMessage::SaveDirSubmitted))
+                        .padding(10),
+                    )
+                    .push(theme.action_button(
+                        ButtonView::Text("Use"),
+                        Message::Prefs(PrefsMessage::SaveDirSubmitted),
+                        use_save_dir_button_state,
+                    ))
+                    .push(theme.action_button(
+                        ButtonView::Text("Open"),
+                        Message::Prefs(PrefsMessage::OpenSaveDir),
+                        open_save_dir_button_state,
+                    )),
+            )
+            .push(heading(theme, "Launch Civ V with"));
+        for directx in [
+            DirectXVersion::Dx9,
+            DirectXVersion::Dx11,
+            DirectXVersion::Auto,
+        ] {
+            column = column.push(theme.labelled(
+                Radio::new(directx, "", Some(config.directx), |d| {
+                    Message::Prefs(PrefsMessage::DirectX(d))
+                }),
+                &directx.to_string(),
+            ));
+        }
+
+        column = column.push(heading(theme, "Appearance"));
+        for choice in ThemeChoice::ALL {
+            column = column.push(theme.labelled(
+                Radio::new(choice, "", Some(config.theme), |t| {
+                    Message::Prefs(PrefsMessage::Theme(t))
+                }),
+                &choice.to_string(),
+            ));
+        }
+        let mut accents = Row::new().spacing(RELAXED_PADDING / 2);
+        for accent in Accent::ALL {
+            accents = accents.push(theme.labelled(
+                Radio::new(accent, "", Some(config.accent), |a| {
+                    Message::Prefs(PrefsMessage::Accent(a))
+                }),
+                &accent.to_string(),
+            ));
+        }
+        column = column
+            .push(accents)
+            .push(theme.labelled(
+                Checkbox::new(config.high_contrast, "", |on| {
+                    Message::Prefs(PrefsMessage::HighContrast(on))
+                }),
+                "High contrast",
+            ))
+            .push(theme.labelled(
+                Checkbox::new(config.reduced_motion, "", |on| {
+                    Message::Prefs(PrefsMessage::ReducedMotion(on))
+                }),
+                "Reduce motion",
+            ))
+            .push(theme.normal_text("Size").size(16));
+        let mut scales = Row::new().spacing(RELAXED_PADDING / 2);
+        for percent in UI_SCALES.iter().copied() {
+            scales = scales.push(theme.labelled(
+                Radio::new(percent, "", Some(config.ui_scale_percent), |p| {
+                    Message::Prefs(PrefsMessage::UiScale(p))
+                }),
+                &format!("{}%", percent),
+            ));
+        }
+        column = column
+            .push(scales)
+            .push(theme.action_button(
+                ButtonView::Text("Reset window size"),
+                Message::Prefs(PrefsMessage::ResetWindow),
+                reset_window_button_state,
+            ))
+            .push(
+                theme
+                    .normal_text(
+                        "The window goes back to its default size next time civfun \
+                         starts.",
+                    )
+                    .size(16),
+            );
+
+        let weekends = config.quiet_hours.as_ref().is_some_and(|q| q.weekends);
+        let nights = config
+            .quiet_hours
+            .as_ref()
+            .is_some_and(|q| q.start != q.end);
+        column = column
+            .push(heading(theme, "Check GMR every (seconds)"))
+            .push(
+                TextInput::new(poll_input_state, "60", poll_seconds, |s| {
+                    Message::Prefs(PrefsMessage::PollSecondsChanged(s))
+                })
+                .padding(10),
+            )
+            .push(heading(theme, "Notifications"))
+            .push(theme.labelled(
+                Checkbox::new(config.notifications, "", |on| {
+                    Message::Prefs(PrefsMessage::Notifications(on))
+                }),
+                "Desktop notifications",
+            ))
+            .push(theme.labelled(
+                Checkbox::new(config.request_attention, "", |on| {
+                    Message::Prefs(PrefsMessage::RequestAttention(on))
+                }),
+                "Flash the window when it's my turn",
+            ))
+            .push(theme.labelled(
+                Checkbox::new(nights, "", |on| {
+                    Message::Prefs(PrefsMessage::QuietNights(on))
+                }),
+                "Quiet from 22:00 to 07:00",
+            ))
+            .push(theme.labelled(
+                Checkbox::new(weekends, "", |on| {
+                    Message::Prefs(PrefsMessage::QuietWeekends(on))
+                }),
+                "Quiet at weekends",
+            ))
+            .push(heading(theme, "Bandwidth (KiB/s, blank for no limit)"))
+            .push(
+                Row::new()
+                    .spacing(RELAXED_PADDING / 2)
+                    .push(theme.normal_text("Down"))
+                    .push(
+                        TextInput::new(download_limit_input_state, "", download_limit, |s| {
+                            Message::Prefs(PrefsMessage::DownloadLimitChanged(s))
+                        })
+                        .padding(10),
+                    )
+                    .push(theme.normal_text("Up"))
+                    .push(
+                        TextInput::new(upload_limit_input_state, "", upload_limit, |s| {
+                            Message::Prefs(PrefsMessage::UploadLimitChanged(s))
+                        })
+                        .padding(10),
+                    ),
+            )
+            .push(heading(theme, "Updates"))
+            .push(theme.labelled(
+                Checkbox::new(config.check_for_updates, "", |on| {
+                    Message::Prefs(PrefsMessage::CheckForUpdates(on))
+                }),
+                "Check for new versions",
+            ))
+            .push(heading(theme, "Archived saves"))
+            .push(theme.action_button(
+                ButtonView::Text("Browse archived saves"),
+                Message::SetScreen(Screen::Archive),
+                archive_button_state,
+            ))
+            .push(heading(theme, "Accounts"))
+            .push(theme.action_button(
+                ButtonView::Text("Manage accounts"),
+                Message::SetScreen(Screen::Account),
+                account_button_state,
+            ))
+            .push(Space::new(Length::Fill, Length::Units(RELAXED_PADDING)))
+            .push(close_button);
+
+        Scrollable::new(scroll_state)
+            .width(Length::Fill)
+            .push(column)
+            .into()
+    }
+}
+
+/// Quiet hours for the checkboxes: 22:00 to 07:00 for nights, or no time of day at all for
+/// weekends only.
+fn quiet_hours(nights: bool, weekends: bool) -> Option<QuietHours> {
+    if !nights && !weekends {
+        return None;
     }
+    let (start, end) = if nights {
+        (NaiveTime::from_hms(22, 0, 0), NaiveTime::from_hms(7, 0, 0))
+    } else {
+        (NaiveTime::from_hms(0, 0, 0), NaiveTime::from_hms(0, 0, 0))
+    };
+    Some(QuietHours {
+        start,
+        end,
+        weekends,
+    })
+}
+
+fn heading(theme: Theme, s: &str) -> Element<'static, Message> {
+    theme.normal_text(s).size(24).into()
 }
@@ -0,0 +1,94 @@
+use iced::{button, Column, Element};
+use std::path::Path;
+
+use crate::ui::style::{centered_column, vertically_centered_content, ButtonView, Theme};
+use crate::ui::Message;
+use civfun_gmr::manager::{PendingSave, SaveCandidate};
+
+/// Asks which game a new save belongs to when the manager couldn't tell. Saves are asked about
+/// one at a time, oldest first.
+#[derive(Default, Debug)]
+pub struct SaveMatchDialog {
+    pending: Vec<PendingSave>,
+    candidate_button_states: Vec<button::State>,
+    dismiss_button_state: button::State,
+}
+
+impl SaveMatchDialog {
+    /// Saves that were waiting from before a restart.
+    pub fn load(&mut self, pending: Vec<PendingSave>) {
+        for save in pending {
+            self.add(save);
+        }
+    }
+
+    pub fn add(&mut self, save: PendingSave) {
+        self.pending.retain(|p| p.path != save.path);
+        self.pending.push(save);
+    }
+
+    /// The user has picked a game or dismissed the save.
+    pub fn resolved(&mut self, path: &Path) {
+        self.pending.retain(|p| p.path != path);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<'_, Message> {
+        let save = match self.pending.first() {
+            Some(save) => save,
+            None => return theme.normal_text("Nothing to sort out.").into(),
+        };
+        let name = save
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut column = centered_column()
+            .push(theme.title_text("Which game?"))
+            .push(theme.normal_text(&format!(
+                "{} could be a turn in more than one game, or it doesn't look like any of them.",
+                name
+            )));
+
+        self.candidate_button_states
+            .resize_with(save.candidates.len(), Default::default);
+        let mut buttons = Column::new().spacing(4);
+        for (candidate, state) in save
+            .candidates
+            .iter()
+            .zip(self.candidate_button_states.iter_mut())
+        {
+            buttons = buttons.push(theme.action_button(
+                ButtonView::Text(&describe(candidate)),
+                Message::ConfirmSave {
+                    path: save.path.clone(),
+                    game_id: candidate.game.game_id,
+                },
+                state,
+            ));
+        }
+        column = column.push(buttons).push(theme.action_button(
+            ButtonView::Text("It's not a turn, ignore it"),
+            Message::DismissSave(save.path.clone()),
+            &mut self.dismiss_button_state,
+        ));
+
+        vertically_centered_content(column).into()
+    }
+}
+
+/// e.g. `Marathon Madness, turn 43, difference 12`. A lower difference is a closer match.
+fn describe(candidate: &SaveCandidate) -> String {
+    let difference = match candidate.difference {
+        Some(difference) => format!("difference {}", difference),
+        None => "nothing to compare".to_string(),
+    };
+    format!(
+        "{}, turn {}, {}",
+        candidate.game.name, candidate.game.current_turn.number, difference
+    )
+}
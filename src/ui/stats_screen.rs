@@ -0,0 +1,202 @@
+use chrono::Duration;
+use iced::{
+    button, scrollable, Column, Container, Element, Length, Row, Scrollable, Space,
+    VerticalAlignment,
+};
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::ui::games_list::{short_duration, GamesList};
+use crate::ui::style::{BarStyle, ButtonView, Theme, RELAXED_PADDING};
+use crate::ui::{Message, Screen};
+use civfun_gmr::api::GameId;
+use civfun_gmr::manager::Manager;
+use civfun_gmr::stats::Dashboard;
+
+/// How far back the weekly graphs go.
+const WEEKS: usize = 12;
+const AVATAR_SIZE: u16 = 24;
+const GRAPH_HEIGHT: u16 = 60;
+/// The longest bar in the opponents chart.
+const BAR_WIDTH: u16 = 300;
+
+/// Turn times over every game: who's slow, how quick the user has been lately and how much
+/// they've played. Opened from the actions bar.
+#[derive(Default, Debug)]
+pub struct StatsScreen {
+    dashboard: Dashboard,
+    game_names: HashMap<GameId, String>,
+    message: Option<String>,
+
+    scroll_state: scrollable::State,
+    back_button_state: button::State,
+}
+
+impl StatsScreen {
+    pub fn load(&mut self, manager: &Manager) {
+        self.message = None;
+        self.dashboard = manager.dashboard(WEEKS).unwrap_or_else(|err| {
+            warn!(?err, "Loading stats.");
+            self.message = Some(format!("⚠ {:#}", err));
+            Default::default()
+        });
+        self.game_names = manager
+            .games()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|game| (game.game_id, game.name))
+            .collect();
+    }
+
+    pub fn view<'a>(&'a mut self, theme: Theme, games_list: &GamesList) -> Element<'a, Message> {
+        let Self {
+            dashboard,
+            game_names,
+            message,
+            scroll_state,
+            back_button_state,
+        } = self;
+
+        let mut column = Column::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(theme.title_text("Stats"));
+        if let Some(message) = message {
+            column = column.push(theme.normal_text(message).size(16));
+        }
+
+        column = column.push(heading(theme, "Slowest game"));
+        column = column.push(match dashboard.slowest_game {
+            Some((game_id, average)) => theme
+                .normal_text(&format!(
+                    "{}, {} a turn",
+                    game_names
+                        .get(&game_id)
+                        .cloned()
+                        .unwrap_or_else(|| format!("Game {}", game_id)),
+                    short_duration(average)
+                ))
+                .size(16),
+            None => theme.normal_text("No finished turns yet.").size(16),
+        });
+
+        column = column.push(heading(theme, "Average turn by opponent"));
+        if dashboard.opponents.is_empty() {
+            column = column.push(theme.normal_text("No finished turns yet.").size(16));
+        }
+        let slowest = dashboard
+            .opponents
+            .first()
+            .map(|(_, average)| average.num_minutes())
+            .unwrap_or(0)
+            .max(1);
+        for (user_id, average) in &dashboard.opponents {
+            let width = (average.num_minutes() * BAR_WIDTH as i64 / slowest).max(1) as u16;
+            column = column.push(
+                Row::new()
+                    .spacing(RELAXED_PADDING / 2)
+                    .height(Length::Units(AVATAR_SIZE))
+                    .push(games_list.avatar(theme, user_id, AVATAR_SIZE))
+                    .push(
+                        theme
+                            .normal_text(&games_list.name(user_id))
+                            .size(16)
+                            .width(Length::Units(160))
+                            .vertical_alignment(VerticalAlignment::Center),
+                    )
+                    .push(bar(theme, Length::Units(width), Length::Fill))
+                    .push(
+                        theme
+                            .normal_text(&short_duration(*average))
+                            .size(16)
+                            .vertical_alignment(VerticalAlignment::Center),
+                    ),
+            );
+        }
+
+        let trend: Vec<i64> = dashboard
+            .response_trend
+            .iter()
+            .map(|average| average.map_or(0, |d| d.num_minutes()))
+            .collect();
+        let recent = dashboard
+            .response_trend
+            .iter()
+            .rev()
+            .find_map(|average| *average);
+        column = column
+            .push(heading(theme, "Your turn time by week"))
+            .push(sparkline(theme, &trend))
+            .push(theme.normal_text(&caption(recent)).size(16));
+
+        let turns: Vec<i64> = dashboard
+            .turns_per_week
+            .iter()
+            .map(|&turns| turns as i64)
+            .collect();
+        column = column
+            .push(heading(theme, "Turns played by week"))
+            .push(sparkline(theme, &turns))
+            .push(
+                theme
+                    .normal_text(&format!(
+                        "{} turns in the last {} weeks",
+                        turns.iter().sum::<i64>(),
+                        WEEKS
+                    ))
+                    .size(16),
+            );
+
+        column = column
+            .push(Space::new(Length::Fill, Length::Units(RELAXED_PADDING)))
+            .push(theme.action_button(
+                ButtonView::Text("Back"),
+                Message::SetScreen(Screen::Games),
+                back_button_state,
+            ));
+
+        Scrollable::new(scroll_state)
+            .width(Length::Fill)
+            .push(column)
+            .into()
+    }
+}
+
+fn heading<'a>(theme: Theme, s: &str) -> Element<'a, Message> {
+    theme.normal_text(s).size(24).into()
+}
+
+fn bar<'a>(theme: Theme, width: Length, height: Length) -> Element<'a, Message> {
+    Container::new(Space::new(Length::Fill, Length::Fill))
+        .width(width)
+        .height(height)
+        .style(BarStyle {
+            theme,
+            highlight: false,
+        })
+        .into()
+}
+
+/// A bar per value, oldest on the left, scaled to the biggest.
+fn sparkline<'a>(theme: Theme, values: &[i64]) -> Element<'a, Message> {
+    let biggest = values.iter().copied().max().unwrap_or(0).max(1);
+    let mut row = Row::new()
+        .spacing(2)
+        .height(Length::Units(GRAPH_HEIGHT))
+        .align_items(iced::Align::End);
+    for value in values {
+        let height = (value * GRAPH_HEIGHT as i64 / biggest).max(1) as u16;
+        row = row.push(bar(theme, Length::Units(16), Length::Units(height)));
+    }
+    row.into()
+}
+
+fn caption(recent: Option<Duration>) -> String {
+    match recent {
+        Some(average) => format!(
+            "Over the last {} weeks, most recently {} a turn",
+            WEEKS,
+            short_duration(average)
+        ),
+        None => format!("No turns played in the last {} weeks", WEEKS),
+    }
+}
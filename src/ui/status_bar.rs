@@ -0,0 +1,87 @@
+use chrono::{DateTime, Local};
+use iced::{Element, Length, Row, VerticalAlignment};
+
+use crate::ui::style::{Theme, RELAXED_PADDING};
+use crate::ui::Message;
+use civfun_gmr::manager::{Event, Manager};
+
+const STATUS_TEXT_SIZE: u16 = 16;
+
+/// The line at the bottom of the window. The latest message is set by whoever handles an
+/// event, the rest is kept up to date from events and `poll()`.
+#[derive(Default, Debug)]
+pub struct StatusBar {
+    message: String,
+    last_refresh: Option<DateTime<Local>>,
+    /// The last fetch failed, until one works again.
+    offline: bool,
+    fetching: bool,
+    transfers: usize,
+    watching: bool,
+    spinner_frame: usize,
+}
+
+impl StatusBar {
+    pub fn set_message<S: Into<String>>(&mut self, message: S) {
+        self.message = message.into();
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::UpdatedGames(_) => {
+                self.last_refresh = Some(Local::now());
+                self.offline = false;
+            }
+            Event::FetchGamesFailed { .. } => {
+                self.offline = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Picks up what the manager is doing. Called as often as the manager is processed.
+    pub fn poll(&mut self, manager: &Manager) {
+        self.fetching = manager.fetching();
+        self.watching = manager.watching_saves();
+        self.transfers = manager
+            .download_status()
+            .iter()
+            .filter(|s| s.state.is_active())
+            .count();
+        self.spinner_frame = self.spinner_frame.wrapping_add(1);
+    }
+
+    pub fn view(&self, theme: Theme) -> Element<'_, Message> {
+        let mut parts = vec![];
+        if self.fetching {
+            parts.push(format!("{} Refreshing", theme.spinner(self.spinner_frame)));
+        } else if let Some(last_refresh) = self.last_refresh {
+            parts.push(format!("Refreshed {}", last_refresh.format("%H:%M")));
+        }
+        if self.offline {
+            parts.push("⚠ Offline".to_string());
+        }
+        if self.transfers > 0 {
+            parts.push(format!("{} transferring", self.transfers));
+        }
+        if !self.watching {
+            parts.push("⚠ Not watching for saves".to_string());
+        }
+
+        Row::new()
+            .spacing(RELAXED_PADDING)
+            .push(
+                theme
+                    .normal_text(&self.message)
+                    .size(STATUS_TEXT_SIZE)
+                    .width(Length::Fill)
+                    .vertical_alignment(VerticalAlignment::Center),
+            )
+            .push(
+                theme
+                    .normal_text(&parts.join("  ·  "))
+                    .size(STATUS_TEXT_SIZE),
+            )
+            .into()
+    }
+}
@@ -1,16 +1,22 @@
 use iced::{
-    button, Align, Application, Button, Color, Column, Container, Element, Font,
-    HorizontalAlignment, Length, Row, Space, Text, VerticalAlignment,
+    button, container, Align, Button, Color, Column, Container, Element, Font, HorizontalAlignment,
+    Length, Row, Space, Text, VerticalAlignment,
 };
 
+use crate::ui::toasts::Severity;
 use crate::ui::Message;
 use crate::TITLE;
+use civfun_gmr::api::Urgency;
+use civfun_gmr::config::{Accent, Config, ThemeChoice};
+use civfun_gmr::os_theme;
 
 pub const ROW_HEIGHT: u16 = 40;
 pub const NORMAL_ICON_SIZE: u16 = 20;
 
 pub const RELAXED_PADDING: u16 = 20;
 
+const SPINNER: [&str; 4] = ["◐", "◓", "◑", "◒"];
+
 const FA_SOLID_ICONS: Font = Font::External {
     name: "FA Solid Icons",
     bytes: include_bytes!("../../fonts/fa-solid-900.ttf"),
@@ -21,6 +27,188 @@ const FA_BRANDS_ICONS: Font = Font::External {
     bytes: include_bytes!("../../fonts/fa-brands-400.ttf"),
 };
 
+/// The colours everything is drawn with. Made from the config with `Theme::from_config()` and
+/// passed down to each view.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub background: Color,
+    pub text: Color,
+    pub accent: Color,
+    /// Behind buttons, a little darker than the background.
+    surface: Color,
+    pressed: Color,
+    disabled: Color,
+    /// Buttons get an outline so they can be told apart from the background.
+    outlined: bool,
+    /// See `Config::reduced_motion`.
+    pub reduced_motion: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new(true, Accent::default())
+    }
+}
+
+impl Theme {
+    pub fn new(dark: bool, accent: Accent) -> Self {
+        let accent = accent_colour(accent);
+        if dark {
+            Self {
+                background: Color::from_rgb(0.168, 0.243, 0.313),
+                text: Color::from_rgb(0.9, 0.9, 1.0),
+                accent,
+                surface: Color::new(0.0, 0.0, 0.0, 0.25),
+                pressed: Color::new(0.0, 0.0, 0.0, 0.5),
+                disabled: Color::new(0.5, 0.5, 0.5, 0.5),
+                outlined: false,
+                reduced_motion: false,
+            }
+        } else {
+            Self {
+                background: Color::from_rgb(0.94, 0.95, 0.97),
+                text: Color::from_rgb(0.12, 0.14, 0.18),
+                accent,
+                surface: Color::new(0.0, 0.0, 0.0, 0.08),
+                pressed: Color::new(0.0, 0.0, 0.0, 0.2),
+                disabled: Color::new(0.5, 0.5, 0.5, 0.3),
+                outlined: false,
+                reduced_motion: false,
+            }
+        }
+    }
+
+    /// Pure black and white, with solid colours instead of see-through ones.
+    pub fn high_contrast(dark: bool, accent: Accent) -> Self {
+        let (background, text, surface, pressed) = if dark {
+            (
+                Color::BLACK,
+                Color::WHITE,
+                Color::from_rgb(0.12, 0.12, 0.12),
+                Color::from_rgb(0.3, 0.3, 0.3),
+            )
+        } else {
+            (
+                Color::WHITE,
+                Color::BLACK,
+                Color::from_rgb(0.88, 0.88, 0.88),
+                Color::from_rgb(0.7, 0.7, 0.7),
+            )
+        };
+        Self {
+            background,
+            text,
+            accent: accent_colour(accent),
+            surface,
+            pressed,
+            disabled: Color::from_rgb(0.5, 0.5, 0.5),
+            outlined: true,
+            reduced_motion: false,
+        }
+    }
+
+    /// Asks the OS for `ThemeChoice::System`, so this is best called when the config changes
+    /// rather than for every view.
+    pub fn from_config(config: &Config) -> Self {
+        let dark = match config.theme {
+            ThemeChoice::System => os_theme::prefers_dark().unwrap_or(true),
+            ThemeChoice::Dark => true,
+            ThemeChoice::Light => false,
+        };
+        let theme = if config.high_contrast {
+            Self::high_contrast(dark, config.accent)
+        } else {
+            Self::new(dark, config.accent)
+        };
+        Self {
+            reduced_motion: config.reduced_motion,
+            ..theme
+        }
+    }
+
+    /// Something to show next to work in progress, which turns on each `frame` unless motion
+    /// is reduced.
+    pub fn spinner(&self, frame: usize) -> &'static str {
+        if self.reduced_motion {
+            "…"
+        } else {
+            SPINNER[frame % SPINNER.len()]
+        }
+    }
+
+    pub fn title(&self) -> Element<'static, Message> {
+        Text::new(TITLE)
+            .width(Length::Fill)
+            .height(Length::Shrink)
+            .size(30)
+            .color(self.accent)
+            .horizontal_alignment(HorizontalAlignment::Left)
+            .vertical_alignment(VerticalAlignment::Top)
+            .into()
+    }
+
+    pub fn title_text(&self, s: &str) -> Text {
+        Text::new(s).color(self.text).size(40)
+    }
+
+    pub fn normal_text(&self, s: &str) -> Text {
+        Text::new(s).color(self.text)
+    }
+
+    /// The built in checkbox and radio labels are black, which can't be read on a dark
+    /// background.
+    pub fn labelled<'a, M: 'a, E>(&self, widget: E, label: &str) -> Element<'a, M>
+    where
+        E: Into<Element<'a, M>>,
+    {
+        Row::new()
+            .spacing(RELAXED_PADDING / 2)
+            .push(widget)
+            .push(self.normal_text(label))
+            .into()
+    }
+
+    pub fn action_button<'a, M: 'a>(
+        &self,
+        view: ButtonView,
+        message: M,
+        state: &'a mut button::State,
+    ) -> Button<'a, M>
+    where
+        M: Clone,
+    {
+        Button::new(state, self.button_row(view))
+            .on_press(message)
+            .style(ActionButtonStyle(*self))
+    }
+
+    fn button_row<'a, M: 'a>(&self, view: ButtonView) -> Row<'a, M> {
+        let mut row: Row<M> = Row::new().height(Length::Units(ROW_HEIGHT));
+        let (text, icon) = view.parts();
+        if let Some(icon) = icon {
+            row = row.push(button_side_pad()).push(icon);
+        }
+        if let Some(text) = text {
+            row = row.push(button_side_pad()).push(
+                self.normal_text(text)
+                    .vertical_alignment(VerticalAlignment::Center)
+                    .height(Length::Fill),
+            );
+        }
+        row.push(button_side_pad())
+    }
+}
+
+fn accent_colour(accent: Accent) -> Color {
+    match accent {
+        Accent::Blue => Color::from_rgb(0.25, 0.5, 0.8),
+        Accent::Green => Color::from_rgb(0.25, 0.6, 0.35),
+        Accent::Orange => Color::from_rgb(0.85, 0.5, 0.15),
+        Accent::Purple => Color::from_rgb(0.55, 0.35, 0.75),
+        Accent::Red => Color::from_rgb(0.75, 0.25, 0.25),
+    }
+}
+
 pub fn centered_column<'a, M>() -> Column<'a, M> {
     Column::new()
         .width(Length::Fill)
@@ -38,17 +226,6 @@ where
         .align_y(Align::Center)
 }
 
-pub fn title() -> Element<'static, Message> {
-    Text::new(TITLE)
-        .width(Length::Fill)
-        .height(Length::Shrink)
-        .size(30)
-        .color(text_colour())
-        .horizontal_alignment(HorizontalAlignment::Left)
-        .vertical_alignment(VerticalAlignment::Top)
-        .into()
-}
-
 fn button_side_pad() -> Space {
     Space::new(Length::Units(10), Length::Units(24))
 }
@@ -76,36 +253,7 @@ impl<'a> ButtonView<'a> {
     }
 }
 
-fn button_row<'a, M: 'a>(view: ButtonView) -> Row<'a, M> {
-    let mut row: Row<M> = Row::new().height(Length::Units(ROW_HEIGHT));
-    let (text, icon) = view.parts();
-    if let Some(icon) = icon {
-        row = row.push(button_side_pad()).push(icon);
-    }
-    if let Some(text) = text {
-        row = row.push(button_side_pad()).push(
-            normal_text(text)
-                .vertical_alignment(VerticalAlignment::Center)
-                .height(Length::Fill),
-        );
-    }
-    row.push(button_side_pad())
-}
-
-pub fn action_button<'a, M: 'a>(
-    view: ButtonView,
-    message: M,
-    state: &'a mut button::State,
-) -> Button<'a, M>
-where
-    M: Clone,
-{
-    Button::new(state, button_row(view))
-        .on_press(message)
-        .style(ActionButtonStyle)
-        .into()
-}
-
+/// Without a colour of its own, an icon takes the text colour of the button it's in.
 fn icon(font: Font, unicode: char, size: u16) -> Text {
     Text::new(&unicode.to_string())
         .font(font)
@@ -113,7 +261,6 @@ fn icon(font: Font, unicode: char, size: u16) -> Text {
         .height(Length::Fill)
         .horizontal_alignment(HorizontalAlignment::Center)
         .vertical_alignment(VerticalAlignment::Center)
-        .color(Color::WHITE)
         .size(size)
 }
 
@@ -125,49 +272,159 @@ pub fn steam_icon(size: u16) -> Text {
     icon(FA_BRANDS_ICONS, '', size)
 }
 
+pub fn user_icon(size: u16) -> Text {
+    icon(FA_SOLID_ICONS, '', size)
+}
+
 pub fn done_icon(size: u16) -> Text {
     icon(FA_SOLID_ICONS, '', size)
 }
 
-fn text_colour() -> Color {
-    Color::from_rgb(0.9, 0.9, 1.0)
+pub fn bell_icon(size: u16) -> Text {
+    icon(FA_SOLID_ICONS, '', size)
 }
 
-fn black() -> Color {
-    Color::BLACK
+pub fn bell_slash_icon(size: u16) -> Text {
+    icon(FA_SOLID_ICONS, '', size)
 }
 
-fn black_50alpha() -> Color {
-    Color::new(0.0, 0.0, 0.0, 0.5)
+/// A plain block of colour, e.g. for a bar in a graph.
+pub struct BarStyle {
+    pub theme: Theme,
+    pub highlight: bool,
 }
 
-fn black_25alpha() -> Color {
-    Color::new(0.0, 0.0, 0.0, 0.25)
+impl container::StyleSheet for BarStyle {
+    fn style(&self) -> container::Style {
+        let colour = if self.highlight {
+            Color::from_rgb(0.8, 0.3, 0.3)
+        } else {
+            self.theme.accent
+        };
+        container::Style {
+            background: Some(colour.into()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Colours to tell players apart by, e.g. in a game's turn timeline.
+const PLAYER_COLOURS: [Color; 8] = [
+    Color::from_rgb(0.3, 0.55, 0.85),
+    Color::from_rgb(0.9, 0.6, 0.2),
+    Color::from_rgb(0.35, 0.7, 0.4),
+    Color::from_rgb(0.65, 0.45, 0.8),
+    Color::from_rgb(0.85, 0.75, 0.3),
+    Color::from_rgb(0.3, 0.7, 0.7),
+    Color::from_rgb(0.85, 0.45, 0.6),
+    Color::from_rgb(0.6, 0.6, 0.6),
+];
+
+/// The colour for the player at `index`, e.g. their place in the turn order.
+pub fn player_colour(index: usize) -> Color {
+    PLAYER_COLOURS[index % PLAYER_COLOURS.len()]
 }
 
-fn grey_50alpha() -> Color {
-    Color::new(0.5, 0.5, 0.5, 0.5)
+/// One turn in a timeline, in its player's colour. Skipped turns get a red outline.
+pub struct SegmentStyle {
+    pub theme: Theme,
+    pub colour: Color,
+    pub skipped: bool,
 }
 
-pub fn background_color() -> Color {
-    Color::from_rgb(0.168, 0.243, 0.313)
+impl container::StyleSheet for SegmentStyle {
+    fn style(&self) -> container::Style {
+        container::Style {
+            background: Some(self.colour.into()),
+            border_width: if self.skipped { 2.0 } else { 1.0 },
+            border_color: if self.skipped {
+                Color::from_rgb(0.8, 0.3, 0.3)
+            } else {
+                self.theme.background
+            },
+            ..Default::default()
+        }
+    }
 }
 
-pub fn title_text(s: &str) -> Text {
-    Text::new(s).color(text_colour()).size(40)
+pub struct ToastStyle {
+    pub theme: Theme,
+    pub severity: Severity,
 }
 
-pub fn normal_text(s: &str) -> Text {
-    Text::new(s).color(text_colour())
+impl container::StyleSheet for ToastStyle {
+    fn style(&self) -> container::Style {
+        let colour = match self.severity {
+            Severity::Info => self.theme.accent,
+            Severity::Success => Color::from_rgb(0.2, 0.45, 0.3),
+            Severity::Warning => Color::from_rgb(0.55, 0.45, 0.15),
+            Severity::Error => Color::from_rgb(0.6, 0.2, 0.2),
+        };
+        container::Style {
+            background: Some(colour.into()),
+            text_color: Some(Color::WHITE),
+            ..Default::default()
+        }
+    }
+}
+
+/// A turn's countdown: green, yellow or red as the deadline gets closer.
+pub struct BadgeStyle(pub Urgency);
+
+impl container::StyleSheet for BadgeStyle {
+    fn style(&self) -> container::Style {
+        let colour = match self.0 {
+            Urgency::Relaxed => Color::from_rgb(0.2, 0.55, 0.3),
+            Urgency::Soon => Color::from_rgb(0.75, 0.6, 0.1),
+            Urgency::Urgent => Color::from_rgb(0.75, 0.2, 0.2),
+        };
+        container::Style {
+            background: Some(colour.into()),
+            text_color: Some(Color::WHITE),
+            border_radius: 4.0,
+            ..Default::default()
+        }
+    }
+}
+
+/// A block that stands out from the rest of a screen.
+pub struct PanelStyle(pub Theme);
+
+impl container::StyleSheet for PanelStyle {
+    fn style(&self) -> container::Style {
+        container::Style {
+            background: Some(self.0.surface.into()),
+            text_color: Some(self.0.text),
+            border_radius: 4.0,
+            border_width: 1.0,
+            border_color: self.0.accent,
+        }
+    }
+}
+
+pub struct TooltipStyle(pub Theme);
+
+impl container::StyleSheet for TooltipStyle {
+    fn style(&self) -> container::Style {
+        container::Style {
+            background: Some(self.0.background.into()),
+            text_color: Some(self.0.text),
+            border_radius: 4.0,
+            border_width: 1.0,
+            border_color: self.0.accent,
+        }
+    }
 }
 
-pub struct ActionButtonStyle;
+pub struct ActionButtonStyle(pub Theme);
 
 impl ActionButtonStyle {
-    fn base() -> button::Style {
+    fn base(&self) -> button::Style {
         button::Style {
-            background: Some(black_25alpha().into()),
-            text_color: Color::WHITE,
+            background: Some(self.0.surface.into()),
+            text_color: self.0.text,
+            border_width: if self.0.outlined { 1.0 } else { 0.0 },
+            border_color: self.0.text,
             ..Default::default()
         }
     }
@@ -175,27 +432,27 @@ impl ActionButtonStyle {
 
 impl button::StyleSheet for ActionButtonStyle {
     fn active(&self) -> button::Style {
-        Self::base()
+        self.base()
     }
 
     fn hovered(&self) -> button::Style {
         button::Style {
-            background: Some(black().into()),
-            ..Self::base()
+            background: Some(self.0.accent.into()),
+            ..self.base()
         }
     }
 
     fn pressed(&self) -> button::Style {
         button::Style {
-            background: Some(black_50alpha().into()),
-            ..Self::base()
+            background: Some(self.0.pressed.into()),
+            ..self.base()
         }
     }
 
     fn disabled(&self) -> button::Style {
         button::Style {
-            background: Some(grey_50alpha().into()),
-            ..Self::base()
+            background: Some(self.0.disabled.into()),
+            ..self.base()
         }
     }
 }
@@ -0,0 +1,143 @@
+use iced::{button, Button, Column, Container, Element, Length, Row, VerticalAlignment};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::ui::style::{ActionButtonStyle, Theme, ToastStyle, RELAXED_PADDING};
+use crate::ui::Message;
+use civfun_gmr::api::Game;
+use civfun_gmr::event_log;
+use civfun_gmr::manager::Event;
+
+/// Older toasts are dropped once there are more than this.
+const MAX_TOASTS: usize = 4;
+const TOAST_TEXT_SIZE: u16 = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Errors stay up for longer so they aren't missed.
+    fn duration(&self) -> Duration {
+        match self {
+            Severity::Info | Severity::Success => Duration::from_secs(6),
+            Severity::Warning => Duration::from_secs(12),
+            Severity::Error => Duration::from_secs(30),
+        }
+    }
+
+    /// Which events get a toast, and how bad they are.
+    fn of(event: &Event) -> Option<Severity> {
+        Some(match event {
+            Event::NewTurn(_)
+            | Event::DownloadComplete { .. }
+            | Event::DuplicateTurnIgnored { .. }
+            | Event::AccountSwitched(_) => Severity::Info,
+            Event::UploadComplete(_) | Event::AuthenticationSuccess => Severity::Success,
+            Event::TurnDeadlineApproaching { .. }
+            | Event::FetchGamesFailed { .. }
+            | Event::SaveRepaired { .. }
+            | Event::Error {
+                recoverable: true, ..
+            } => Severity::Warning,
+            Event::DownloadFailed { .. }
+            | Event::UploadFailed { .. }
+            | Event::AuthenticationFailure
+            | Event::TurnAboutToBeSkipped { .. }
+            | Event::DatabaseCorrupt { .. }
+            | Event::Error { .. } => Severity::Error,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Toast {
+    id: u64,
+    severity: Severity,
+    text: String,
+    shown_at: Instant,
+    dismiss_button_state: button::State,
+}
+
+/// Short lived messages shown in the window for things the user might have missed as a
+/// desktop notification. Each one goes away by itself or when clicked.
+#[derive(Default, Debug)]
+pub struct Toasts {
+    toasts: VecDeque<Toast>,
+    next_id: u64,
+}
+
+impl Toasts {
+    pub fn push<S: Into<String>>(&mut self, severity: Severity, text: S) {
+        self.toasts.push_back(Toast {
+            id: self.next_id,
+            severity,
+            text: text.into(),
+            shown_at: Instant::now(),
+            dismiss_button_state: Default::default(),
+        });
+        self.next_id += 1;
+        while self.toasts.len() > MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+    }
+
+    /// `games` is used to name the game an event is about.
+    pub fn handle_event(&mut self, event: &Event, games: &[Game]) {
+        let severity = match Severity::of(event) {
+            Some(severity) => severity,
+            None => return,
+        };
+        let game = event
+            .game_id()
+            .and_then(|game_id| games.iter().find(|g| g.game_id == game_id));
+        if let Some(text) = event_log::describe(event, game) {
+            self.push(severity, text);
+        }
+    }
+
+    pub fn dismiss(&mut self, id: u64) {
+        self.toasts.retain(|t| t.id != id);
+    }
+
+    /// Removes toasts that have been up long enough.
+    pub fn expire(&mut self, now: Instant) {
+        self.toasts
+            .retain(|t| now.saturating_duration_since(t.shown_at) < t.severity.duration());
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<'_, Message> {
+        let mut column = Column::new().spacing(RELAXED_PADDING / 4);
+        for toast in self.toasts.iter_mut() {
+            let content = Row::new().push(
+                theme
+                    .normal_text(&toast.text)
+                    .size(TOAST_TEXT_SIZE)
+                    .width(Length::Fill)
+                    .vertical_alignment(VerticalAlignment::Center),
+            );
+            column = column.push(
+                Button::new(
+                    &mut toast.dismiss_button_state,
+                    Container::new(content)
+                        .width(Length::Fill)
+                        .padding(RELAXED_PADDING / 4)
+                        .style(ToastStyle {
+                            theme,
+                            severity: toast.severity,
+                        }),
+                )
+                .width(Length::Fill)
+                .padding(0)
+                .on_press(Message::DismissToast(toast.id))
+                .style(ActionButtonStyle(theme)),
+            );
+        }
+        column.into()
+    }
+}
@@ -0,0 +1,91 @@
+use iced::{button, Column, Container, Element, Length, Row, Space};
+
+use crate::ui::style::{ButtonView, PanelStyle, Theme, RELAXED_PADDING};
+use crate::ui::Message;
+use civfun_gmr::update::Release;
+
+/// A strip under the title when there's a newer release, which can be put away until the next
+/// check finds it again.
+#[derive(Default, Debug)]
+pub struct UpdateBanner {
+    release: Option<Release>,
+    show_notes: bool,
+    notes_button_state: button::State,
+    open_button_state: button::State,
+    dismiss_button_state: button::State,
+}
+
+#[derive(Debug, Clone)]
+pub enum UpdateMessage {
+    ToggleNotes,
+    Open,
+    Dismiss,
+}
+
+impl UpdateBanner {
+    pub fn found(&mut self, release: Release) {
+        self.release = Some(release);
+    }
+
+    pub fn update(&mut self, message: UpdateMessage) -> anyhow::Result<()> {
+        match message {
+            UpdateMessage::ToggleNotes => self.show_notes = !self.show_notes,
+            UpdateMessage::Open => {
+                if let Some(release) = &self.release {
+                    open::that(&release.html_url)?;
+                }
+            }
+            UpdateMessage::Dismiss => {
+                self.release = None;
+                self.show_notes = false;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<'_, Message> {
+        let release = match &self.release {
+            Some(release) => release,
+            None => return Space::new(Length::Shrink, Length::Shrink).into(),
+        };
+        let notes = release.notes();
+
+        let mut buttons = Row::new().spacing(RELAXED_PADDING / 2);
+        if notes.is_some() {
+            buttons = buttons.push(theme.action_button(
+                ButtonView::Text(if self.show_notes {
+                    "Hide changes"
+                } else {
+                    "What's new"
+                }),
+                Message::Update(UpdateMessage::ToggleNotes),
+                &mut self.notes_button_state,
+            ));
+        }
+        buttons = buttons
+            .push(theme.action_button(
+                ButtonView::Text("Download"),
+                Message::Update(UpdateMessage::Open),
+                &mut self.open_button_state,
+            ))
+            .push(theme.action_button(
+                ButtonView::Text("Later"),
+                Message::Update(UpdateMessage::Dismiss),
+                &mut self.dismiss_button_state,
+            ));
+
+        let mut column = Column::new()
+            .spacing(4)
+            .push(theme.normal_text(&format!("civfun {} is out", release.version())))
+            .push(buttons);
+        if let (true, Some(notes)) = (self.show_notes, notes) {
+            column = column.push(theme.normal_text(&notes).size(16));
+        }
+
+        Container::new(column)
+            .width(Length::Fill)
+            .padding(RELAXED_PADDING / 2)
+            .style(PanelStyle(theme))
+            .into()
+    }
+}
@@ -0,0 +1,99 @@
+use iced::{button, Column, Container, Element, Length, Row, Space};
+
+use crate::ui::confirm::Confirmation;
+use crate::ui::style::{ButtonView, PanelStyle, Theme, RELAXED_PADDING};
+use crate::ui::Message;
+use civfun_gmr::manager::{Manager, UploadSummary};
+use tracing::warn;
+
+/// Consecutive turns of the same game are usually a handful apart, unrelated games hundreds.
+const CLOSE_DIFFERENCE: u32 = 50;
+
+/// Shown above the games while `confirm_uploads` is holding saves back, one block per save with
+/// what was found in it.
+#[derive(Default, Debug)]
+pub struct UploadApproval {
+    summaries: Vec<UploadSummary>,
+    /// Approve and discard for each summary.
+    button_states: Vec<(button::State, button::State)>,
+}
+
+impl UploadApproval {
+    pub fn load(&mut self, manager: &Manager) {
+        match manager.uploads_awaiting_approval() {
+            Ok(summaries) => self.summaries = summaries,
+            Err(err) => warn!(?err, "Loading uploads awaiting approval."),
+        }
+    }
+
+    pub fn view(&mut self, theme: Theme) -> Element<'_, Message> {
+        if self.summaries.is_empty() {
+            return Space::new(Length::Shrink, Length::Shrink).into();
+        }
+        self.button_states
+            .resize_with(self.summaries.len(), Default::default);
+
+        let mut column = Column::new().spacing(RELAXED_PADDING / 2);
+        for (summary, (approve_state, discard_state)) in
+            self.summaries.iter().zip(self.button_states.iter_mut())
+        {
+            let game_id = summary.game.game_id;
+            let name = summary
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let mut details = format!("Turn {} in the save", summary.turn);
+            if let Some(leader) = &summary.leader {
+                details.push_str(&format!(", {} to play next", leader));
+            }
+            let content = Column::new()
+                .spacing(4)
+                .push(theme.normal_text(&format!("Upload {}?", summary.game.name)))
+                .push(theme.normal_text(&name).size(16))
+                .push(theme.normal_text(&details).size(16))
+                .push(theme.normal_text(&confidence(summary.difference)).size(16))
+                .push(
+                    Row::new()
+                        .spacing(RELAXED_PADDING / 2)
+                        .push(theme.action_button(
+                            ButtonView::Text("Approve"),
+                            Message::Upload(game_id),
+                            approve_state,
+                        ))
+                        .push(theme.action_button(
+                            ButtonView::Text("Discard"),
+                            Message::Confirm(Confirmation::new(
+                                "Discard this save?",
+                                "It won't be uploaded. The file stays where it is, but civfun \
+                                 won't offer it again.",
+                                "Discard",
+                                Message::DiscardUpload(game_id),
+                            )),
+                            discard_state,
+                        )),
+                );
+            column = column.push(
+                Container::new(content)
+                    .width(Length::Fill)
+                    .padding(RELAXED_PADDING / 2)
+                    .style(PanelStyle(theme)),
+            );
+        }
+        column.into()
+    }
+}
+
+/// How sure we are the save is a turn of this game, going by its difference score.
+fn confidence(difference: Option<u32>) -> String {
+    match difference {
+        None => "Nothing downloaded to compare it with".to_string(),
+        Some(difference) if difference <= CLOSE_DIFFERENCE => {
+            format!("Looks like this game (difference {})", difference)
+        }
+        Some(difference) => format!(
+            "⚠ Doesn't look much like this game (difference {})",
+            difference
+        ),
+    }
+}
@@ -0,0 +1,172 @@
+use anyhow::Context;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+type Result<T> = anyhow::Result<T>;
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/civfun/civfun_gmr/releases/latest";
+/// GitHub turns away API requests without one.
+const USER_AGENT: &str = "civfun_gmr";
+/// Release notes can go on for pages, only the start fits in a banner.
+const MAX_NOTES_LINES: usize = 12;
+
+/// How often to look for a new version while the app is left running.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The parts of a GitHub release that are used.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Release {
+    /// e.g. `v0.2.0`.
+    pub tag_name: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The release notes, in Markdown.
+    #[serde(default)]
+    pub body: Option<String>,
+    /// The release's page, which has the downloads.
+    pub html_url: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+}
+
+impl Release {
+    /// e.g. `0.2.0`, without the `v`.
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    /// The start of the release notes, or None when there aren't any.
+    pub fn notes(&self) -> Option<String> {
+        let body = self.body.as_deref()?.trim();
+        if body.is_empty() {
+            return None;
+        }
+        let lines: Vec<&str> = body.lines().collect();
+        let mut notes = lines[..lines.len().min(MAX_NOTES_LINES)].join("\n");
+        if lines.len() > MAX_NOTES_LINES {
+            notes.push_str("\n…");
+        }
+        Some(notes)
+    }
+
+    /// Whether this release is newer than `current`, e.g. `0.0.1`. Drafts, prereleases and
+    /// tags that aren't versions never are.
+    pub fn is_newer_than(&self, current: &str) -> bool {
+        if self.draft || self.prerelease {
+            return false;
+        }
+        match (parse_version(self.version()), parse_version(current)) {
+            (Some(release), Some(current)) => release > current,
+            _ => false,
+        }
+    }
+}
+
+/// `1.2.3` as `(1, 2, 3)`. Anything after a `-` or `+` is ignored, and missing parts are 0.
+fn parse_version(s: &str) -> Option<(u64, u64, u64)> {
+    let s = s.trim().trim_start_matches('v');
+    let core = s.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+#[instrument]
+async fn latest_release() -> Result<Release> {
+    debug!("Checking for a new release.");
+    let bytes = reqwest::Client::new()
+        .get(LATEST_RELEASE_URL)
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await
+        .context("Fetching the latest release.")?
+        .error_for_status()
+        .context("Latest release response.")?
+        .bytes()
+        .await?;
+    serde_json::from_slice(&bytes).context("Decoding the latest release.")
+}
+
+/// The latest release if it's newer than `current`.
+pub async fn check(current: &str) -> Result<Option<Release>> {
+    let release = latest_release().await?;
+    debug!(version = release.version(), current, "Latest release.");
+    Ok(Some(release).filter(|r| r.is_newer_than(current)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag_name: &str) -> Release {
+        Release {
+            tag_name: tag_name.into(),
+            name: None,
+            body: None,
+            html_url: "https://github.com/civfun/civfun_gmr/releases/tag/v0.1.0".into(),
+            prerelease: false,
+            draft: false,
+        }
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(parse_version("0.0.1"), Some((0, 0, 1)));
+        assert_eq!(parse_version("v1.2"), Some((1, 2, 0)));
+        assert_eq!(parse_version("1.2.3-beta.1"), Some((1, 2, 3)));
+        assert_eq!(parse_version("nightly"), None);
+        assert_eq!(parse_version("1.2.3.4"), None);
+    }
+
+    #[test]
+    fn newer() {
+        assert!(release("v0.1.0").is_newer_than("0.0.1"));
+        assert!(release("v0.0.10").is_newer_than("0.0.9"));
+        assert!(!release("v0.0.1").is_newer_than("0.0.1"));
+        assert!(!release("v0.0.1").is_newer_than("0.1.0"));
+        assert!(!release("nightly").is_newer_than("0.0.1"));
+
+        let mut prerelease = release("v0.1.0");
+        prerelease.prerelease = true;
+        assert!(!prerelease.is_newer_than("0.0.1"));
+    }
+
+    #[test]
+    fn notes() {
+        let mut r = release("v0.1.0");
+        assert_eq!(r.notes(), None);
+        r.body = Some("  \n".into());
+        assert_eq!(r.notes(), None);
+        r.body = Some("- Faster\n- Prettier\n".into());
+        assert_eq!(r.notes().unwrap(), "- Faster\n- Prettier");
+        r.body = Some((0..20).map(|i| format!("- {}\n", i)).collect());
+        let notes = r.notes().unwrap();
+        assert_eq!(notes.lines().count(), MAX_NOTES_LINES + 1);
+        assert!(notes.ends_with('…'));
+    }
+
+    #[test]
+    fn decode() {
+        let json = r#"{
+            "tag_name": "v0.2.0",
+            "name": "0.2.0",
+            "body": "Notes",
+            "html_url": "https://github.com/civfun/civfun_gmr/releases/tag/v0.2.0",
+            "prerelease": false,
+            "draft": false,
+            "assets": []
+        }"#;
+        let release: Release = serde_json::from_str(json).unwrap();
+        assert_eq!(release.version(), "0.2.0");
+        assert!(release.is_newer_than("0.0.1"));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::api::GameId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Save bytes sent and received, for users on metered connections. Only finished transfers are
+/// counted, and API calls and avatars are left out since they're tiny next to a save.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transferred {
+    pub downloaded_bytes: u64,
+    pub uploaded_bytes: u64,
+    pub downloads: u32,
+    pub uploads: u32,
+}
+
+impl Transferred {
+    pub fn add_download(&mut self, bytes: u64) {
+        self.downloaded_bytes += bytes;
+        self.downloads += 1;
+    }
+
+    pub fn add_upload(&mut self, bytes: u64) {
+        self.uploaded_bytes += bytes;
+        self.uploads += 1;
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.downloaded_bytes + self.uploaded_bytes
+    }
+
+    fn add(&mut self, other: &Transferred) {
+        self.downloaded_bytes += other.downloaded_bytes;
+        self.uploaded_bytes += other.uploaded_bytes;
+        self.downloads += other.downloads;
+        self.uploads += other.uploads;
+    }
+}
+
+/// What the current account has transferred, in total and for each game.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Usage {
+    pub total: Transferred,
+    pub games: HashMap<GameId, Transferred>,
+}
+
+impl Usage {
+    pub fn new(games: HashMap<GameId, Transferred>) -> Self {
+        let mut total = Transferred::default();
+        for transferred in games.values() {
+            total.add(transferred);
+        }
+        Self { total, games }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn totals() {
+        let mut first = Transferred::default();
+        first.add_download(100);
+        first.add_upload(120);
+        let mut second = Transferred::default();
+        second.add_download(50);
+
+        let usage = Usage::new(
+            vec![(GameId::from(1), first), (GameId::from(2), second)]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(
+            usage.total,
+            Transferred {
+                downloaded_bytes: 150,
+                uploaded_bytes: 120,
+                downloads: 2,
+                uploads: 1,
+            }
+        );
+        assert_eq!(usage.total.total_bytes(), 270);
+    }
+}
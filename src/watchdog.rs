@@ -0,0 +1,55 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+/// How loudly to warn about a turn that's about to be skipped. Each level also uses the ones
+/// before it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Escalation {
+    /// A desktop notification.
+    Toast,
+    /// Webhooks as well, so the group can chase the player.
+    Webhook,
+    /// Email as well, for when the desktop client isn't being looked at.
+    Email,
+}
+
+/// The level for a turn with `remaining` time left, when warnings start `warning` before the
+/// turn is skipped. Escalates at half and a quarter of the warning period.
+pub fn escalation(remaining: Duration, warning: Duration) -> Option<Escalation> {
+    if remaining > warning {
+        None
+    } else if remaining > warning / 2 {
+        Some(Escalation::Toast)
+    } else if remaining > warning / 4 {
+        Some(Escalation::Webhook)
+    } else {
+        Some(Escalation::Email)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escalates() {
+        let warning = Duration::hours(8);
+        assert_eq!(escalation(Duration::hours(9), warning), None);
+        assert_eq!(
+            escalation(Duration::hours(8), warning),
+            Some(Escalation::Toast)
+        );
+        assert_eq!(
+            escalation(Duration::hours(3), warning),
+            Some(Escalation::Webhook)
+        );
+        assert_eq!(
+            escalation(Duration::hours(1), warning),
+            Some(Escalation::Email)
+        );
+        assert_eq!(
+            escalation(Duration::minutes(-5), warning),
+            Some(Escalation::Email)
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::Context;
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, info, trace, warn};
+
+type Result<T> = anyhow::Result<T>;
+
+/// A save has to be left alone for this long before it's reported. Civ V can write a save in a
+/// few goes, and some platforms write a temp file and rename it.
+const SETTLE: Duration = Duration::from_secs(1);
+
+/// How often the loop wakes up to report settled saves and check on the directory.
+const TICK: Duration = Duration::from_millis(200);
+
+/// Watches `dir` on its own thread and sends the filename of each new or changed save to `tx`.
+///
+/// If the directory is removed, the watch is set up again once it comes back. The thread stops
+/// when `tx` is closed.
+pub fn spawn(dir: PathBuf, tx: Sender<String>) -> Result<()> {
+    let (watch_tx, watch_rx) = std::sync::mpsc::channel();
+    let mut watcher: RecommendedWatcher = Watcher::new(watch_tx, Duration::from_millis(250))?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Watching {:?}", dir))?;
+
+    std::thread::Builder::new()
+        .name("civfun save watcher".into())
+        .spawn(move || watch_loop(watcher, watch_rx, &dir, tx))?;
+    Ok(())
+}
+
+fn watch_loop(
+    mut watcher: RecommendedWatcher,
+    watch_rx: std::sync::mpsc::Receiver<DebouncedEvent>,
+    dir: &Path,
+    tx: Sender<String>,
+) {
+    trace!("Loop started.");
+    let mut debouncer = Debouncer::default();
+    let mut watching = true;
+
+    while !tx.is_closed() {
+        match watch_rx.recv_timeout(TICK) {
+            Ok(event) => {
+                trace!(?event);
+                if let Some(path) = save_path(&event) {
+                    debouncer.add(path.to_path_buf(), Instant::now());
+                } else if lost_dir(&event, dir) {
+                    warn!(?dir, "Lost the save directory.");
+                    let _ = watcher.unwatch(dir);
+                    watching = false;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                warn!("Watcher disconnected.");
+                return;
+            }
+        }
+
+        if !watching && dir.is_dir() {
+            match watcher.watch(dir, RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    info!(?dir, "Watching the save directory again.");
+                    watching = true;
+                }
+                Err(err) => debug!(?err, "Can't watch the save directory yet."),
+            }
+        }
+
+        for path in debouncer.ready(Instant::now()) {
+            let filename = match path.file_name() {
+                Some(filename) => filename.to_string_lossy().to_string(),
+                None => continue,
+            };
+            info!(?filename, "Save settled.");
+            if tx.blocking_send(filename).is_err() {
+                break;
+            }
+        }
+    }
+    trace!("Loop stopped.");
+}
+
+/// The save a watcher event is about, if it's about one. Only `.Civ5Save` files count, so temp
+/// files and anything else in the directory are ignored.
+fn save_path(event: &DebouncedEvent) -> Option<&Path> {
+    let path = match event {
+        DebouncedEvent::Create(path) => path,
+        DebouncedEvent::Write(path) => path,
+        DebouncedEvent::Rename(_, to) => to,
+        _ => return None,
+    };
+    if is_save(path) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+pub fn is_save(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.eq_ignore_ascii_case("Civ5Save"))
+        .unwrap_or(false)
+}
+
+/// Identifies a save by its length and CRC, e.g. `1234567-0a1b2c3d`, so the same save is only
+/// handled once however it turns up.
+pub fn fingerprint(bytes: &[u8]) -> String {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(bytes);
+    format!("{}-{:08x}", bytes.len(), hasher.finalize())
+}
+
+fn lost_dir(event: &DebouncedEvent, dir: &Path) -> bool {
+    match event {
+        DebouncedEvent::Remove(path) | DebouncedEvent::Rename(path, _) => path == dir,
+        DebouncedEvent::Error(_, Some(path)) => path == dir,
+        DebouncedEvent::Rescan => !dir.is_dir(),
+        _ => false,
+    }
+}
+
+/// Holds on to each path until nothing has happened to it for `SETTLE`.
+#[derive(Default)]
+struct Debouncer {
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl Debouncer {
+    fn add(&mut self, path: PathBuf, now: Instant) {
+        self.pending.insert(path, now);
+    }
+
+    fn ready(&mut self, now: Instant) -> Vec<PathBuf> {
+        let ready: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, last)| now.duration_since(**last) >= SETTLE)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &ready {
+            self.pending.remove(path);
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_saves() {
+        let save = PathBuf::from("/saves/Casimir III_0028 BC-2320.Civ5Save");
+        let temp = PathBuf::from("/saves/Casimir III_0028 BC-2320.Civ5Save.tmp");
+        assert_eq!(
+            save_path(&DebouncedEvent::Create(save.clone())),
+            Some(save.as_path())
+        );
+        assert_eq!(
+            save_path(&DebouncedEvent::Write(save.clone())),
+            Some(save.as_path())
+        );
+        assert_eq!(
+            save_path(&DebouncedEvent::Rename(temp.clone(), save.clone())),
+            Some(save.as_path())
+        );
+        assert_eq!(save_path(&DebouncedEvent::Create(temp)), None);
+        assert_eq!(save_path(&DebouncedEvent::Remove(save)), None);
+        assert_eq!(
+            save_path(&DebouncedEvent::Create("/saves/lower.civ5save".into())),
+            Some(Path::new("/saves/lower.civ5save"))
+        );
+    }
+
+    #[test]
+    fn debounce_per_file() {
+        let start = Instant::now();
+        let a = PathBuf::from("a.Civ5Save");
+        let b = PathBuf::from("b.Civ5Save");
+        let mut debouncer = Debouncer::default();
+
+        debouncer.add(a.clone(), start);
+        debouncer.add(b.clone(), start + SETTLE / 2);
+        debouncer.add(a.clone(), start + SETTLE / 2);
+        assert!(debouncer.ready(start + SETTLE).is_empty());
+
+        let mut ready = debouncer.ready(start + SETTLE * 2);
+        ready.sort();
+        assert_eq!(ready, vec![a, b]);
+        assert!(debouncer.ready(start + SETTLE * 3).is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+use crate::api::Game;
+use crate::manager::Event;
+use crate::notify::Notification;
+use crate::watchdog::Escalation;
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{debug, instrument};
+
+type Result<T> = anyhow::Result<T>;
+
+const USERNAME: &str = "civ.fun";
+
+/// Somewhere to POST to when there's news about a game, e.g. a Discord channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WebhookFormat {
+    /// Every detail as its own field, for scripts and bots.
+    #[default]
+    Json,
+    /// A message Discord's webhook API will post as is.
+    Discord,
+}
+
+impl Webhook {
+    pub fn validate(&self) -> Result<()> {
+        if !self.url.starts_with("https://") {
+            return Err(anyhow!(
+                "The webhook URL {:?} needs to start with https://",
+                self.url
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The body to send for `event`, or None if webhooks don't care about it. `game` is the game the
+/// event is about and `player` the Steam name of the user.
+pub fn payload(
+    format: WebhookFormat,
+    event: &Event,
+    game: &Game,
+    player: Option<&str>,
+) -> Option<Value> {
+    let who = player.unwrap_or("A player");
+    let turn = game.current_turn.number;
+    let (kind, message, minutes_remaining) = match event {
+        Event::NewTurn(_) => (
+            "new_turn",
+            format!("{} is up for turn {} in {}.", who, turn, game.name),
+            None,
+        ),
+        Event::UploadComplete(_) => (
+            "upload_complete",
+            format!("{} has played turn {} in {}.", who, turn, game.name),
+            None,
+        ),
+        Event::TurnDeadlineApproaching { remaining, .. } => (
+            "turn_deadline_approaching",
+            format!(
+                "{} has {}h {}m left for turn {} in {}.",
+                who,
+                remaining.num_hours(),
+                remaining.num_minutes() % 60,
+                turn,
+                game.name
+            ),
+            Some(remaining.num_minutes()),
+        ),
+        Event::TurnAboutToBeSkipped {
+            remaining,
+            escalation,
+            ..
+        } if *escalation >= Escalation::Webhook => (
+            "turn_about_to_be_skipped",
+            format!(
+                "{} will be skipped in {}h {}m unless they play turn {} in {}!",
+                who,
+                remaining.num_hours(),
+                remaining.num_minutes() % 60,
+                turn,
+                game.name
+            ),
+            Some(remaining.num_minutes()),
+        ),
+        _ => return None,
+    };
+    Some(match format {
+        WebhookFormat::Json => json!({
+            "event": kind,
+            "game_id": game.game_id,
+            "game_name": game.name,
+            "turn": turn,
+            "player": player,
+            "minutes_remaining": minutes_remaining,
+            "message": message,
+        }),
+        WebhookFormat::Discord => json!({
+            "username": USERNAME,
+            "content": message,
+        }),
+    })
+}
+
+/// The body for a summary of alerts held back during quiet hours.
+pub fn summary_payload(format: WebhookFormat, summary: &Notification) -> Value {
+    let message = format!("{}:\n{}", summary.summary, summary.body);
+    match format {
+        WebhookFormat::Json => json!({
+            "event": "deferred_alerts",
+            "message": message,
+        }),
+        WebhookFormat::Discord => json!({
+            "username": USERNAME,
+            "content": message,
+        }),
+    }
+}
+
+#[instrument(skip(payload))]
+pub async fn send(url: &str, payload: &Value) -> Result<()> {
+    debug!("Sending webhook.");
+    reqwest::Client::new()
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(payload)?)
+        .send()
+        .await
+        .context("Sending webhook.")?
+        .error_for_status()
+        .context("Webhook response.")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{CurrentTurn, GameId};
+
+    fn game() -> Game {
+        Game {
+            name: "Marathon Madness".into(),
+            game_id: GameId::from(7),
+            current_turn: CurrentTurn {
+                number: 43,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn payloads() {
+        let event = Event::NewTurn(game());
+        assert_eq!(
+            payload(WebhookFormat::Discord, &event, &game(), Some("gak")).unwrap(),
+            json!({
+                "username": "civ.fun",
+                "content": "gak is up for turn 43 in Marathon Madness.",
+            })
+        );
+
+        let event = Event::TurnDeadlineApproaching {
+            game: game(),
+            remaining: chrono::Duration::minutes(90),
+        };
+        let json = payload(WebhookFormat::Json, &event, &game(), None).unwrap();
+        assert_eq!(json["event"], "turn_deadline_approaching");
+        assert_eq!(json["game_id"], 7);
+        assert_eq!(json["minutes_remaining"], 90);
+        assert_eq!(
+            json["message"],
+            "A player has 1h 30m left for turn 43 in Marathon Madness."
+        );
+
+        assert!(payload(
+            WebhookFormat::Json,
+            &Event::GameRunning(true),
+            &game(),
+            None
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn https_only() {
+        let webhook = |url: &str| Webhook {
+            url: url.to_string(),
+            format: WebhookFormat::Json,
+        };
+        assert!(webhook("https://discord.com/api/webhooks/1/abc")
+            .validate()
+            .is_ok());
+        assert!(webhook("http://example.com/hook").validate().is_err());
+        assert!(webhook("discord.com/api/webhooks/1/abc")
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Drives a whole `Manager` against a mock GMR server and an in-memory db: authenticate, fetch
+//! games, download the turn, play it and upload the new save. The clock, data dir and key store
+//! are all the test's own, so nothing outside a temp dir is touched.
+
+use chrono::{DateTime, TimeZone, Utc};
+use civfun_gmr::api::{CurrentTurn, Game, GameId, GetGamesAndPlayers, PlayerOrder, UserId};
+use civfun_gmr::clock::MockClock;
+use civfun_gmr::keychain::MemoryKeyStore;
+use civfun_gmr::manager::{Event, Manager};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+
+const USER_ID: u64 = 76561197960287930;
+const GAME_ID: u32 = 1234;
+const TURN_ID: u64 = 5678;
+
+fn save(name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("civ5save")
+        .join("saves")
+        .join(name);
+    std::fs::read(path).unwrap()
+}
+
+fn now() -> DateTime<Utc> {
+    Utc.ymd(2021, 10, 16).and_hms(12, 0, 0)
+}
+
+fn game() -> Game {
+    Game {
+        name: "Test Game".into(),
+        game_id: GameId::from(GAME_ID),
+        players: vec![PlayerOrder {
+            user_id: UserId::from(USER_ID),
+            turn_order: 0,
+        }],
+        current_turn: CurrentTurn {
+            turn_id: TURN_ID.into(),
+            number: 28,
+            user_id: UserId::from(USER_ID),
+            started: now() - chrono::Duration::hours(1),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Just enough of GMR's API, recording every upload.
+#[derive(Clone)]
+struct MockGmr {
+    download: Vec<u8>,
+    uploads: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl MockGmr {
+    async fn start(download: Vec<u8>) -> (Self, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let mock = Self {
+            download,
+            uploads: Default::default(),
+        };
+        let server = mock.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let server = server.clone();
+                tokio::spawn(async move { server.handle(stream).await });
+            }
+        });
+        (mock, url)
+    }
+
+    async fn handle(&self, mut stream: TcpStream) {
+        let (path, body) = read_request(&mut stream).await;
+        let response = if path.starts_with("/api/Diplomacy/AuthenticateUser") {
+            USER_ID.to_string().into_bytes()
+        } else if path.starts_with("/api/Diplomacy/GetGamesAndPlayers") {
+            let games = GetGamesAndPlayers {
+                games: vec![game()],
+                players: vec![],
+                current_total_points: 0,
+            };
+            serde_json::to_vec(&games).unwrap()
+        } else if path.starts_with("/api/Diplomacy/GetLatestSaveFileBytes") {
+            self.download.clone()
+        } else if path.starts_with("/Game/UploadSaveClient") {
+            self.uploads.lock().unwrap().push(body);
+            br#"{"ResultType":1,"PointsEarned":10}"#.to_vec()
+        } else {
+            panic!("Unexpected request for {}", path);
+        };
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response.len()
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(&response).await.unwrap();
+    }
+}
+
+/// Returns the path and body of one HTTP/1.1 request.
+async fn read_request(stream: &mut TcpStream) -> (String, Vec<u8>) {
+    let mut data = vec![];
+    let head_end = loop {
+        if let Some(pos) = find(&data, b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let mut buf = [0; 4096];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert_ne!(n, 0, "Connection closed mid request.");
+        data.extend_from_slice(&buf[..n]);
+    };
+    let head = String::from_utf8_lossy(&data[..head_end]).to_string();
+    let path = head.split(' ').nth(1).unwrap_or_default().to_string();
+    let head = head.to_lowercase();
+    let mut body = data[head_end..].to_vec();
+    let chunked = head.contains("transfer-encoding: chunked");
+    let length = head
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length: "))
+        .map(|len| len.trim().parse::<usize>().unwrap());
+    loop {
+        let done = match length {
+            Some(length) => body.len() >= length,
+            None if chunked => body.ends_with(b"0\r\n\r\n"),
+            None => true,
+        };
+        if done {
+            break;
+        }
+        let mut buf = [0; 16 * 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert_ne!(n, 0, "Connection closed mid body.");
+        body.extend_from_slice(&buf[..n]);
+    }
+    // Chunk headers are left in, which is fine for looking for the save inside.
+    (path, body)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Keeps calling `process()` until an event matches, failing on any error.
+async fn process_until<F>(manager: &Manager, events: &mut broadcast::Receiver<Event>, f: F) -> Event
+where
+    F: Fn(&Event) -> bool,
+{
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(20);
+    loop {
+        manager.process().unwrap();
+        while let Ok(event) = events.try_recv() {
+            if let Event::Error {
+                source, message, ..
+            } = &event
+            {
+                panic!("Processing {} failed: {}", source, message);
+            }
+            if f(&event) {
+                return event;
+            }
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "Timed out waiting for an event."
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_play_and_upload_a_turn() {
+    let downloaded = save("Casimir III_0028 BC-2320.Civ5Save");
+    let played = save("Casimir III_0029 BC-2260.Civ5Save");
+    let (gmr, url) = MockGmr::start(downloaded.clone()).await;
+
+    let save_dir = tempfile::tempdir().unwrap();
+    let data_dir = tempfile::tempdir().unwrap();
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    let manager = Manager::new(db)
+        .unwrap()
+        .with_api_url(&url)
+        .with_clock(Arc::new(MockClock::new(now())))
+        .with_data_dir(data_dir.path())
+        .with_key_store(Arc::new(MemoryKeyStore::default()));
+    let mut events = manager.subscribe();
+    manager
+        .update_config(|config| config.save_dir = Some(save_dir.path().to_path_buf()))
+        .unwrap();
+
+    manager.authenticate("secret").unwrap();
+    process_until(&manager, &mut events, |e| {
+        matches!(e, Event::AuthenticationSuccess)
+    })
+    .await;
+    assert_eq!(manager.user_id().unwrap(), Some(UserId::from(USER_ID)));
+
+    // The first fetch only seeds the stored games, so there's no `NewTurn` for a turn that was
+    // already waiting.
+    process_until(&manager, &mut events, |e| {
+        matches!(e, Event::UpdatedGames(_))
+    })
+    .await;
+    let path = match process_until(&manager, &mut events, |e| {
+        matches!(e, Event::DownloadComplete { .. })
+    })
+    .await
+    {
+        Event::DownloadComplete { path, .. } => path,
+        _ => unreachable!(),
+    };
+    assert_eq!(std::fs::read(&path).unwrap(), downloaded);
+
+    // Play the turn, which leaves a new save next to the downloaded one.
+    let played_path: PathBuf = save_dir.path().join("Casimir III_0029 BC-2260.Civ5Save");
+    std::fs::write(&played_path, &played).unwrap();
+    assert_eq!(manager.scan_for_missed_saves().unwrap(), 1);
+
+    process_until(
+        &manager,
+        &mut events,
+        |e| matches!(e, Event::UploadComplete(game_id) if *game_id == GameId::from(GAME_ID)),
+    )
+    .await;
+    let usage = manager.usage().unwrap().total;
+    assert_eq!(usage.downloaded_bytes, downloaded.len() as u64);
+    assert_eq!(usage.uploaded_bytes, played.len() as u64);
+
+    let uploads = gmr.uploads.lock().unwrap();
+    assert_eq!(uploads.len(), 1);
+    assert!(find(&uploads[0], &played[..1024]).is_some());
+    assert!(find(&uploads[0], TURN_ID.to_string().as_bytes()).is_some());
+}
+
+#[tokio::test]
+async fn integrity_check_finds_undecodable_records() {
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    let manager = Manager::new(db.clone()).unwrap();
+    db.insert("game-settings-1234", "not json").unwrap();
+    let mut events = manager.subscribe();
+    assert_eq!(manager.check_integrity().unwrap(), 1);
+    assert!(matches!(
+        events.try_recv().unwrap(),
+        Event::DatabaseCorrupt { .. }
+    ));
+}